@@ -0,0 +1,5 @@
+fn main() {
+    // Link flags for loading the library as a Node.js addon
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
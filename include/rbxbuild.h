@@ -0,0 +1,81 @@
+/* Generated with cbindgen from src/ffi.rs; don't edit by hand. */
+
+#ifndef RBXBUILD_H
+#define RBXBUILD_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The call succeeded.
+ */
+#define RBXBUILD_OK 0
+
+/**
+ * A pointer was null, the JSON wasn't UTF-8 or the format was unknown.
+ */
+#define RBXBUILD_INVALID_ARGUMENT 1
+
+/**
+ * The project didn't build; see [`rbxbuild_last_error`].
+ */
+#define RBXBUILD_BUILD_FAILED 2
+
+/**
+ * rbxbuild panicked. This is a bug.
+ */
+#define RBXBUILD_PANICKED 3
+
+#define RBXBUILD_FORMAT_BINARY 0
+
+#define RBXBUILD_FORMAT_XML 1
+
+/**
+ * Bytes owned by rbxbuild, which have to be released with
+ * [`rbxbuild_buffer_free`].
+ */
+typedef struct RbxbuildBuffer {
+  uint8_t *data;
+  size_t len;
+} RbxbuildBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Builds the project in the `len` bytes of UTF-8 JSON at `json` and writes
+ * the place or model, in `format`, to `out`. Warnings are left out; a
+ * project that fails to build returns [`RBXBUILD_BUILD_FAILED`].
+ *
+ * # Safety
+ *
+ * `json` has to point to `len` readable bytes, and `out` to a writable
+ * [`RbxbuildBuffer`].
+ */
+int32_t rbxbuild_build(const uint8_t *json, size_t len, uint32_t format, struct RbxbuildBuffer *out);
+
+/**
+ * Releases a buffer returned by [`rbxbuild_build`]. Freeing an empty buffer
+ * does nothing.
+ *
+ * # Safety
+ *
+ * `buffer` has to come from rbxbuild and not have been freed already.
+ */
+void rbxbuild_buffer_free(struct RbxbuildBuffer buffer);
+
+/**
+ * The message of the last error on this thread, or null if there wasn't
+ * one. Valid until the next call into rbxbuild on the same thread.
+ */
+const char *rbxbuild_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RBXBUILD_H */
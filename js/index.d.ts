@@ -0,0 +1,8 @@
+/**
+ * Builds a project and returns the place or model file. Throws an `Error`
+ * describing the problem if the project doesn't build.
+ */
+export function buildProject(
+    project: string | object,
+    format?: "binary" | "xml",
+): Promise<Uint8Array>;
@@ -0,0 +1,24 @@
+// Wrapper around the wasm-pack output in ./pkg, built with
+//
+//     wasm-pack build --target web --out-dir js/pkg -- --no-default-features --features wasm
+//
+// The module is loaded on the first call, so importing this file is cheap.
+
+import init, { buildProject as build } from "./pkg/rbxbuild.js";
+
+let ready = null;
+
+/**
+ * Builds a project and returns the place or model file.
+ *
+ * @param {string | object} project The project, as JSON or as an object.
+ * @param {"binary" | "xml"} [format] Binary unless told otherwise.
+ * @returns {Promise<Uint8Array>}
+ */
+export async function buildProject(project, format) {
+    ready ??= init();
+    await ready;
+
+    const json = typeof project === "string" ? project : JSON.stringify(project);
+    return build(json, format);
+}
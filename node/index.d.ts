@@ -0,0 +1,21 @@
+/* Generated by napi-rs from src/node.rs; `npm run build` rewrites it. */
+
+/** A build warning, as handed to JavaScript. */
+export interface JsDiagnostic {
+  severity: string
+  path: string
+  property?: string
+  message: string
+}
+/**
+ * Builds a project from its JSON, resolving to the place or model file. The
+ * format is `"binary"` unless `"xml"` is given.
+ */
+export function build(project: string, format?: string | undefined | null): Promise<Buffer>
+/** Like `build`, but blocks the event loop until the file is built. */
+export function buildSync(project: string, format?: string | undefined | null): Buffer
+/**
+ * Builds a project without writing it anywhere, resolving to its warnings.
+ * Rejects if it doesn't build.
+ */
+export function validate(project: string): Promise<Array<JsDiagnostic>>
@@ -0,0 +1,2 @@
+// Loads the addon built by `npm run build`. See src/node.rs for the exports.
+module.exports = require("./rbxbuild.node");
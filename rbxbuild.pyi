@@ -0,0 +1,28 @@
+from typing import Literal, Optional, TypedDict
+
+__version__: str
+
+class BuildError(Exception):
+    """A project couldn't be built or a file couldn't be read."""
+
+class Diagnostic(TypedDict):
+    severity: Literal["warning", "error"]
+    path: str
+    property: Optional[str]
+    message: str
+
+def build_project(project: str, format: Literal["binary", "xml"] = "binary") -> bytes:
+    """Builds a project from its JSON and returns the place or model file."""
+
+def validate(project: str) -> list[Diagnostic]:
+    """Builds a project without writing it anywhere and returns its warnings.
+    Raises `BuildError` if it doesn't build."""
+
+def decompile(
+    data: bytes,
+    format: Literal["binary", "xml"] = "binary",
+    name: str = "ROOT",
+    place: bool = False,
+) -> str:
+    """Turns a model file's contents, or a place's with `place=True`, back
+    into project JSON."""
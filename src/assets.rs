@@ -0,0 +1,224 @@
+//! Finding the assets a build refers to, for auditing and preloading them.
+//!
+//! References come from `Content` and `ContentId` properties, font families,
+//! and any URI with an asset scheme (`rbxassetid://123`, `rbxasset://...`,
+//! `rbxthumb://...`) inside string properties and attributes, which covers
+//! script sources.
+
+use std::{collections::BTreeMap, fmt};
+
+use rbx_dom_weak::{types::Variant, WeakDom};
+use serde::Serialize;
+
+use crate::query::{descendants, path_of};
+
+/// URI schemes that refer to an asset.
+const SCHEMES: &[&str] = &["rbxassetid", "rbxasset", "rbxthumb", "rbxhttp"];
+
+/// Every asset a DOM refers to, returned by [`scan`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AssetReport {
+    /// One entry per distinct URI, sorted by URI.
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Asset {
+    pub uri: String,
+
+    /// The properties that refer to this asset, in the order they were found.
+    pub uses: Vec<AssetUse>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssetUse {
+    /// Slash-separated path from the root, as printed by `query`.
+    pub path: String,
+    pub property: String,
+}
+
+/// Collects the asset references in every instance of the DOM.
+pub fn scan(dom: &WeakDom) -> AssetReport {
+    let mut assets: BTreeMap<String, Vec<AssetUse>> = BTreeMap::new();
+
+    for instance in descendants(dom, dom.root_ref()) {
+        // Sorted so a place gives the same report every time
+        let mut properties: Vec<_> = instance.properties.iter().collect();
+        properties.sort_by_key(|(name, _)| name.as_str());
+
+        for (property, value) in properties {
+            let mut uris = Vec::new();
+            references(value, &mut uris);
+
+            for uri in uris {
+                let uses = assets.entry(uri.to_owned()).or_default();
+                let path = path_of(dom, instance.referent());
+
+                // A script can mention the same asset many times
+                if !uses
+                    .iter()
+                    .any(|existing| existing.path == path && existing.property == property.as_str())
+                {
+                    uses.push(AssetUse {
+                        path,
+                        property: property.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    AssetReport {
+        assets: assets
+            .into_iter()
+            .map(|(uri, uses)| Asset { uri, uses })
+            .collect(),
+    }
+}
+
+fn references<'a>(value: &'a Variant, uris: &mut Vec<&'a str>) {
+    match value {
+        Variant::ContentId(id) => push_uri(id.as_str(), uris),
+        Variant::Content(content) => push_uri(content.as_uri().unwrap_or_default(), uris),
+        Variant::Font(font) => push_uri(&font.family, uris),
+        Variant::String(string) => uris.extend(find_uris(string)),
+        Variant::Attributes(attributes) => {
+            for (_, value) in attributes.iter() {
+                references(value, uris);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn push_uri<'a>(uri: &'a str, uris: &mut Vec<&'a str>) {
+    let uri = uri.trim();
+    if !uri.is_empty() {
+        uris.push(uri);
+    }
+}
+
+/// Finds URIs with an asset scheme in free text, such as a script's source.
+fn find_uris(text: &str) -> Vec<&str> {
+    let mut uris = Vec::new();
+    let mut searched = 0;
+
+    while let Some(offset) = text[searched..].find("://") {
+        let separator = searched + offset;
+        searched = separator + 3;
+
+        let scheme_start = text[..separator]
+            .rfind(|c: char| !c.is_ascii_alphanumeric())
+            .map_or(0, |index| index + 1);
+        if !SCHEMES.contains(&&text[scheme_start..separator]) {
+            continue;
+        }
+
+        // The URI runs until whitespace or anything that would end a string
+        // literal or call in Luau
+        let end = text[searched..]
+            .find(|c: char| c.is_whitespace() || "\"'`)]},;".contains(c))
+            .map_or(text.len(), |index| searched + index);
+
+        if end > searched {
+            uris.push(&text[scheme_start..end]);
+            searched = end;
+        }
+    }
+
+    uris
+}
+
+impl fmt::Display for AssetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for asset in &self.assets {
+            writeln!(f, "{}", asset.uri)?;
+            for asset_use in &asset.uses {
+                writeln!(f, "  {}.{}", asset_use.path, asset_use.property)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{types::ContentId, InstanceBuilder};
+
+    #[test]
+    fn finds_uris_in_text() {
+        let source = r#"
+            local icon = "rbxassetid://123"
+            sound.SoundId = 'rbxassetid://456' -- also rbxassetid://123
+            local url = "https://example.com/rbxassetid"
+            Image = `rbxthumb://type=Asset&id=789&w=150&h=150`
+        "#;
+
+        assert_eq!(
+            find_uris(source),
+            [
+                "rbxassetid://123",
+                "rbxassetid://456",
+                "rbxassetid://123",
+                "rbxthumb://type=Asset&id=789&w=150&h=150",
+            ]
+        );
+    }
+
+    #[test]
+    fn collects_uses_per_asset() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_name("Game")
+                .with_child(
+                    InstanceBuilder::new("Decal")
+                        .with_name("Logo")
+                        .with_property("Texture", ContentId::from("rbxassetid://1")),
+                )
+                .with_child(
+                    InstanceBuilder::new("LocalScript")
+                        .with_name("Preload")
+                        .with_property(
+                            "Source",
+                            "preload({ 'rbxassetid://1', 'rbxassetid://2', 'rbxassetid://1' })",
+                        ),
+                )
+                .with_child(
+                    InstanceBuilder::new("Sound")
+                        .with_name("Silent")
+                        .with_property("SoundId", ContentId::from("")),
+                ),
+        );
+
+        let report = scan(&dom);
+        let summary: Vec<_> = report
+            .assets
+            .iter()
+            .map(|asset| {
+                let uses: Vec<_> = asset
+                    .uses
+                    .iter()
+                    .map(|asset_use| format!("{}.{}", asset_use.path, asset_use.property))
+                    .collect();
+                (asset.uri.as_str(), uses)
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            [
+                (
+                    "rbxassetid://1",
+                    vec![
+                        "Game/Logo.Texture".to_owned(),
+                        "Game/Preload.Source".to_owned()
+                    ]
+                ),
+                ("rbxassetid://2", vec!["Game/Preload.Source".to_owned()]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Building every project in a directory at once, for repositories with many
+//! places.
+//!
+//! Projects are found by their `.project.json` suffix and built in parallel
+//! when the `parallel` feature is enabled. The reflection database is loaded
+//! once up front and shared by every build.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use serde::Serialize;
+
+use crate::database;
+use crate::error::Result;
+
+/// The file name suffix that marks a project file.
+pub const PROJECT_SUFFIX: &str = ".project.json";
+
+/// Finds every project file under `dir`, sorted by path. Hidden directories
+/// and `node_modules` are skipped.
+pub fn discover(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut projects = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+
+            if entry.file_type()?.is_dir() {
+                if !name.starts_with('.') && name != "node_modules" {
+                    stack.push(path);
+                }
+            } else if name.ends_with(PROJECT_SUFFIX) {
+                projects.push(path);
+            }
+        }
+    }
+
+    projects.sort();
+    Ok(projects)
+}
+
+/// Where a project's output goes: its path relative to `dir`, under
+/// `out_dir`, with the project suffix replaced by `extension`. For example
+/// `places/lobby.project.json` becomes `build/places/lobby.rbxl`.
+pub fn output_path(dir: &Path, out_dir: &Path, project: &Path, extension: &str) -> PathBuf {
+    let relative = project.strip_prefix(dir).unwrap_or(project);
+    let name = relative
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let stem = name.strip_suffix(PROJECT_SUFFIX).unwrap_or(&name);
+
+    out_dir
+        .join(relative)
+        .with_file_name(format!("{stem}.{extension}"))
+}
+
+/// What a successful build produced.
+#[derive(Debug, Clone, Default)]
+pub struct Outcome {
+    pub output: Option<PathBuf>,
+    pub warnings: usize,
+}
+
+/// How one project's build went.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectBuild {
+    pub project: PathBuf,
+    pub output: Option<PathBuf>,
+    pub warnings: usize,
+    pub seconds: f64,
+    /// Why the build failed, if it did.
+    pub error: Option<String>,
+}
+
+impl ProjectBuild {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The builds of every project, in the order they were given.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    pub builds: Vec<ProjectBuild>,
+    pub seconds: f64,
+}
+
+impl BatchReport {
+    pub fn failed(&self) -> usize {
+        self.builds
+            .iter()
+            .filter(|build| !build.succeeded())
+            .count()
+    }
+}
+
+/// Builds each project with `build`, in parallel if the `parallel` feature is
+/// enabled. Project paths in the report are relative to `dir`.
+pub fn build_all<F>(dir: &Path, projects: &[PathBuf], build: F) -> BatchReport
+where
+    F: Fn(&Path) -> Result<Outcome, String> + Sync,
+{
+    let started = Instant::now();
+
+    // Otherwise the first builds would all wait on it; if it can't be
+    // loaded, each build reports why
+    let _ = database::get();
+
+    let run = |project: &PathBuf| {
+        let start = Instant::now();
+        let result = build(project);
+        let seconds = start.elapsed().as_secs_f64();
+        let project = project.strip_prefix(dir).unwrap_or(project).to_path_buf();
+
+        match result {
+            Ok(outcome) => ProjectBuild {
+                project,
+                output: outcome.output,
+                warnings: outcome.warnings,
+                seconds,
+                error: None,
+            },
+            Err(error) => ProjectBuild {
+                project,
+                output: None,
+                warnings: 0,
+                seconds,
+                error: Some(error),
+            },
+        }
+    };
+
+    #[cfg(feature = "parallel")]
+    let builds = {
+        use rayon::prelude::*;
+
+        projects.par_iter().map(run).collect()
+    };
+
+    #[cfg(not(feature = "parallel"))]
+    let builds = projects.iter().map(run).collect();
+
+    BatchReport {
+        builds,
+        seconds: started.elapsed().as_secs_f64(),
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .builds
+            .iter()
+            .map(|build| build.project.display().to_string().len())
+            .chain(["PROJECT".len()])
+            .max()
+            .unwrap_or_default();
+
+        writeln!(
+            f,
+            "{:<8}{:<width$}  {:>8}  {:>8}",
+            "STATUS", "PROJECT", "WARNINGS", "TIME"
+        )?;
+        for build in &self.builds {
+            let (status, warnings) = if build.succeeded() {
+                ("ok", build.warnings.to_string())
+            } else {
+                ("FAILED", "-".to_owned())
+            };
+            writeln!(
+                f,
+                "{status:<8}{:<width$}  {warnings:>8}  {:>7.2}s",
+                build.project.display(),
+                build.seconds
+            )?;
+            if let Some(error) = &build.error {
+                writeln!(f, "        {error}")?;
+            }
+        }
+
+        let failed = self.failed();
+        writeln!(
+            f,
+            "{} built, {failed} failed in {:.2}s",
+            self.builds.len() - failed,
+            self.seconds
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn discovers_and_reports_projects() {
+        let dir = std::env::temp_dir().join(format!("rbxbuild-batch-{}", std::process::id()));
+        fs::create_dir_all(dir.join("places/.git")).unwrap();
+        fs::write(dir.join("lobby.project.json"), "{}").unwrap();
+        fs::write(dir.join("places/arena.project.json"), "{}").unwrap();
+        fs::write(dir.join("places/.git/old.project.json"), "{}").unwrap();
+        fs::write(dir.join("places/notes.json"), "{}").unwrap();
+
+        let projects = discover(&dir).unwrap();
+        assert_eq!(
+            projects,
+            [
+                dir.join("lobby.project.json"),
+                dir.join("places/arena.project.json")
+            ]
+        );
+        assert_eq!(
+            output_path(&dir, Path::new("build"), &projects[1], "rbxl"),
+            Path::new("build/places/arena.rbxl")
+        );
+
+        let report = build_all(&dir, &projects, |project| {
+            if project.ends_with("lobby.project.json") {
+                Ok(Outcome {
+                    output: None,
+                    warnings: 2,
+                })
+            } else {
+                Err("no tree".to_owned())
+            }
+        });
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.builds[0].project, Path::new("lobby.project.json"));
+        assert_eq!(report.builds[0].warnings, 2);
+
+        let table = report.to_string();
+        assert!(table.contains("ok      lobby.project.json"));
+        assert!(table.contains("        no tree\n"));
+        assert!(table.contains("1 built, 1 failed in"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,337 @@
+//! Packaging a build into a single archive for distribution.
+//!
+//! A bundle holds, at its top level:
+//!
+//! - the place or model, named after the project (`Game.rbxl`),
+//! - `sourcemap.json`, the built tree in the shape of a Rojo sourcemap,
+//! - `diagnostics.json`, everything reported during the build,
+//! - `manifest.json`, which describes the build and lists the other files.
+//!
+//! Entries are always written in that order with a fixed timestamp, so
+//! bundling the same build twice gives the same archive.
+
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::Result;
+use crate::options::BuildOptions;
+use crate::output::{self, OutputFormat};
+use crate::query;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    /// Uncompressed tar
+    Tar,
+    /// Zip, with entries stored uncompressed
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Guesses the format from a file extension, defaulting to tar.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("zip") => ArchiveFormat::Zip,
+            _ => ArchiveFormat::Tar,
+        }
+    }
+}
+
+/// A file in a bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: String,
+    pub contents: Vec<u8>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SourcemapNode {
+    name: String,
+    class_name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<SourcemapNode>,
+}
+
+/// Serializes a built DOM and collects the files that go in its bundle.
+pub fn bundle(
+    name: &str,
+    dom: &WeakDom,
+    format: OutputFormat,
+    options: &BuildOptions,
+    diagnostics: &[Diagnostic],
+) -> Result<Vec<Entry>> {
+    let extension = output::file_extension(dom, options.root, format);
+
+    let mut place = Vec::new();
+    output::write_dom(&mut place, dom, format, options)?;
+
+    let mut entries = vec![
+        Entry {
+            path: format!("{name}.{extension}"),
+            contents: place,
+        },
+        Entry {
+            path: "sourcemap.json".to_owned(),
+            contents: serde_json::to_vec_pretty(&sourcemap(dom, dom.root_ref()))?,
+        },
+        Entry {
+            path: "diagnostics.json".to_owned(),
+            contents: serde_json::to_vec_pretty(diagnostics)?,
+        },
+    ];
+
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let files: Vec<_> = entries
+        .iter()
+        .map(|entry| json!({ "path": entry.path, "size": entry.contents.len() }))
+        .collect();
+
+    let manifest = json!({
+        "name": name,
+        "rbxbuildVersion": env!("CARGO_PKG_VERSION"),
+        "format": extension,
+        "profile": options.profile,
+        "instances": query::descendants(dom, dom.root_ref()).count(),
+        "warnings": count(Severity::Warning),
+        "errors": count(Severity::Error),
+        "files": files,
+    });
+    entries.push(Entry {
+        path: "manifest.json".to_owned(),
+        contents: serde_json::to_vec_pretty(&manifest)?,
+    });
+
+    Ok(entries)
+}
+
+fn sourcemap(dom: &WeakDom, referent: Ref) -> SourcemapNode {
+    let instance = dom.get_by_ref(referent).unwrap();
+
+    SourcemapNode {
+        name: instance.name.clone(),
+        class_name: instance.class.to_string(),
+        children: instance
+            .children()
+            .iter()
+            .map(|&child| sourcemap(dom, child))
+            .collect(),
+    }
+}
+
+/// Writes the entries as an archive.
+pub fn write_archive<W: Write>(
+    writer: W,
+    format: ArchiveFormat,
+    entries: &[Entry],
+) -> io::Result<()> {
+    match format {
+        ArchiveFormat::Tar => write_tar(writer, entries),
+        ArchiveFormat::Zip => write_zip(writer, entries),
+    }
+}
+
+fn write_tar<W: Write>(mut writer: W, entries: &[Entry]) -> io::Result<()> {
+    const BLOCK: usize = 512;
+
+    for entry in entries {
+        let name = entry.path.as_bytes();
+        if name.len() > 100 {
+            return Err(invalid(format!(
+                "{} is too long for a tar entry",
+                entry.path
+            )));
+        }
+
+        let mut header = [0u8; BLOCK];
+        header[..name.len()].copy_from_slice(name);
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[108..116].copy_from_slice(b"0000000\0");
+        header[116..124].copy_from_slice(b"0000000\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", entry.contents.len()).as_bytes());
+        header[136..148].copy_from_slice(b"00000000000\0");
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+
+        // The checksum is computed with its own field filled with spaces
+        header[148..156].fill(b' ');
+        let checksum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+        header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+        writer.write_all(&header)?;
+        writer.write_all(&entry.contents)?;
+
+        let padding = (BLOCK - entry.contents.len() % BLOCK) % BLOCK;
+        writer.write_all(&[0; BLOCK][..padding])?;
+    }
+
+    // Two empty blocks mark the end of the archive
+    writer.write_all(&[0; BLOCK * 2])?;
+    writer.flush()
+}
+
+fn write_zip<W: Write>(mut writer: W, entries: &[Entry]) -> io::Result<()> {
+    // 1980-01-01 00:00, the earliest time a zip can hold
+    const DOS_TIME: u16 = 0;
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    // Entry names are UTF-8
+    const FLAGS: u16 = 1 << 11;
+
+    let mut central = Vec::new();
+    let mut offset = 0u32;
+
+    for entry in entries {
+        let name = entry.path.as_bytes();
+        let size = u32::try_from(entry.contents.len())
+            .map_err(|_| invalid(format!("{} is too large for a zip entry", entry.path)))?;
+        let crc = crc32(&entry.contents);
+
+        let mut local = Vec::with_capacity(30 + name.len());
+        local.extend(0x04034b50u32.to_le_bytes());
+        local.extend(20u16.to_le_bytes());
+        local.extend(FLAGS.to_le_bytes());
+        local.extend(0u16.to_le_bytes());
+        local.extend(DOS_TIME.to_le_bytes());
+        local.extend(DOS_DATE.to_le_bytes());
+        local.extend(crc.to_le_bytes());
+        local.extend(size.to_le_bytes());
+        local.extend(size.to_le_bytes());
+        local.extend((name.len() as u16).to_le_bytes());
+        local.extend(0u16.to_le_bytes());
+        local.extend(name);
+
+        central.extend(0x02014b50u32.to_le_bytes());
+        central.extend(20u16.to_le_bytes());
+        central.extend_from_slice(&local[4..30]);
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u16.to_le_bytes());
+        central.extend(0u32.to_le_bytes());
+        central.extend(offset.to_le_bytes());
+        central.extend(name);
+
+        writer.write_all(&local)?;
+        writer.write_all(&entry.contents)?;
+
+        offset = u32::try_from(local.len())
+            .ok()
+            .and_then(|len| offset.checked_add(len)?.checked_add(size))
+            .ok_or_else(|| invalid("The bundle is too large for a zip file".to_owned()))?;
+    }
+
+    let count = entries.len() as u16;
+    let mut end = Vec::with_capacity(22);
+    end.extend(0x06054b50u32.to_le_bytes());
+    end.extend(0u16.to_le_bytes());
+    end.extend(0u16.to_le_bytes());
+    end.extend(count.to_le_bytes());
+    end.extend(count.to_le_bytes());
+    end.extend((central.len() as u32).to_le_bytes());
+    end.extend(offset.to_le_bytes());
+    end.extend(0u16.to_le_bytes());
+
+    writer.write_all(&central)?;
+    writer.write_all(&end)?;
+    writer.flush()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        CRC_TABLE[usize::from((crc as u8) ^ byte)] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry {
+                path: "a.txt".to_owned(),
+                contents: b"hello".to_vec(),
+            },
+            Entry {
+                path: "b.json".to_owned(),
+                contents: vec![b'x'; 600],
+            },
+        ]
+    }
+
+    #[test]
+    fn tar_layout() {
+        let mut tar = Vec::new();
+        write_archive(&mut tar, ArchiveFormat::Tar, &entries()).unwrap();
+
+        // Header and one block for a.txt, header and two blocks for b.json,
+        // then the two end blocks
+        assert_eq!(tar.len(), 512 * 7);
+        assert_eq!(&tar[..5], b"a.txt");
+        assert_eq!(&tar[124..136], b"00000000005\0");
+        assert_eq!(&tar[512..517], b"hello");
+        assert_eq!(&tar[1024..1030], b"b.json");
+
+        let mut header = tar[..512].to_vec();
+        header[148..156].fill(b' ');
+        let checksum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+        assert_eq!(&tar[148..156], format!("{checksum:06o}\0 ").as_bytes());
+    }
+
+    #[test]
+    fn zip_layout() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+
+        let mut zip = Vec::new();
+        write_archive(&mut zip, ArchiveFormat::Zip, &entries()).unwrap();
+
+        assert_eq!(&zip[..4], b"PK\x03\x04");
+        assert_eq!(&zip[30..35], b"a.txt");
+        assert_eq!(&zip[35..40], b"hello");
+
+        let end = &zip[zip.len() - 22..];
+        assert_eq!(&end[..4], b"PK\x05\x06");
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 2);
+
+        let central = u32::from_le_bytes(end[16..20].try_into().unwrap()) as usize;
+        assert_eq!(&zip[central..central + 4], b"PK\x01\x02");
+        assert_eq!(&zip[central + 46..central + 51], b"a.txt");
+    }
+}
@@ -0,0 +1,194 @@
+//! Reuse of top-level subtrees from earlier builds.
+//!
+//! Each top-level subtree (for a place, each service) is keyed by a hash of
+//! everything that affects how it's built: the subtree itself, the defines,
+//! the service table, the relevant build options, the reflection database
+//! version and the rbxbuild version. Subtrees whose key matches an earlier
+//! build are copied from the cache instead of being instantiated again.
+//!
+//! Subtrees that produced warnings aren't cached, so their warnings are
+//! reported on every build. Builds with custom property resolvers are never
+//! cached, since there's no way to tell whether a resolver's output changed.
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use rbx_dom_weak::WeakDom;
+
+use crate::options::BuildOptions;
+use crate::project::ProjectNode;
+
+pub(crate) type CacheKey = blake3::Hash;
+
+/// A cache of built subtrees, kept in memory and optionally on disk.
+///
+/// Keep one cache around between builds (for example in watch mode) to reuse
+/// subtrees in memory, or give it a directory to reuse them between runs.
+#[derive(Default)]
+pub struct BuildCache {
+    directory: Option<PathBuf>,
+    entries: Mutex<HashMap<CacheKey, Arc<[u8]>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl BuildCache {
+    /// Creates a cache that only lives in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a cache that also stores subtrees in `directory`.
+    pub fn with_directory(directory: impl Into<PathBuf>) -> Self {
+        BuildCache {
+            directory: Some(directory.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Number of subtrees that were taken from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of cacheable subtrees that had to be built.
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Number of subtrees held in memory.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets the subtrees held in memory. Ones on disk are kept.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub(crate) fn key(
+        &self,
+        name: &str,
+        parent_class: &str,
+        node: &ProjectNode,
+        options: &BuildOptions,
+    ) -> Option<CacheKey> {
+        if !options.resolvers.is_empty() {
+            return None;
+        }
+
+        let database = crate::database::get().ok()?;
+
+        // Converting to a Value first sorts every map, so the same inputs
+        // always hash the same regardless of HashMap iteration order. That
+        // loses the order of children, which the output follows, so it's
+        // hashed separately
+        let inputs = serde_json::to_value((
+            env!("CARGO_PKG_VERSION"),
+            database.version,
+            name,
+            parent_class,
+            node,
+            child_order(node),
+            &options.defines,
+            &options.services,
+            &options.class_suffixes,
+            options.fill_defaults,
+            options.deterministic,
+            options.sort_children,
+        ))
+        .ok()?;
+
+        Some(blake3::hash(&serde_json::to_vec(&inputs).ok()?))
+    }
+
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<WeakDom> {
+        let bytes = self.load(key);
+
+        let dom = bytes.and_then(|bytes| match rbx_binary::from_reader(&*bytes) {
+            Ok(dom) => Some(dom),
+            Err(err) => {
+                log::warn!("Ignoring unreadable cache entry {}: {err}", key.to_hex());
+                None
+            }
+        });
+
+        match dom {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+
+        dom
+    }
+
+    pub(crate) fn insert(&self, key: CacheKey, dom: &WeakDom) {
+        let mut bytes = Vec::new();
+        if let Err(err) = rbx_binary::to_writer(&mut bytes, dom, dom.root().children()) {
+            log::warn!("Could not cache subtree: {err}");
+            return;
+        }
+
+        if let Some(directory) = &self.directory {
+            let result = fs::create_dir_all(directory)
+                .and_then(|_| fs::write(directory.join(file_name(&key)), &bytes));
+
+            if let Err(err) = result {
+                log::warn!("Could not write to cache {}: {err}", directory.display());
+            }
+        }
+
+        self.entries.lock().unwrap().insert(key, bytes.into());
+    }
+
+    fn load(&self, key: &CacheKey) -> Option<Arc<[u8]>> {
+        if let Some(bytes) = self.entries.lock().unwrap().get(key) {
+            return Some(Arc::clone(bytes));
+        }
+
+        let directory = self.directory.as_ref()?;
+        let bytes: Arc<[u8]> = fs::read(directory.join(file_name(key))).ok()?.into();
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(*key, Arc::clone(&bytes));
+
+        Some(bytes)
+    }
+}
+
+impl fmt::Debug for BuildCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuildCache")
+            .field("directory", &self.directory)
+            .field("entries", &self.entries.lock().unwrap().len())
+            .field("hits", &self.hits())
+            .field("misses", &self.misses())
+            .finish()
+    }
+}
+
+fn file_name(key: &CacheKey) -> String {
+    format!("{}.rbxm", key.to_hex())
+}
+
+/// The names of every node's children in order, listing nodes depth-first.
+fn child_order(node: &ProjectNode) -> Vec<Vec<&str>> {
+    let mut order = Vec::new();
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        order.push(node.children.keys().map(String::as_str).collect());
+        stack.extend(node.children.values().rev());
+    }
+    order
+}
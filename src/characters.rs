@@ -0,0 +1,186 @@
+//! Handling of characters XML 1.0 doesn't allow.
+//!
+//! XML can't hold most control characters, even escaped, so a script or
+//! string with a stray one from a bad copy and paste or a generated file is
+//! written as a place Studio refuses to open. [`InvalidCharacters`] finds
+//! them in every string property, including `Source`, and fails the build,
+//! strips them or escapes them.
+//!
+//! Escaped characters are written as `\u{X}`, with the character's code in
+//! hexadecimal. That's how Luau escapes a character in a string literal, so a
+//! character inside one keeps its meaning in scripts.
+
+use std::fmt::Write;
+
+use rbx_dom_weak::{types::Variant, WeakDom};
+use serde::Deserialize;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::query;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum InvalidCharacterPolicy {
+    /// Fail the build.
+    #[default]
+    Error,
+
+    /// Remove the characters.
+    Strip,
+
+    /// Replace each character with its Luau escape, `\u{X}`.
+    Escape,
+}
+
+/// Whether XML 1.0 allows a character. Everything but tab, line feed and
+/// carriage return below U+0020 is left out, as are U+FFFE and U+FFFF.
+pub fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}')
+        || c >= '\u{10000}'
+}
+
+/// Reports every string property holding a character XML 1.0 doesn't allow,
+/// and handles it according to the policy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InvalidCharacters(pub InvalidCharacterPolicy);
+
+impl Transform for InvalidCharacters {
+    fn name(&self) -> &str {
+        "invalid-characters"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let mut found = Vec::new();
+
+        for instance in query::descendants(dom, dom.root_ref()) {
+            let mut properties: Vec<_> = instance.properties.iter().collect();
+            properties.sort_by_key(|(key, _)| key.as_str());
+
+            for (key, value) in properties {
+                let Variant::String(value) = value else {
+                    continue;
+                };
+                let Some(position) = value.find(|c| !is_xml_char(c)) else {
+                    continue;
+                };
+                found.push((instance.referent(), *key, position, value.clone()));
+            }
+        }
+
+        let count = found.len();
+        for (referent, key, position, value) in found {
+            let c = value[position..].chars().next().unwrap();
+            let line = value[..position].matches('\n').count() + 1;
+            let invalid = value.chars().filter(|&c| !is_xml_char(c)).count();
+            let what = match invalid {
+                1 => format!("U+{:04X} on line {line}", c as u32),
+                _ => format!(
+                    "{invalid} characters XML doesn't allow, the first U+{:04X} on line {line}",
+                    c as u32
+                ),
+            };
+
+            let path = query::path_of(dom, referent);
+            let diagnostic = match self.0 {
+                InvalidCharacterPolicy::Error => Diagnostic::error(
+                    path,
+                    format!("The value contains {what}, which can't be written as XML"),
+                ),
+                InvalidCharacterPolicy::Strip => Diagnostic::warning(
+                    path,
+                    format!("The value contains {what}, which was removed"),
+                ),
+                InvalidCharacterPolicy::Escape => Diagnostic::warning(
+                    path,
+                    format!("The value contains {what}, which was escaped"),
+                ),
+            };
+            diagnostics.report(diagnostic.with_property(key.as_str()));
+
+            let replaced = match self.0 {
+                InvalidCharacterPolicy::Error => continue,
+                InvalidCharacterPolicy::Strip => strip(&value),
+                InvalidCharacterPolicy::Escape => escape(&value),
+            };
+            let instance = dom.get_by_ref_mut(referent).unwrap();
+            instance.properties.insert(key, Variant::String(replaced));
+        }
+
+        match (self.0, count) {
+            (InvalidCharacterPolicy::Error, 1) => Err("found a string XML can't hold".into()),
+            (InvalidCharacterPolicy::Error, 2..) => {
+                Err(format!("found {count} strings XML can't hold").into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn strip(value: &str) -> String {
+    value.chars().filter(|&c| is_xml_char(c)).collect()
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if is_xml_char(c) {
+            escaped.push(c);
+        } else {
+            write!(escaped, "\\u{{{:X}}}", c as u32).unwrap();
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rbx_dom_weak::InstanceBuilder;
+    use ustr::Ustr;
+
+    #[test]
+    fn handles_invalid_characters() {
+        let build = || {
+            WeakDom::new(
+                InstanceBuilder::new("Script")
+                    .with_name("Main")
+                    .with_property("Source", "print('a')\nprint('b\u{1}c\u{1B}')\n")
+                    .with_property("Attribute", "tab\tand\r\nnewlines"),
+            )
+        };
+        let run = |policy| {
+            let mut dom = build();
+            let mut diagnostics = Vec::new();
+            let result = InvalidCharacters(policy)
+                .transform_dom(&mut dom, &mut |diagnostic: Diagnostic| {
+                    diagnostics.push(diagnostic)
+                });
+            let source = dom.root().properties[&Ustr::from("Source")].clone();
+            (source, diagnostics, result.is_ok())
+        };
+
+        let (_, diagnostics, ok) = run(InvalidCharacterPolicy::Error);
+        assert!(!ok);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].property.as_deref(), Some("Source"));
+        assert!(diagnostics[0].message.contains("U+0001 on line 2"));
+
+        let (source, _, ok) = run(InvalidCharacterPolicy::Strip);
+        assert!(ok);
+        assert_eq!(source, Variant::from("print('a')\nprint('bc')\n"));
+
+        let (source, _, ok) = run(InvalidCharacterPolicy::Escape);
+        assert!(ok);
+        assert_eq!(
+            source,
+            Variant::from("print('a')\nprint('b\\u{1}c\\u{1B}')\n")
+        );
+    }
+}
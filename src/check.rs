@@ -0,0 +1,167 @@
+//! Quick checks for pre-commit hooks: projects are parsed and resolved but
+//! never serialized, and checking stops when a time budget runs out, so a
+//! slow project can't hold up a commit indefinitely.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::batch::PROJECT_SUFFIX;
+use crate::error::Result;
+
+/// How checking a project went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checked {
+    Passed { warnings: usize },
+    Failed,
+}
+
+/// What the summary line says.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheckSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub warnings: usize,
+    /// Projects that weren't checked before the budget ran out.
+    pub unchecked: usize,
+    pub budget: Duration,
+    pub elapsed: Duration,
+}
+
+impl CheckSummary {
+    pub fn succeeded(&self) -> bool {
+        self.failed == 0 && self.unchecked == 0
+    }
+}
+
+impl fmt::Display for CheckSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.passed + self.failed + self.unchecked;
+        let projects = if total == 1 { "project" } else { "projects" };
+
+        if self.failed == 0 {
+            write!(f, "rbxbuild check: {} {projects} ok", self.passed)?;
+        } else {
+            write!(
+                f,
+                "rbxbuild check: {} of {total} {projects} failed",
+                self.failed
+            )?;
+        }
+        write!(
+            f,
+            ", {} warnings in {:.2}s",
+            self.warnings,
+            self.elapsed.as_secs_f64()
+        )?;
+        if self.unchecked > 0 {
+            write!(
+                f,
+                "; {} not checked within the {:.0?} budget",
+                self.unchecked, self.budget
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The project files staged in git, relative to the current directory.
+/// Deleted files are left out.
+pub fn staged_projects() -> Result<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .args([
+            "diff",
+            "--cached",
+            "--name-only",
+            "--relative",
+            "--diff-filter=ACMR",
+            "-z",
+        ])
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!("git diff failed: {}", stderr.trim())).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| path.ends_with(PROJECT_SUFFIX))
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Checks each project in order on a separate thread, and stops waiting once
+/// `budget` has passed. `check` reports the project's diagnostics itself; the
+/// thread is left to finish or be torn down when the process exits.
+pub fn check_within<F>(projects: Vec<PathBuf>, budget: Duration, check: F) -> CheckSummary
+where
+    F: Fn(&Path) -> Checked + Send + 'static,
+{
+    let started = Instant::now();
+    let total = projects.len();
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for project in projects {
+            if sender.send(check(&project)).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut summary = CheckSummary {
+        budget,
+        ..Default::default()
+    };
+    for _ in 0..total {
+        let remaining = budget.saturating_sub(started.elapsed());
+        match receiver.recv_timeout(remaining) {
+            Ok(Checked::Passed { warnings }) => {
+                summary.passed += 1;
+                summary.warnings += warnings;
+            }
+            Ok(Checked::Failed) => summary.failed += 1,
+            Err(_) => break,
+        }
+    }
+
+    summary.unchecked = total - summary.passed - summary.failed;
+    summary.elapsed = started.elapsed();
+    summary
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stops_at_the_budget() {
+        let projects = ["a", "b", "slow", "c"].map(PathBuf::from).to_vec();
+        let summary = check_within(
+            projects,
+            Duration::from_millis(200),
+            |project| match project.to_str() {
+                Some("a") => Checked::Passed { warnings: 2 },
+                Some("b") => Checked::Failed,
+                _ => {
+                    thread::sleep(Duration::from_secs(5));
+                    Checked::Passed { warnings: 0 }
+                }
+            },
+        );
+
+        assert_eq!((summary.passed, summary.failed), (1, 1));
+        assert_eq!((summary.warnings, summary.unchecked), (2, 2));
+        assert!(!summary.succeeded());
+        assert!(summary.elapsed < Duration::from_secs(5));
+
+        let line = summary.to_string();
+        assert!(line.starts_with("rbxbuild check: 1 of 4 projects failed, 2 warnings in"));
+        assert!(line.ends_with("; 2 not checked within the 200ms budget"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Collision groups declared in the project.
+//!
+//! Studio keeps a place's collision groups in `Workspace.CollisionGroupData`,
+//! a binary blob, so they can't be written as a property. A project lists
+//! them in its `collisionGroups` table instead:
+//!
+//! ```json
+//! "collisionGroups": {
+//!     "groups": ["Players", "Enemies", "Ghosts"],
+//!     "noCollide": [["Players", "Players"], ["Ghosts", "Default"]]
+//! }
+//! ```
+//!
+//! Every group collides with every other, and with itself, unless the pair is
+//! in `noCollide`. Parts join a group by name through their `CollisionGroup`
+//! property.
+//!
+//! The blob is a version byte (`01`) and the number of groups, followed by
+//! each group's ID, a `04` byte, its collision mask as a little-endian `i32`
+//! (bit N set if it collides with the group with ID N), and its name's length
+//! and bytes. `Default` always has ID 0.
+
+use rbx_dom_weak::{
+    types::{BinaryString, Variant},
+    WeakDom,
+};
+use serde::{Deserialize, Serialize};
+use ustr::Ustr;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::error::{Error, Result};
+use crate::query::{descendants, path_of};
+
+/// The group every part is in unless it names another.
+pub const DEFAULT_GROUP: &str = "Default";
+
+/// Roblox allows 32 groups, including `Default`.
+pub const MAX_GROUPS: usize = 32;
+
+/// Names longer than this are cut off by Studio.
+const MAX_NAME_LENGTH: usize = 100;
+
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CollisionGroups {
+    /// The groups besides `Default`, in the order they get their IDs.
+    pub groups: Vec<String>,
+
+    /// Pairs of groups that don't collide. A group paired with itself doesn't
+    /// collide with other parts in the same group.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_collide: Vec<[String; 2]>,
+}
+
+impl CollisionGroups {
+    /// Every group's name, by ID.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(DEFAULT_GROUP).chain(self.groups.iter().map(String::as_str))
+    }
+
+    fn id(&self, name: &str) -> Result<usize> {
+        self.names().position(|group| group == name).ok_or_else(|| {
+            Error::CollisionGroups(format!("{name} in noCollide isn't a declared group"))
+        })
+    }
+
+    /// Encodes the groups as `Workspace.CollisionGroupData`.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let names: Vec<&str> = self.names().collect();
+        if names.len() > MAX_GROUPS {
+            return Err(Error::CollisionGroups(format!(
+                "A place can have {MAX_GROUPS} groups including {DEFAULT_GROUP}, not {}",
+                names.len()
+            )));
+        }
+        for (index, name) in names.iter().enumerate() {
+            if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+                return Err(Error::CollisionGroups(format!(
+                    "Group names have to be 1 to {MAX_NAME_LENGTH} bytes long, got {name:?}"
+                )));
+            }
+            if names[..index].contains(name) {
+                return Err(Error::CollisionGroups(format!(
+                    "{name} is declared more than once"
+                )));
+            }
+        }
+
+        let mut masks = vec![-1i32; names.len()];
+        for [a, b] in &self.no_collide {
+            let (a, b) = (self.id(a)?, self.id(b)?);
+            masks[a] &= !(1 << b);
+            masks[b] &= !(1 << a);
+        }
+
+        let mut bytes = vec![VERSION, names.len() as u8];
+        for (id, (name, mask)) in names.iter().zip(masks).enumerate() {
+            bytes.extend_from_slice(&[id as u8, 4]);
+            bytes.extend_from_slice(&mask.to_le_bytes());
+            bytes.push(name.len() as u8);
+            bytes.extend_from_slice(name.as_bytes());
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Writes the groups into the built place's Workspace, and warns about parts
+/// in groups that aren't declared, which would end up in `Default` when the
+/// place loads.
+pub fn apply(
+    groups: &CollisionGroups,
+    dom: &mut WeakDom,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<()> {
+    let data = groups.encode()?;
+    let key = Ustr::from("CollisionGroup");
+
+    for instance in descendants(dom, dom.root_ref()) {
+        if let Some(Variant::String(group)) = instance.properties.get(&key) {
+            if !group.is_empty() && !groups.names().any(|name| name == group) {
+                diagnostics.report(
+                    Diagnostic::warning(
+                        path_of(dom, instance.referent()),
+                        format!("The collision group {group} isn't declared in collisionGroups"),
+                    )
+                    .with_property("CollisionGroup"),
+                );
+            }
+        }
+    }
+
+    let root = dom.root();
+    let workspace = if root.class == "Workspace" {
+        Some(dom.root_ref())
+    } else {
+        root.children().iter().copied().find(|&child| {
+            dom.get_by_ref(child)
+                .is_some_and(|child| child.class == "Workspace")
+        })
+    };
+
+    match workspace.and_then(|workspace| dom.get_by_ref_mut(workspace)) {
+        Some(workspace) => {
+            workspace.properties.insert(
+                Ustr::from("CollisionGroupData"),
+                Variant::BinaryString(BinaryString::from(data)),
+            );
+        }
+        None => diagnostics.report(Diagnostic::warning(
+            dom.root().name.clone(),
+            "The collision groups were ignored, since there's no Workspace to put them in",
+        )),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn groups() -> CollisionGroups {
+        serde_json::from_str(
+            r#"{
+                "groups": ["Players", "Ghosts"],
+                "noCollide": [["Players", "Players"], ["Ghosts", "Default"]]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn encodes_the_matrix() {
+        let mut expected = vec![VERSION, 3];
+        expected.extend_from_slice(&[0, 4]);
+        expected.extend_from_slice(&(!0b100i32).to_le_bytes());
+        expected.extend_from_slice(b"\x07Default");
+        expected.extend_from_slice(&[1, 4]);
+        expected.extend_from_slice(&(!0b010i32).to_le_bytes());
+        expected.extend_from_slice(b"\x07Players");
+        expected.extend_from_slice(&[2, 4]);
+        expected.extend_from_slice(&(!0b001i32).to_le_bytes());
+        expected.extend_from_slice(b"\x06Ghosts");
+        assert_eq!(groups().encode().unwrap(), expected);
+
+        let mut unknown = groups();
+        unknown
+            .no_collide
+            .push(["Players".to_owned(), "Walls".to_owned()]);
+        assert!(unknown.encode().is_err());
+
+        let mut duplicate = groups();
+        duplicate.groups.push("Default".to_owned());
+        assert!(duplicate.encode().is_err());
+    }
+
+    #[test]
+    fn applies_to_the_workspace() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_name("Game"));
+        let workspace = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("Workspace").with_name("Workspace"),
+        );
+        dom.insert(
+            workspace,
+            InstanceBuilder::new("Part")
+                .with_name("Wall")
+                .with_property("CollisionGroup", "Walls"),
+        );
+
+        let mut diagnostics = Vec::new();
+        apply(&groups(), &mut dom, &mut |diagnostic: Diagnostic| {
+            diagnostics.push(diagnostic)
+        })
+        .unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("Walls"));
+        assert!(dom
+            .get_by_ref(workspace)
+            .unwrap()
+            .properties
+            .contains_key(&Ustr::from("CollisionGroupData")));
+    }
+}
@@ -0,0 +1,703 @@
+//! Per-repository defaults loaded from `rbxbuild.toml`.
+//!
+//! The file is looked up in the current directory and its ancestors, so one
+//! config at the repository root applies to every build run inside it. Values
+//! given on the command line always take precedence over the config file.
+//!
+//! [`Config::build_options`] turns a config into the options a build uses,
+//! for the CLI and anything else that should build a project the same way,
+//! like the daemon.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::characters::{InvalidCharacterPolicy, InvalidCharacters};
+use crate::defines::Defines;
+use crate::error::{Error, Result};
+use crate::filter::{FilterServices, StripClasses};
+use crate::floats::{CanonicalFloats, NonFiniteFloats, NonFinitePolicy, RoundFloats};
+use crate::hooks::Hooks;
+use crate::input::BOM;
+use crate::limits::Limits;
+use crate::meshes::CheckMeshParts;
+use crate::names::{NameCollisionPolicy, NameCollisions};
+use crate::options::{BuildOptions, RootHandling};
+use crate::output::OutputFormat;
+use crate::placement::CheckPlacement;
+use crate::presets::{Preset, SelectPreset};
+use crate::project::Project;
+use crate::provenance::{self, InjectProvenance, Provenance, ProvenanceStyle};
+use crate::scripts::{
+    AddBanner, CheckLinkedSources, CheckRequires, InjectConstants, MinifyScripts,
+    NormalizeLineEndings, StripDevBlocks,
+};
+use crate::scrub::{Scrub, ScrubRule};
+use crate::templates::{ApplyTemplate, Template};
+use crate::unique_ids::{UniqueIdMode, UniqueIds};
+
+pub const CONFIG_FILE_NAME: &str = "rbxbuild.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Where to write the built file instead of stdout.
+    pub output: Option<PathBuf>,
+
+    /// Output format, otherwise inferred from the output file's extension.
+    pub format: Option<OutputFormat>,
+
+    /// Treat property and child resolution failures as errors.
+    pub strict: Option<bool>,
+
+    /// Fill in every property's default value from the reflection database.
+    pub fill_defaults: Option<bool>,
+
+    /// Make the output byte-identical for identical inputs.
+    pub deterministic: Option<bool>,
+
+    /// Build siblings sorted by name instead of in project order.
+    pub sort_children: Option<bool>,
+
+    /// Strip comments and whitespace from script sources.
+    pub minify_scripts: Option<bool>,
+
+    /// Convert CRLF line endings to LF and remove byte order marks in script
+    /// sources.
+    pub normalize_line_endings: Option<bool>,
+
+    /// Comment prepended to every script, e.g. a license header. Can refer to
+    /// `{year}` and to defines.
+    pub banner: Option<String>,
+
+    /// Built-in template merged under the project's tree.
+    pub template: Option<Template>,
+
+    /// Workspace and Lighting preset for projects that don't pick one.
+    pub preset: Option<Preset>,
+
+    /// Path below the root to write the build's commit, time, version and
+    /// profile at.
+    pub provenance: Option<String>,
+
+    /// How provenance is written.
+    pub provenance_style: Option<ProvenanceStyle>,
+
+    /// Whether to remove, derive or randomly generate UniqueIds.
+    pub unique_ids: Option<UniqueIdMode>,
+
+    /// Fail the build if a script requires a module that isn't in the tree.
+    pub check_requires: Option<bool>,
+
+    /// Read the output back after writing it and fail if it doesn't match.
+    pub verify: Option<bool>,
+
+    /// Fail the build if a script looks like it contains a secret.
+    pub scan_secrets: Option<bool>,
+
+    /// Extra patterns for `scan-secrets`, as name to regular expression.
+    #[serde(default)]
+    pub secret_patterns: HashMap<String, String>,
+
+    /// Services of a place to build, leaving out the rest.
+    #[serde(default)]
+    pub only: Vec<String>,
+
+    /// Services of a place to leave out of the build.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// `$buildTags` whose nodes are built.
+    #[serde(default)]
+    pub with_tags: Vec<String>,
+
+    /// `$buildTags` whose nodes are left out even if they have a tag in
+    /// `with-tags`.
+    #[serde(default)]
+    pub without_tags: Vec<String>,
+
+    /// Classes whose instances are removed from the build.
+    #[serde(default)]
+    pub strip_classes: Vec<String>,
+
+    /// Remove the children of stripped instances too, instead of keeping
+    /// them in their place.
+    pub strip_descendants: Option<bool>,
+
+    /// Properties to blank or remove before the build is written.
+    #[serde(default)]
+    pub scrub: Vec<ScrubRule>,
+
+    /// Number of decimal places every float is rounded to.
+    pub float_precision: Option<u32>,
+
+    /// Whether NaN and infinity fail the build, are clamped or are written
+    /// as they are.
+    pub non_finite: Option<NonFinitePolicy>,
+
+    /// Whether characters XML doesn't allow fail the build, are stripped or
+    /// are escaped.
+    pub invalid_characters: Option<InvalidCharacterPolicy>,
+
+    /// Whether siblings with the same name fail the build, are warned
+    /// about or are renamed.
+    pub name_collisions: Option<NameCollisionPolicy>,
+
+    /// Limits that fail the build when it goes over them.
+    #[serde(default)]
+    pub limits: Limits,
+
+    /// Read project files that aren't valid UTF-8 as Latin-1 instead of
+    /// failing.
+    pub latin1_fallback: Option<bool>,
+
+    /// Start XML output with an XML declaration.
+    pub xml_declaration: Option<bool>,
+
+    /// How the root of the tree is written out.
+    pub root: Option<RootHandling>,
+
+    /// Profile used when none is selected on the command line.
+    pub profile: Option<String>,
+
+    /// Path to a local reflection database to use instead of the bundled one.
+    pub reflection_database: Option<PathBuf>,
+
+    /// Place or model to merge the built project into.
+    pub merge_into: Option<PathBuf>,
+
+    /// Directory to write each service to as its own model file.
+    pub split_output: Option<PathBuf>,
+
+    /// Archive to package the build into.
+    pub bundle: Option<PathBuf>,
+
+    /// JSON file to write per-instance hashes to.
+    pub manifest: Option<PathBuf>,
+
+    /// HTML file to write a build report to.
+    pub html_report: Option<PathBuf>,
+
+    /// Creator to upload `$asset` files as, `user:ID` or `group:ID`.
+    pub upload_assets: Option<String>,
+
+    /// JSON file remembering the IDs of uploaded assets.
+    pub asset_cache: Option<PathBuf>,
+
+    /// Environment variable holding the Open Cloud API key used to upload
+    /// assets and publish, `ROBLOX_API_KEY` by default.
+    pub api_key_env: Option<String>,
+
+    /// Directory to keep built subtrees in between runs.
+    pub cache: Option<PathBuf>,
+
+    /// Values merged over the project's own `defines`.
+    #[serde(default)]
+    pub defines: Defines,
+
+    /// Extra instance name to class name mappings used when a node has no
+    /// `$className`, checked before the built-in service list, so they can
+    /// add to it or change what one of its names means.
+    #[serde(default)]
+    pub services: HashMap<String, String>,
+
+    /// Key suffixes that give a node without `$className` its class, such as
+    /// `server = "Script"` for `Main.server`.
+    #[serde(default)]
+    pub class_suffixes: HashMap<String, String>,
+
+    /// Named sets of defines, selected with `--profile`.
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+
+    /// Shell commands to run before and after each build.
+    #[serde(default)]
+    pub hooks: Hooks,
+}
+
+/// A named set of defines, e.g. for separate development and release builds.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Profile {
+    /// Values merged over the config's own `defines`.
+    #[serde(default)]
+    pub defines: Defines,
+
+    /// Remove `--!dev` ... `--!end` regions from scripts.
+    #[serde(default)]
+    pub strip_dev: bool,
+
+    /// Scrub rules applied on top of the config's own.
+    #[serde(default)]
+    pub scrub: Vec<ScrubRule>,
+}
+
+/// Settings that win over everything in a config, including the selected
+/// profile. Most command-line flags just replace the config's value, so
+/// they're set on the config itself; these can't be.
+#[derive(Debug, Default)]
+pub struct Overrides {
+    /// Merged over the project's, the config's and the profile's defines.
+    pub defines: Defines,
+
+    /// Whether to remove `--!dev` ... `--!end` regions from scripts, whatever
+    /// the profile says.
+    pub strip_dev: Option<bool>,
+
+    /// Used even if the project names a preset of its own, which the
+    /// config's `preset` isn't.
+    pub preset: Option<Preset>,
+}
+
+impl Config {
+    /// Loads the config at `path`, or searches for `rbxbuild.toml` starting in
+    /// the current directory if no path is given. Returns the default config if
+    /// none could be found.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::load_file(path),
+            None => Self::load_for(&std::env::current_dir()?),
+        }
+    }
+
+    /// Loads the `rbxbuild.toml` in `dir` or the closest of its ancestors,
+    /// like a build run in `dir` would. Returns the default config if there's
+    /// none.
+    pub fn load_for(dir: &Path) -> Result<Self> {
+        match Self::find(dir) {
+            Some(path) => Self::load_file(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn load_file(path: &Path) -> Result<Self> {
+        let path = path.to_path_buf();
+        let contents = fs::read_to_string(&path).map_err(|err| {
+            std::io::Error::new(
+                err.kind(),
+                format!("Could not read config file {}: {err}", path.display()),
+            )
+        })?;
+        let contents = contents.strip_prefix(BOM).unwrap_or(&contents);
+        let mut config: Config = toml::from_str(contents).map_err(|source| Error::Config {
+            path: path.clone(),
+            source,
+        })?;
+
+        // Paths in the config are relative to the file itself, not to wherever
+        // the tool happens to be invoked from.
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        config.output = config.output.map(|output| base.join(output));
+        config.reflection_database = config.reflection_database.map(|db| base.join(db));
+        config.cache = config.cache.map(|cache| base.join(cache));
+        config.merge_into = config.merge_into.map(|path| base.join(path));
+        config.bundle = config.bundle.map(|path| base.join(path));
+        config.split_output = config.split_output.map(|path| base.join(path));
+        config.manifest = config.manifest.map(|path| base.join(path));
+        config.html_report = config.html_report.map(|path| base.join(path));
+        config.asset_cache = config.asset_cache.map(|path| base.join(path));
+        config.hooks.directory = Some(base.to_path_buf()).filter(|dir| !dir.as_os_str().is_empty());
+
+        Ok(config)
+    }
+
+    fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// The options the CLI builds `project` with under this config, with
+    /// `overrides` on top. Defines from the overrides win over the selected
+    /// profile, which wins over the config, which wins over the project
+    /// itself.
+    ///
+    /// `$generate` scripts, heightmaps and `$asset` files are read relative
+    /// to the working directory.
+    pub fn build_options(&self, project: &Project, overrides: Overrides) -> Result<BuildOptions> {
+        let mut defines = project.defines.clone();
+        defines.extend(self.defines.clone());
+
+        let mut strip_dev = false;
+        let mut scrub = self.scrub.clone();
+
+        if let Some(name) = &self.profile {
+            let Some(selected) = self.profiles.get(name) else {
+                return Err(Error::Options(format!("Unknown profile {name}")));
+            };
+            defines.extend(selected.defines.clone());
+            strip_dev = selected.strip_dev;
+            scrub.extend(selected.scrub.iter().cloned());
+        }
+
+        defines.extend(overrides.defines);
+        let strip_dev = overrides.strip_dev.unwrap_or(strip_dev);
+
+        let mut build_options = BuildOptions {
+            strict: self.strict.unwrap_or_default(),
+            fill_defaults: self.fill_defaults.unwrap_or_default(),
+            deterministic: self.deterministic.unwrap_or_default(),
+            // Deterministic builds sort siblings too, unless the config or a
+            // flag says otherwise
+            sort_children: self
+                .sort_children
+                .unwrap_or(self.deterministic.unwrap_or_default()),
+            root: self.root.unwrap_or_default(),
+            services: self.services.clone(),
+            class_suffixes: self.class_suffixes.clone(),
+            profile: self.profile.clone(),
+            defines,
+            meta: project.meta.clone(),
+            with_tags: self.with_tags.clone(),
+            without_tags: self.without_tags.clone(),
+            #[cfg(feature = "cache")]
+            cache: self
+                .cache
+                .clone()
+                .map(crate::cache::BuildCache::with_directory),
+            xml_declaration: self.xml_declaration.unwrap_or_default(),
+            limits: self.limits,
+            ..Default::default()
+        };
+
+        // Before anything reads the time, such as provenance and banners
+        if build_options.deterministic {
+            provenance::pin_build_time();
+        }
+
+        // The template goes first, so everything after sees the merged tree
+        if let Some(template) = self.template {
+            build_options.transforms.push(ApplyTemplate(template));
+        }
+
+        // The project's own preset wins over the config's, but not the
+        // override's
+        if let Some(preset) = overrides
+            .preset
+            .or_else(|| self.preset.filter(|_| project.preset.is_none()))
+        {
+            build_options.transforms.push(SelectPreset(preset));
+        }
+
+        #[cfg(feature = "luau")]
+        build_options
+            .transforms
+            .push(crate::generate::GenerateNodes::default());
+        #[cfg(feature = "terrain")]
+        if crate::terrain::uses_heightmaps(&project.tree) {
+            build_options
+                .resolvers
+                .push(Box::new(crate::terrain::HeightmapImporter::default()));
+        }
+
+        if let Some(creator) = &self.upload_assets {
+            build_options.resolvers.push(self.asset_uploader(creator)?);
+        }
+
+        if self.normalize_line_endings.unwrap_or_default() {
+            build_options.transforms.push(NormalizeLineEndings);
+        }
+
+        // Dev blocks go first so constants they refer to don't have to exist
+        // in production builds, and constants have to be injected before
+        // minifying, which would remove the markers
+        if strip_dev {
+            build_options.transforms.push(StripDevBlocks);
+        }
+
+        let constants = InjectConstants::new(build_options.defines.clone());
+        build_options.transforms.push(constants);
+
+        if self.minify_scripts.unwrap_or_default() {
+            build_options.transforms.push(MinifyScripts);
+        }
+
+        if let Some(banner) = self.banner.clone() {
+            let banner = AddBanner::new(banner, build_options.defines.clone());
+            build_options.transforms.push(banner);
+        }
+
+        if let Some(path) = self.provenance.clone() {
+            let style = self.provenance_style.unwrap_or_default();
+            let provenance = Provenance::collect(build_options.profile.as_deref());
+            build_options
+                .transforms
+                .push(InjectProvenance::new(path, style, provenance));
+        }
+
+        // After everything that adds instances, so they get IDs too. Random IDs
+        // would make every deterministic build different, so those leave IDs
+        // out unless asked to derive them
+        if build_options.deterministic && self.unique_ids == Some(UniqueIdMode::Random) {
+            return Err(Error::Options(
+                "Random UniqueIds can't be used in a deterministic build, use --unique-ids derive"
+                    .to_owned(),
+            ));
+        }
+        if let Some(mode) = self
+            .unique_ids
+            .or(build_options.deterministic.then_some(UniqueIdMode::Omit))
+        {
+            build_options.transforms.push(UniqueIds(mode));
+        }
+
+        // Services are filtered after everything that adds instances, so a
+        // provenance record in an excluded service is left out too
+        let filter = FilterServices {
+            only: self.only.clone(),
+            exclude: self.exclude.clone(),
+        };
+        if !filter.is_empty() {
+            build_options.transforms.push(filter);
+        }
+
+        if !self.strip_classes.is_empty() {
+            build_options.transforms.push(StripClasses {
+                classes: self.strip_classes.clone(),
+                descendants: self.strip_descendants.unwrap_or_default(),
+            });
+        }
+
+        if !scrub.is_empty() {
+            build_options.transforms.push(Scrub(scrub));
+        }
+
+        let non_finite = self.non_finite.unwrap_or_default();
+        build_options.transforms.push(NonFiniteFloats(non_finite));
+
+        if let Some(digits) = self.float_precision {
+            build_options.transforms.push(RoundFloats(digits));
+        }
+
+        // After rounding, which can leave a small negative number as -0
+        if build_options.deterministic {
+            build_options.transforms.push(CanonicalFloats);
+        }
+
+        let invalid_characters = self.invalid_characters.unwrap_or_default();
+        build_options
+            .transforms
+            .push(InvalidCharacters(invalid_characters));
+
+        let name_collisions = self.name_collisions.unwrap_or_default();
+        build_options
+            .transforms
+            .push(NameCollisions(name_collisions));
+
+        // Checked last, so only what actually ships is looked at
+        let linked_sources = CheckLinkedSources::new(build_options.strict);
+        build_options.transforms.push(linked_sources);
+        build_options.transforms.push(CheckMeshParts);
+        build_options.transforms.push(CheckPlacement);
+
+        if self.check_requires.unwrap_or_default() {
+            build_options.transforms.push(CheckRequires);
+        }
+
+        if self.scan_secrets.unwrap_or_default() {
+            self.push_secret_scanner(&mut build_options)?;
+        }
+
+        Ok(build_options)
+    }
+
+    #[cfg(feature = "publish")]
+    fn asset_uploader(&self, creator: &str) -> Result<Box<dyn crate::resolver::PropertyResolver>> {
+        use crate::publish::assets::{parse_creator, AssetUploader, DEFAULT_CACHE_FILE};
+        use crate::publish::DEFAULT_API_KEY_ENV;
+
+        let creator = parse_creator(creator).map_err(Error::Options)?;
+        let variable = self.api_key_env.as_deref().unwrap_or(DEFAULT_API_KEY_ENV);
+        let api_key = std::env::var(variable).map_err(|_| {
+            Error::Options(format!(
+                "Set {variable} to an Open Cloud API key to upload assets"
+            ))
+        })?;
+        let cache = self
+            .asset_cache
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_FILE));
+        Ok(Box::new(AssetUploader::new(".", cache, creator, api_key)?))
+    }
+
+    #[cfg(not(feature = "publish"))]
+    fn asset_uploader(&self, _: &str) -> Result<Box<dyn crate::resolver::PropertyResolver>> {
+        Err(Error::Options(
+            "Uploading assets needs rbxbuild's publish feature".to_owned(),
+        ))
+    }
+
+    #[cfg(feature = "secrets")]
+    fn push_secret_scanner(&self, build_options: &mut BuildOptions) -> Result<()> {
+        let mut patterns: Vec<_> = self.secret_patterns.iter().collect();
+        patterns.sort();
+
+        let mut scanner = crate::scripts::ScanSecrets::new();
+        for (name, pattern) in patterns {
+            scanner = scanner.with_pattern(name, pattern).map_err(|error| {
+                Error::Options(format!("Invalid secret pattern {name:?}: {error}"))
+            })?;
+        }
+        build_options.transforms.push(scanner);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "secrets"))]
+    fn push_secret_scanner(&self, _: &mut BuildOptions) -> Result<()> {
+        Err(Error::Options(
+            "Scanning for secrets needs rbxbuild's secrets feature".to_owned(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::defines::DefineValue;
+
+    #[test]
+    fn parse_full_config() {
+        let config: Config = toml::from_str(
+            r#"
+            output = "build/place.rbxl"
+            format = "binary"
+            strict = true
+            normalize-line-endings = true
+            reflection-database = "db.msgpack"
+            banner = "Copyright {year} Acme"
+
+            [defines]
+            version = "1.2.0"
+            tiles = 8
+
+            [services]
+            TextChatService = "TextChatService"
+
+            [class-suffixes]
+            server = "Script"
+
+            [secret-patterns]
+            "internal token" = "itk_[0-9a-f]{32}"
+
+            [hooks]
+            pre-build = ["lune run codegen"]
+            post-build = ["./upload.sh", "echo done"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.output, Some(PathBuf::from("build/place.rbxl")));
+        assert_eq!(config.format, Some(OutputFormat::Binary));
+        assert_eq!(config.strict, Some(true));
+        assert_eq!(config.normalize_line_endings, Some(true));
+        assert_eq!(config.secret_patterns.len(), 1);
+        assert_eq!(config.banner.as_deref(), Some("Copyright {year} Acme"));
+        assert_eq!(config.defines.get("tiles"), Some(&DefineValue::Number(8.0)));
+        assert_eq!(
+            config.services.get("TextChatService").map(String::as_str),
+            Some("TextChatService")
+        );
+        assert_eq!(
+            config.class_suffixes.get("server").map(String::as_str),
+            Some("Script")
+        );
+        assert_eq!(config.hooks.pre_build, ["lune run codegen"]);
+        assert_eq!(config.hooks.post_build.len(), 2);
+    }
+
+    #[test]
+    fn parse_profiles() {
+        let config: Config = toml::from_str(
+            r#"
+            profile = "dev"
+            fill-defaults = true
+            root = "model"
+
+            [profiles.dev.defines]
+            debug = true
+
+            [profiles.release]
+            strip-dev = true
+
+            [[profiles.release.scrub]]
+            class = "ModuleScript"
+            property = "Source"
+
+            [profiles.release.defines]
+            debug = false
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.profile.as_deref(), Some("dev"));
+        assert_eq!(config.fill_defaults, Some(true));
+        assert_eq!(config.root, Some(RootHandling::Model));
+        assert_eq!(
+            config.profiles["release"].defines.get("debug"),
+            Some(&DefineValue::Bool(false))
+        );
+        assert!(config.profiles["release"].strip_dev);
+        assert_eq!(config.profiles["release"].scrub[0].path, "**");
+        assert!(!config.profiles["dev"].strip_dev);
+    }
+
+    #[test]
+    fn build_options_layer_defines() {
+        let config: Config = toml::from_str(
+            r#"
+            profile = "release"
+            strict = true
+
+            [defines]
+            version = "1.0"
+            debug = true
+
+            [profiles.release.defines]
+            debug = false
+            "#,
+        )
+        .unwrap();
+
+        let mut project = Project::new("Game", crate::ProjectNode::new("Folder"));
+        project
+            .defines
+            .insert("name".into(), DefineValue::String("Game".into()));
+        project
+            .defines
+            .insert("version".into(), DefineValue::String("0.1".into()));
+
+        let overrides = Overrides {
+            defines: [("version".into(), DefineValue::String("2.0".into()))].into(),
+            ..Default::default()
+        };
+        let options = config.build_options(&project, overrides).unwrap();
+
+        assert!(options.strict);
+        assert_eq!(options.profile.as_deref(), Some("release"));
+        assert_eq!(options.defines["name"], DefineValue::String("Game".into()));
+        assert_eq!(options.defines["debug"], DefineValue::Bool(false));
+        assert_eq!(
+            options.defines["version"],
+            DefineValue::String("2.0".into())
+        );
+
+        let unknown = Config {
+            profile: Some("beta".into()),
+            ..config
+        };
+        assert!(unknown
+            .build_options(&project, Overrides::default())
+            .is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("colour = true").is_err());
+    }
+}
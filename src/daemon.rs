@@ -0,0 +1,385 @@
+//! A long-running build server that keeps the reflection database and the
+//! build cache warm, for editor integrations that build many times a minute.
+//!
+//! Clients connect over TCP and send one JSON request per line, getting one
+//! JSON response line back for each. A connection can stay open for any
+//! number of requests. Builds run one at a time, since they share the cache.
+//!
+//! ```json
+//! {"command": "build", "project": "/abs/game.project.json", "output": "/abs/build/game.rbxl"}
+//! {"ok": true, "output": "/abs/build/game.rbxl", "bytes": 51234, "durationMs": 41.7, "cache": {"hits": 6, "misses": 1}, "diagnostics": []}
+//! ```
+//!
+//! Projects are built with the `rbxbuild.toml` found from the project's
+//! directory, the way `rbxbuild` run there would build them. Build requests
+//! can also set `format`, `strict`, `as` and `defines`, which work like the
+//! CLI flags. The daemon keeps its own cache, so the config's `cache`
+//! directory isn't used. Without an `output`, the project is only built,
+//! which is enough to check it for problems. Relative paths are relative to
+//! the daemon's working directory. The other commands are `status` and
+//! `shutdown`.
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::cache::BuildCache;
+use crate::config::{Config, Overrides};
+use crate::defines::Defines;
+use crate::diagnostics::Diagnostic;
+use crate::error::Result;
+use crate::options::{BuildOptions, RootHandling};
+use crate::output::{self, OutputFormat};
+use crate::project::Project;
+
+/// The port the daemon listens on unless told otherwise.
+pub const DEFAULT_PORT: u16 = 34874;
+
+/// Subtrees kept in memory before the cache is emptied, so a daemon that
+/// runs all day doesn't keep every version of every service.
+const MAX_CACHED_SUBTREES: usize = 1024;
+
+/// A request, one per line.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum DaemonRequest {
+    Build(BuildRequest),
+    Status,
+    Shutdown,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BuildRequest {
+    /// The project file to build.
+    pub project: PathBuf,
+
+    /// Where to write the place or model, if anywhere.
+    pub output: Option<PathBuf>,
+
+    /// Taken from the config, or inferred from the output's extension, if
+    /// omitted.
+    pub format: Option<OutputFormat>,
+
+    /// Taken from the config if omitted.
+    pub strict: Option<bool>,
+
+    #[serde(default, rename = "as")]
+    pub root: Option<RootHandling>,
+
+    /// Merged over the defines from the project, the config and its profile.
+    #[serde(default)]
+    pub defines: Defines,
+}
+
+/// The daemon's state, shared by every connection.
+pub struct Daemon {
+    /// Taken out for the duration of each build, which also keeps builds from
+    /// running at the same time.
+    cache: Mutex<Option<BuildCache>>,
+    builds: AtomicUsize,
+    started: Instant,
+    stopping: AtomicBool,
+}
+
+impl Default for Daemon {
+    fn default() -> Self {
+        Daemon {
+            cache: Mutex::new(Some(BuildCache::new())),
+            builds: AtomicUsize::new(0),
+            started: Instant::now(),
+            stopping: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Daemon {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a client asked the daemon to shut down.
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::Relaxed)
+    }
+
+    /// Answers one request line.
+    pub fn handle_line(&self, line: &str) -> Value {
+        let request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(error) => {
+                return json!({ "ok": false, "error": format!("Invalid request: {error}") })
+            }
+        };
+
+        match request {
+            DaemonRequest::Build(request) => self.build(&request),
+            DaemonRequest::Status => {
+                let cached = self
+                    .cache
+                    .lock()
+                    .unwrap_or_else(|error| error.into_inner())
+                    .as_ref()
+                    .map_or(0, BuildCache::len);
+                json!({
+                    "ok": true,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "builds": self.builds.load(Ordering::Relaxed),
+                    "uptimeSeconds": self.started.elapsed().as_secs(),
+                    "cachedSubtrees": cached,
+                })
+            }
+            DaemonRequest::Shutdown => {
+                self.stopping.store(true, Ordering::Relaxed);
+                json!({ "ok": true })
+            }
+        }
+    }
+
+    fn build(&self, request: &BuildRequest) -> Value {
+        let start = Instant::now();
+        let mut diagnostics = Vec::new();
+
+        let mut cache = self.cache.lock().unwrap_or_else(|error| error.into_inner());
+        let (hits, misses) = cache
+            .as_ref()
+            .map_or((0, 0), |cache| (cache.hits(), cache.misses()));
+        let mut options = BuildOptions {
+            cache: cache.take(),
+            ..Default::default()
+        };
+
+        let result = build_request(request, &mut options, &mut diagnostics);
+
+        // Put the cache back for the next build, failed or not
+        let built = options.cache.take().unwrap_or_default();
+        let (hits, misses) = (built.hits() - hits, built.misses() - misses);
+        if built.len() > MAX_CACHED_SUBTREES {
+            built.clear();
+        }
+        *cache = Some(built);
+        drop(cache);
+
+        self.builds.fetch_add(1, Ordering::Relaxed);
+        let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        match result {
+            Ok(bytes) => json!({
+                "ok": true,
+                "output": request.output,
+                "bytes": bytes,
+                "durationMs": duration_ms,
+                "cache": { "hits": hits, "misses": misses },
+                "diagnostics": diagnostics,
+            }),
+            Err(error) => json!({
+                "ok": false,
+                "error": error.to_string(),
+                "durationMs": duration_ms,
+                "diagnostics": diagnostics,
+            }),
+        }
+    }
+}
+
+/// Builds a project like the CLI does with its config (see
+/// [`Config::build_options`]), with the request's settings on top, returning
+/// how many bytes were written if there's an output. `options` carries the
+/// cache in and out.
+fn build_request(
+    request: &BuildRequest,
+    options: &mut BuildOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Option<u64>> {
+    let json = fs::read_to_string(&request.project).map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("Could not read {}: {error}", request.project.display()),
+        )
+    })?;
+    let project = Project::from_json(&json)?;
+
+    let path = fs::canonicalize(&request.project)?;
+    let mut config = Config::load_for(path.parent().unwrap_or(&path))?;
+    config.strict = request.strict.or(config.strict);
+    config.root = request.root.or(config.root);
+
+    let overrides = Overrides {
+        defines: request.defines.clone(),
+        ..Default::default()
+    };
+    let cache = options.cache.take();
+    *options = config.build_options(&project, overrides)?;
+    options.cache = cache;
+
+    let dom = crate::build(project, options, &mut |diagnostic: Diagnostic| {
+        diagnostics.push(diagnostic)
+    })?;
+
+    let Some(path) = &request.output else {
+        return Ok(None);
+    };
+    let format = request
+        .format
+        .or(config.format)
+        .unwrap_or_else(|| OutputFormat::from_path(path));
+    let stats = output::write_to_file(path, &dom, format, options)?;
+
+    Ok(Some(stats.bytes))
+}
+
+/// Answers requests on each connection, on its own thread, until a client
+/// asks the daemon to shut down.
+pub fn run(listener: TcpListener, daemon: Arc<Daemon>) -> io::Result<()> {
+    // Loaded now rather than by the first build
+    if let Err(error) = crate::database::get() {
+        return Err(io::Error::other(error.to_string()));
+    }
+
+    let address = listener.local_addr()?;
+
+    for stream in listener.incoming() {
+        if daemon.is_stopping() {
+            break;
+        }
+
+        let stream = stream?;
+        let daemon = Arc::clone(&daemon);
+        thread::spawn(move || {
+            if let Err(error) = handle_connection(&stream, &daemon) {
+                log::debug!("Connection failed: {error}");
+            }
+
+            // Wake the accept loop so it notices
+            if daemon.is_stopping() {
+                let _ = TcpStream::connect(address);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn handle_connection(stream: &TcpStream, daemon: &Daemon) -> io::Result<()> {
+    let mut writer = BufWriter::new(stream);
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = daemon.handle_line(&line);
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+
+        if daemon.is_stopping() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn answers_requests() {
+        let daemon = Daemon::new();
+
+        let status = daemon.handle_line(r#"{"command": "status"}"#);
+        assert_eq!(status["ok"], true);
+        assert_eq!(status["builds"], 0);
+
+        let invalid = daemon.handle_line(r#"{"command": "dance"}"#);
+        assert_eq!(invalid["ok"], false);
+
+        let missing = daemon.handle_line(r#"{"command": "build", "project": "nope.json"}"#);
+        assert_eq!(missing["ok"], false);
+        assert_eq!(daemon.handle_line(r#"{"command": "status"}"#)["builds"], 1);
+
+        assert!(!daemon.is_stopping());
+        daemon.handle_line(r#"{"command": "shutdown"}"#);
+        assert!(daemon.is_stopping());
+    }
+
+    #[test]
+    fn reuses_the_cache_between_builds() {
+        let dir = std::env::temp_dir().join(format!("rbxbuild-daemon-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let project = dir.join("game.project.json");
+        fs::write(
+            &project,
+            r#"{
+                "name": "Game",
+                "tree": {
+                    "$className": "DataModel",
+                    "Workspace": { "Baseplate": { "$className": "Part" } },
+                    "Lighting": {}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let daemon = Daemon::new();
+        let request = json!({ "command": "build", "project": project }).to_string();
+
+        let first = daemon.handle_line(&request);
+        let second = daemon.handle_line(&request);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first["ok"], true, "{first}");
+        assert_eq!(first["bytes"], Value::Null);
+        assert_eq!(first["cache"]["hits"], 0);
+        assert_eq!(second["cache"]["hits"], 2);
+    }
+
+    #[test]
+    fn reads_the_projects_config() {
+        let dir =
+            std::env::temp_dir().join(format!("rbxbuild-daemon-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let project = dir.join("game.project.json");
+        fs::write(
+            &project,
+            r#"{
+                "name": "Game",
+                "tree": {
+                    "$className": "DataModel",
+                    "Workspace": { "Baseplate": { "$className": "Part" } }
+                }
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join(crate::config::CONFIG_FILE_NAME),
+            "[limits]\nmax-instances = 2\n",
+        )
+        .unwrap();
+
+        let daemon = Daemon::new();
+        let result =
+            daemon.handle_line(&json!({ "command": "build", "project": project }).to_string());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result["ok"], false, "{result}");
+        assert!(
+            result["error"].as_str().unwrap().contains("instances"),
+            "{result}"
+        );
+    }
+}
@@ -0,0 +1,17 @@
+//! Access to the reflection database.
+//!
+//! The database is only loaded the first time a class or property is looked
+//! up, so invocations that never resolve a property (like `--help`) don't pay
+//! for it. Once loaded it's kept for the rest of the process and shared by
+//! every build.
+
+use rbx_reflection::ReflectionDatabase;
+
+use crate::error::{Error, Result};
+
+/// Returns the reflection database, loading it on first use. Honors the
+/// `RBX_DATABASE` environment variable, which must be set before the first
+/// call.
+pub fn get() -> Result<&'static ReflectionDatabase<'static>> {
+    rbx_reflection_database::get().map_err(|err| Error::Database(err.to_string()))
+}
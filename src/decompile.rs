@@ -0,0 +1,262 @@
+//! Turning an existing place or model back into a project.
+//!
+//! Properties are written in the same shorthand forms a project would use
+//! (`"Size": [4, 1, 2]`, `"Material": "Grass"`) when the reflection database
+//! knows their type, and fully qualified otherwise. Properties that still
+//! have their class's default value are left out.
+
+use std::collections::HashMap;
+
+use rbx_dom_weak::{types::Variant, Instance, WeakDom};
+use rbx_reflection::DataType;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::instantiate::{infer_child_class, infer_class_from_name};
+use crate::options::RootHandling;
+use crate::project::{Project, ProjectNode};
+use crate::resolution::{find_descriptor, AmbiguousValue, UnresolvedValue};
+
+/// Converts a DOM read with [`input::read_dom`](crate::input::read_dom) into
+/// a project called `name`.
+///
+/// A place becomes a `DataModel` tree. A model with a single top-level
+/// instance becomes a tree rooted at that instance; a model with several is
+/// wrapped in a Folder. Anything that can't be expressed in a project, like
+/// `Ref` properties, is left out and reported to `diagnostics`.
+pub fn decompile(
+    dom: &WeakDom,
+    name: &str,
+    root: RootHandling,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Project {
+    let root_instance = dom.root();
+    let is_place = match root {
+        RootHandling::Auto => root_instance.class == "DataModel",
+        RootHandling::Place => true,
+        RootHandling::Model => false,
+    };
+
+    if is_place {
+        let mut tree = decompile_instance(dom, root_instance, name, diagnostics);
+        tree.class_name = Some("DataModel".into());
+        return Project::new(name, tree);
+    }
+
+    match root_instance.children() {
+        [only] => {
+            let instance = dom.get_by_ref(*only).unwrap();
+            let tree = decompile_instance(dom, instance, &instance.name, diagnostics);
+            Project::new(instance.name.as_str(), tree)
+        }
+        _ => {
+            diagnostics.report(Diagnostic::warning(
+                name,
+                "The model has several top-level instances, so they were put in a Folder",
+            ));
+            let mut tree = decompile_instance(dom, root_instance, name, diagnostics);
+            tree.class_name = Some("Folder".into());
+            Project::new(name, tree)
+        }
+    }
+}
+
+fn decompile_instance(
+    dom: &WeakDom,
+    instance: &Instance,
+    path: &str,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> ProjectNode {
+    let class = instance.class.as_str();
+    let mut node = ProjectNode::default();
+
+    let parent_class = dom
+        .get_by_ref(instance.parent())
+        .map_or("", |parent| parent.class.as_str());
+    let inferred = infer_child_class(parent_class, &instance.name)
+        .or_else(|| infer_class_from_name(&instance.name))
+        .unwrap_or("Folder");
+    if inferred != class {
+        node.class_name = Some(instance.class);
+    }
+
+    let defaults = crate::database::get()
+        .ok()
+        .and_then(|database| database.classes.get(class))
+        .map(|class| &class.default_properties);
+
+    for (key, value) in &instance.properties {
+        let property = key.as_str();
+
+        if defaults.and_then(|defaults| defaults.get(property)) == Some(value) {
+            continue;
+        }
+
+        if let Variant::Ref(_) = value {
+            diagnostics.report(
+                Diagnostic::warning(path, "Ref properties can't be written in a project")
+                    .with_property(property),
+            );
+            continue;
+        }
+
+        node.properties
+            .insert(*key, shorthand(class, property, value));
+    }
+
+    // Siblings with the same name need distinct keys, so every one after the
+    // first keeps its real name in a Name property instead
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+
+    for &referent in instance.children() {
+        let child = dom.get_by_ref(referent).unwrap();
+        let child_name = child.name.as_str();
+
+        let count = seen.entry(child_name).or_default();
+        *count += 1;
+
+        let key = if *count == 1 && !child_name.starts_with('$') {
+            child_name.to_owned()
+        } else {
+            format!("_{child_name} ({count})")
+        };
+
+        let child_path = format!("{path}/{key}");
+        let mut child_node = decompile_instance(dom, child, &child_path, diagnostics);
+        if key != child_name {
+            child_node = child_node.property("Name", child_name);
+        }
+
+        node.children.insert(key, child_node);
+    }
+
+    node
+}
+
+/// Writes a value in the shortest form that resolves back to it.
+pub(crate) fn shorthand(class: &str, property: &str, value: &Variant) -> UnresolvedValue {
+    let fully_qualified = || UnresolvedValue::FullyQualified(value.clone());
+
+    let Ok(Some(descriptor)) = find_descriptor(class, property) else {
+        return fully_qualified();
+    };
+
+    let ambiguous = match (&descriptor.data_type, value) {
+        (DataType::Enum(enum_name), Variant::Enum(item)) => crate::database::get()
+            .ok()
+            .and_then(|database| database.enums.get(enum_name))
+            .and_then(|descriptor| {
+                descriptor
+                    .items
+                    .iter()
+                    .find(|(_, number)| **number == item.to_u32())
+            })
+            .map(|(name, _)| AmbiguousValue::String(name.to_string())),
+        (DataType::Value(ty), value) if *ty == value.ty() => ambiguous_value(value),
+        _ => None,
+    };
+
+    ambiguous.map_or_else(fully_qualified, UnresolvedValue::Ambiguous)
+}
+
+fn ambiguous_value(value: &Variant) -> Option<AmbiguousValue> {
+    Some(match value {
+        Variant::Bool(value) => AmbiguousValue::Bool(*value),
+        Variant::Float32(value) => AmbiguousValue::Number(widen(*value)),
+        Variant::Float64(value) => AmbiguousValue::Number(*value),
+        Variant::Int32(value) => AmbiguousValue::Number(f64::from(*value)),
+        // Beyond 2^53 a number can't hold every integer
+        Variant::Int64(value) if value.unsigned_abs() <= 1 << 53 => {
+            AmbiguousValue::Number(*value as f64)
+        }
+        Variant::String(value) => AmbiguousValue::String(value.clone()),
+        Variant::Tags(tags) => {
+            AmbiguousValue::StringArray(tags.iter().map(str::to_owned).collect())
+        }
+        Variant::Content(content) => AmbiguousValue::String(content.as_uri()?.to_owned()),
+        Variant::ContentId(id) => AmbiguousValue::String(id.as_str().to_owned()),
+        Variant::Vector2(v) => AmbiguousValue::Array2([widen(v.x), widen(v.y)]),
+        Variant::Vector3(v) => AmbiguousValue::Array3([widen(v.x), widen(v.y), widen(v.z)]),
+        Variant::Color3(c) => AmbiguousValue::Array3([widen(c.r), widen(c.g), widen(c.b)]),
+        Variant::CFrame(cframe) => {
+            let (p, m) = (cframe.position, cframe.orientation);
+            AmbiguousValue::Array12(
+                [
+                    p.x, p.y, p.z, m.x.x, m.x.y, m.x.z, m.y.x, m.y.y, m.y.z, m.z.x, m.z.y, m.z.z,
+                ]
+                .map(widen),
+            )
+        }
+        Variant::Attributes(value) => AmbiguousValue::Attributes(value.clone()),
+        Variant::Font(value) => AmbiguousValue::Font(value.clone()),
+        Variant::MaterialColors(value) => AmbiguousValue::MaterialColors(value.clone()),
+        _ => return None,
+    })
+}
+
+/// Converts an `f32` to the `f64` with the same shortest decimal form, so
+/// `0.1f32` is written as `0.1` rather than `0.10000000149011612`.
+fn widen(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(f64::from(value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{
+        types::{Enum, Vector3},
+        InstanceBuilder,
+    };
+
+    #[test]
+    fn uses_shorthand_and_skips_defaults() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Workspace")
+                    .with_name("Workspace")
+                    .with_child(
+                        InstanceBuilder::new("Part")
+                            .with_name("Baseplate")
+                            .with_property("Anchored", true)
+                            .with_property("Size", Vector3::new(64.0, 0.1, 64.0))
+                            .with_property("Material", Enum::from_u32(1280))
+                            .with_property("CastShadow", true),
+                    ),
+            ),
+        );
+
+        let project = decompile(&dom, "Place", RootHandling::Place, &mut |_: Diagnostic| {});
+        assert_eq!(project.tree.class_name.as_deref(), Some("DataModel"));
+
+        let workspace = &project.tree.children["Workspace"];
+        assert_eq!(workspace.class_name, None);
+
+        let part = &workspace.children["Baseplate"];
+        let json = serde_json::to_value(&part.properties).unwrap();
+        assert_eq!(json["Anchored"], true);
+        assert_eq!(json["Size"], serde_json::json!([64.0, 0.1, 64.0]));
+        assert_eq!(json["Material"], "Grass");
+        // CastShadow is true by default
+        assert!(json.get("CastShadow").is_none());
+    }
+
+    #[test]
+    fn duplicate_names_and_models() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Model")
+                    .with_name("Tree")
+                    .with_child(InstanceBuilder::new("Part").with_name("Leaf"))
+                    .with_child(InstanceBuilder::new("Part").with_name("Leaf")),
+            ),
+        );
+
+        let project = decompile(&dom, "tree", RootHandling::Model, &mut |_: Diagnostic| {});
+        assert_eq!(project.name.as_deref(), Some("Tree"));
+        assert_eq!(project.tree.class_name.as_deref(), Some("Model"));
+
+        let mut keys: Vec<_> = project.tree.children.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["Leaf", "_Leaf (2)"]);
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Named build-time values declared in a project's `defines` table.
+pub type Defines = HashMap<String, DefineValue>;
+
+/// A single define. Numbers can be referenced from property expressions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DefineValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl DefineValue {
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            DefineValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a `NAME=VALUE` command line define. Values that look like numbers or
+/// booleans are treated as such, everything else is a string.
+pub fn parse_define(arg: &str) -> Result<(String, DefineValue), String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got {arg:?}"))?;
+
+    let value = if let Ok(number) = value.parse() {
+        DefineValue::Number(number)
+    } else if let Ok(boolean) = value.parse() {
+        DefineValue::Bool(boolean)
+    } else {
+        DefineValue::String(value.to_owned())
+    };
+
+    Ok((name.to_owned(), value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_defines() {
+        assert_eq!(
+            parse_define("tiles=8"),
+            Ok(("tiles".to_owned(), DefineValue::Number(8.0)))
+        );
+        assert_eq!(
+            parse_define("debug=true"),
+            Ok(("debug".to_owned(), DefineValue::Bool(true)))
+        );
+        assert_eq!(
+            parse_define("endpoint=https://example.com/?a=b"),
+            Ok((
+                "endpoint".to_owned(),
+                DefineValue::String("https://example.com/?a=b".to_owned())
+            ))
+        );
+        assert!(parse_define("nothing").is_err());
+    }
+}
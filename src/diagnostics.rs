@@ -0,0 +1,209 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::source::{self, Key};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("Warning"),
+            Severity::Error => f.write_str("Error"),
+        }
+    }
+}
+
+/// A problem found while building a project. Warnings don't stop the build;
+/// errors are reported before the step that found them fails.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// Slash-separated path of the project node this refers to, made of the
+    /// keys used in the project file, e.g. `Game/Workspace/Baseplate`.
+    pub path: String,
+
+    /// The property this refers to, if any.
+    pub property: Option<String>,
+
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            path: path.into(),
+            property: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            path: path.into(),
+            property: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn with_property(mut self, property: impl Into<String>) -> Self {
+        self.property = Some(property.into());
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} (at {}", self.severity, self.message, self.path)?;
+        if let Some(property) = &self.property {
+            write!(f, ".{property}")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Receives diagnostics as they are produced during a build.
+///
+/// Implemented for any `FnMut(Diagnostic)` closure, so diagnostics can be
+/// collected with `&mut |d: Diagnostic| warnings.push(d)` or ignored with
+/// `&mut |_: Diagnostic| {}`.
+pub trait DiagnosticSink {
+    fn report(&mut self, diagnostic: Diagnostic);
+}
+
+impl<F: FnMut(Diagnostic)> DiagnosticSink for F {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self(diagnostic)
+    }
+}
+
+/// How the CLI prints diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticFormat {
+    /// `Warning: message (at Game/Workspace/Part.Size)`
+    #[default]
+    Text,
+    /// One JSON object per line, as in bundles and HTTP responses.
+    Json,
+    /// `file:line:column: warning: message`, for editors' problem matchers.
+    Editor,
+}
+
+/// Writes diagnostics as `file:line:column: severity: message`, the shape
+/// problem matchers like VS Code's `$gcc` expect, pointing at the key in the
+/// project file that each one is about.
+pub struct EditorFormat<'a> {
+    file: &'a str,
+    text: &'a str,
+    keys: Vec<Key>,
+}
+
+impl<'a> EditorFormat<'a> {
+    /// `text` is the project file's contents, and `file` what to call it.
+    pub fn new(file: &'a str, text: &'a str) -> Self {
+        EditorFormat {
+            file,
+            text,
+            keys: source::keys(text),
+        }
+    }
+
+    /// Formats a diagnostic reported while building the project. Ones that
+    /// can't be placed point at the start of the file.
+    pub fn line(&self, diagnostic: &Diagnostic) -> String {
+        let offset = source::locate(&self.keys, diagnostic).map_or(0, |span| span.start);
+        let mut message = diagnostic.message.clone();
+        if let Some(property) = &diagnostic.property {
+            message = format!("{message} ({}.{property})", diagnostic.path);
+        } else if !diagnostic.path.is_empty() {
+            message = format!("{message} ({})", diagnostic.path);
+        }
+
+        self.at(offset, diagnostic.severity, &message)
+    }
+
+    /// Formats an error that stopped the build, such as a syntax error at a
+    /// 1-based line and column.
+    pub fn error(&self, position: Option<(usize, usize)>, message: &str) -> String {
+        let offset = position.map_or(0, |(line, column)| {
+            let line_start: usize = self
+                .text
+                .split_inclusive('\n')
+                .take(line.saturating_sub(1))
+                .map(str::len)
+                .sum();
+            let offset = (line_start + column.saturating_sub(1)).min(self.text.len());
+            (0..=offset)
+                .rev()
+                .find(|&offset| self.text.is_char_boundary(offset))
+                .unwrap_or(0)
+        });
+
+        self.at(offset, Severity::Error, message)
+    }
+
+    fn at(&self, offset: usize, severity: Severity, message: &str) -> String {
+        let (line, column) = source::position(self.text, offset);
+        let severity = match severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        format!(
+            "{}:{}:{}: {severity}: {message}",
+            self.file,
+            line + 1,
+            column + 1
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let diagnostic =
+            Diagnostic::warning("Game/Workspace/Part", "Unknown property").with_property("Sizee");
+
+        assert_eq!(
+            diagnostic.to_string(),
+            "Warning: Unknown property (at Game/Workspace/Part.Sizee)"
+        );
+    }
+
+    #[test]
+    fn editor_format() {
+        let text = "{\n  \"name\": \"Game\",\n  \"tree\": {\n    \"Part\": { \"$properties\": { \"Sizee\": 1 } }\n  }\n}";
+        let format = EditorFormat::new("game.project.json", text);
+
+        let diagnostic =
+            Diagnostic::warning("Game/Part", "Unknown property").with_property("Sizee");
+        assert_eq!(
+            format.line(&diagnostic),
+            "game.project.json:4:32: warning: Unknown property (Game/Part.Sizee)"
+        );
+
+        let elsewhere = Diagnostic::error("Game/Missing", "Nope");
+        assert_eq!(
+            format.line(&elsewhere),
+            "game.project.json:1:1: error: Nope (Game/Missing)"
+        );
+
+        assert_eq!(
+            format.error(Some((3, 3)), "Bad"),
+            "game.project.json:3:3: error: Bad"
+        );
+    }
+}
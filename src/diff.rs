@@ -0,0 +1,394 @@
+//! Structural comparison of two DOMs.
+//!
+//! Instances are matched by their path from the top level of the file.
+//! Siblings that share a name are matched in order, and the second and later
+//! ones get a `(2)`, `(3)`, ... suffix in paths. An instance whose class
+//! changed is reported as removed and added again.
+//!
+//! [`verify_written`] uses the same comparison to check that a written file
+//! reads back as the DOM it was written from. [`first_divergence`] compares
+//! two written files byte by byte instead, for telling whether a build is
+//! reproducible.
+
+use std::{collections::HashMap, fmt};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::input::read_dom;
+use crate::options::{BuildOptions, RootHandling};
+use crate::output::{top_level_refs, OutputFormat};
+
+/// A single difference between two DOMs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Change {
+    /// An instance, along with its descendants, only exists in the new DOM.
+    Added { path: String, class: String },
+
+    /// An instance, along with its descendants, only exists in the old DOM.
+    Removed { path: String, class: String },
+
+    /// A property was added, removed or changed on an instance in both DOMs.
+    Property {
+        path: String,
+        property: String,
+        old: Option<Variant>,
+        new: Option<Variant>,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, class } => write!(f, "+ {path} ({class})"),
+            Change::Removed { path, class } => write!(f, "- {path} ({class})"),
+            Change::Property {
+                path,
+                property,
+                old,
+                new,
+            } => write!(
+                f,
+                "~ {path}.{property}: {} -> {}",
+                describe(old.as_ref()),
+                describe(new.as_ref())
+            ),
+        }
+    }
+}
+
+/// Compares the top-level instances of two DOMs (the services of a place, or
+/// the root of a model) and everything below them.
+pub fn diff(old: &WeakDom, new: &WeakDom) -> Vec<Change> {
+    diff_top_level(old, RootHandling::Auto, new, RootHandling::Auto)
+}
+
+/// Like [`diff`], but with the top-level instances of each DOM chosen the way
+/// they would be when writing it with the given [`RootHandling`].
+pub fn diff_top_level(
+    old: &WeakDom,
+    old_root: RootHandling,
+    new: &WeakDom,
+    new_root: RootHandling,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut stack = vec![(
+        String::new(),
+        top_level_refs(old, old_root),
+        top_level_refs(new, new_root),
+    )];
+
+    while let Some((parent_path, old_children, new_children)) = stack.pop() {
+        let mut old_children: HashMap<_, _> = keyed(old, &old_children).into_iter().collect();
+        let mut matched = Vec::new();
+
+        for (segment, new_ref) in keyed(new, &new_children) {
+            let path = join(&parent_path, &segment);
+            let new_instance = new.get_by_ref(new_ref).unwrap();
+
+            let Some(old_ref) = old_children.remove(&segment) else {
+                changes.push(Change::Added {
+                    path,
+                    class: new_instance.class.to_string(),
+                });
+                continue;
+            };
+
+            let old_instance = old.get_by_ref(old_ref).unwrap();
+            if old_instance.class != new_instance.class {
+                changes.push(Change::Removed {
+                    path: path.clone(),
+                    class: old_instance.class.to_string(),
+                });
+                changes.push(Change::Added {
+                    path,
+                    class: new_instance.class.to_string(),
+                });
+                continue;
+            }
+
+            let mut properties: Vec<_> = old_instance
+                .properties
+                .keys()
+                .chain(new_instance.properties.keys())
+                .collect();
+            properties.sort();
+            properties.dedup();
+
+            for property in properties {
+                let old_value = old_instance.properties.get(property);
+                let new_value = new_instance.properties.get(property);
+
+                // Refs point into different DOMs, so they never compare equal
+                if old_value != new_value && !both_refs(old_value, new_value) {
+                    changes.push(Change::Property {
+                        path: path.clone(),
+                        property: property.to_string(),
+                        old: old_value.cloned(),
+                        new: new_value.cloned(),
+                    });
+                }
+            }
+
+            matched.push((
+                path,
+                old_instance.children().to_vec(),
+                new_instance.children().to_vec(),
+            ));
+        }
+
+        let mut removed: Vec<_> = old_children.into_iter().collect();
+        removed.sort_by_key(|(_, referent)| {
+            old.get_by_ref(*referent)
+                .and_then(|instance| old.get_by_ref(instance.parent()))
+                .and_then(|parent| parent.children().iter().position(|c| c == referent))
+        });
+        for (segment, old_ref) in removed {
+            changes.push(Change::Removed {
+                path: join(&parent_path, &segment),
+                class: old.get_by_ref(old_ref).unwrap().class.to_string(),
+            });
+        }
+
+        // Reversed so children are compared in order
+        stack.extend(matched.into_iter().rev());
+    }
+
+    changes
+}
+
+/// Reads back a file that `dom` was written to and reports everything that
+/// didn't survive the round trip, with the built DOM as the old side.
+pub fn verify_written(
+    dom: &WeakDom,
+    written: &[u8],
+    format: OutputFormat,
+    options: &BuildOptions,
+) -> Result<Vec<Change>> {
+    // Whatever was written, the DOM that's read back holds it under a
+    // DataModel root
+    let read = read_dom(written, format)?;
+    Ok(diff_top_level(
+        dom,
+        options.root,
+        &read,
+        RootHandling::Place,
+    ))
+}
+
+/// Where two files first differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Divergence {
+    /// Offset of the first byte that differs, or of the end of the shorter
+    /// file if one is the start of the other.
+    pub offset: usize,
+
+    /// Line and column of `offset` in the first file, counting from 1. Only
+    /// meaningful for XML.
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "byte {} (line {}, column {})",
+            self.offset, self.line, self.column
+        )
+    }
+}
+
+/// Finds the first byte at which two files differ, or `None` if they're
+/// identical.
+pub fn first_divergence(old: &[u8], new: &[u8]) -> Option<Divergence> {
+    let offset = old
+        .iter()
+        .zip(new)
+        .position(|(old, new)| old != new)
+        .or_else(|| (old.len() != new.len()).then_some(old.len().min(new.len())))?;
+
+    let before = &old[..offset];
+    let line_start = before
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    Some(Divergence {
+        offset,
+        line: before.iter().filter(|&&byte| byte == b'\n').count() + 1,
+        column: offset - line_start + 1,
+    })
+}
+
+/// Pairs each child with its path segment.
+pub(crate) fn keyed(dom: &WeakDom, children: &[Ref]) -> Vec<(String, Ref)> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+
+    children
+        .iter()
+        .filter_map(|&referent| {
+            let instance = dom.get_by_ref(referent)?;
+            let count = seen.entry(instance.name.as_str()).or_default();
+            *count += 1;
+
+            let segment = match *count {
+                1 => instance.name.clone(),
+                n => format!("{} ({n})", instance.name),
+            };
+            Some((segment, referent))
+        })
+        .collect()
+}
+
+fn join(parent: &str, segment: &str) -> String {
+    if parent.is_empty() {
+        segment.to_owned()
+    } else {
+        format!("{parent}/{segment}")
+    }
+}
+
+fn both_refs(old: Option<&Variant>, new: Option<&Variant>) -> bool {
+    matches!((old, new), (Some(Variant::Ref(_)), Some(Variant::Ref(_))))
+}
+
+/// A short, single-line description of a value for text output.
+fn describe(value: Option<&Variant>) -> String {
+    const MAX_LEN: usize = 60;
+
+    let Some(value) = value else {
+        return "(none)".to_owned();
+    };
+
+    let text = match value {
+        Variant::String(text) if text.contains('\n') || text.len() > MAX_LEN => {
+            return format!("({} lines, {} bytes)", text.lines().count(), text.len());
+        }
+        Variant::String(text) => format!("{text:?}"),
+        other => serde_json::to_string(other).unwrap_or_else(|_| format!("{other:?}")),
+    };
+
+    match text.char_indices().nth(MAX_LEN) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn place(workspace: InstanceBuilder) -> WeakDom {
+        WeakDom::new(InstanceBuilder::new("DataModel").with_child(workspace))
+    }
+
+    #[test]
+    fn reports_changes_by_path() {
+        let old = place(
+            InstanceBuilder::new("Workspace")
+                .with_name("Workspace")
+                .with_child(
+                    InstanceBuilder::new("Part")
+                        .with_name("Baseplate")
+                        .with_property("Anchored", false),
+                )
+                .with_child(InstanceBuilder::new("Folder").with_name("Old"))
+                .with_child(InstanceBuilder::new("Part").with_name("Shape")),
+        );
+        let new = place(
+            InstanceBuilder::new("Workspace")
+                .with_name("Workspace")
+                .with_child(
+                    InstanceBuilder::new("Part")
+                        .with_name("Baseplate")
+                        .with_property("Anchored", true),
+                )
+                .with_child(InstanceBuilder::new("Folder").with_name("New"))
+                .with_child(InstanceBuilder::new("WedgePart").with_name("Shape")),
+        );
+
+        let changes: Vec<_> = diff(&old, &new).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                "~ Workspace/Baseplate.Anchored: {\"Bool\":false} -> {\"Bool\":true}",
+                "+ Workspace/New (Folder)",
+                "- Workspace/Shape (Part)",
+                "+ Workspace/Shape (WedgePart)",
+                "- Workspace/Old (Folder)",
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_doms_and_duplicate_names() {
+        let build = || {
+            place(
+                InstanceBuilder::new("Workspace")
+                    .with_name("Workspace")
+                    .with_child(InstanceBuilder::new("Part").with_name("Part"))
+                    .with_child(InstanceBuilder::new("Part").with_name("Part")),
+            )
+        };
+        assert!(diff(&build(), &build()).is_empty());
+
+        let mut fewer = build();
+        let workspace = fewer.root().children()[0];
+        let second = fewer.get_by_ref(workspace).unwrap().children()[1];
+        fewer.destroy(second);
+
+        assert_eq!(
+            diff(&build(), &fewer),
+            [Change::Removed {
+                path: "Workspace/Part (2)".to_owned(),
+                class: "Part".to_owned(),
+            }]
+        );
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn verifies_written_files() {
+        let build = |transparency: f32| {
+            WeakDom::new(
+                InstanceBuilder::new("Model").with_name("Tree").with_child(
+                    InstanceBuilder::new("Part")
+                        .with_name("Leaf")
+                        .with_property("Transparency", transparency)
+                        .with_property("Anchored", true),
+                ),
+            )
+        };
+        let options = BuildOptions::default();
+
+        let mut written = Vec::new();
+        crate::output::write_dom(&mut written, &build(0.1), OutputFormat::Xml, &options).unwrap();
+
+        let changes = verify_written(&build(0.1), &written, OutputFormat::Xml, &options).unwrap();
+        assert!(changes.is_empty(), "{changes:?}");
+
+        let changes = verify_written(&build(0.5), &written, OutputFormat::Xml, &options).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0]
+            .to_string()
+            .starts_with("~ Tree/Leaf.Transparency"));
+    }
+
+    #[test]
+    fn finds_the_first_divergence() {
+        assert_eq!(first_divergence(b"<a>\n<b/>", b"<a>\n<b/>"), None);
+
+        let divergence = first_divergence(b"<a>\n  <b x=\"1\"/>", b"<a>\n  <b x=\"2\"/>").unwrap();
+        assert_eq!(divergence.offset, 12);
+        assert_eq!((divergence.line, divergence.column), (2, 9));
+
+        assert_eq!(first_divergence(b"<a/>", b"<a/>\n").unwrap().offset, 4);
+    }
+}
@@ -0,0 +1,105 @@
+//! Building, checking and decompiling projects held in memory, without
+//! touching the file system. This is what the language bindings call.
+
+use crate::decompile::decompile;
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::error::Result;
+use crate::input;
+use crate::options::{BuildOptions, RootHandling};
+use crate::output::{self, OutputFormat};
+use crate::project::Project;
+
+/// Builds a project from its JSON the way the CLI does by default, with the
+/// project's defines filled into scripts, and returns the place or model.
+pub fn build_json(
+    json: &str,
+    format: OutputFormat,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Vec<u8>> {
+    let project = Project::from_json(json)?;
+    let options = BuildOptions::for_project(&project, false);
+    let dom = crate::build(project, &options, diagnostics)?;
+
+    let mut bytes = Vec::new();
+    output::write_dom(&mut bytes, &dom, format, &options)?;
+    Ok(bytes)
+}
+
+/// Builds a project without serializing it, returning the warnings. Fails
+/// the same way [`build_json`] would.
+pub fn validate_json(json: &str) -> Result<Vec<Diagnostic>> {
+    let project = Project::from_json(json)?;
+    let options = BuildOptions::for_project(&project, false);
+
+    let mut diagnostics = Vec::new();
+    crate::build(project, &options, &mut |diagnostic: Diagnostic| {
+        diagnostics.push(diagnostic)
+    })?;
+    Ok(diagnostics)
+}
+
+/// Turns a serialized place or model into project JSON, with every map
+/// sorted so the same file always gives the same output.
+pub fn decompile_bytes(
+    bytes: &[u8],
+    format: OutputFormat,
+    name: &str,
+    root: RootHandling,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<String> {
+    let dom = input::read_dom(bytes, format)?;
+    let project = decompile(&dom, name, root, diagnostics);
+    Ok(serde_json::to_string_pretty(&serde_json::to_value(
+        &project,
+    )?)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builds_projects_in_memory() {
+        let json = r#"{
+            "name": "Tree",
+            "tree": {
+                "$className": "Model",
+                "Trunk": { "$className": "Part", "$properties": { "Anchored": true } }
+            }
+        }"#;
+        let mut ignore = |_: Diagnostic| {};
+
+        let binary = build_json(json, OutputFormat::Binary, &mut ignore).unwrap();
+        assert!(binary.starts_with(b"<roblox!"));
+
+        let xml = build_json(json, OutputFormat::Xml, &mut ignore).unwrap();
+        assert!(String::from_utf8(xml).unwrap().contains("Trunk"));
+
+        assert!(build_json("{", OutputFormat::Binary, &mut ignore).is_err());
+    }
+
+    #[test]
+    fn validates_and_decompiles() {
+        let json = r#"{
+            "name": "Rock",
+            "tree": { "$className": "Part", "$properties": { "Nope": 1 } }
+        }"#;
+
+        let warnings = validate_json(json).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].property.as_deref(), Some("Nope"));
+
+        let mut ignore = |_: Diagnostic| {};
+        let bytes = build_json(json, OutputFormat::Binary, &mut ignore).unwrap();
+        let decompiled = decompile_bytes(
+            &bytes,
+            OutputFormat::Binary,
+            "Rock",
+            RootHandling::Model,
+            &mut ignore,
+        )
+        .unwrap();
+        let project = Project::from_json(&decompiled).unwrap();
+        assert_eq!(project.tree.class_name.as_deref(), Some("Part"));
+    }
+}
@@ -0,0 +1,79 @@
+use thiserror::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the library API.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The project JSON could not be parsed.
+    #[error("Could not parse project: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    /// A config file could not be parsed.
+    #[cfg(feature = "config")]
+    #[error("Could not parse config file {}: {source}", path.display())]
+    Config {
+        path: std::path::PathBuf,
+        source: toml::de::Error,
+    },
+
+    /// The build's settings name something that doesn't exist or can't be
+    /// used together.
+    #[cfg(feature = "config")]
+    #[error("{0}")]
+    Options(String),
+
+    /// The reflection database could not be loaded.
+    #[error("Could not load the reflection database: {0}")]
+    Database(String),
+
+    /// A property value could not be resolved. Only returned in strict mode;
+    /// otherwise these are reported as warnings.
+    #[error("Failed to resolve property {class}.{property}: {reason}")]
+    Resolve {
+        class: String,
+        property: String,
+        reason: String,
+    },
+
+    /// A class name only matched a known class with different case. Only
+    /// returned in strict mode; otherwise it's corrected with a warning.
+    #[error("Unknown class {class} at {path}; did you mean {suggestion}?")]
+    ClassNameCase {
+        path: String,
+        class: String,
+        suggestion: String,
+    },
+
+    /// A transform in the pipeline failed.
+    #[error("Transform {name} failed: {reason}")]
+    Transform { name: String, reason: String },
+
+    /// The project's collision groups can't be written to a place.
+    #[error("Invalid collision groups: {0}")]
+    CollisionGroups(String),
+
+    /// The build went over one of its [`Limits`](crate::limits::Limits).
+    #[error("{0}")]
+    Limit(String),
+
+    /// A pre- or post-build hook couldn't be run or exited with an error.
+    #[error("Hook {command:?} failed: {reason}")]
+    Hook { command: String, reason: String },
+
+    /// A place or model file could not be read.
+    #[error("Could not read input: {0}")]
+    Deserialize(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// The built tree could not be written in the requested format.
+    #[error("Could not serialize output: {0}")]
+    Serialize(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// Open Cloud refused an upload or couldn't be reached.
+    #[cfg(feature = "publish")]
+    #[error("Could not publish: {0}")]
+    Publish(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
@@ -0,0 +1,471 @@
+//! An interactive terminal browser for a built DOM, for debugging how a
+//! project was resolved without digging through the written file.
+//!
+//! [`Explorer`] holds the browsing state and doesn't touch the terminal;
+//! [`run`] draws it with `ratatui` and feeds it key presses until the user
+//! quits.
+
+use std::{collections::HashSet, io};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Row as TableRow, Table},
+    DefaultTerminal, Frame,
+};
+use rbx_dom_weak::{types::Ref, WeakDom};
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::query::property_value;
+
+const HELP: &str = " ↑↓ move  → expand  ← collapse  tab diagnostics  n next diagnostic  q quit ";
+
+/// Which pane the arrow keys move in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Tree,
+    Diagnostics,
+}
+
+/// A visible line of the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Row {
+    pub referent: Ref,
+    pub depth: usize,
+}
+
+/// What's expanded and selected while browsing a DOM.
+pub struct Explorer<'a> {
+    dom: &'a WeakDom,
+    expanded: HashSet<Ref>,
+    rows: Vec<Row>,
+    selected: usize,
+
+    /// Each diagnostic with the instance it's about, if it could be found.
+    diagnostics: Vec<(Diagnostic, Option<Ref>)>,
+    /// None until a diagnostic has been selected.
+    selected_diagnostic: Option<usize>,
+    focus: Focus,
+}
+
+impl<'a> Explorer<'a> {
+    /// Starts with the root expanded and selected.
+    pub fn new(dom: &'a WeakDom, diagnostics: Vec<Diagnostic>) -> Self {
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|diagnostic| {
+                let referent = find_path(dom, &diagnostic.path);
+                (diagnostic, referent)
+            })
+            .collect();
+
+        let mut explorer = Explorer {
+            dom,
+            expanded: HashSet::from([dom.root_ref()]),
+            rows: Vec::new(),
+            selected: 0,
+            diagnostics,
+            selected_diagnostic: None,
+            focus: Focus::Tree,
+        };
+        explorer.refresh_rows();
+        explorer
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    pub fn selected(&self) -> Ref {
+        self.rows[self.selected].referent
+    }
+
+    pub fn focus(&self) -> Focus {
+        self.focus
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::Tree if !self.diagnostics.is_empty() => {
+                self.selected_diagnostic.get_or_insert(0);
+                Focus::Diagnostics
+            }
+            _ => Focus::Tree,
+        };
+    }
+
+    /// Moves the selection in the focused pane, stopping at either end.
+    pub fn move_by(&mut self, delta: isize) {
+        let step = |index: usize, len: usize| {
+            index
+                .saturating_add_signed(delta)
+                .min(len.saturating_sub(1))
+        };
+
+        match self.focus {
+            Focus::Tree => self.selected = step(self.selected, self.rows.len()),
+            Focus::Diagnostics => {
+                let index = self.selected_diagnostic.unwrap_or(0);
+                self.selected_diagnostic = Some(step(index, self.diagnostics.len()));
+            }
+        }
+    }
+
+    /// Shows the selected instance's children.
+    pub fn expand(&mut self) {
+        let referent = self.selected();
+        if self.expanded.insert(referent) {
+            self.refresh_rows();
+        }
+    }
+
+    /// Hides the selected instance's children, or if they're already hidden,
+    /// selects its parent.
+    pub fn collapse(&mut self) {
+        let referent = self.selected();
+        if self.expanded.remove(&referent) {
+            self.refresh_rows();
+        } else if let Some(parent) = self.dom.get_by_ref(referent).map(|i| i.parent()) {
+            if let Some(index) = self.rows.iter().position(|row| row.referent == parent) {
+                self.selected = index;
+            }
+        }
+    }
+
+    /// Expands everything above an instance and selects it.
+    pub fn reveal(&mut self, referent: Ref) {
+        let dom = self.dom;
+        let mut current = dom
+            .get_by_ref(referent)
+            .and_then(|instance| dom.get_by_ref(instance.parent()));
+        while let Some(ancestor) = current {
+            self.expanded.insert(ancestor.referent());
+            current = dom.get_by_ref(ancestor.parent());
+        }
+
+        self.refresh_rows();
+        if let Some(index) = self.rows.iter().position(|row| row.referent == referent) {
+            self.selected = index;
+        }
+    }
+
+    /// Selects the instance the selected diagnostic is about.
+    pub fn jump_to_diagnostic(&mut self) {
+        let Some(index) = self.selected_diagnostic else {
+            return;
+        };
+        if let Some(&(_, Some(referent))) = self.diagnostics.get(index) {
+            self.reveal(referent);
+            self.focus = Focus::Tree;
+        }
+    }
+
+    /// Selects the next diagnostic, wrapping around, and jumps to it.
+    pub fn next_diagnostic(&mut self) {
+        if self.diagnostics.is_empty() {
+            return;
+        }
+
+        let len = self.diagnostics.len();
+        self.selected_diagnostic = Some(
+            self.selected_diagnostic
+                .map_or(0, |index| (index + 1) % len),
+        );
+        self.jump_to_diagnostic();
+    }
+
+    /// The selected instance's properties as name and value, sorted by name.
+    /// Values are in the same shorthand a project uses.
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let Some(instance) = self.dom.get_by_ref(self.selected()) else {
+            return Vec::new();
+        };
+
+        let mut names: Vec<_> = instance
+            .properties
+            .keys()
+            .map(|name| name.as_str())
+            .collect();
+        names.sort_unstable();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let value = property_value(instance, name)
+                    .map(|value| value.to_string())
+                    .unwrap_or_default();
+                (name.to_owned(), value)
+            })
+            .collect()
+    }
+
+    fn refresh_rows(&mut self) {
+        let selected = self.rows.get(self.selected).map(|row| row.referent);
+
+        self.rows.clear();
+        let mut stack = vec![(self.dom.root_ref(), 0)];
+        while let Some((referent, depth)) = stack.pop() {
+            let Some(instance) = self.dom.get_by_ref(referent) else {
+                continue;
+            };
+            self.rows.push(Row { referent, depth });
+
+            if self.expanded.contains(&referent) {
+                // Pushed in reverse so children come out in order
+                for &child in instance.children().iter().rev() {
+                    stack.push((child, depth + 1));
+                }
+            }
+        }
+
+        // Keep the same instance selected, as long as it's still visible
+        self.selected = selected
+            .and_then(|selected| self.rows.iter().position(|row| row.referent == selected))
+            .unwrap_or(0);
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let diagnostics_height = match self.diagnostics.len() {
+            0 => 0,
+            len => len.min(6) as u16 + 2,
+        };
+        let [main, diagnostics_area, help_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(diagnostics_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [tree_area, properties_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+
+        let highlight = Style::new().add_modifier(Modifier::REVERSED);
+
+        let items: Vec<_> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let instance = self.dom.get_by_ref(row.referent).unwrap();
+                let marker = match (
+                    instance.children().is_empty(),
+                    self.expanded.contains(&row.referent),
+                ) {
+                    (true, _) => "  ",
+                    (false, true) => "▾ ",
+                    (false, false) => "▸ ",
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw("  ".repeat(row.depth)),
+                    Span::raw(marker),
+                    Span::raw(instance.name.clone()),
+                    Span::raw(format!(" {}", instance.class)).dark_gray(),
+                ]))
+            })
+            .collect();
+        let mut tree_state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(" Tree "))
+                .highlight_style(highlight),
+            tree_area,
+            &mut tree_state,
+        );
+
+        let properties = self.properties();
+        let name_width = properties
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0) as u16;
+        let rows = properties
+            .into_iter()
+            .map(|(name, value)| TableRow::new([Line::from(name).bold(), Line::from(value)]));
+        frame.render_widget(
+            Table::new(rows, [Constraint::Length(name_width), Constraint::Fill(1)])
+                .block(Block::bordered().title(" Properties ")),
+            properties_area,
+        );
+
+        if !self.diagnostics.is_empty() {
+            let items: Vec<_> = self
+                .diagnostics
+                .iter()
+                .map(|(diagnostic, _)| {
+                    let color = match diagnostic.severity {
+                        Severity::Warning => Color::Yellow,
+                        Severity::Error => Color::Red,
+                    };
+                    ListItem::new(Line::from(diagnostic.to_string()).fg(color))
+                })
+                .collect();
+            let mut diagnostics_state =
+                ListState::default().with_selected(self.selected_diagnostic);
+            frame.render_stateful_widget(
+                List::new(items)
+                    .block(Block::bordered().title(" Diagnostics "))
+                    .highlight_style(highlight),
+                diagnostics_area,
+                &mut diagnostics_state,
+            );
+        }
+
+        frame.render_widget(Line::from(HELP).dark_gray(), help_area);
+    }
+}
+
+/// Finds the instance a diagnostic's path refers to, or its closest ancestor
+/// that exists. Diagnostic paths are made of project keys, which are the
+/// instance names unless a node was renamed.
+fn find_path(dom: &WeakDom, path: &str) -> Option<Ref> {
+    let mut segments = path.split('/');
+    let root = dom.root();
+    if segments.next() != Some(root.name.as_str()) {
+        return None;
+    }
+
+    let mut current = root;
+    for segment in segments {
+        let child = current
+            .children()
+            .iter()
+            .filter_map(|&child| dom.get_by_ref(child))
+            .find(|child| child.name == segment);
+        match child {
+            Some(child) => current = child,
+            None => break,
+        }
+    }
+
+    Some(current.referent())
+}
+
+/// Opens the explorer in the terminal and returns once the user quits.
+pub fn run(dom: &WeakDom, diagnostics: Vec<Diagnostic>) -> io::Result<()> {
+    let mut explorer = Explorer::new(dom, diagnostics);
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut explorer, &mut terminal);
+    ratatui::restore();
+
+    result
+}
+
+fn event_loop(explorer: &mut Explorer, terminal: &mut DefaultTerminal) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| explorer.draw(frame))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => explorer.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => explorer.move_by(1),
+            KeyCode::PageUp => explorer.move_by(-20),
+            KeyCode::PageDown => explorer.move_by(20),
+            KeyCode::Tab => explorer.toggle_focus(),
+            KeyCode::Char('n') => explorer.next_diagnostic(),
+            KeyCode::Enter if explorer.focus() == Focus::Diagnostics => {
+                explorer.jump_to_diagnostic()
+            }
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter => explorer.expand(),
+            KeyCode::Left | KeyCode::Char('h') => explorer.collapse(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn sample() -> WeakDom {
+        WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_name("Game")
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_name("Workspace")
+                        .with_child(
+                            InstanceBuilder::new("Model")
+                                .with_name("House")
+                                .with_child(InstanceBuilder::new("Part").with_name("Door")),
+                        ),
+                )
+                .with_child(InstanceBuilder::new("Lighting").with_name("Lighting")),
+        )
+    }
+
+    fn names(explorer: &Explorer) -> Vec<String> {
+        explorer
+            .rows()
+            .iter()
+            .map(|row| {
+                let name = &explorer.dom.get_by_ref(row.referent).unwrap().name;
+                format!("{}{name}", "  ".repeat(row.depth))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn expands_and_collapses() {
+        let dom = sample();
+        let mut explorer = Explorer::new(&dom, Vec::new());
+        assert_eq!(names(&explorer), ["Game", "  Workspace", "  Lighting"]);
+
+        explorer.move_by(1);
+        explorer.expand();
+        assert_eq!(
+            names(&explorer),
+            ["Game", "  Workspace", "    House", "  Lighting"]
+        );
+
+        // Collapsing a collapsed instance selects its parent
+        explorer.move_by(1);
+        explorer.collapse();
+        assert_eq!(explorer.selected(), dom.root().children()[0]);
+
+        explorer.collapse();
+        assert_eq!(names(&explorer), ["Game", "  Workspace", "  Lighting"]);
+
+        explorer.move_by(-10);
+        assert_eq!(explorer.selected(), dom.root_ref());
+    }
+
+    #[test]
+    fn jumps_to_diagnostics() {
+        let dom = sample();
+        let diagnostics = vec![
+            Diagnostic::warning("Game/Workspace/House/Door", "Unknown property")
+                .with_property("Colour"),
+            // The missing node falls back to its parent
+            Diagnostic::error("Game/Lighting/Sky", "Unknown class"),
+        ];
+        let mut explorer = Explorer::new(&dom, diagnostics);
+
+        explorer.next_diagnostic();
+        assert_eq!(
+            names(&explorer),
+            [
+                "Game",
+                "  Workspace",
+                "    House",
+                "      Door",
+                "  Lighting"
+            ]
+        );
+        assert_eq!(explorer.rows()[explorer.selected].depth, 3);
+
+        explorer.next_diagnostic();
+        assert_eq!(explorer.selected(), dom.root().children()[1]);
+        assert_eq!(explorer.focus(), Focus::Tree);
+    }
+}
@@ -0,0 +1,418 @@
+//! Build-time arithmetic in numeric property values.
+//!
+//! Any number inside a property value may be written as a string starting with
+//! `=`, for example `"Size": ["=4*3", 1, "=base+2"]`. Expressions support
+//! `+ - * / %`, parentheses, unary minus, the functions `abs`, `floor`, `ceil`,
+//! `round`, `sqrt`, `min` and `max`, and identifiers naming numeric defines.
+//!
+//! Expressions are only evaluated when a value doesn't resolve as written, so
+//! string properties whose contents happen to start with `=` are unaffected.
+
+use anyhow::{bail, format_err};
+use rbx_dom_weak::types::Variant;
+
+use crate::defines::Defines;
+use crate::resolution::{AmbiguousValue, NumberOrExpression, UnresolvedValue};
+
+/// Resolves a property value, falling back to evaluating any expressions it
+/// contains if the value can't be resolved as written.
+pub fn resolve(
+    value: UnresolvedValue,
+    class_name: &str,
+    prop_name: &str,
+    defines: &Defines,
+) -> anyhow::Result<Variant> {
+    // Only values that look like expressions need to be kept around for a
+    // second attempt; everything else (like large Source strings) is moved
+    // straight into the resolved Variant.
+    if !has_expressions(&value) {
+        return value.resolve(class_name, prop_name);
+    }
+
+    let error = match value.clone().resolve(class_name, prop_name) {
+        Ok(variant) => return Ok(variant),
+        Err(error) => error,
+    };
+
+    match evaluate_value(&value, defines)? {
+        Some(evaluated) => evaluated.resolve(class_name, prop_name),
+        None => Err(error),
+    }
+}
+
+/// Whether `evaluate_value` would try to evaluate anything in the value.
+fn has_expressions(value: &UnresolvedValue) -> bool {
+    match value {
+        UnresolvedValue::Ambiguous(AmbiguousValue::String(source)) => is_expression(source),
+        UnresolvedValue::Ambiguous(AmbiguousValue::StringArray(items)) => {
+            items.iter().all(|item| is_expression(item))
+        }
+        UnresolvedValue::Ambiguous(AmbiguousValue::ExpressionArray(_)) => true,
+        _ => false,
+    }
+}
+
+/// Replaces expressions inside a value with the numbers they evaluate to.
+/// Returns `None` if the value contains no expressions.
+fn evaluate_value(
+    value: &UnresolvedValue,
+    defines: &Defines,
+) -> anyhow::Result<Option<UnresolvedValue>> {
+    let ambiguous = match value {
+        UnresolvedValue::Ambiguous(ambiguous) => ambiguous,
+        UnresolvedValue::FullyQualified(_) => return Ok(None),
+    };
+
+    let evaluated = match ambiguous {
+        AmbiguousValue::String(source) if is_expression(source) => {
+            AmbiguousValue::Number(evaluate(&source[1..], defines)?)
+        }
+        AmbiguousValue::StringArray(items) if items.iter().all(|item| is_expression(item)) => {
+            let numbers = items
+                .iter()
+                .map(|item| evaluate(&item[1..], defines))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            number_array(numbers)?
+        }
+        AmbiguousValue::ExpressionArray(items) => {
+            let numbers = items
+                .iter()
+                .map(|item| match item {
+                    NumberOrExpression::Number(value) => Ok(*value),
+                    NumberOrExpression::Expression(source) if is_expression(source) => {
+                        evaluate(&source[1..], defines)
+                    }
+                    NumberOrExpression::Expression(source) => Err(format_err!(
+                        "Expected a number or an expression starting with '=', got {:?}",
+                        source
+                    )),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            number_array(numbers)?
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(UnresolvedValue::Ambiguous(evaluated)))
+}
+
+fn is_expression(value: &str) -> bool {
+    value.starts_with('=')
+}
+
+fn number_array(numbers: Vec<f64>) -> anyhow::Result<AmbiguousValue> {
+    Ok(match numbers.len() {
+        2 => AmbiguousValue::Array2([numbers[0], numbers[1]]),
+        3 => AmbiguousValue::Array3([numbers[0], numbers[1], numbers[2]]),
+        4 => AmbiguousValue::Array4([numbers[0], numbers[1], numbers[2], numbers[3]]),
+        12 => {
+            let mut array = [0.0; 12];
+            array.copy_from_slice(&numbers);
+            AmbiguousValue::Array12(array)
+        }
+        len => bail!("Arrays of expressions must have 2, 3, 4 or 12 elements, got {len}"),
+    })
+}
+
+/// Evaluates a single expression (without its leading `=`).
+pub fn evaluate(source: &str, defines: &Defines) -> anyhow::Result<f64> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        defines,
+    };
+
+    let value = parser.expression()?;
+    if let Some(token) = parser.peek() {
+        bail!("Unexpected {} in expression {:?}", token.describe(), source);
+    }
+
+    Ok(value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Comma,
+    Open,
+    Close,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Number(value) => format!("number {value}"),
+            Token::Ident(name) => format!("identifier `{name}`"),
+            Token::Op(op) => format!("operator `{op}`"),
+            Token::Comma => "`,`".to_owned(),
+            Token::Open => "`(`".to_owned(),
+            Token::Close => "`)`".to_owned(),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek() {
+                if c.is_ascii_digit() || c == '.' {
+                    end = index + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            let text = &source[start..end];
+            let value = text
+                .parse()
+                .map_err(|_| format_err!("Invalid number {:?} in expression {:?}", text, source))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    end = index + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            tokens.push(Token::Ident(source[start..end].to_owned()));
+        } else {
+            chars.next();
+            tokens.push(match c {
+                '+' | '-' | '*' | '/' | '%' => Token::Op(c),
+                ',' => Token::Comma,
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => bail!("Unexpected character {:?} in expression {:?}", c, source),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    defines: &'a Defines,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> anyhow::Result<()> {
+        match self.next() {
+            Some(token) if *token == expected => Ok(()),
+            Some(token) => bail!("Expected {}, got {}", expected.describe(), token.describe()),
+            None => bail!("Expected {}, got end of expression", expected.describe()),
+        }
+    }
+
+    fn expression(&mut self) -> anyhow::Result<f64> {
+        let mut value = self.term()?;
+
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+            let op = *op;
+            self.position += 1;
+
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+
+        Ok(value)
+    }
+
+    fn term(&mut self) -> anyhow::Result<f64> {
+        let mut value = self.unary()?;
+
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek() {
+            let op = *op;
+            self.position += 1;
+
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> anyhow::Result<f64> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.position += 1;
+                Ok(-self.unary()?)
+            }
+            Some(Token::Op('+')) => {
+                self.position += 1;
+                self.unary()
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> anyhow::Result<f64> {
+        match self.next().cloned() {
+            Some(Token::Number(value)) => Ok(value),
+            Some(Token::Open) => {
+                let value = self.expression()?;
+                self.expect(Token::Close)?;
+                Ok(value)
+            }
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::Open) => {
+                self.position += 1;
+                let args = self.arguments()?;
+                call(&name, &args)
+            }
+            Some(Token::Ident(name)) => match self.defines.get(&name) {
+                Some(define) => define
+                    .as_number()
+                    .ok_or_else(|| format_err!("Define `{name}` is not a number")),
+                None => bail!("Unknown define `{name}`"),
+            },
+            Some(token) => bail!("Unexpected {} in expression", token.describe()),
+            None => bail!("Unexpected end of expression"),
+        }
+    }
+
+    fn arguments(&mut self) -> anyhow::Result<Vec<f64>> {
+        let mut args = Vec::new();
+
+        if self.peek() == Some(&Token::Close) {
+            self.position += 1;
+            return Ok(args);
+        }
+
+        loop {
+            args.push(self.expression()?);
+
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::Close) => return Ok(args),
+                Some(token) => bail!("Expected `,` or `)`, got {}", token.describe()),
+                None => bail!("Expected `)`, got end of expression"),
+            }
+        }
+    }
+}
+
+fn call(name: &str, args: &[f64]) -> anyhow::Result<f64> {
+    let unary = |f: fn(f64) -> f64| match args {
+        [value] => Ok(f(*value)),
+        _ => Err(format_err!("{name}() takes 1 argument, got {}", args.len())),
+    };
+
+    match name {
+        "abs" => unary(f64::abs),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "round" => unary(f64::round),
+        "sqrt" => unary(f64::sqrt),
+        "min" | "max" => {
+            if args.is_empty() {
+                bail!("{name}() takes at least 1 argument");
+            }
+
+            let fold: fn(f64, f64) -> f64 = if name == "min" { f64::min } else { f64::max };
+            Ok(args.iter().copied().reduce(fold).unwrap())
+        }
+        _ => bail!("Unknown function `{name}`"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::defines::DefineValue;
+
+    fn eval(source: &str) -> f64 {
+        let mut defines = Defines::new();
+        defines.insert("base".to_owned(), DefineValue::Number(2.0));
+
+        evaluate(source, &defines).unwrap()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(eval("4*3"), 12.0);
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("-4 / 2"), -2.0);
+        assert_eq!(eval("7 % 4"), 3.0);
+        assert_eq!(eval("0.5 * 3"), 1.5);
+    }
+
+    #[test]
+    fn defines_and_functions() {
+        assert_eq!(eval("base + 2"), 4.0);
+        assert_eq!(eval("max(1, base * 3, 5)"), 6.0);
+        assert_eq!(eval("floor(7 / base)"), 3.0);
+    }
+
+    #[test]
+    fn errors() {
+        let defines = Defines::new();
+
+        assert!(evaluate("1 +", &defines).is_err());
+        assert!(evaluate("(1", &defines).is_err());
+        assert!(evaluate("1 2", &defines).is_err());
+        assert!(evaluate("missing * 2", &defines).is_err());
+        assert!(evaluate("nope(1)", &defines).is_err());
+    }
+
+    #[test]
+    fn string_defines_are_not_numbers() {
+        let mut defines = Defines::new();
+        defines.insert("label".to_owned(), DefineValue::String("hi".to_owned()));
+
+        assert!(evaluate("label + 1", &defines).is_err());
+    }
+
+    #[test]
+    fn mixed_arrays() {
+        let mut defines = Defines::new();
+        defines.insert("base".to_owned(), DefineValue::Number(2.0));
+
+        let value: UnresolvedValue = serde_json::from_str(r#"["=4*3", 1, "=base+2"]"#).unwrap();
+        assert_eq!(
+            evaluate_value(&value, &defines).unwrap(),
+            Some(UnresolvedValue::Ambiguous(AmbiguousValue::Array3([
+                12.0, 1.0, 4.0
+            ])))
+        );
+    }
+
+    #[test]
+    fn plain_strings_are_left_alone() {
+        let value: UnresolvedValue = serde_json::from_str(r#""hello""#).unwrap();
+        assert!(!has_expressions(&value));
+        assert_eq!(evaluate_value(&value, &Defines::new()).unwrap(), None);
+    }
+}
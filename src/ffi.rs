@@ -0,0 +1,182 @@
+//! C ABI for calling the builder in-process from C or C++, declared in
+//! `include/rbxbuild.h`.
+//!
+//! ```c
+//! RbxbuildBuffer place;
+//! if (rbxbuild_build(json, json_len, RBXBUILD_FORMAT_BINARY, &place) != RBXBUILD_OK) {
+//!     fprintf(stderr, "%s\n", rbxbuild_last_error());
+//!     return 1;
+//! }
+//! fwrite(place.data, 1, place.len, file);
+//! rbxbuild_buffer_free(place);
+//! ```
+//!
+//! Every function can be called from any thread. The header is generated
+//! with `cbindgen --config cbindgen.toml --output include/rbxbuild.h`.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use crate::diagnostics::Diagnostic;
+use crate::embed;
+use crate::output::OutputFormat;
+
+/// The call succeeded.
+pub const RBXBUILD_OK: i32 = 0;
+/// A pointer was null, the JSON wasn't UTF-8 or the format was unknown.
+pub const RBXBUILD_INVALID_ARGUMENT: i32 = 1;
+/// The project didn't build; see [`rbxbuild_last_error`].
+pub const RBXBUILD_BUILD_FAILED: i32 = 2;
+/// rbxbuild panicked. This is a bug.
+pub const RBXBUILD_PANICKED: i32 = 3;
+
+pub const RBXBUILD_FORMAT_BINARY: u32 = 0;
+pub const RBXBUILD_FORMAT_XML: u32 = 1;
+
+/// Bytes owned by rbxbuild, which have to be released with
+/// [`rbxbuild_buffer_free`].
+#[repr(C)]
+pub struct RbxbuildBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    // Interior NULs would cut the message short in C, so drop them
+    let message = message.into().replace('\0', "");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+}
+
+/// Builds the project in the `len` bytes of UTF-8 JSON at `json` and writes
+/// the place or model, in `format`, to `out`. Warnings are left out; a
+/// project that fails to build returns [`RBXBUILD_BUILD_FAILED`].
+///
+/// # Safety
+///
+/// `json` has to point to `len` readable bytes, and `out` to a writable
+/// [`RbxbuildBuffer`].
+#[no_mangle]
+pub unsafe extern "C" fn rbxbuild_build(
+    json: *const u8,
+    len: usize,
+    format: u32,
+    out: *mut RbxbuildBuffer,
+) -> i32 {
+    if json.is_null() || out.is_null() {
+        set_last_error("json and out can't be null");
+        return RBXBUILD_INVALID_ARGUMENT;
+    }
+    let format = match format {
+        RBXBUILD_FORMAT_BINARY => OutputFormat::Binary,
+        RBXBUILD_FORMAT_XML => OutputFormat::Xml,
+        other => {
+            set_last_error(format!("Unknown output format {other}"));
+            return RBXBUILD_INVALID_ARGUMENT;
+        }
+    };
+    let json = match std::str::from_utf8(slice::from_raw_parts(json, len)) {
+        Ok(json) => json,
+        Err(error) => {
+            set_last_error(format!("The project isn't UTF-8: {error}"));
+            return RBXBUILD_INVALID_ARGUMENT;
+        }
+    };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        embed::build_json(json, format, &mut |_: Diagnostic| {})
+    }));
+
+    match result {
+        Ok(Ok(bytes)) => {
+            let bytes = Box::into_raw(bytes.into_boxed_slice());
+            *out = RbxbuildBuffer {
+                data: bytes.cast(),
+                len: bytes.len(),
+            };
+            RBXBUILD_OK
+        }
+        Ok(Err(error)) => {
+            set_last_error(error.to_string());
+            RBXBUILD_BUILD_FAILED
+        }
+        Err(_) => {
+            set_last_error("rbxbuild panicked");
+            RBXBUILD_PANICKED
+        }
+    }
+}
+
+/// Releases a buffer returned by [`rbxbuild_build`]. Freeing an empty buffer
+/// does nothing.
+///
+/// # Safety
+///
+/// `buffer` has to come from rbxbuild and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn rbxbuild_buffer_free(buffer: RbxbuildBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// The message of the last error on this thread, or null if there wasn't
+/// one. Valid until the next call into rbxbuild on the same thread.
+#[no_mangle]
+pub extern "C" fn rbxbuild_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::CStr;
+
+    use super::*;
+
+    fn build(json: &str, format: u32) -> (i32, Vec<u8>) {
+        let mut out = RbxbuildBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+
+        unsafe {
+            let status = rbxbuild_build(json.as_ptr(), json.len(), format, &mut out);
+            let bytes = if out.data.is_null() {
+                Vec::new()
+            } else {
+                slice::from_raw_parts(out.data, out.len).to_vec()
+            };
+            rbxbuild_buffer_free(out);
+            (status, bytes)
+        }
+    }
+
+    #[test]
+    fn builds_through_the_c_abi() {
+        let json = r#"{ "name": "Rock", "tree": { "$className": "Part" } }"#;
+
+        let (status, bytes) = build(json, RBXBUILD_FORMAT_BINARY);
+        assert_eq!(status, RBXBUILD_OK);
+        assert!(bytes.starts_with(b"<roblox!"));
+
+        assert_eq!(build(json, 7).0, RBXBUILD_INVALID_ARGUMENT);
+
+        assert_eq!(build("{", RBXBUILD_FORMAT_XML).0, RBXBUILD_BUILD_FAILED);
+        let message = unsafe { CStr::from_ptr(rbxbuild_last_error()) }.to_string_lossy();
+        assert!(message.starts_with("Could not parse project"));
+    }
+}
@@ -0,0 +1,269 @@
+//! Passes that leave parts of a place out of a build.
+//!
+//! One project can describe a whole game while a build only ships part of it,
+//! such as a client-only artifact without ServerStorage and
+//! ServerScriptService, an asset bundle with nothing but Workspace and
+//! ReplicatedStorage, or art without any scripts. Nodes that only some builds
+//! need, such as QA fixtures, can be marked with `$buildTags` and are left
+//! out unless a build asks for them.
+
+use rbx_dom_weak::WeakDom;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::project::ProjectNode;
+use crate::query::descendants;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+/// Removes the nodes below `node` whose `$buildTags` weren't asked for. A
+/// node with build tags is kept when one of them is in `with` and none of
+/// them are in `without`; nodes without tags are always kept.
+pub fn filter_build_tags(node: &mut ProjectNode, with: &[String], without: &[String]) {
+    // A stack rather than recursion, so deep trees don't overflow it
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        node.children.retain(|_, child| {
+            child.build_tags.is_empty()
+                || (child.build_tags.iter().any(|tag| with.contains(tag))
+                    && !child.build_tags.iter().any(|tag| without.contains(tag)))
+        });
+        stack.extend(node.children.values_mut());
+    }
+}
+
+/// Keeps or removes services of a place by name. With `only`, every service
+/// it doesn't name is removed; `exclude` removes the ones it names after that.
+#[derive(Debug, Clone, Default)]
+pub struct FilterServices {
+    pub only: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl FilterServices {
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.exclude.is_empty()
+    }
+
+    fn keeps(&self, name: &str) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|only| only == name))
+            && !self.exclude.iter().any(|exclude| exclude == name)
+    }
+}
+
+impl Transform for FilterServices {
+    fn name(&self) -> &str {
+        "filter-services"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let root = dom.root();
+        if root.class != "DataModel" {
+            diagnostics.report(Diagnostic::warning(
+                root.name.clone(),
+                "The service filters were ignored, since the root isn't a DataModel",
+            ));
+            return Ok(());
+        }
+
+        // A misspelled service would otherwise quietly keep or remove
+        // nothing
+        for name in self.only.iter().chain(&self.exclude) {
+            let found = root.children().iter().any(|&child| {
+                dom.get_by_ref(child)
+                    .is_some_and(|child| child.name == *name)
+            });
+            if !found {
+                diagnostics.report(Diagnostic::warning(
+                    root.name.clone(),
+                    format!("The service filters name {name}, which isn't in the place"),
+                ));
+            }
+        }
+
+        let removed: Vec<_> = root
+            .children()
+            .iter()
+            .copied()
+            .filter(|&child| {
+                dom.get_by_ref(child)
+                    .is_some_and(|child| !self.keeps(&child.name))
+            })
+            .collect();
+        for child in removed {
+            dom.destroy(child);
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes every instance of the given classes. Their children are moved up
+/// to take their place, unless `descendants` is set, in which case they're
+/// removed too.
+#[derive(Debug, Clone, Default)]
+pub struct StripClasses {
+    pub classes: Vec<String>,
+    pub descendants: bool,
+}
+
+impl Transform for StripClasses {
+    fn name(&self) -> &str {
+        "strip-classes"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let root = dom.root_ref();
+        let stripped_class = |class: &str| self.classes.iter().any(|name| name == class);
+        if stripped_class(&dom.root().class) {
+            diagnostics.report(Diagnostic::warning(
+                dom.root().name.clone(),
+                format!(
+                    "The root is a {}, but was kept since there'd be nothing left without it",
+                    dom.root().class
+                ),
+            ));
+        }
+
+        // Parents come before their children, so a child that was already
+        // removed along with its parent is skipped
+        let stripped: Vec<_> = descendants(dom, root)
+            .filter(|instance| instance.referent() != root && stripped_class(&instance.class))
+            .map(|instance| instance.referent())
+            .collect();
+
+        for referent in stripped {
+            let Some(instance) = dom.get_by_ref(referent) else {
+                continue;
+            };
+            if !self.descendants {
+                let parent = instance.parent();
+                for child in instance.children().to_vec() {
+                    dom.transfer_within(child, parent);
+                }
+            }
+            dom.destroy(referent);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn filters_services() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_name("Game"));
+        for service in [
+            "Workspace",
+            "ReplicatedStorage",
+            "ServerStorage",
+            "Lighting",
+        ] {
+            dom.insert(
+                dom.root_ref(),
+                InstanceBuilder::new(service).with_name(service),
+            );
+        }
+
+        let filter = FilterServices {
+            only: vec!["Workspace".to_owned(), "ReplicatedStorage".to_owned()],
+            exclude: vec!["ReplicatedStorage".to_owned(), "StarterGui".to_owned()],
+        };
+        let mut diagnostics = Vec::new();
+        filter
+            .transform_dom(&mut dom, &mut |diagnostic: Diagnostic| {
+                diagnostics.push(diagnostic)
+            })
+            .unwrap();
+
+        let kept: Vec<_> = dom
+            .root()
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap().name.as_str())
+            .collect();
+        assert_eq!(kept, ["Workspace"]);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("StarterGui"));
+    }
+
+    #[test]
+    fn strips_classes() {
+        let build = || {
+            let mut dom = WeakDom::new(InstanceBuilder::new("Model").with_name("Tower"));
+            let script = dom.insert(
+                dom.root_ref(),
+                InstanceBuilder::new("Script").with_name("Spin"),
+            );
+            dom.insert(script, InstanceBuilder::new("Part").with_name("Blade"));
+            dom.insert(
+                script,
+                InstanceBuilder::new("LocalScript").with_name("Effects"),
+            );
+            dom
+        };
+        let strip = |with_descendants: bool| -> Vec<String> {
+            let mut dom = build();
+            let strip = StripClasses {
+                classes: vec!["Script".to_owned(), "LocalScript".to_owned()],
+                descendants: with_descendants,
+            };
+            strip
+                .transform_dom(&mut dom, &mut |_: Diagnostic| {})
+                .unwrap();
+            descendants(&dom, dom.root_ref())
+                .map(|instance| instance.name.clone())
+                .collect()
+        };
+
+        assert_eq!(strip(false), ["Tower", "Blade"]);
+        assert_eq!(strip(true), ["Tower"]);
+    }
+
+    #[test]
+    fn filters_build_tags() {
+        let project = crate::project::Project::from_json(
+            r#"{
+                "tree": {
+                    "$className": "Folder",
+                    "Map": {},
+                    "Fixtures": {
+                        "$buildTags": ["qa"],
+                        "Bot": {},
+                        "Profiler": { "$buildTags": ["qa", "profiling"] }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let filter = |with: &[&str], without: &[&str]| {
+            let with: Vec<_> = with.iter().map(|tag| tag.to_string()).collect();
+            let without: Vec<_> = without.iter().map(|tag| tag.to_string()).collect();
+            let mut tree = project.tree.clone();
+            filter_build_tags(&mut tree, &with, &without);
+            tree
+        };
+
+        let shipped = filter(&[], &[]);
+        assert!(shipped.children.contains_key("Map"));
+        assert!(!shipped.children.contains_key("Fixtures"));
+
+        let qa = filter(&["qa"], &[]);
+        assert!(qa.children["Fixtures"].children.contains_key("Profiler"));
+
+        let qa = filter(&["qa"], &["profiling"]);
+        assert!(qa.children["Fixtures"].children.contains_key("Bot"));
+        assert!(!qa.children["Fixtures"].children.contains_key("Profiler"));
+    }
+}
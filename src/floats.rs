@@ -0,0 +1,408 @@
+//! Passes over the floating point numbers in a built tree.
+//!
+//! Values computed by generators or converted from other tools often carry
+//! long decimal tails, like `0.30000000000000004` for `0.1 + 0.2`. Rounding
+//! them to a fixed number of decimal places before the tree is written keeps
+//! the output short and the diffs between builds small. The serializers already
+//! write the shortest text that reads back as the same number, so a rounded
+//! value comes out as written.
+//!
+//! Generated inputs also occasionally contain NaN or infinity, which Studio
+//! handles badly. [`NonFiniteFloats`] decides what happens to them.
+//!
+//! [`CanonicalFloats`] makes numbers that are equal come out as the same
+//! bytes, for deterministic builds.
+
+use rbx_dom_weak::{types::Variant, WeakDom};
+use serde::Deserialize;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::query;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum NonFinitePolicy {
+    /// Fail the build.
+    Error,
+
+    /// Replace NaN with 0 and infinity with the largest finite value the
+    /// property can hold.
+    Clamp,
+
+    /// Write the values as they are.
+    #[default]
+    PassThrough,
+}
+
+/// Reports every property holding NaN or infinity, anywhere in its value,
+/// and handles it according to the policy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NonFiniteFloats(pub NonFinitePolicy);
+
+impl Transform for NonFiniteFloats {
+    fn name(&self) -> &str {
+        "non-finite-floats"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let mut found = Vec::new();
+
+        for instance in query::descendants(dom, dom.root_ref()) {
+            let mut properties: Vec<_> = instance.properties.iter().collect();
+            properties.sort_by_key(|(key, _)| key.as_str());
+
+            for (key, value) in properties {
+                let mut nan = false;
+                let mut infinite = false;
+                let mut clamped = value.clone();
+                map_floats(&mut clamped, &mut |value, max| {
+                    if value.is_nan() {
+                        nan = true;
+                        0.0
+                    } else if value.is_infinite() {
+                        infinite = true;
+                        max.copysign(value)
+                    } else {
+                        value
+                    }
+                });
+
+                let what = match (nan, infinite) {
+                    (false, false) => continue,
+                    (true, false) => "NaN",
+                    (false, true) => "infinity",
+                    (true, true) => "NaN and infinity",
+                };
+                found.push((instance.referent(), *key, what, clamped));
+            }
+        }
+
+        let count = found.len();
+        for (referent, key, what, clamped) in found {
+            let path = query::path_of(dom, referent);
+            let diagnostic = match self.0 {
+                NonFinitePolicy::Error => {
+                    Diagnostic::error(path, format!("The value contains {what}"))
+                }
+                NonFinitePolicy::Clamp => Diagnostic::warning(
+                    path,
+                    format!("The value contains {what}, which was clamped to a finite value"),
+                ),
+                NonFinitePolicy::PassThrough => Diagnostic::warning(
+                    path,
+                    format!("The value contains {what}, which Studio may not handle"),
+                ),
+            };
+            diagnostics.report(diagnostic.with_property(key.as_str()));
+
+            if self.0 == NonFinitePolicy::Clamp {
+                let instance = dom.get_by_ref_mut(referent).unwrap();
+                instance.properties.insert(key, clamped);
+            }
+        }
+
+        match (self.0, count) {
+            (NonFinitePolicy::Error, 1) => Err("found a NaN or infinite property".into()),
+            (NonFinitePolicy::Error, 2..) => {
+                Err(format!("found {count} NaN or infinite properties").into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Rounds every float in every property to this many decimal places.
+///
+/// Covers numbers, vectors, colors, UDims, rects, ranges, sequences and the
+/// position of CFrames. The rotation of a CFrame is left alone, since
+/// rounding it would skew whatever it rotates.
+#[derive(Debug, Clone, Copy)]
+pub struct RoundFloats(pub u32);
+
+impl Transform for RoundFloats {
+    fn name(&self) -> &str {
+        "round-floats"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let scale = 10f64.powi(self.0 as i32);
+        let mut round = |value: f64, _| {
+            let rounded = (value * scale).round() / scale;
+            // Too large to have any decimals, or not a number at all
+            if rounded.is_finite() {
+                rounded
+            } else {
+                value
+            }
+        };
+
+        let referents: Vec<_> = crate::query::descendants(dom, dom.root_ref())
+            .map(|instance| instance.referent())
+            .collect();
+        for referent in referents {
+            let instance = dom.get_by_ref_mut(referent).unwrap();
+            for value in instance.properties.values_mut() {
+                map_floats(value, &mut round);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes negative zero as zero and every NaN as the same NaN, everywhere
+/// including a CFrame's rotation. Otherwise the same input can come out as
+/// `0` or `-0` in XML, or with different bits in binary files, depending on
+/// how a value was computed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CanonicalFloats;
+
+impl Transform for CanonicalFloats {
+    fn name(&self) -> &str {
+        "canonical-floats"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let referents: Vec<_> = crate::query::descendants(dom, dom.root_ref())
+            .map(|instance| instance.referent())
+            .collect();
+        for referent in referents {
+            let instance = dom.get_by_ref_mut(referent).unwrap();
+            for value in instance.properties.values_mut() {
+                map_floats(value, &mut |value, _| canonical(value));
+
+                // Left out by `map_floats`, since rounding would skew it,
+                // but a sign on a zero doesn't change anything
+                if let Variant::CFrame(cframe) = value {
+                    let rotation = &mut cframe.orientation;
+                    for axis in [&mut rotation.x, &mut rotation.y, &mut rotation.z] {
+                        for component in [&mut axis.x, &mut axis.y, &mut axis.z] {
+                            *component = canonical(*component as f64) as f32;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn canonical(value: f64) -> f64 {
+    if value.is_nan() {
+        f64::NAN
+    } else if value == 0.0 {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// Replaces every float in a value with `f` of it and the largest finite
+/// value its type can hold. Single precision floats go through `f64` and
+/// back, which is exact.
+pub(crate) fn map_floats(value: &mut Variant, f: &mut dyn FnMut(f64, f64) -> f64) {
+    let mut single = |value: &mut f32| *value = f(*value as f64, f32::MAX as f64) as f32;
+
+    match value {
+        Variant::Float32(value) => single(value),
+        Variant::Float64(value) => *value = f(*value, f64::MAX),
+        Variant::Vector2(vector) => {
+            single(&mut vector.x);
+            single(&mut vector.y);
+        }
+        Variant::Vector3(vector) => {
+            single(&mut vector.x);
+            single(&mut vector.y);
+            single(&mut vector.z);
+        }
+        Variant::CFrame(cframe) => {
+            single(&mut cframe.position.x);
+            single(&mut cframe.position.y);
+            single(&mut cframe.position.z);
+        }
+        Variant::Color3(color) => {
+            single(&mut color.r);
+            single(&mut color.g);
+            single(&mut color.b);
+        }
+        Variant::UDim(udim) => single(&mut udim.scale),
+        Variant::UDim2(udim) => {
+            single(&mut udim.x.scale);
+            single(&mut udim.y.scale);
+        }
+        Variant::Rect(rect) => {
+            single(&mut rect.min.x);
+            single(&mut rect.min.y);
+            single(&mut rect.max.x);
+            single(&mut rect.max.y);
+        }
+        Variant::NumberRange(range) => {
+            single(&mut range.min);
+            single(&mut range.max);
+        }
+        Variant::NumberSequence(sequence) => {
+            for keypoint in &mut sequence.keypoints {
+                single(&mut keypoint.time);
+                single(&mut keypoint.value);
+                single(&mut keypoint.envelope);
+            }
+        }
+        Variant::ColorSequence(sequence) => {
+            for keypoint in &mut sequence.keypoints {
+                single(&mut keypoint.time);
+                single(&mut keypoint.color.r);
+                single(&mut keypoint.color.g);
+                single(&mut keypoint.color.b);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rbx_dom_weak::{
+        types::{CFrame, Matrix3, Vector3},
+        InstanceBuilder,
+    };
+    use ustr::Ustr;
+
+    #[test]
+    fn rounds_floats() {
+        let rotation = Matrix3::new(
+            Vector3::new(0.70710677, 0.0, 0.70710677),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(-0.70710677, 0.0, 0.70710677),
+        );
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Part")
+                .with_property("Transparency", 0.1f64 + 0.2f64)
+                .with_property("Size", Vector3::new(4.000001, 1.0 / 3.0, 2.0))
+                .with_property(
+                    "CFrame",
+                    CFrame::new(Vector3::new(10.123456, 0.0, 0.0), rotation),
+                ),
+        );
+
+        RoundFloats(3)
+            .transform_dom(&mut dom, &mut |_: Diagnostic| {})
+            .unwrap();
+
+        let property = |name: &str| dom.root().properties[&Ustr::from(name)].clone();
+        assert_eq!(property("Transparency"), Variant::Float64(0.3));
+        assert_eq!(
+            property("Size"),
+            Variant::Vector3(Vector3::new(4.0, 0.333, 2.0))
+        );
+        assert_eq!(
+            property("CFrame"),
+            Variant::CFrame(CFrame::new(Vector3::new(10.123, 0.0, 0.0), rotation))
+        );
+    }
+
+    #[test]
+    fn canonicalizes_floats() {
+        let rotation = Matrix3::new(
+            Vector3::new(1.0, -0.0, 0.0),
+            Vector3::new(0.0, 1.0, -0.0),
+            Vector3::new(-0.0, 0.0, 1.0),
+        );
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Part")
+                .with_property("Transparency", -0.0f32)
+                .with_property("Size", Vector3::new(-0.0, -f32::NAN, 2.0))
+                .with_property(
+                    "CFrame",
+                    CFrame::new(Vector3::new(-0.0, 1.0, 0.0), rotation),
+                ),
+        );
+
+        CanonicalFloats
+            .transform_dom(&mut dom, &mut |_: Diagnostic| {})
+            .unwrap();
+
+        let property = |name: &str| dom.root().properties[&Ustr::from(name)].clone();
+        let Variant::Float32(transparency) = property("Transparency") else {
+            panic!("Transparency isn't a float");
+        };
+        assert!(transparency.is_sign_positive());
+
+        let Variant::Vector3(size) = property("Size") else {
+            panic!("Size isn't a Vector3");
+        };
+        assert!(size.x.is_sign_positive());
+        assert!(size.y.is_nan());
+
+        let Variant::CFrame(cframe) = property("CFrame") else {
+            panic!("CFrame isn't a CFrame");
+        };
+        assert!(cframe.position.x.is_sign_positive());
+        assert!(cframe.orientation.x.y.is_sign_positive());
+        assert!(cframe.orientation.z.x.is_sign_positive());
+    }
+
+    #[test]
+    fn handles_non_finite_floats() {
+        let build = || {
+            let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_name("Game"));
+            dom.insert(
+                dom.root_ref(),
+                InstanceBuilder::new("Part")
+                    .with_name("Broken")
+                    .with_property("Transparency", f32::NAN)
+                    .with_property("Size", Vector3::new(1.0, f32::NEG_INFINITY, 1.0))
+                    .with_property("Reflectance", 0.5f32),
+            );
+            dom
+        };
+        let run = |policy| {
+            let mut dom = build();
+            let mut diagnostics = Vec::new();
+            let result = NonFiniteFloats(policy)
+                .transform_dom(&mut dom, &mut |diagnostic: Diagnostic| {
+                    diagnostics.push(diagnostic)
+                });
+            (dom, diagnostics, result.is_ok())
+        };
+
+        let (_, diagnostics, ok) = run(NonFinitePolicy::Error);
+        assert!(!ok);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].path, "Game/Broken");
+        assert_eq!(diagnostics[0].property.as_deref(), Some("Size"));
+
+        let (dom, diagnostics, ok) = run(NonFinitePolicy::Clamp);
+        assert!(ok);
+        assert_eq!(diagnostics.len(), 2);
+        let part = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        assert_eq!(
+            part.properties[&Ustr::from("Transparency")],
+            Variant::Float32(0.0)
+        );
+        assert_eq!(
+            part.properties[&Ustr::from("Size")],
+            Variant::Vector3(Vector3::new(1.0, -f32::MAX, 1.0))
+        );
+
+        let (_, diagnostics, ok) = run(NonFinitePolicy::PassThrough);
+        assert!(ok);
+        assert_eq!(diagnostics.len(), 2);
+    }
+}
@@ -0,0 +1,200 @@
+//! Procedurally generated subtrees.
+//!
+//! A node can name a Luau script with `$generate`. The script runs at build
+//! time in a sandboxed Luau VM and returns a table in the same shape as a
+//! project node, which is merged into the node:
+//!
+//! ```json
+//! "Grid": { "$className": "Model", "$generate": "gen/grid.luau" }
+//! ```
+//!
+//! ```lua
+//! local node = { ["$className"] = "Model" }
+//! for i = 1, defines.tiles do
+//!     node["Tile" .. i] = {
+//!         ["$className"] = "Part",
+//!         ["$properties"] = { Position = { i * 4, 0, 0 } },
+//!     }
+//! end
+//! return node
+//! ```
+//!
+//! The project's defines are available to the script as the `defines` table,
+//! and the node's path as `path`. Anything the node sets itself takes
+//! precedence over what the script returns.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use mlua::{Lua, LuaSerdeExt};
+
+use crate::defines::Defines;
+use crate::diagnostics::DiagnosticSink;
+use crate::project::{Project, ProjectNode};
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+/// Runs the `$generate` script of every node in the project. Scripts are
+/// looked up relative to `base`.
+#[derive(Debug, Clone, Default)]
+pub struct GenerateNodes {
+    pub base: PathBuf,
+}
+
+impl GenerateNodes {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        GenerateNodes { base: base.into() }
+    }
+}
+
+impl Transform for GenerateNodes {
+    fn name(&self) -> &str {
+        "generate"
+    }
+
+    fn transform_project(
+        &self,
+        project: &mut Project,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let root_name = project.name.clone().unwrap_or_else(|| "ROOT".to_owned());
+        let mut stack = vec![(root_name, &mut project.tree)];
+
+        while let Some((path, node)) = stack.pop() {
+            if let Some(script) = node.generate.take() {
+                let generated = generate(&self.base.join(&script), &path, &project.defines)
+                    .map_err(|err| format!("{path}: {script}: {err}"))?;
+                merge(node, generated).map_err(|err| format!("{path}: {script}: {err}"))?;
+            }
+
+            for (name, child) in &mut node.children {
+                stack.push((format!("{path}/{name}"), child));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn generate(script: &Path, path: &str, defines: &Defines) -> HookResult<ProjectNode> {
+    let source = fs::read_to_string(script)?;
+
+    let lua = Lua::new();
+    let globals = lua.globals();
+    globals.set("defines", lua.to_value(defines)?)?;
+    globals.set("path", path)?;
+    lua.sandbox(true)?;
+
+    let result: mlua::Value = lua
+        .load(&source)
+        .set_name(script.display().to_string())
+        .eval()?;
+
+    // Going through JSON means the table is read exactly like the same node
+    // written in the project file
+    let json: serde_json::Value = lua.from_value(result)?;
+    Ok(serde_json::from_value(json)?)
+}
+
+fn merge(node: &mut ProjectNode, generated: ProjectNode) -> Result<(), String> {
+    if generated.generate.is_some() {
+        return Err("generated nodes can't use $generate".to_owned());
+    }
+
+    if node.class_name.is_none() {
+        node.class_name = generated.class_name;
+    }
+
+    if node.surface.is_none() {
+        node.surface = generated.surface;
+    }
+
+    if node.outfit.is_none() {
+        node.outfit = generated.outfit;
+    }
+
+    if node.build_tags.is_empty() {
+        node.build_tags = generated.build_tags;
+    }
+
+    if node.order.is_none() {
+        node.order = generated.order;
+    }
+
+    for (key, value) in generated.properties {
+        node.properties.entry(key).or_insert(value);
+    }
+
+    for (name, child) in generated.children {
+        node.children.entry(name).or_insert(child);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::defines::DefineValue;
+
+    #[test]
+    fn generates_children() {
+        let dir = std::env::temp_dir().join(format!("rbxbuild-generate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("grid.luau"),
+            r#"
+            local node = { ["$className"] = "Model" }
+            for i = 1, defines.tiles do
+                node["Tile" .. i] = { ["$className"] = "Part" }
+            end
+            node["Label"] = { ["$className"] = "StringValue", ["$properties"] = { Value = path } }
+            return node
+            "#,
+        )
+        .unwrap();
+
+        let mut project: Project = serde_json::from_str(
+            r#"{
+                "name": "Game",
+                "defines": { "tiles": 3 },
+                "tree": {
+                    "Grid": { "$generate": "grid.luau", "Tile1": { "$className": "Folder" } }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        GenerateNodes::new(&dir)
+            .transform_project(&mut project, &mut |_: crate::diagnostics::Diagnostic| {})
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let grid = &project.tree.children["Grid"];
+        assert_eq!(grid.class_name.as_deref(), Some("Model"));
+        assert_eq!(grid.generate, None);
+        assert_eq!(grid.children.len(), 4);
+        // Children from the project win over generated ones
+        assert_eq!(grid.children["Tile1"].class_name.as_deref(), Some("Folder"));
+        assert_eq!(grid.children["Tile3"].class_name.as_deref(), Some("Part"));
+        assert_eq!(project.defines["tiles"], DefineValue::Number(3.0));
+    }
+
+    #[test]
+    fn missing_script_fails() {
+        let mut project = Project::new(
+            "Game",
+            ProjectNode {
+                generate: Some("does-not-exist.luau".to_owned()),
+                ..Default::default()
+            },
+        );
+
+        assert!(GenerateNodes::new(".")
+            .transform_project(&mut project, &mut |_: crate::diagnostics::Diagnostic| {})
+            .is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Shell commands run before and after a build, from the `[hooks]` table of
+//! the config file, so code generation and uploads don't need a wrapper
+//! script.
+//!
+//! Commands run through `sh -c` (`cmd /C` on Windows) in the config file's
+//! directory. Their output goes to stderr, so it never mixes with a place
+//! written to stdout. A command that fails stops the build.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::output::OutputFormat;
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Hooks {
+    /// Run before the project is read.
+    #[serde(default)]
+    pub pre_build: Vec<String>,
+
+    /// Run after everything was written, with [`BuildInfo`] in `RBXBUILD_*`
+    /// environment variables.
+    #[serde(default)]
+    pub post_build: Vec<String>,
+
+    /// Where the commands run. Set to the config file's directory when it's
+    /// loaded.
+    #[serde(skip)]
+    pub directory: Option<PathBuf>,
+}
+
+impl Hooks {
+    pub fn run_pre_build(&self) -> Result<()> {
+        run(&self.pre_build, self.directory.as_deref(), &[])
+    }
+
+    pub fn run_post_build(&self, info: &BuildInfo<'_>) -> Result<()> {
+        run(&self.post_build, self.directory.as_deref(), &info.env())
+    }
+}
+
+/// What a post-build hook is told about the build.
+#[derive(Debug, Clone)]
+pub struct BuildInfo<'a> {
+    /// The project's name.
+    pub name: &'a str,
+
+    /// The file the output was written to, if it wasn't stdout.
+    pub output: Option<&'a Path>,
+    pub format: OutputFormat,
+
+    /// Size of the written output, if it went to a file.
+    pub bytes: Option<u64>,
+    pub instances: usize,
+    pub warnings: usize,
+    pub profile: Option<&'a str>,
+    pub duration: Duration,
+}
+
+impl BuildInfo<'_> {
+    /// The environment variables post-build hooks get. Values that don't
+    /// apply are empty rather than missing, so scripts can use them without
+    /// checking. The output path is made absolute, since hooks run in the
+    /// config file's directory rather than the one rbxbuild was run in.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let format = match self.format {
+            OutputFormat::Xml => "xml",
+            OutputFormat::Binary => "binary",
+        };
+
+        vec![
+            ("RBXBUILD_NAME", self.name.to_owned()),
+            (
+                "RBXBUILD_OUTPUT",
+                self.output
+                    .map(|path| {
+                        std::path::absolute(path)
+                            .unwrap_or_else(|_| path.to_path_buf())
+                            .display()
+                            .to_string()
+                    })
+                    .unwrap_or_default(),
+            ),
+            ("RBXBUILD_FORMAT", format.to_owned()),
+            (
+                "RBXBUILD_BYTES",
+                self.bytes
+                    .map(|bytes| bytes.to_string())
+                    .unwrap_or_default(),
+            ),
+            ("RBXBUILD_INSTANCES", self.instances.to_string()),
+            ("RBXBUILD_WARNINGS", self.warnings.to_string()),
+            (
+                "RBXBUILD_PROFILE",
+                self.profile.unwrap_or_default().to_owned(),
+            ),
+            (
+                "RBXBUILD_DURATION_MS",
+                self.duration.as_millis().to_string(),
+            ),
+        ]
+    }
+}
+
+/// Runs each command in turn, stopping at the first one that fails.
+pub fn run(commands: &[String], directory: Option<&Path>, env: &[(&str, String)]) -> Result<()> {
+    for command in commands {
+        log::info!("Running hook {command:?}");
+
+        let mut process = shell(command);
+        if let Some(directory) = directory {
+            process.current_dir(directory);
+        }
+
+        let status = process
+            .envs(env.iter().map(|(name, value)| (*name, value)))
+            .stdin(Stdio::null())
+            .stdout(std::io::stderr())
+            .status()
+            .map_err(|err| Error::Hook {
+                command: command.clone(),
+                reason: err.to_string(),
+            })?;
+
+        if !status.success() {
+            return Err(Error::Hook {
+                command: command.clone(),
+                reason: status.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.arg("/C").arg(command);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn describes_builds() {
+        let info = BuildInfo {
+            name: "Game",
+            output: Some(Path::new("build/game.rbxl")),
+            format: OutputFormat::Binary,
+            bytes: Some(2048),
+            instances: 12,
+            warnings: 0,
+            profile: None,
+            duration: Duration::from_millis(1500),
+        };
+        let env = info.env();
+        let get = |name: &str| env.iter().find(|(key, _)| *key == name).unwrap().1.as_str();
+
+        let output = std::env::current_dir().unwrap().join("build/game.rbxl");
+        assert_eq!(get("RBXBUILD_OUTPUT"), output.display().to_string());
+        assert_eq!(get("RBXBUILD_FORMAT"), "binary");
+        assert_eq!(get("RBXBUILD_BYTES"), "2048");
+        assert_eq!(get("RBXBUILD_PROFILE"), "");
+        assert_eq!(get("RBXBUILD_DURATION_MS"), "1500");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn runs_commands() {
+        let env = [("RBXBUILD_NAME", "Game".to_owned())];
+
+        run(&[r#"test "$RBXBUILD_NAME" = Game"#.to_owned()], None, &env).unwrap();
+
+        let error = run(&["true".to_owned(), "exit 3".to_owned()], None, &env).unwrap_err();
+        assert!(matches!(error, Error::Hook { command, .. } if command == "exit 3"));
+    }
+}
@@ -0,0 +1,249 @@
+//! The `$outfit` shorthand for HumanoidDescriptions.
+//!
+//! A HumanoidDescription has dozens of properties, most of them asset IDs,
+//! which makes NPCs tedious to describe property by property. `$outfit` sets
+//! them from a smaller spec:
+//!
+//! ```json
+//! "Guard": {
+//!     "$className": "HumanoidDescription",
+//!     "$outfit": {
+//!         "shirt": 4047884046,
+//!         "pants": "rbxassetid://4047886060",
+//!         "accessories": { "hat": [4819740796], "hair": [5890690147] },
+//!         "skinColor": [0.8, 0.6, 0.45],
+//!         "scale": { "height": 1.05, "width": 0.95 },
+//!         "animations": { "idle": 2510196951, "walk": 2510202577 }
+//!     }
+//! }
+//! ```
+//!
+//! Properties the node sets itself win over the outfit.
+
+use rbx_dom_weak::types::{Color3, Variant};
+use serde::{Deserialize, Serialize};
+
+/// An asset ID, written as a number or an `rbxassetid://` URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "AssetIdValue", into = "i64")]
+pub struct AssetId(pub i64);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AssetIdValue {
+    Id(i64),
+    Uri(String),
+}
+
+impl TryFrom<AssetIdValue> for AssetId {
+    type Error = String;
+
+    fn try_from(value: AssetIdValue) -> Result<Self, Self::Error> {
+        match value {
+            AssetIdValue::Id(id) => Ok(AssetId(id)),
+            AssetIdValue::Uri(uri) => uri
+                .strip_prefix("rbxassetid://")
+                .and_then(|id| id.parse().ok())
+                .map(AssetId)
+                .ok_or_else(|| format!("Expected an asset ID or rbxassetid:// URI, got {uri:?}")),
+        }
+    }
+}
+
+impl From<AssetId> for i64 {
+    fn from(id: AssetId) -> Self {
+        id.0
+    }
+}
+
+/// A HumanoidDescription's clothing, body parts, accessories, colors, scales
+/// and animations. Anything left out keeps the HumanoidDescription's default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Outfit {
+    pub shirt: Option<AssetId>,
+    pub pants: Option<AssetId>,
+    pub t_shirt: Option<AssetId>,
+    pub face: Option<AssetId>,
+    pub head: Option<AssetId>,
+    pub torso: Option<AssetId>,
+    pub left_arm: Option<AssetId>,
+    pub right_arm: Option<AssetId>,
+    pub left_leg: Option<AssetId>,
+    pub right_leg: Option<AssetId>,
+
+    #[serde(default)]
+    pub accessories: Accessories,
+
+    /// The color of every body part.
+    pub skin_color: Option<[f32; 3]>,
+
+    #[serde(default)]
+    pub scale: Scale,
+
+    #[serde(default)]
+    pub animations: Animations,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Accessories {
+    pub hat: Vec<AssetId>,
+    pub hair: Vec<AssetId>,
+    pub face: Vec<AssetId>,
+    pub neck: Vec<AssetId>,
+    pub shoulders: Vec<AssetId>,
+    pub front: Vec<AssetId>,
+    pub back: Vec<AssetId>,
+    pub waist: Vec<AssetId>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct Scale {
+    pub height: Option<f32>,
+    pub width: Option<f32>,
+    pub depth: Option<f32>,
+    pub head: Option<f32>,
+    pub body_type: Option<f32>,
+    pub proportion: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Animations {
+    pub climb: Option<AssetId>,
+    pub fall: Option<AssetId>,
+    pub idle: Option<AssetId>,
+    pub jump: Option<AssetId>,
+    pub mood: Option<AssetId>,
+    pub run: Option<AssetId>,
+    pub swim: Option<AssetId>,
+    pub walk: Option<AssetId>,
+}
+
+impl Outfit {
+    /// The HumanoidDescription properties the outfit sets.
+    pub fn properties(&self) -> Vec<(&'static str, Variant)> {
+        let mut properties = Vec::new();
+
+        let assets = [
+            ("Shirt", self.shirt),
+            ("Pants", self.pants),
+            ("GraphicTShirt", self.t_shirt),
+            ("Face", self.face),
+            ("Head", self.head),
+            ("Torso", self.torso),
+            ("LeftArm", self.left_arm),
+            ("RightArm", self.right_arm),
+            ("LeftLeg", self.left_leg),
+            ("RightLeg", self.right_leg),
+            ("ClimbAnimation", self.animations.climb),
+            ("FallAnimation", self.animations.fall),
+            ("IdleAnimation", self.animations.idle),
+            ("JumpAnimation", self.animations.jump),
+            ("MoodAnimation", self.animations.mood),
+            ("RunAnimation", self.animations.run),
+            ("SwimAnimation", self.animations.swim),
+            ("WalkAnimation", self.animations.walk),
+        ];
+        properties.extend(
+            assets
+                .into_iter()
+                .filter_map(|(property, id)| Some((property, Variant::Int64(id?.0)))),
+        );
+
+        // Accessories of a kind are a comma-separated list of IDs
+        let accessories = [
+            ("HatAccessory", &self.accessories.hat),
+            ("HairAccessory", &self.accessories.hair),
+            ("FaceAccessory", &self.accessories.face),
+            ("NeckAccessory", &self.accessories.neck),
+            ("ShouldersAccessory", &self.accessories.shoulders),
+            ("FrontAccessory", &self.accessories.front),
+            ("BackAccessory", &self.accessories.back),
+            ("WaistAccessory", &self.accessories.waist),
+        ];
+        for (property, ids) in accessories {
+            if !ids.is_empty() {
+                let ids: Vec<String> = ids.iter().map(|id| id.0.to_string()).collect();
+                properties.push((property, Variant::String(ids.join(","))));
+            }
+        }
+
+        if let Some([r, g, b]) = self.skin_color {
+            for property in [
+                "HeadColor",
+                "TorsoColor",
+                "LeftArmColor",
+                "RightArmColor",
+                "LeftLegColor",
+                "RightLegColor",
+            ] {
+                properties.push((property, Variant::Color3(Color3::new(r, g, b))));
+            }
+        }
+
+        let scales = [
+            ("HeightScale", self.scale.height),
+            ("WidthScale", self.scale.width),
+            ("DepthScale", self.scale.depth),
+            ("HeadScale", self.scale.head),
+            ("BodyTypeScale", self.scale.body_type),
+            ("ProportionScale", self.scale.proportion),
+        ];
+        properties.extend(
+            scales
+                .into_iter()
+                .filter_map(|(property, scale)| Some((property, Variant::Float32(scale?)))),
+        );
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expands_outfits() {
+        let outfit: Outfit = serde_json::from_str(
+            r#"{
+                "shirt": 101,
+                "pants": "rbxassetid://102",
+                "accessories": { "hat": [201, 202] },
+                "skinColor": [1, 0.5, 0],
+                "scale": { "bodyType": 0.3 },
+                "animations": { "walk": 301 }
+            }"#,
+        )
+        .unwrap();
+
+        let properties = outfit.properties();
+        let get = |name: &str| {
+            properties
+                .iter()
+                .find(|(property, _)| *property == name)
+                .map(|(_, value)| value.clone())
+        };
+
+        assert_eq!(get("Shirt"), Some(Variant::Int64(101)));
+        assert_eq!(get("Pants"), Some(Variant::Int64(102)));
+        assert_eq!(get("WalkAnimation"), Some(Variant::Int64(301)));
+        assert_eq!(
+            get("HatAccessory"),
+            Some(Variant::String("201,202".to_owned()))
+        );
+        assert_eq!(get("HairAccessory"), None);
+        assert_eq!(
+            get("RightLegColor"),
+            Some(Variant::Color3(Color3::new(1.0, 0.5, 0.0)))
+        );
+        assert_eq!(get("BodyTypeScale"), Some(Variant::Float32(0.3)));
+        assert_eq!(properties.len(), 11);
+
+        assert!(serde_json::from_str::<Outfit>(r#"{ "shirt": "https://example.com" }"#).is_err());
+        assert!(serde_json::from_str::<Outfit>(r#"{ "hat": [1] }"#).is_err());
+    }
+}
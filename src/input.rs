@@ -0,0 +1,128 @@
+//! Reading existing place and model files, and project files that weren't
+//! saved as plain UTF-8.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use rbx_dom_weak::WeakDom;
+
+use crate::error::{Error, Result};
+use crate::output::{OutputFormat, OUTPUT_BUFFER_SIZE};
+
+/// Deserializes a place or model in the given format.
+///
+/// The root of the returned DOM is always a `DataModel` holding the file's
+/// top-level instances, whether the file is a place or a model. Like
+/// [`write_dom`](crate::output::write_dom), each format needs its cargo
+/// feature.
+#[cfg_attr(not(all(feature = "xml", feature = "binary")), allow(unused_variables))]
+pub fn read_dom<R: Read>(reader: R, format: OutputFormat) -> Result<WeakDom> {
+    match format {
+        #[cfg(feature = "xml")]
+        OutputFormat::Xml => {
+            rbx_xml::from_reader_default(reader).map_err(|err| Error::Deserialize(Box::new(err)))
+        }
+        #[cfg(feature = "binary")]
+        OutputFormat::Binary => {
+            rbx_binary::from_reader(reader).map_err(|err| Error::Deserialize(Box::new(err)))
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(Error::Deserialize(
+            format!("rbxbuild was built without support for {format:?} files").into(),
+        )),
+    }
+}
+
+/// Reads a place or model file, choosing the format from its extension.
+pub fn read_file(path: &Path) -> Result<WeakDom> {
+    let file = File::open(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("Could not open {}: {err}", path.display()),
+        )
+    })?;
+
+    read_dom(
+        BufReader::with_capacity(OUTPUT_BUFFER_SIZE, file),
+        OutputFormat::from_path(path),
+    )
+}
+
+/// The byte order mark some editors, mostly on Windows, start UTF-8 files
+/// with.
+pub const BOM: char = '\u{FEFF}';
+
+/// Text read by [`decode_text`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedText {
+    pub text: String,
+
+    /// If the text wasn't valid UTF-8 and was read as Latin-1 instead, the
+    /// offset of the first byte that wasn't.
+    pub latin1_from: Option<usize>,
+}
+
+/// Reads the bytes of a text file, such as a project, as UTF-8 without a
+/// byte order mark.
+///
+/// Text that isn't valid UTF-8 is an error, unless `latin1_fallback` is set,
+/// in which case every byte is read as the Latin-1 character with that code.
+/// That's right for files saved in Windows-1252 too, as long as they don't
+/// use its curly quotes and dashes.
+pub fn decode_text(bytes: Vec<u8>, latin1_fallback: bool) -> Result<DecodedText> {
+    let (text, latin1_from) = match String::from_utf8(bytes) {
+        Ok(text) => (text, None),
+        Err(err) if latin1_fallback => {
+            let offset = err.utf8_error().valid_up_to();
+            let text = err.into_bytes().into_iter().map(char::from).collect();
+            (text, Some(offset))
+        }
+        Err(err) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Not valid UTF-8 from byte {}",
+                    err.utf8_error().valid_up_to()
+                ),
+            )
+            .into())
+        }
+    };
+
+    let text = match text.strip_prefix(BOM) {
+        Some(rest) => rest.to_owned(),
+        None => text,
+    };
+    Ok(DecodedText { text, latin1_from })
+}
+
+/// Whether a file extension is that of a place (`.rbxl`/`.rbxlx`) rather than
+/// a model.
+pub fn is_place_path(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("rbxl" | "rbxlx")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decodes_text() {
+        let decoded =
+            decode_text(b"\xEF\xBB\xBF{\"name\": \"Caf\xC3\xA9\"}".to_vec(), false).unwrap();
+        assert_eq!(decoded.text, "{\"name\": \"Caf\u{E9}\"}");
+        assert_eq!(decoded.latin1_from, None);
+
+        let latin1 = b"{\"name\": \"Caf\xE9\"}".to_vec();
+        assert!(decode_text(latin1.clone(), false).is_err());
+        let decoded = decode_text(latin1, true).unwrap();
+        assert_eq!(decoded.text, "{\"name\": \"Caf\u{E9}\"}");
+        assert_eq!(decoded.latin1_from, Some(13));
+    }
+}
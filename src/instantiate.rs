@@ -0,0 +1,1540 @@
+use indexmap::IndexMap;
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    InstanceBuilder, WeakDom,
+};
+use ustr::Ustr;
+
+use crate::collision;
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::error::{Error, Result};
+use crate::filter;
+use crate::options::BuildOptions;
+use crate::project::{Project, ProjectNode};
+use crate::resolution::UnresolvedValue;
+use crate::resolver::{self, PropertyContext};
+use crate::timing::{self, Phase};
+
+/// Builds a project into a WeakDom, running the transform pipeline before and
+/// after instantiation.
+pub fn build(
+    mut project: Project,
+    options: &BuildOptions,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<WeakDom> {
+    let timings = options.timings.as_ref();
+
+    timing::timed(timings, Phase::Transform, || {
+        options.transforms.run_project(&mut project, diagnostics)
+    })?;
+
+    // After the transforms, so nodes from templates and generators are
+    // filtered too
+    filter::filter_build_tags(&mut project.tree, &options.with_tags, &options.without_tags);
+
+    let root_name = project.name.as_deref().unwrap_or("ROOT");
+
+    // After the transforms, so a merged template wins over the preset too
+    if let Some(preset) = project.preset {
+        preset.apply(&mut project.tree, root_name, diagnostics);
+    }
+
+    options.limits.check_project(&project.tree, root_name)?;
+    let mut dom = instantiate(project.tree, root_name, options, diagnostics)?;
+
+    if let Some(groups) = &project.collision_groups {
+        collision::apply(groups, &mut dom, diagnostics)?;
+    }
+
+    timing::timed(timings, Phase::Transform, || {
+        options.transforms.run_dom(&mut dom, diagnostics)
+    })?;
+
+    // Last, so the limits apply to what's actually written
+    options.limits.check_dom(&dom)?;
+
+    Ok(dom)
+}
+
+/// Instantiate a ProjectNode tree into a WeakDom (ported from Rojo)
+///
+/// The tree is consumed so that property values can be moved into the DOM
+/// instead of copied. Problems that don't stop the build are reported to
+/// `diagnostics`.
+pub fn instantiate(
+    node: ProjectNode,
+    instance_name: &str,
+    options: &BuildOptions,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<WeakDom> {
+    timing::timed(options.timings.as_ref(), Phase::Instantiate, || {
+        instantiate_tree(node, instance_name, options, diagnostics)
+    })
+}
+
+fn instantiate_tree(
+    node: ProjectNode,
+    instance_name: &str,
+    options: &BuildOptions,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<WeakDom> {
+    let (root, children) =
+        instantiate_node(node, instance_name, instance_name, "", options, diagnostics)?;
+    let mut dom = WeakDom::new(root);
+
+    let mut stack = Vec::new();
+    push_children(
+        &mut stack,
+        dom.root_ref(),
+        dom.root().class,
+        instance_name,
+        children,
+        options,
+    );
+
+    if use_subtrees(&stack, options) {
+        instantiate_subtrees(&mut dom, stack, options, diagnostics)?;
+    } else {
+        instantiate_pending(&mut dom, stack, options, diagnostics)?;
+    }
+
+    Ok(dom)
+}
+
+/// Instantiates everything on the stack, and all of their descendants, into
+/// the DOM.
+///
+/// Descendants are instantiated from an explicit stack rather than by
+/// recursion, so arbitrarily deep trees can't overflow the call stack.
+fn instantiate_pending(
+    dom: &mut WeakDom,
+    mut stack: Vec<PendingNode>,
+    options: &BuildOptions,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<()> {
+    while let Some(pending) = stack.pop() {
+        match instantiate_node(
+            pending.node,
+            &pending.name,
+            &pending.path,
+            &pending.parent_class,
+            options,
+            diagnostics,
+        ) {
+            Ok((builder, children)) => {
+                let referent = dom.insert(pending.parent, builder);
+                let class = dom.get_by_ref(referent).unwrap().class;
+                push_children(
+                    &mut stack,
+                    referent,
+                    class,
+                    &pending.path,
+                    children,
+                    options,
+                );
+            }
+            Err(e) if options.strict => return Err(e),
+            Err(e) => {
+                diagnostics.report(Diagnostic::warning(
+                    pending.path,
+                    format!("Failed to instantiate child {}: {}", pending.name, e),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the top-level subtrees should be built separately, so they can be
+/// built in parallel or taken from the cache.
+#[cfg_attr(not(feature = "cache"), allow(unused_variables))]
+fn use_subtrees(stack: &[PendingNode], options: &BuildOptions) -> bool {
+    #[cfg(feature = "cache")]
+    if options.cache.is_some() {
+        return true;
+    }
+
+    cfg!(feature = "parallel") && stack.len() > 1
+}
+
+/// Instantiates each top-level subtree into its own DOM, on the rayon thread
+/// pool if the `parallel` feature is enabled, then moves them into `dom` in
+/// their original order.
+///
+/// Diagnostics are collected per subtree and reported in order afterwards, so
+/// the output is the same as a single-threaded build.
+fn instantiate_subtrees(
+    dom: &mut WeakDom,
+    stack: Vec<PendingNode>,
+    options: &BuildOptions,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<()> {
+    // The stack is in reverse order
+    #[cfg(feature = "parallel")]
+    let subtrees: Vec<_> = {
+        use rayon::prelude::*;
+
+        stack
+            .into_par_iter()
+            .rev()
+            .map(|pending| instantiate_subtree(pending, options))
+            .collect()
+    };
+
+    #[cfg(not(feature = "parallel"))]
+    let subtrees: Vec<_> = stack
+        .into_iter()
+        .rev()
+        .map(|pending| instantiate_subtree(pending, options))
+        .collect();
+
+    for mut subtree in subtrees {
+        for diagnostic in subtree.diagnostics {
+            diagnostics.report(diagnostic);
+        }
+        subtree.result?;
+
+        for child in subtree.dom.root().children().to_vec() {
+            subtree.dom.transfer(child, dom, subtree.parent);
+        }
+    }
+
+    Ok(())
+}
+
+/// A top-level subtree built on its own, under a placeholder root.
+struct Subtree {
+    parent: Ref,
+    dom: WeakDom,
+    result: Result<()>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn instantiate_subtree(mut pending: PendingNode, options: &BuildOptions) -> Subtree {
+    let parent = pending.parent;
+
+    #[cfg(feature = "cache")]
+    let cache = options.cache.as_ref().and_then(|cache| {
+        let key = cache.key(&pending.name, &pending.parent_class, &pending.node, options)?;
+        Some((cache, key))
+    });
+
+    #[cfg(feature = "cache")]
+    if let Some(dom) = cache.as_ref().and_then(|(cache, key)| cache.get(key)) {
+        return Subtree {
+            parent,
+            dom,
+            result: Ok(()),
+            diagnostics: Vec::new(),
+        };
+    }
+
+    let mut dom = WeakDom::new(InstanceBuilder::new("Folder"));
+    pending.parent = dom.root_ref();
+
+    let mut diagnostics = Vec::new();
+    let result = instantiate_pending(
+        &mut dom,
+        vec![pending],
+        options,
+        &mut |diagnostic: Diagnostic| diagnostics.push(diagnostic),
+    );
+
+    // Subtrees with warnings are rebuilt every time so the warnings are
+    // reported on every build, not just the first
+    #[cfg(feature = "cache")]
+    if let Some((cache, key)) = cache {
+        if result.is_ok() && diagnostics.is_empty() {
+            cache.insert(key, &dom);
+        }
+    }
+
+    Subtree {
+        parent,
+        dom,
+        result,
+        diagnostics,
+    }
+}
+
+/// A project node waiting to be instantiated under an existing instance.
+struct PendingNode {
+    parent: Ref,
+    parent_class: Ustr,
+    name: String,
+    path: String,
+    node: ProjectNode,
+}
+
+fn push_children(
+    stack: &mut Vec<PendingNode>,
+    parent: Ref,
+    parent_class: Ustr,
+    parent_path: &str,
+    mut children: IndexMap<String, ProjectNode>,
+    options: &BuildOptions,
+) {
+    // A node's children all have different names, so the name alone decides
+    // their order
+    if options.sort_children {
+        children.sort_keys();
+    }
+
+    // Stable, so siblings with the same $order stay in the order above
+    if children.values().any(|child| child.order.is_some()) {
+        children.sort_by(|_, a, _, b| a.order.unwrap_or(0).cmp(&b.order.unwrap_or(0)));
+    }
+
+    // Pushed in reverse so they're popped, and inserted, in order
+    stack.extend(children.into_iter().rev().map(|(name, node)| PendingNode {
+        parent,
+        parent_class,
+        path: format!("{parent_path}/{name}"),
+        name,
+        node,
+    }));
+}
+
+/// Convert a ProjectNode into an InstanceBuilder (ported from Rojo), returning
+/// the node's children for the caller to instantiate.
+fn instantiate_node(
+    node: ProjectNode,
+    name: &str,
+    path: &str,
+    parent_class: &str,
+    options: &BuildOptions,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(InstanceBuilder, IndexMap<String, ProjectNode>)> {
+    if let Some(script) = &node.generate {
+        diagnostics.report(Diagnostic::warning(
+            path,
+            format!("$generate script {script} was not run, since the build has no GenerateNodes transform"),
+        ));
+    }
+
+    // Determine class name - infer from known service names if not specified
+    let mut name = name;
+    let class_name = if let Some(class) = &node.class_name {
+        class.as_str()
+    } else {
+        // A suffix like `Main.server` names the class, and is dropped from
+        // the instance's name
+        let suffixed = name.rsplit_once('.').and_then(|(base, suffix)| {
+            let class = options.class_suffixes.get(suffix)?;
+            (!base.is_empty()).then_some((base, class.as_str()))
+        });
+
+        // Try to infer from configured and known services
+        match (options.services.get(name), suffixed) {
+            (Some(class), _) => class.as_str(),
+            (None, Some((base, class))) => {
+                name = base;
+                class
+            }
+            (None, None) => infer_child_class(parent_class, name)
+                .or_else(|| infer_class_from_name(name))
+                .unwrap_or("Folder"),
+        }
+    };
+
+    // Generated projects often get the case wrong, like "part" for Part
+    let class_name = match correct_class_case(class_name) {
+        Some(corrected) if options.strict => {
+            return Err(Error::ClassNameCase {
+                path: path.to_owned(),
+                class: class_name.to_owned(),
+                suggestion: corrected.to_owned(),
+            });
+        }
+        Some(corrected) => {
+            diagnostics.report(Diagnostic::warning(
+                path,
+                format!("There's no class called {class_name}, so {corrected} was used instead"),
+            ));
+            corrected
+        }
+        None => class_name,
+    };
+
+    let mut properties = node.properties;
+
+    // Properties set on the node itself win over its outfit
+    if let Some(outfit) = &node.outfit {
+        if class_name == "HumanoidDescription" {
+            for (property, value) in outfit.properties() {
+                properties
+                    .entry(Ustr::from(property))
+                    .or_insert(UnresolvedValue::FullyQualified(value));
+            }
+        } else {
+            diagnostics.report(Diagnostic::warning(
+                path,
+                format!("$outfit was ignored, since {class_name} isn't a HumanoidDescription"),
+            ));
+        }
+    }
+
+    // Check if there's an explicit Name property override. It's removed from
+    // the properties since it's set via with_name() instead.
+    let instance_name_override = properties
+        .remove(&Ustr::from("Name"))
+        .and_then(|name_value| name_value.resolve_unambiguous().ok())
+        .and_then(|variant| match variant {
+            Variant::String(s) => Some(s),
+            _ => None,
+        });
+
+    let instance_name = instance_name_override.as_deref().unwrap_or(name);
+
+    let mut builder = InstanceBuilder::new(class_name).with_name(instance_name);
+
+    // Defaults go in first so that explicit properties overwrite them
+    if options.fill_defaults {
+        builder = fill_default_properties(builder, class_name)?;
+    }
+
+    // Add properties with proper resolution
+    for (key, unresolved) in properties {
+        let ctx = PropertyContext {
+            class_name,
+            property: key.as_str(),
+            path,
+        };
+
+        let resolved = timing::timed(options.timings.as_ref(), Phase::Resolve, || {
+            resolver::resolve_property(unresolved, &ctx, &options.resolvers, &options.defines)
+        });
+
+        match resolved {
+            Ok(variant) => {
+                builder = builder.with_property(key, variant);
+            }
+            Err(e) if options.strict => {
+                return Err(Error::Resolve {
+                    class: class_name.to_owned(),
+                    property: key.to_string(),
+                    reason: e.to_string(),
+                });
+            }
+            Err(e) => {
+                diagnostics.report(
+                    Diagnostic::warning(
+                        path,
+                        format!("Failed to resolve property {}.{}: {}", class_name, key, e),
+                    )
+                    .with_property(key.as_str()),
+                );
+            }
+        }
+    }
+
+    let mut children = node.children;
+    if let Some(surface) = node.surface {
+        surface.add_to(&mut children);
+    }
+
+    Ok((builder, children))
+}
+
+/// The known class a class name matches when case is ignored, if it isn't a
+/// known class already
+fn correct_class_case(class_name: &str) -> Option<&'static str> {
+    let database = crate::database::get().ok()?;
+    if database.classes.contains_key(class_name) {
+        return None;
+    }
+
+    database
+        .classes
+        .values()
+        .map(|class| class.name.as_ref())
+        .filter(|name| name.eq_ignore_ascii_case(class_name))
+        .min()
+}
+
+/// Adds the reflection database's default value of every property of the class
+fn fill_default_properties(
+    mut builder: InstanceBuilder,
+    class_name: &str,
+) -> Result<InstanceBuilder> {
+    let database = crate::database::get()?;
+
+    if let Some(class) = database.classes.get(class_name) {
+        for (property, value) in &class.default_properties {
+            let property: &str = property;
+            builder = builder.with_property(property, value.clone());
+        }
+    }
+
+    Ok(builder)
+}
+
+/// Services a node is inferred to be when it has their name and no
+/// `$className`. Configured mappings are checked first, so they can add to
+/// this list or map one of these names to something else.
+pub(crate) const KNOWN_SERVICES: &[&str] = &[
+    "Workspace",
+    "Players",
+    "Lighting",
+    "MaterialService",
+    "ReplicatedFirst",
+    "ReplicatedStorage",
+    "ServerScriptService",
+    "ServerStorage",
+    "StarterGui",
+    "StarterPack",
+    "StarterPlayer",
+    "Teams",
+    "SoundService",
+    "Chat",
+    "TextChatService",
+    "VoiceChatService",
+    "LocalizationService",
+    "ProximityPromptService",
+    "CollectionService",
+    "HttpService",
+    "TweenService",
+    "RunService",
+    "UserInputService",
+    "MarketplaceService",
+    "PhysicsService",
+    "PathfindingService",
+    "TestService",
+];
+
+/// Infer a class name from an instance name (common service names)
+pub(crate) fn infer_class_from_name(name: &str) -> Option<&'static str> {
+    KNOWN_SERVICES
+        .iter()
+        .copied()
+        .find(|&service| service == name)
+}
+
+/// Infer a class name from an instance name under a parent of the given
+/// class, for containers that only exist inside one service
+pub(crate) fn infer_child_class(parent_class: &str, name: &str) -> Option<&'static str> {
+    match (parent_class, name) {
+        ("StarterPlayer", "StarterPlayerScripts") => Some("StarterPlayerScripts"),
+        ("StarterPlayer", "StarterCharacterScripts") => Some("StarterCharacterScripts"),
+        _ => None,
+    }
+}
+
+#[cfg(all(test, feature = "xml"))]
+mod tests {
+    //! # Test Suite for JSON to XML Conversion
+    //!
+    //! This module contains comprehensive tests for the rojo-build-lite tool,
+    //! which converts Rojo-style JSON project files to Roblox XML format.
+    //!
+    //! ## Test Coverage
+    //!
+    //! ### Basic Functionality
+    //! - `test_simple_folder`: Verifies basic folder conversion
+    //! - `test_nested_children`: Tests hierarchical instance structures
+    //! - `test_folder_without_explicit_classname`: Tests default Folder inference
+    //!
+    //! ### Name Property Handling
+    //! - `test_no_duplicate_name_property`: Ensures Name properties aren't duplicated when explicitly provided
+    //! - `test_custom_name_override`: Verifies that explicit Name properties override the JSON key
+    //!
+    //! ### Service Inference
+    //! - `test_service_inference`: Tests automatic class name inference for common Roblox services
+    //! - `test_starter_player_inference`: StarterPlayer's script containers are inferred under it
+    //!
+    //! ### DataModel vs Model Files
+    //! - `test_datamodel_place_file`: Verifies that DataModel children are written as root-level siblings
+    //! - `test_model_file`: Ensures Model instances are properly included in the output
+    //!
+    //! ### Property Type Conversion
+    //! - `test_boolean_property`: Boolean values
+    //! - `test_number_properties`: Float/Int number properties
+    //! - `test_vector3_property`: Vector3 arrays [x, y, z]
+    //! - `test_color3_property`: Color3 arrays [r, g, b]
+    //! - `test_cframe_property`: CFrame 12-element arrays
+    //! - `test_enum_property`: String-based enum values
+    //! - `test_multiple_properties`: Multiple properties on a single instance
+    //! - `test_shared_strings_are_deduplicated`: Identical SharedStrings are written once
+    //! - `test_expression_properties`: `=` expressions referring to defines
+    //!
+    //! ### Build Options
+    //! - `test_configured_services`: Service mappings from rbxbuild.toml
+    //! - `test_class_suffixes`: Key suffixes give a class and are dropped from the name
+    //! - `test_strict_mode`: Resolution failures are warnings, or errors in strict mode
+    //! - `test_class_name_case`: Class names in the wrong case are corrected, or errors in strict mode
+    //! - `test_transform_pipeline`: Project and DOM passes run around instantiation
+    //! - `test_fill_defaults`: Reflection defaults are filled in under explicit values
+    //! - `test_root_handling`: Forcing place or model output
+    //! - `test_deterministic_referents`: Identical inputs give identical output
+    //! - `test_child_order_is_preserved`: Children are built in project order, or by name when sorted
+    //! - `test_order_directive`: `$order` pins siblings' order, sorted or not
+    //! - `test_build_cache`: Unchanged subtrees are reused, ones with warnings aren't
+    //! - `test_many_top_level_subtrees`: Top-level subtrees keep their order and warnings
+    //! - `test_deeply_nested_tree`: Deep trees don't overflow the stack
+    //! - `test_deeply_nested_json`: Deep project files parse and build
+    //!
+    //! ### Scripts
+    //! - `test_script_with_source`: Script instances with Source property
+    //!
+    //! ## Running Tests
+    //!
+    //! Run all tests with:
+    //! ```bash
+    //! cargo test
+    //! ```
+    //!
+    //! Run a specific test:
+    //! ```bash
+    //! cargo test test_no_duplicate_name_property
+    //! ```
+    //!
+    //! Run with verbose output:
+    //! ```bash
+    //! cargo test -- --nocapture
+    //! ```
+
+    use super::*;
+
+    use crate::options::RootHandling;
+    use crate::output::{self, OutputFormat};
+    use crate::transform::Pipeline;
+
+    /// Helper function to convert JSON to XML string
+    fn json_to_xml(json_str: &str) -> anyhow::Result<String> {
+        let project = Project::from_json(json_str)?;
+        let root_name = project.name.as_deref().unwrap_or("ROOT");
+        let options = BuildOptions {
+            defines: project.defines.clone(),
+            ..Default::default()
+        };
+        let dom = instantiate(project.tree, root_name, &options, &mut |_: Diagnostic| {})?;
+
+        let mut buffer = Vec::new();
+        output::write_dom(&mut buffer, &dom, OutputFormat::Xml, &options)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    #[test]
+    fn test_simple_folder() {
+        let json = r#"{
+            "name": "TestProject",
+            "tree": {
+                "$className": "Folder"
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+        assert!(xml.contains(r#"<Item class="Folder""#));
+        assert!(xml.contains(r#"<string name="Name">TestProject</string>"#));
+    }
+
+    #[test]
+    fn test_no_duplicate_name_property() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "DataModel",
+                "ServerScriptService": {
+                    "$className": "ServerScriptService",
+                    "MyScript": {
+                        "$className": "Script",
+                        "$properties": {
+                            "Name": "MyScript",
+                            "Source": "print('hello')"
+                        }
+                    }
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        // Ensure no duplicate Name properties
+        let name_count = xml
+            .matches(r#"<string name="Name">MyScript</string>"#)
+            .count();
+        assert_eq!(
+            name_count, 1,
+            "Name property should appear exactly once, not duplicated"
+        );
+
+        // Verify the Source property is also present
+        assert!(xml.contains(r#"<string name="Source">print('hello')</string>"#));
+    }
+
+    #[test]
+    fn test_custom_name_override() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "DataModel",
+                "Workspace": {
+                    "$className": "Workspace",
+                    "PartKey": {
+                        "$className": "Part",
+                        "$properties": {
+                            "Name": "CustomPartName"
+                        }
+                    }
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        // Should use the custom name, not the key
+        assert!(xml.contains(r#"<string name="Name">CustomPartName</string>"#));
+        assert!(!xml.contains(r#"<string name="Name">PartKey</string>"#));
+    }
+
+    #[test]
+    fn test_service_inference() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "DataModel",
+                "Workspace": {
+                    "MyPart": {
+                        "$className": "Part"
+                    }
+                },
+                "ReplicatedStorage": {
+                    "MyFolder": {
+                        "$className": "Folder"
+                    }
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        // Should infer Workspace class
+        assert!(xml.contains(r#"<Item class="Workspace""#));
+        // Should infer ReplicatedStorage class
+        assert!(xml.contains(r#"<Item class="ReplicatedStorage""#));
+    }
+
+    #[test]
+    fn test_starter_player_inference() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "DataModel",
+                "StarterPlayer": {
+                    "StarterPlayerScripts": {},
+                    "StarterCharacterScripts": {}
+                },
+                "ReplicatedStorage": {
+                    "StarterPlayerScripts": {}
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        assert!(xml.contains(r#"<Item class="StarterPlayerScripts""#));
+        assert!(xml.contains(r#"<Item class="StarterCharacterScripts""#));
+        // Only under StarterPlayer
+        assert_eq!(
+            xml.matches(r#"<Item class="StarterPlayerScripts""#).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_nested_children() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Folder",
+                "Child1": {
+                    "$className": "Folder",
+                    "GrandChild": {
+                        "$className": "Folder"
+                    }
+                },
+                "Child2": {
+                    "$className": "Folder"
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        assert!(xml.contains(r#"<string name="Name">Test</string>"#));
+        assert!(xml.contains(r#"<string name="Name">Child1</string>"#));
+        assert!(xml.contains(r#"<string name="Name">Child2</string>"#));
+        assert!(xml.contains(r#"<string name="Name">GrandChild</string>"#));
+    }
+
+    #[test]
+    fn test_script_with_source() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Script",
+                "$properties": {
+                    "Source": "print('Hello, World!')"
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        assert!(xml.contains(r#"<Item class="Script""#));
+        assert!(xml.contains(r#"<string name="Source">print('Hello, World!')</string>"#));
+    }
+
+    #[test]
+    fn test_vector3_property() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Part",
+                "$properties": {
+                    "Size": [10, 20, 30]
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        assert!(xml.contains(r#"<Vector3 name="size">"#));
+        assert!(xml.contains("<X>10</X>"));
+        assert!(xml.contains("<Y>20</Y>"));
+        assert!(xml.contains("<Z>30</Z>"));
+    }
+
+    #[test]
+    fn test_boolean_property() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Part",
+                "$properties": {
+                    "Anchored": true
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        assert!(xml.contains(r#"<bool name="Anchored">true</bool>"#));
+    }
+
+    #[test]
+    fn test_number_properties() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Part",
+                "$properties": {
+                    "Transparency": 0.5
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        assert!(xml.contains(r#"<float name="Transparency">0.5</float>"#));
+    }
+
+    #[test]
+    fn test_color3_property() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Part",
+                "$properties": {
+                    "Color": [1, 0.5, 0.25]
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        // Color3 gets serialized as Color3uint8 with a packed integer value
+        assert!(xml.contains(r#"<Color3uint8 name="Color3uint8">"#));
+    }
+
+    #[test]
+    fn test_shared_strings_are_deduplicated() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Model",
+                "A": {
+                    "$className": "MeshPart",
+                    "$properties": {
+                        "PhysicalConfigData": { "SharedString": "aGVsbG8gd29ybGQ=" }
+                    }
+                },
+                "B": {
+                    "$className": "MeshPart",
+                    "$properties": {
+                        "PhysicalConfigData": { "SharedString": "aGVsbG8gd29ybGQ=" }
+                    }
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        // Both instances point at a single copy of the payload
+        assert_eq!(
+            xml.matches(r#"<SharedString name="PhysicalConfigData">"#)
+                .count(),
+            2
+        );
+        assert_eq!(xml.matches("<SharedString md5=").count(), 1);
+    }
+
+    #[test]
+    fn test_datamodel_place_file() {
+        let json = r#"{
+            "name": "PlaceFile",
+            "tree": {
+                "$className": "DataModel",
+                "Workspace": {
+                    "$className": "Workspace"
+                },
+                "ServerScriptService": {
+                    "$className": "ServerScriptService"
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        // DataModel children should be at root level (siblings)
+        assert!(!xml.contains(r#"<Item class="DataModel""#));
+        assert!(xml.contains(r#"<Item class="Workspace""#));
+        assert!(xml.contains(r#"<Item class="ServerScriptService""#));
+    }
+
+    #[test]
+    fn test_model_file() {
+        let json = r#"{
+            "name": "MyModel",
+            "tree": {
+                "$className": "Model",
+                "Part1": {
+                    "$className": "Part"
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        // Model should be included (not like DataModel)
+        assert!(xml.contains(r#"<Item class="Model""#));
+        assert!(xml.contains(r#"<string name="Name">MyModel</string>"#));
+    }
+
+    #[test]
+    fn test_cframe_property() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Part",
+                "$properties": {
+                    "CFrame": [0, 10, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1]
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        assert!(xml.contains(r#"<CoordinateFrame name="CFrame">"#));
+        assert!(xml.contains("<X>0</X>"));
+        assert!(xml.contains("<Y>10</Y>"));
+        assert!(xml.contains("<Z>0</Z>"));
+    }
+
+    #[test]
+    fn test_enum_property() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Part",
+                "$properties": {
+                    "Material": "Grass"
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        // Material enum should be serialized as token
+        assert!(xml.contains(r#"<token name="Material">"#));
+    }
+
+    #[test]
+    fn test_multiple_properties() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Part",
+                "$properties": {
+                    "Size": [4, 4, 4],
+                    "Anchored": true,
+                    "Transparency": 0.5,
+                    "Color": [1, 0, 0]
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        assert!(xml.contains(r#"<Vector3 name="size">"#));
+        assert!(xml.contains(r#"<bool name="Anchored">true</bool>"#));
+        assert!(xml.contains(r#"<float name="Transparency">0.5</float>"#));
+        assert!(xml.contains(r#"<Color3uint8 name="Color3uint8">"#));
+    }
+
+    #[test]
+    fn test_expression_properties() {
+        let json = r#"{
+            "name": "Test",
+            "defines": {
+                "base": 2
+            },
+            "tree": {
+                "$className": "Part",
+                "$properties": {
+                    "Size": ["=4*3", 1, "=base+2"],
+                    "Transparency": "=base / 4"
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        assert!(xml.contains("<X>12</X>"));
+        assert!(xml.contains("<Y>1</Y>"));
+        assert!(xml.contains("<Z>4</Z>"));
+        assert!(xml.contains(r#"<float name="Transparency">0.5</float>"#));
+    }
+
+    #[test]
+    fn test_configured_services() {
+        let project: Project = serde_json::from_str(
+            r#"{
+                "tree": {
+                    "$className": "DataModel",
+                    "TextChatService": {},
+                    "Shared": {},
+                    "Chat": {}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut options = BuildOptions::default();
+        options
+            .services
+            .insert("Shared".to_owned(), "ReplicatedStorage".to_owned());
+        options
+            .services
+            .insert("Chat".to_owned(), "Folder".to_owned());
+
+        let dom = instantiate(project.tree, "Test", &options, &mut |_: Diagnostic| {}).unwrap();
+        let root = dom.get_by_ref(dom.root_ref()).unwrap();
+        let classes: Vec<_> = root
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap().class.as_str())
+            .collect();
+        assert_eq!(classes, ["TextChatService", "ReplicatedStorage", "Folder"]);
+    }
+
+    #[test]
+    fn test_class_suffixes() {
+        let project: Project = serde_json::from_str(
+            r#"{
+                "tree": {
+                    "$className": "Folder",
+                    "Main.server": {},
+                    "Util.module": { "$className": "Folder" },
+                    "HUD.screen": {},
+                    "Config.json": {},
+                    ".server": {}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut options = BuildOptions::default();
+        for (suffix, class) in [
+            ("server", "Script"),
+            ("module", "ModuleScript"),
+            ("screen", "ScreenGui"),
+        ] {
+            options
+                .class_suffixes
+                .insert(suffix.to_owned(), class.to_owned());
+        }
+
+        let dom = instantiate(project.tree, "Test", &options, &mut |_: Diagnostic| {}).unwrap();
+        let root = dom.get_by_ref(dom.root_ref()).unwrap();
+        let children: Vec<_> = root
+            .children()
+            .iter()
+            .map(|&child| {
+                let child = dom.get_by_ref(child).unwrap();
+                (child.name.as_str(), child.class.as_str())
+            })
+            .collect();
+        assert_eq!(
+            children,
+            [
+                ("Main", "Script"),
+                ("Util.module", "Folder"),
+                ("HUD", "ScreenGui"),
+                ("Config.json", "Folder"),
+                (".server", "Folder"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let project: Project = serde_json::from_str(
+            r#"{
+                "tree": {
+                    "$className": "Part",
+                    "$properties": {
+                        "NotARealProperty": true
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut warnings = Vec::new();
+        let lenient = BuildOptions::default();
+        assert!(instantiate(
+            project.tree.clone(),
+            "Test",
+            &lenient,
+            &mut |d: Diagnostic| { warnings.push(d) }
+        )
+        .is_ok());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "Test");
+        assert_eq!(warnings[0].property.as_deref(), Some("NotARealProperty"));
+
+        let strict = BuildOptions {
+            strict: true,
+            ..Default::default()
+        };
+        match instantiate(project.tree, "Test", &strict, &mut |_: Diagnostic| {}) {
+            Err(Error::Resolve {
+                class, property, ..
+            }) => {
+                assert_eq!(class, "Part");
+                assert_eq!(property, "NotARealProperty");
+            }
+            other => panic!("expected a resolve error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_class_name_case() {
+        let project: Project = serde_json::from_str(
+            r#"{
+                "tree": {
+                    "$className": "DataModel",
+                    "Workspace": {
+                        "$className": "workspace",
+                        "Floor": { "$className": "part" }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut warnings = Vec::new();
+        let dom = instantiate(
+            project.tree.clone(),
+            "Test",
+            &BuildOptions::default(),
+            &mut |d: Diagnostic| warnings.push(d),
+        )
+        .unwrap();
+        let workspace = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        let floor = dom.get_by_ref(workspace.children()[0]).unwrap();
+        assert_eq!(workspace.class, "Workspace");
+        assert_eq!(floor.class, "Part");
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[1].path, "Test/Workspace/Floor");
+
+        let strict = BuildOptions {
+            strict: true,
+            ..Default::default()
+        };
+        match instantiate(project.tree, "Test", &strict, &mut |_: Diagnostic| {}) {
+            Err(Error::ClassNameCase {
+                class, suggestion, ..
+            }) => {
+                assert_eq!(class, "workspace");
+                assert_eq!(suggestion, "Workspace");
+            }
+            other => panic!("expected a class name error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_transform_pipeline() {
+        let project = Project::from_json(
+            r#"{
+                "name": "Test",
+                "tree": {
+                    "$className": "Folder"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let options = BuildOptions {
+            transforms: Pipeline::new()
+                .project_pass("rename", |project, _| {
+                    project.name = Some("Renamed".to_owned());
+                    Ok(())
+                })
+                .dom_pass("inject", |dom, _| {
+                    let root = dom.root_ref();
+                    dom.insert(root, InstanceBuilder::new("StringValue").with_name("Meta"));
+                    Ok(())
+                }),
+            ..Default::default()
+        };
+
+        let dom = build(project, &options, &mut |_: Diagnostic| {}).unwrap();
+        let root = dom.root();
+        assert_eq!(root.name, "Renamed");
+        assert_eq!(root.children().len(), 1);
+
+        let failing = BuildOptions {
+            transforms: Pipeline::new().dom_pass("fail", |_, _| Err("nope".into())),
+            ..Default::default()
+        };
+        let project = Project::new("Test", ProjectNode::new("Folder"));
+        match build(project, &failing, &mut |_: Diagnostic| {}) {
+            Err(Error::Transform { name, .. }) => assert_eq!(name, "fail"),
+            other => panic!("expected a transform error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_fill_defaults() {
+        let project = Project::new("Test", ProjectNode::new("Part").property("Anchored", true));
+
+        let options = BuildOptions {
+            fill_defaults: true,
+            ..Default::default()
+        };
+        let dom = build(project, &options, &mut |_: Diagnostic| {}).unwrap();
+        let part = dom.root();
+
+        // Explicit properties win over defaults
+        assert_eq!(part.properties.get(&"Anchored".into()), Some(&true.into()));
+        assert!(part.properties.contains_key(&"Transparency".into()));
+    }
+
+    #[test]
+    fn test_root_handling() {
+        let project = || {
+            Project::new(
+                "Test",
+                ProjectNode::new("Folder").child("Inner", ProjectNode::new("Model")),
+            )
+        };
+
+        let to_xml = |root| {
+            let options = BuildOptions {
+                root,
+                ..Default::default()
+            };
+            let dom = build(project(), &options, &mut |_: Diagnostic| {}).unwrap();
+
+            let mut buffer = Vec::new();
+            output::write_dom(&mut buffer, &dom, OutputFormat::Xml, &options).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert!(to_xml(RootHandling::Auto).contains(r#"<Item class="Folder""#));
+        assert!(to_xml(RootHandling::Model).contains(r#"<Item class="Folder""#));
+
+        let place = to_xml(RootHandling::Place);
+        assert!(!place.contains(r#"<Item class="Folder""#));
+        assert!(place.contains(r#"<Item class="Model""#));
+    }
+
+    #[test]
+    fn test_child_order_is_preserved() {
+        let json = r#"{
+            "name": "Menu",
+            "tree": {
+                "$className": "Frame",
+                "Title": { "$className": "TextLabel" },
+                "Play": { "$className": "TextButton" },
+                "Settings": { "$className": "TextButton" },
+                "Quit": { "$className": "TextButton" }
+            }
+        }"#;
+
+        let project = Project::from_json(json).unwrap();
+        let dom = build(project, &BuildOptions::default(), &mut |_: Diagnostic| {}).unwrap();
+        let names: Vec<_> = dom
+            .root()
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap().name.as_str())
+            .collect();
+        assert_eq!(names, ["Title", "Play", "Settings", "Quit"]);
+
+        let options = BuildOptions {
+            sort_children: true,
+            ..Default::default()
+        };
+        let project = Project::from_json(json).unwrap();
+        let dom = build(project, &options, &mut |_: Diagnostic| {}).unwrap();
+        let names: Vec<_> = dom
+            .root()
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap().name.as_str())
+            .collect();
+        assert_eq!(names, ["Play", "Quit", "Settings", "Title"]);
+    }
+
+    #[test]
+    fn test_order_directive() {
+        let json = r#"{
+            "name": "Menu",
+            "tree": {
+                "$className": "Frame",
+                "Quit": { "$className": "TextButton", "$order": 10 },
+                "Title": { "$className": "TextLabel", "$order": -1 },
+                "Play": { "$className": "TextButton" },
+                "Settings": { "$className": "TextButton" }
+            }
+        }"#;
+
+        for sort_children in [false, true] {
+            let options = BuildOptions {
+                sort_children,
+                ..Default::default()
+            };
+            let project = Project::from_json(json).unwrap();
+            let dom = build(project, &options, &mut |_: Diagnostic| {}).unwrap();
+            let names: Vec<_> = dom
+                .root()
+                .children()
+                .iter()
+                .map(|&child| dom.get_by_ref(child).unwrap().name.as_str())
+                .collect();
+            assert_eq!(names, ["Title", "Play", "Settings", "Quit"]);
+        }
+    }
+
+    #[test]
+    fn test_deterministic_referents() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "Folder",
+                "A": {}, "B": {}, "C": {}, "D": {}, "E": {}, "F": {}
+            }
+        }"#;
+
+        let to_xml = || {
+            let options = BuildOptions {
+                deterministic: true,
+                ..Default::default()
+            };
+            let project = Project::from_json(json).unwrap();
+            let dom = build(project, &options, &mut |_: Diagnostic| {}).unwrap();
+
+            let mut buffer = Vec::new();
+            output::write_dom(&mut buffer, &dom, OutputFormat::Xml, &options).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(to_xml(), to_xml());
+    }
+
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_build_cache() {
+        use crate::cache::BuildCache;
+
+        let project = || {
+            Project::new(
+                "Game",
+                ProjectNode::new("DataModel")
+                    .child(
+                        "Workspace",
+                        ProjectNode::default().child(
+                            "Baseplate",
+                            ProjectNode::new("Part").property("Anchored", true),
+                        ),
+                    )
+                    .child(
+                        "Lighting",
+                        ProjectNode::default().property("NotARealProperty", true),
+                    ),
+            )
+        };
+
+        let options = BuildOptions {
+            deterministic: true,
+            cache: Some(BuildCache::new()),
+            ..Default::default()
+        };
+
+        let to_xml = |warnings: &mut Vec<Diagnostic>| {
+            let dom = build(project(), &options, &mut |d: Diagnostic| warnings.push(d)).unwrap();
+
+            let mut buffer = Vec::new();
+            output::write_dom(&mut buffer, &dom, OutputFormat::Xml, &options).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        let mut first_warnings = Vec::new();
+        let first = to_xml(&mut first_warnings);
+        let cache = options.cache.as_ref().unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (0, 2));
+
+        // Workspace comes from the cache; Lighting had a warning, so it's
+        // rebuilt and the warning is reported again
+        let mut second_warnings = Vec::new();
+        let second = to_xml(&mut second_warnings);
+        assert_eq!((cache.hits(), cache.misses()), (1, 3));
+
+        assert_eq!(first, second);
+        assert_eq!(first_warnings, second_warnings);
+        assert!(second.contains(r#"<string name="Name">Baseplate</string>"#));
+    }
+
+    #[test]
+    fn test_many_top_level_subtrees() {
+        let mut tree = ProjectNode::new("DataModel");
+        for service in [
+            "Workspace",
+            "Lighting",
+            "ReplicatedStorage",
+            "ServerStorage",
+        ] {
+            tree = tree.child(
+                service,
+                ProjectNode::new("Folder")
+                    .child("Inner", ProjectNode::new("Part").property("Bogus", true)),
+            );
+        }
+
+        let options = BuildOptions {
+            sort_children: true,
+            ..Default::default()
+        };
+        let mut warnings = Vec::new();
+        let dom = instantiate(tree, "Game", &options, &mut |d: Diagnostic| {
+            warnings.push(d)
+        })
+        .unwrap();
+
+        let names: Vec<_> = dom
+            .root()
+            .children()
+            .iter()
+            .map(|&child| dom.get_by_ref(child).unwrap().name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "Lighting",
+                "ReplicatedStorage",
+                "ServerStorage",
+                "Workspace"
+            ]
+        );
+        assert!(crate::query::find_by_path(&dom, "Game/Lighting/Inner").is_some());
+
+        // Warnings come out in the same order as a single-threaded build
+        let paths: Vec<_> = warnings.iter().map(|w| w.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "Game/Lighting/Inner",
+                "Game/ReplicatedStorage/Inner",
+                "Game/ServerStorage/Inner",
+                "Game/Workspace/Inner"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested_tree() {
+        const DEPTH: usize = 20_000;
+
+        let mut node = ProjectNode::new("Folder");
+        for _ in 0..DEPTH {
+            node = ProjectNode::new("Folder").child("Inner", node);
+        }
+
+        // Through `build` rather than `instantiate`, so the passes before
+        // instantiation walk the whole depth too
+        let dom = build(
+            Project::new("Root", node),
+            &BuildOptions::default(),
+            &mut |_: Diagnostic| {},
+        )
+        .unwrap();
+        assert_eq!(
+            crate::query::descendants(&dom, dom.root_ref()).count(),
+            DEPTH + 1
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested_json() {
+        const DEPTH: usize = 20_000;
+
+        let json = format!(
+            r#"{{"name": "Root", "tree": {}{{"$className": "Folder"}}{}}}"#,
+            r#"{"$className": "Folder", "Inner": "#.repeat(DEPTH),
+            "}".repeat(DEPTH),
+        );
+
+        let project = Project::from_json(&json).unwrap();
+        let dom = build(project, &BuildOptions::default(), &mut |_: Diagnostic| {}).unwrap();
+        assert_eq!(
+            crate::query::descendants(&dom, dom.root_ref()).count(),
+            DEPTH + 1
+        );
+    }
+
+    #[test]
+    fn test_folder_without_explicit_classname() {
+        let json = r#"{
+            "name": "Test",
+            "tree": {
+                "$className": "DataModel",
+                "Workspace": {
+                    "$className": "Workspace",
+                    "SomeFolder": {
+                        "InnerFolder": {}
+                    }
+                }
+            }
+        }"#;
+
+        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+
+        // Should default to Folder when no className is specified
+        assert!(xml.contains(r#"<string name="Name">SomeFolder</string>"#));
+        assert!(xml.contains(r#"<string name="Name">InnerFolder</string>"#));
+    }
+}
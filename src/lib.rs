@@ -0,0 +1,123 @@
+//! Library interface to rbxbuild.
+//!
+//! Projects can be parsed from JSON or put together in code with the builder
+//! methods on [`ProjectNode`], turned into a [`WeakDom`](rbx_dom_weak::WeakDom)
+//! with [`instantiate`], inspected with the helpers in [`query`], and written
+//! out with [`output::write_dom`].
+//!
+//! ```no_run
+//! use rbxbuild::{
+//!     diagnostics::Diagnostic, instantiate, output, BuildOptions, Project, ProjectNode,
+//! };
+//!
+//! let project = Project::new(
+//!     "Baseplate",
+//!     ProjectNode::new("Part")
+//!         .property("Anchored", true)
+//!         .property("Size", [64.0, 1.0, 64.0]),
+//! );
+//!
+//! let mut warnings = Vec::new();
+//! let dom = instantiate(
+//!     project.tree,
+//!     "Baseplate",
+//!     &BuildOptions::default(),
+//!     &mut |d: Diagnostic| warnings.push(d),
+//! )?;
+//! output::write_dom(
+//!     std::io::stdout(),
+//!     &dom,
+//!     output::OutputFormat::Xml,
+//!     &BuildOptions::default(),
+//! )?;
+//! # Ok::<(), rbxbuild::Error>(())
+//! ```
+
+pub mod assets;
+#[cfg(feature = "cli")]
+pub mod batch;
+pub mod bundle;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod characters;
+#[cfg(feature = "cli")]
+pub mod check;
+pub mod collision;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "daemon")]
+pub mod daemon;
+pub mod database;
+pub mod decompile;
+pub mod defines;
+pub mod diagnostics;
+pub mod diff;
+pub mod embed;
+mod error;
+#[cfg(feature = "explore")]
+pub mod explore;
+mod expression;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter;
+pub mod floats;
+#[cfg(feature = "luau")]
+pub mod generate;
+#[cfg(feature = "config")]
+pub mod hooks;
+pub mod humanoid;
+pub mod input;
+mod instantiate;
+pub mod limits;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+#[cfg(feature = "manifest")]
+pub mod manifest;
+pub mod merge;
+pub mod meshes;
+pub mod migrate;
+pub mod names;
+#[cfg(feature = "node")]
+mod node;
+pub mod options;
+pub mod output;
+pub mod placement;
+pub mod presets;
+mod project;
+pub mod provenance;
+#[cfg(feature = "publish")]
+pub mod publish;
+#[cfg(feature = "python")]
+mod python;
+pub mod query;
+pub mod report;
+mod resolution;
+pub mod resolver;
+pub mod scripts;
+pub mod scrub;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod source;
+pub mod split;
+pub mod stats;
+pub mod surface;
+pub mod templates;
+#[cfg(feature = "terrain")]
+pub mod terrain;
+pub mod timing;
+pub mod transform;
+pub mod unique_ids;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use error::{Error, Result};
+pub use instantiate::{build, instantiate};
+pub use options::{BuildOptions, RootHandling};
+pub use project::{Project, ProjectNode};
+pub use resolution::{
+    AmbiguousValue, AssetReference, HeightmapReference, MaterialColor, NumberOrExpression,
+    UnresolvedValue,
+};
+
+// Required by resolution module
+const REF_POINTER_ATTRIBUTE_PREFIX: &str = "RojoId_";
@@ -0,0 +1,169 @@
+//! Limits on how big a build can get.
+//!
+//! Generated projects can go wrong in ways that produce millions of nodes or
+//! trees thousands of levels deep, which is better caught before it takes a
+//! build server's memory than after. The same limits can pre-flight a place
+//! against the ones Roblox enforces.
+
+use rbx_dom_weak::{types::Variant, WeakDom};
+use serde::Deserialize;
+use ustr::Ustr;
+
+use crate::error::{Error, Result};
+use crate::project::ProjectNode;
+use crate::query;
+
+/// Limits on the size of a build. Each one is off unless set.
+///
+/// Depth counts levels below the root, so a root with children and no
+/// grandchildren is 1 deep.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Limits {
+    pub max_instances: Option<usize>,
+    pub max_depth: Option<usize>,
+
+    /// Combined length of every script's `Source`, in bytes.
+    pub max_source_bytes: Option<usize>,
+}
+
+impl Limits {
+    /// Checks the project tree before it's instantiated, so a tree that's too
+    /// big fails before it takes up any more memory.
+    pub fn check_project(&self, tree: &ProjectNode, root_name: &str) -> Result<()> {
+        if self.max_instances.is_none() && self.max_depth.is_none() {
+            return Ok(());
+        }
+
+        let mut count = 0;
+        let mut stack = vec![(root_name.to_owned(), tree, 0)];
+        while let Some((path, node, depth)) = stack.pop() {
+            count += 1;
+            self.check_instances(count)?;
+            self.check_depth(&path, depth)?;
+
+            stack.extend(
+                node.children
+                    .iter()
+                    .map(|(name, child)| (format!("{path}/{name}"), child, depth + 1)),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Checks the built tree, which can have more instances than the project
+    /// listed, and is the only place script sources are known.
+    pub fn check_dom(&self, dom: &WeakDom) -> Result<()> {
+        let source_key = Ustr::from("Source");
+        let mut count = 0;
+        let mut source_bytes = 0;
+        let mut largest: Option<(usize, _)> = None;
+
+        let mut stack = vec![(dom.root_ref(), 0)];
+        while let Some((referent, depth)) = stack.pop() {
+            let instance = dom.get_by_ref(referent).unwrap();
+            count += 1;
+            self.check_instances(count)?;
+            if self.max_depth.is_some_and(|max| depth > max) {
+                self.check_depth(&query::path_of(dom, referent), depth)?;
+            }
+
+            if let Some(Variant::String(source)) = instance.properties.get(&source_key) {
+                source_bytes += source.len();
+                if !largest.is_some_and(|(bytes, _)| source.len() <= bytes) {
+                    largest = Some((source.len(), referent));
+                }
+            }
+
+            stack.extend(instance.children().iter().map(|&child| (child, depth + 1)));
+        }
+
+        if let (Some(max), Some((bytes, referent))) = (self.max_source_bytes, largest) {
+            if source_bytes > max {
+                return Err(Error::Limit(format!(
+                    "Scripts hold {source_bytes} bytes of source, more than the limit of {max}; the largest is {} with {bytes}",
+                    query::path_of(dom, referent)
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_instances(&self, count: usize) -> Result<()> {
+        match self.max_instances {
+            Some(max) if count > max => Err(Error::Limit(format!(
+                "The build has more than the limit of {max} instances"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_depth(&self, path: &str, depth: usize) -> Result<()> {
+        match self.max_depth {
+            Some(max) if depth > max => Err(Error::Limit(format!(
+                "{path} is {depth} levels deep, more than the limit of {max}"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::project::Project;
+
+    #[test]
+    fn enforces_limits() {
+        let project = Project::from_json(
+            r#"{
+                "name": "Game",
+                "tree": {
+                    "$className": "Folder",
+                    "Scripts": {
+                        "$className": "Folder",
+                        "Main": {
+                            "$className": "Script",
+                            "$properties": { "Source": "print('hello')" }
+                        }
+                    },
+                    "Empty": { "$className": "Folder" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let limits = |max_instances, max_depth, max_source_bytes| Limits {
+            max_instances,
+            max_depth,
+            max_source_bytes,
+        };
+        let tree = &project.tree;
+        assert!(limits(Some(4), Some(2), None)
+            .check_project(tree, "Game")
+            .is_ok());
+        assert!(limits(Some(3), None, None)
+            .check_project(tree, "Game")
+            .is_err());
+        let error = limits(None, Some(1), None)
+            .check_project(tree, "Game")
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Game/Scripts/Main is 2 levels deep"));
+
+        let dom = crate::build(
+            project.clone(),
+            &Default::default(),
+            &mut |_: crate::diagnostics::Diagnostic| {},
+        )
+        .unwrap();
+        assert!(limits(Some(4), Some(2), Some(14)).check_dom(&dom).is_ok());
+        let error = limits(None, None, Some(13)).check_dom(&dom).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("the largest is Game/Scripts/Main"));
+    }
+}
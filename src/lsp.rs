@@ -0,0 +1,671 @@
+//! A language server for project files, so editors can check them as they're
+//! typed and suggest class names, property names and enum items from the
+//! reflection database. `rbxbuild lsp` speaks it over stdin and stdout.
+//!
+//! - Diagnostics: JSON syntax errors, and the warnings and errors a default
+//!   build reports, on the key they're about.
+//! - Hover: a class's ancestry on `$className` values and instance keys, and
+//!   a property's type (with an enum's items) on `$properties` keys.
+//! - Completion: top-level keys, node keys (`$className`, `$properties` and
+//!   a DataModel's services), class names, property names and enum items.
+//!
+//! Documents are synced in full on every change.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{self, BufRead, Write},
+    ops::Range,
+};
+
+use rbx_reflection::{ClassTag, DataType, PropertyDescriptor, PropertyKind, PropertyTag};
+use serde_json::{json, Value};
+
+use crate::diagnostics::Severity;
+use crate::embed;
+use crate::error::Error;
+use crate::instantiate::{infer_child_class, infer_class_from_name};
+use crate::project::Project;
+use crate::source::{self, Context, Key};
+
+/// Keys a project can have at the top level.
+const PROJECT_KEYS: &[&str] = &[
+    "name",
+    "tree",
+    "defines",
+    "gameId",
+    "placeId",
+    "meta",
+    "preset",
+    "collisionGroups",
+];
+
+/// Keys a node can have besides its children.
+const NODE_KEYS: &[&str] = &[
+    "$className",
+    "$properties",
+    "$generate",
+    "$surface",
+    "$outfit",
+    "$buildTags",
+    "$order",
+];
+
+/// Enum items listed in a property's hover before the rest are counted.
+const MAX_HOVER_ITEMS: usize = 20;
+
+// LSP's numbering for the kinds of things it shows
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+const COMPLETION_CLASS: u8 = 7;
+const COMPLETION_PROPERTY: u8 = 10;
+const COMPLETION_KEYWORD: u8 = 14;
+const COMPLETION_ENUM_MEMBER: u8 = 20;
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// The open documents, by URI.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, String>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles one message from the client, returning the responses and
+    /// notifications to send back.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["\""] },
+                },
+                "serverInfo": { "name": "rbxbuild", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => Value::Null,
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_owned(), text.to_owned());
+                return vec![publish_diagnostics(uri, text)];
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                let Some(text) = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                else {
+                    return Vec::new();
+                };
+                self.documents.insert(uri.to_owned(), text.to_owned());
+                return vec![publish_diagnostics(uri, text)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                })];
+            }
+            "textDocument/hover" | "textDocument/completion" => {
+                let text = self.documents.get(uri).map_or("", String::as_str);
+                let position = &params["position"];
+                let offset = source::offset(
+                    text,
+                    position["line"].as_u64().unwrap_or(0) as u32,
+                    position["character"].as_u64().unwrap_or(0) as u32,
+                );
+
+                if method == "textDocument/hover" {
+                    hover(text, offset)
+                } else {
+                    Value::Array(completions(text, offset))
+                }
+            }
+            _ if message.get("id").is_some() => {
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": { "code": METHOD_NOT_FOUND, "message": format!("Unknown method {method}") },
+                })];
+            }
+            // Other notifications, like `initialized`, need no answer
+            _ => return Vec::new(),
+        };
+
+        vec![json!({ "jsonrpc": "2.0", "id": message["id"], "result": result })]
+    }
+}
+
+/// Answers messages from `input` until the client says to exit.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::new();
+
+    while let Some(message) = read_message(&mut input)? {
+        if message["method"] == "exit" {
+            break;
+        }
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a message framed with a `Content-Length` header, or `None` at the
+/// end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Message without a Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+fn range(text: &str, span: Range<usize>) -> Value {
+    let (start_line, start_character) = source::position(text, span.start);
+    let (end_line, end_character) = source::position(text, span.end);
+    json!({
+        "start": { "line": start_line, "character": start_character },
+        "end": { "line": end_line, "character": end_character },
+    })
+}
+
+fn publish_diagnostics(uri: &str, text: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnose(text) },
+    })
+}
+
+/// Builds the document like `rbxbuild` would by default and places what's
+/// reported on the keys it's about.
+pub fn diagnose(text: &str) -> Vec<Value> {
+    let diagnostic = |span: Range<usize>, severity: u8, message: String| {
+        json!({
+            "range": range(text, span),
+            "severity": severity,
+            "source": "rbxbuild",
+            "message": message,
+        })
+    };
+
+    if let Err(Error::Parse(error)) = Project::from_json(text) {
+        // serde_json counts lines from 1 and columns in bytes from 1
+        let line_start = text
+            .split_inclusive('\n')
+            .take(error.line().saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>();
+        let offset = (line_start + error.column().saturating_sub(1)).min(text.len());
+        let offset = (0..=offset)
+            .rev()
+            .find(|&offset| text.is_char_boundary(offset))
+            .unwrap_or(0);
+        return vec![diagnostic(
+            offset..offset,
+            SEVERITY_ERROR,
+            error.to_string(),
+        )];
+    }
+
+    let keys = source::keys(text);
+    match embed::validate_json(text) {
+        Ok(reported) => reported
+            .into_iter()
+            .map(|reported| {
+                let span = source::locate(&keys, &reported).unwrap_or(0..0);
+                let severity = match reported.severity {
+                    Severity::Warning => SEVERITY_WARNING,
+                    Severity::Error => SEVERITY_ERROR,
+                };
+                diagnostic(span, severity, reported.message)
+            })
+            .collect(),
+        Err(error) => vec![diagnostic(0..0, SEVERITY_ERROR, error.to_string())],
+    }
+}
+
+/// Whether the keys lead to a node of the tree rather than to one of a
+/// node's `$` keys.
+fn is_node(path: &[String]) -> bool {
+    path.first().is_some_and(|first| first == "tree")
+        && path[1..].iter().all(|segment| !segment.starts_with('$'))
+}
+
+/// The class of the node at `path`, the way the build works it out.
+fn node_class(keys: &[Key], path: &[String]) -> String {
+    let explicit = keys.iter().find_map(|key| {
+        let is_class_name = key.path.len() == path.len() + 1
+            && key.path.starts_with(path)
+            && key.path.last().is_some_and(|last| last == "$className");
+        is_class_name.then(|| key.string.clone()).flatten()
+    });
+
+    explicit
+        .or_else(|| {
+            let (name, parent) = path.split_last()?;
+            infer_child_class(&node_class(keys, parent), name)
+                .or_else(|| infer_class_from_name(name))
+                .map(str::to_owned)
+        })
+        .unwrap_or_else(|| "Folder".to_owned())
+}
+
+/// A class's superclasses, starting with the class itself.
+fn ancestry(class: &str) -> Vec<&'static str> {
+    let Ok(database) = crate::database::get() else {
+        return Vec::new();
+    };
+
+    let mut ancestry = Vec::new();
+    let mut current = database.classes.get(class);
+    while let Some(descriptor) = current {
+        ancestry.push(descriptor.name.as_ref());
+        current = descriptor
+            .superclass
+            .as_deref()
+            .and_then(|superclass| database.classes.get(superclass));
+    }
+    ancestry
+}
+
+/// Every property instances of `class` can be given in a project, with the
+/// class that declares it.
+fn properties(class: &str) -> Vec<(&'static str, &'static PropertyDescriptor<'static>)> {
+    let Ok(database) = crate::database::get() else {
+        return Vec::new();
+    };
+
+    ancestry(class)
+        .into_iter()
+        .filter_map(|class| database.classes.get(class))
+        .flat_map(|descriptor| {
+            descriptor
+                .properties
+                .values()
+                .map(move |property| (descriptor.name.as_ref(), property))
+        })
+        .filter(|(_, property)| {
+            matches!(property.kind, PropertyKind::Canonical { .. })
+                && !property.tags.contains(&PropertyTag::Deprecated)
+                && !property.tags.contains(&PropertyTag::ReadOnly)
+        })
+        .collect()
+}
+
+fn describe_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Value(ty) => format!("{ty:?}"),
+        DataType::Enum(name) => format!("Enum.{name}"),
+        other => format!("{other:?}"),
+    }
+}
+
+fn enum_items(data_type: &DataType) -> Vec<&'static str> {
+    let DataType::Enum(name) = data_type else {
+        return Vec::new();
+    };
+    let Some(descriptor) = crate::database::get()
+        .ok()
+        .and_then(|database| database.enums.get(name.as_ref()))
+    else {
+        return Vec::new();
+    };
+
+    let mut items: Vec<_> = descriptor.items.keys().map(|item| item.as_ref()).collect();
+    items.sort_unstable();
+    items
+}
+
+fn hover(text: &str, offset: usize) -> Value {
+    let keys = source::keys(text);
+    let Some((key, on_value)) = source::key_at(&keys, offset) else {
+        return Value::Null;
+    };
+    let (parent, name) = key.path.split_at(key.path.len() - 1);
+    let name = &name[0];
+
+    let (contents, span) = if on_value && name == "$className" {
+        let Some(class) = &key.string else {
+            return Value::Null;
+        };
+        (describe_class(class), key.value.clone().unwrap_or_default())
+    } else if on_value {
+        return Value::Null;
+    } else if is_node(&key.path) {
+        (
+            describe_class(&node_class(&keys, &key.path)),
+            key.span.clone(),
+        )
+    } else if parent.last().is_some_and(|last| last == "$properties")
+        && is_node(&parent[..parent.len() - 1])
+    {
+        let class = node_class(&keys, &parent[..parent.len() - 1]);
+        let Some(contents) = describe_property(&class, name) else {
+            return Value::Null;
+        };
+        (contents, key.span.clone())
+    } else {
+        return Value::Null;
+    };
+
+    json!({
+        "contents": { "kind": "markdown", "value": contents },
+        "range": range(text, span),
+    })
+}
+
+fn describe_class(class: &str) -> String {
+    let ancestry = ancestry(class);
+    if ancestry.is_empty() {
+        return format!("**{class}**\n\nNot a class in the reflection database");
+    }
+
+    let mut contents = format!("**{class}**\n\n{}", ancestry.join(" → "));
+    let tags = crate::database::get()
+        .ok()
+        .and_then(|database| database.classes.get(class))
+        .map(|descriptor| &descriptor.tags);
+    if let Some(tags) = tags {
+        if tags.contains(&ClassTag::Service) {
+            contents.push_str("\n\nService");
+        } else if tags.contains(&ClassTag::NotCreatable) {
+            contents.push_str("\n\nCan't be created");
+        }
+        if tags.contains(&ClassTag::Deprecated) {
+            contents.push_str("\n\nDeprecated");
+        }
+    }
+    contents
+}
+
+fn describe_property(class: &str, property: &str) -> Option<String> {
+    let (declared_by, descriptor) = properties(class)
+        .into_iter()
+        .find(|(_, descriptor)| descriptor.name == property)?;
+
+    let mut contents = format!(
+        "**{property}**: `{}`\n\nProperty of {declared_by}",
+        describe_type(&descriptor.data_type)
+    );
+
+    let items = enum_items(&descriptor.data_type);
+    if !items.is_empty() {
+        contents.push_str("\n\n");
+        for item in items.iter().take(MAX_HOVER_ITEMS) {
+            write!(contents, "`{item}` ").unwrap();
+        }
+        if items.len() > MAX_HOVER_ITEMS {
+            write!(contents, "and {} more", items.len() - MAX_HOVER_ITEMS).unwrap();
+        }
+    }
+    Some(contents)
+}
+
+fn completions(text: &str, offset: usize) -> Vec<Value> {
+    let keys = source::keys(text);
+
+    let (candidates, span) = match source::context_at(text, offset) {
+        Context::Key { path, span } => (key_candidates(&keys, &path), span),
+        Context::Value { path, span } => (value_candidates(&keys, &path), span),
+        Context::None => return Vec::new(),
+    };
+
+    // Replace what's inside the string being typed, or insert a new one
+    let (replace, quote) = if span.is_empty() {
+        (offset..offset, true)
+    } else {
+        let closed = span.len() >= 2 && text[span.clone()].ends_with('"');
+        (span.start + 1..span.end - usize::from(closed), false)
+    };
+    let edit_range = range(text, replace);
+
+    candidates
+        .into_iter()
+        .map(|(label, kind, detail)| {
+            let new_text = if quote {
+                format!("\"{label}\"")
+            } else {
+                label.clone()
+            };
+            json!({
+                "label": label,
+                "kind": kind,
+                "detail": detail,
+                "textEdit": { "range": edit_range, "newText": new_text },
+            })
+        })
+        .collect()
+}
+
+type Candidate = (String, u8, String);
+
+fn key_candidates(keys: &[Key], path: &[String]) -> Vec<Candidate> {
+    let keyword = |key: &&str| (key.to_string(), COMPLETION_KEYWORD, String::new());
+
+    if path.is_empty() {
+        return PROJECT_KEYS.iter().map(keyword).collect();
+    }
+
+    if is_node(path) {
+        let mut candidates: Vec<Candidate> = NODE_KEYS.iter().map(keyword).collect();
+
+        if node_class(keys, path) == "DataModel" {
+            if let Ok(database) = crate::database::get() {
+                candidates.extend(
+                    database
+                        .classes
+                        .values()
+                        .filter(|class| class.tags.contains(&ClassTag::Service))
+                        .map(|class| {
+                            (
+                                class.name.to_string(),
+                                COMPLETION_CLASS,
+                                "Service".to_owned(),
+                            )
+                        }),
+                );
+            }
+        }
+        return candidates;
+    }
+
+    let (parent, last) = path.split_at(path.len() - 1);
+    if last[0] == "$properties" && is_node(parent) {
+        return properties(&node_class(keys, parent))
+            .into_iter()
+            .map(|(declared_by, descriptor)| {
+                (
+                    descriptor.name.to_string(),
+                    COMPLETION_PROPERTY,
+                    format!("{} ({declared_by})", describe_type(&descriptor.data_type)),
+                )
+            })
+            .collect();
+    }
+
+    Vec::new()
+}
+
+fn value_candidates(keys: &[Key], path: &[String]) -> Vec<Candidate> {
+    let Some((last, parent)) = path.split_last() else {
+        return Vec::new();
+    };
+
+    if last == "$className" && is_node(parent) {
+        let Ok(database) = crate::database::get() else {
+            return Vec::new();
+        };
+        return database
+            .classes
+            .values()
+            .filter(|class| {
+                !class.tags.contains(&ClassTag::NotCreatable)
+                    || class.tags.contains(&ClassTag::Service)
+            })
+            .map(|class| {
+                let detail = class.superclass.as_deref().unwrap_or_default().to_owned();
+                (class.name.to_string(), COMPLETION_CLASS, detail)
+            })
+            .collect();
+    }
+
+    let Some((properties_key, node)) = parent.split_last() else {
+        return Vec::new();
+    };
+    if properties_key == "$properties" && is_node(node) {
+        let class = node_class(keys, node);
+        if let Some((_, descriptor)) = properties(&class)
+            .into_iter()
+            .find(|(_, descriptor)| descriptor.name == *last)
+        {
+            let detail = describe_type(&descriptor.data_type);
+            return enum_items(&descriptor.data_type)
+                .into_iter()
+                .map(|item| (item.to_owned(), COMPLETION_ENUM_MEMBER, detail.clone()))
+                .collect();
+        }
+    }
+
+    Vec::new()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn open(server: &mut Server, text: &str) -> Value {
+        let mut replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": "file:///game.project.json", "text": text } },
+        }));
+        replies.remove(0)
+    }
+
+    fn request(server: &mut Server, method: &str, line: u32, character: u32) -> Value {
+        let mut replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": {
+                "textDocument": { "uri": "file:///game.project.json" },
+                "position": { "line": line, "character": character },
+            },
+        }));
+        replies.remove(0)["result"].take()
+    }
+
+    #[test]
+    fn reports_problems_on_their_keys() {
+        let mut server = Server::new();
+
+        let published = open(&mut server, "{\n  \"tree\": {\n");
+        let diagnostics = &published["params"]["diagnostics"];
+        assert_eq!(diagnostics[0]["severity"], SEVERITY_ERROR);
+        assert!(diagnostics[0]["message"].as_str().unwrap().contains("EOF"));
+
+        let text = "{\n  \"name\": \"Rock\",\n  \"tree\": {\n    \"$className\": \"Part\",\n    \"$properties\": { \"Nope\": 1 }\n  }\n}";
+        let published = open(&mut server, text);
+        let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["severity"], SEVERITY_WARNING);
+        assert_eq!(
+            diagnostics[0]["range"]["start"],
+            json!({ "line": 4, "character": 21 })
+        );
+    }
+
+    #[test]
+    fn completes_and_describes() {
+        let mut server = Server::new();
+        let text = "{\n  \"tree\": {\n    \"$className\": \"Pa\",\n    \"$properties\": { \"Material\": \"\" }\n  }\n}";
+        open(&mut server, text);
+
+        let classes = request(&mut server, "textDocument/completion", 2, 21);
+        let part = classes
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["label"] == "Part")
+            .unwrap();
+        assert_eq!(part["textEdit"]["newText"], "Part");
+        assert_eq!(part["textEdit"]["range"]["start"]["character"], 19);
+
+        let items = request(&mut server, "textDocument/completion", 3, 34);
+        assert!(items
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|item| item["label"] == "Grass"));
+
+        let hover = request(&mut server, "textDocument/hover", 3, 25);
+        let contents = hover["contents"]["value"].as_str().unwrap();
+        assert!(contents.contains("Enum.Material"), "{contents}");
+
+        let unknown = server.handle(&json!({ "jsonrpc": "2.0", "id": 2, "method": "nope" }));
+        assert_eq!(unknown[0]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn frames_messages() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"shutdown"}"#;
+        let exit = r#"{"jsonrpc":"2.0","method":"exit"}"#;
+        let input = format!(
+            "Content-Length: {}\r\n\r\n{body}Content-Length: {}\r\n\r\n{exit}",
+            body.len(),
+            exit.len()
+        );
+
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let (header, body) = output.split_once("\r\n\r\n").unwrap();
+        assert_eq!(header, format!("Content-Length: {}", body.len()));
+        let reply: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(reply, json!({ "jsonrpc": "2.0", "id": 1, "result": null }));
+    }
+}
@@ -1,35 +1,821 @@
-use anyhow::Result;
-use rbx_dom_weak::{InstanceBuilder, WeakDom};
-use rbx_xml::to_writer_default;
-use serde::Deserialize;
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use rbxbuild::{
+    assets,
+    batch::{self, Outcome},
+    build,
+    bundle::{self, ArchiveFormat},
+    characters::InvalidCharacterPolicy,
+    check::{self, Checked},
+    config::{Config, Overrides},
+    daemon::{self, Daemon},
+    decompile::decompile,
+    defines::DefineValue,
+    diagnostics::{Diagnostic, DiagnosticFormat, DiagnosticSink, EditorFormat},
+    diff::{diff, first_divergence, verify_written},
+    embed, explore,
+    floats::NonFinitePolicy,
+    hooks::BuildInfo,
+    input,
+    limits::Limits,
+    lsp,
+    manifest::manifest,
+    merge::merge,
+    migrate::migrate,
+    names::NameCollisionPolicy,
+    output,
+    output::OutputFormat,
+    presets::Preset,
+    provenance::ProvenanceStyle,
+    publish::{
+        assets::{parse_creator, Creator},
+        PublishTarget, VersionType, DEFAULT_API_KEY_ENV,
+    },
+    query::{self, Predicate},
+    report::html_report,
+    serve::{
+        self,
+        companion::{self, Companion},
+        rojo::LiveSession,
+    },
+    split::split,
+    stats,
+    templates::Template,
+    timing::{Phase, Timings},
+    unique_ids::UniqueIdMode,
+    BuildOptions, Project, RootHandling,
+};
 use std::{
-    collections::HashMap,
-    io::{IsTerminal, Read},
+    fs,
+    io::{BufWriter, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
 };
 
-mod resolution;
+/// Converts Rojo project JSON into Roblox place and model files.
+///
+/// Defaults for most options can be set in an `rbxbuild.toml` file in the
+/// current directory or any of its parents.
+#[derive(Debug, Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Options {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Project JSON. Read from stdin if omitted.
+    input: Option<String>,
+
+    /// Read the project from this file instead.
+    #[arg(long, value_name = "FILE", conflicts_with = "input")]
+    file: Option<PathBuf>,
+
+    /// Write the result to this file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Output format. Inferred from the output file's extension if omitted.
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// Path to the config file to use instead of searching for rbxbuild.toml.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Path to a local reflection database to use instead of the bundled one.
+    #[arg(long)]
+    reflection_database: Option<PathBuf>,
+
+    /// Merge the built project into this place or model, keeping whatever the
+    /// project doesn't mention.
+    #[arg(long, value_name = "FILE")]
+    merge_into: Option<PathBuf>,
+
+    /// Build only the node at this path, e.g. Game/ReplicatedStorage/Door,
+    /// and write it as a model unless `--as` says otherwise.
+    #[arg(long = "root", value_name = "PATH")]
+    subtree: Option<String>,
+
+    /// Name the root instance this instead of the project's name.
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Give the root instance this class instead of the one in the project,
+    /// e.g. to build the same tree as a Model or a Folder.
+    #[arg(long, value_name = "CLASS")]
+    root_class: Option<String>,
+
+    /// Also write each service of a place, or the root of a model, to its
+    /// own model file in this directory, listed in an index.json there.
+    #[arg(long, value_name = "DIR")]
+    split_output: Option<PathBuf>,
+
+    /// Write something other than a place or model, for debugging.
+    #[arg(long, value_enum, value_name = "KIND", conflicts_with_all = ["verify", "manifest", "html_report", "bundle", "split_output"])]
+    emit: Option<Emit>,
+
+    /// Read the output back after writing it and fail if it doesn't match
+    /// the built tree.
+    #[arg(long, overrides_with = "no_verify")]
+    verify: bool,
+
+    /// Overrides `--verify` and the config file.
+    #[arg(long, overrides_with = "verify", hide = true)]
+    no_verify: bool,
+
+    /// Build the project a second time, without the cache, and fail if the
+    /// output isn't byte-identical, showing where it first differs.
+    #[arg(long, conflicts_with = "emit")]
+    repro_check: bool,
+
+    /// Write every built instance's path, class and a hash of its
+    /// properties to this JSON file, for telling what changed between
+    /// releases.
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Write a self-contained HTML page with the build's diagnostics, size
+    /// statistics and a browsable instance tree to this file.
+    #[arg(long, value_name = "FILE")]
+    html_report: Option<PathBuf>,
+
+    /// Also package the output, a sourcemap, a manifest and the build's
+    /// diagnostics into this .zip or .tar file.
+    #[arg(long, value_name = "FILE")]
+    bundle: Option<PathBuf>,
+
+    /// Print the largest properties, scripts and subtrees to stderr.
+    #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "text")]
+    size_report: Option<ReportFormat>,
+
+    /// Print how long each phase of the build took to stderr.
+    #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "text")]
+    timing: Option<ReportFormat>,
+}
+
+/// Options that change how a project is built, shared by the default command,
+/// `build-all` and `watch`. Commands without build flags use the default,
+/// which matches giving none.
+#[derive(Debug, Default, clap::Args)]
+struct BuildArgs {
+    /// Fail the build if any property or child can't be resolved.
+    #[arg(long, overrides_with = "no_strict")]
+    strict: bool,
+
+    /// Overrides `--strict` and the config file.
+    #[arg(long, overrides_with = "strict", hide = true)]
+    no_strict: bool,
+
+    /// Give every instance the default value of each property it doesn't set.
+    #[arg(long, overrides_with = "no_fill_defaults")]
+    fill_defaults: bool,
+
+    /// Overrides `--fill-defaults` and the config file.
+    #[arg(long, overrides_with = "fill_defaults", hide = true)]
+    no_fill_defaults: bool,
+
+    /// Make the output byte-identical for identical inputs: siblings are
+    /// sorted by name, negative zeros and NaNs are written one way,
+    /// timestamps come from the last commit and UniqueIds are left out.
+    #[arg(long, overrides_with = "no_deterministic")]
+    deterministic: bool,
+
+    /// Overrides `--deterministic` and the config file.
+    #[arg(long, overrides_with = "deterministic", hide = true)]
+    no_deterministic: bool,
+
+    /// Build siblings sorted by name instead of in the order the project
+    /// lists them.
+    #[arg(long, overrides_with = "no_sort_children")]
+    sort_children: bool,
+
+    /// Overrides `--sort-children` and the config file.
+    #[arg(long, overrides_with = "sort_children", hide = true)]
+    no_sort_children: bool,
+
+    /// Strip comments and whitespace from scripts.
+    #[arg(long, overrides_with = "no_minify_scripts")]
+    minify_scripts: bool,
+
+    /// Overrides `--minify-scripts` and the config file.
+    #[arg(long, overrides_with = "minify_scripts", hide = true)]
+    no_minify_scripts: bool,
+
+    /// Convert CRLF line endings to LF and remove byte order marks in
+    /// scripts.
+    #[arg(long, overrides_with = "no_normalize_line_endings")]
+    normalize_line_endings: bool,
+
+    /// Overrides `--normalize-line-endings` and the config file.
+    #[arg(long, overrides_with = "normalize_line_endings", hide = true)]
+    no_normalize_line_endings: bool,
+
+    /// Remove `--!dev` ... `--!end` regions from scripts.
+    #[arg(long, overrides_with = "no_strip_dev")]
+    strip_dev: bool,
+
+    /// Overrides `--strip-dev` and the config file.
+    #[arg(long, overrides_with = "strip_dev", hide = true)]
+    no_strip_dev: bool,
+
+    /// Prepend this comment to every script. Can refer to `{year}` and to
+    /// defines, e.g. "Copyright {year} Acme, v{version}".
+    #[arg(long, value_name = "TEXT")]
+    banner: Option<String>,
+
+    /// Fail the build if a script requires a module that isn't in the
+    /// built tree.
+    #[arg(long, overrides_with = "no_check_requires")]
+    check_requires: bool,
+
+    /// Overrides `--check-requires` and the config file.
+    #[arg(long, overrides_with = "check_requires", hide = true)]
+    no_check_requires: bool,
+
+    /// Record the git commit, build time, rbxbuild version and profile at
+    /// this path below the root, e.g. ReplicatedStorage/BuildInfo.
+    #[arg(long, value_name = "PATH")]
+    provenance: Option<String>,
+
+    /// How to record provenance: as attributes on an existing instance, or
+    /// as a new StringValue or ModuleScript.
+    #[arg(long, value_enum, value_name = "STYLE")]
+    provenance_style: Option<ProvenanceStyle>,
+
+    /// Remove every UniqueId and HistoryId, or give every instance a
+    /// UniqueId derived from its path or a random one like Studio.
+    #[arg(long, value_enum, value_name = "MODE")]
+    unique_ids: Option<UniqueIdMode>,
+
+    /// Fail the build if a script looks like it contains an API key, token
+    /// or webhook URL.
+    #[arg(long, overrides_with = "no_scan_secrets")]
+    scan_secrets: bool,
+
+    /// Overrides `--scan-secrets` and the config file.
+    #[arg(long, overrides_with = "scan_secrets", hide = true)]
+    no_scan_secrets: bool,
+
+    /// Only build these services of a place, separated by commas, e.g.
+    /// Workspace,ReplicatedStorage.
+    #[arg(long, value_name = "SERVICES", value_delimiter = ',')]
+    only: Vec<String>,
+
+    /// Leave these services of a place out of the build, separated by
+    /// commas.
+    #[arg(long, value_name = "SERVICES", value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Build nodes marked with these `$buildTags`, separated by commas.
+    /// Tagged nodes are left out otherwise.
+    #[arg(long, value_name = "TAGS", value_delimiter = ',')]
+    with_tags: Vec<String>,
+
+    /// Leave out nodes marked with these `$buildTags`, even if they have a
+    /// tag given to `--with-tags`.
+    #[arg(long, value_name = "TAGS", value_delimiter = ',')]
+    without_tags: Vec<String>,
+
+    /// Remove every instance of these classes, separated by commas, e.g.
+    /// Script,LocalScript. Their children are kept unless
+    /// `--strip-descendants` is given.
+    #[arg(long, value_name = "CLASSES", value_delimiter = ',')]
+    strip_classes: Vec<String>,
+
+    /// Remove the children of instances removed by `--strip-classes` too.
+    #[arg(long, overrides_with = "no_strip_descendants")]
+    strip_descendants: bool,
+
+    /// Overrides `--strip-descendants` and the config file.
+    #[arg(long, overrides_with = "strip_descendants", hide = true)]
+    no_strip_descendants: bool,
+
+    /// Round every float to this many decimal places, so values like
+    /// 0.30000000000000004 are written as 0.3.
+    #[arg(long, value_name = "DIGITS")]
+    float_precision: Option<u32>,
+
+    /// What to do with NaN and infinity in properties: fail the build,
+    /// clamp them to finite values, or write them as they are. Each one is
+    /// reported either way.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    non_finite: Option<NonFinitePolicy>,
+
+    /// What to do with characters XML doesn't allow, such as stray control
+    /// characters, in strings and scripts: fail the build, strip them, or
+    /// escape them as `\u{X}`.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    invalid_characters: Option<InvalidCharacterPolicy>,
+
+    /// What to do when siblings end up with the same name, e.g. through a
+    /// Name property: fail the build, warn, or rename the later ones to
+    /// Name_2, Name_3 and so on.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    name_collisions: Option<NameCollisionPolicy>,
+
+    /// Fail the build if it has more than this many instances.
+    #[arg(long, value_name = "COUNT")]
+    max_instances: Option<usize>,
+
+    /// Fail the build if its tree is more than this many levels deep.
+    #[arg(long, value_name = "LEVELS")]
+    max_depth: Option<usize>,
+
+    /// Fail the build if its scripts' sources add up to more than this many
+    /// bytes.
+    #[arg(long, value_name = "BYTES")]
+    max_source_bytes: Option<usize>,
+
+    /// Read a project file that isn't valid UTF-8 as Latin-1, with a
+    /// warning, instead of failing.
+    #[arg(long, overrides_with = "no_latin1_fallback")]
+    latin1_fallback: bool,
+
+    /// Overrides `--latin1-fallback` and the config file.
+    #[arg(long, overrides_with = "latin1_fallback", hide = true)]
+    no_latin1_fallback: bool,
+
+    /// Start XML output with an `<?xml ...?>` declaration naming its
+    /// encoding.
+    #[arg(long, overrides_with = "no_xml_declaration")]
+    xml_declaration: bool,
+
+    /// Overrides `--xml-declaration` and the config file.
+    #[arg(long, overrides_with = "xml_declaration", hide = true)]
+    no_xml_declaration: bool,
+
+    /// Write the output as a place or a model regardless of the root's class.
+    #[arg(long = "as", value_enum, value_name = "KIND")]
+    root: Option<RootHandling>,
+
+    /// Merge a built-in template under the project's tree, filling in
+    /// whatever the project doesn't set.
+    #[arg(long, value_enum, value_name = "NAME")]
+    template: Option<Template>,
+
+    /// Fill in Workspace and Lighting settings the project doesn't set from
+    /// this preset, instead of the one the project names.
+    #[arg(long, value_enum, value_name = "NAME")]
+    preset: Option<Preset>,
+
+    /// Use the defines from this profile in the config file.
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Set a define, overriding the project and config file.
+    #[arg(short = 'D', long = "define", value_name = "NAME=VALUE", value_parser = rbxbuild::defines::parse_define)]
+    defines: Vec<(String, DefineValue)>,
+
+    /// Upload files that properties refer to with {"$asset": "path"} through
+    /// Open Cloud as this creator, `user:ID` or `group:ID`, and use their
+    /// asset IDs. Needs an API key in ROBLOX_API_KEY, or the variable named
+    /// by --api-key-env.
+    #[arg(long, value_name = "CREATOR", value_parser = parse_creator)]
+    upload_assets: Option<Creator>,
+
+    /// JSON file remembering the IDs of uploaded assets, so unchanged files
+    /// aren't uploaded again. Defaults to rbxbuild-assets.json.
+    #[arg(long, value_name = "FILE")]
+    asset_cache: Option<PathBuf>,
+
+    /// Environment variable holding the Open Cloud API key used to upload
+    /// assets and publish. Defaults to ROBLOX_API_KEY.
+    #[arg(long, value_name = "VAR")]
+    api_key_env: Option<String>,
+
+    /// Reuse unchanged parts of the project from earlier builds, kept in
+    /// this directory.
+    #[arg(long, value_name = "DIR")]
+    cache: Option<PathBuf>,
+
+    /// Don't run the config file's pre- and post-build hooks.
+    #[arg(long)]
+    no_hooks: bool,
+
+    /// How to print warnings and errors: `editor` writes
+    /// `file:line:column: severity: message` for editors' problem matchers.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "text")]
+    diagnostic_format: DiagnosticFormat,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Build every *.project.json file in a directory and its
+    /// subdirectories in parallel, and print a summary of the builds.
+    BuildAll {
+        /// The directory to look for projects in.
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+
+        /// Write each build to this directory, keeping the projects' layout,
+        /// e.g. places/lobby.project.json to DIR/places/lobby.rbxl. Without
+        /// it, projects are only checked to build.
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        /// Output format.
+        #[arg(long, value_enum, default_value = "binary")]
+        format: OutputFormat,
+
+        /// How to print the summary.
+        #[arg(long, value_enum, default_value = "text")]
+        report: ReportFormat,
+
+        #[command(flatten)]
+        build: BuildArgs,
+    },
+
+    /// Check that projects parse and resolve, without writing anything, and
+    /// print a one-line summary. Meant for pre-commit hooks.
+    Check {
+        /// The project files to check. Defaults to every *.project.json in
+        /// the current directory and its subdirectories.
+        projects: Vec<PathBuf>,
+
+        /// Check the *.project.json files staged in git instead.
+        #[arg(long, conflicts_with = "projects")]
+        changed_only: bool,
+
+        /// Stop and fail if checking takes longer than this many seconds.
+        #[arg(long, value_name = "SECONDS", default_value_t = 10.0)]
+        budget: f64,
+
+        /// Fail if there are any warnings, too.
+        #[arg(long)]
+        strict: bool,
+
+        /// How to print warnings and errors.
+        #[arg(long, value_enum, value_name = "FORMAT", default_value = "editor")]
+        diagnostic_format: DiagnosticFormat,
+    },
+
+    /// Turn a place or model file back into project JSON.
+    Decompile {
+        /// The .rbxl, .rbxlx, .rbxm or .rbxmx file to read.
+        input: PathBuf,
+
+        /// Write the project to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Write a starter project to build on.
+    Init {
+        /// Where to write the project.
+        #[arg(default_value = "default.project.json")]
+        output: PathBuf,
+
+        #[arg(long, value_enum, default_value = "baseplate")]
+        template: Template,
+
+        /// The project's name. Defaults to the current directory's name.
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Replace the file if it already exists.
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Turn a Rojo project that uses $path into a self-contained project,
+    /// with scripts inlined as Source strings and models converted to nodes.
+    Migrate {
+        /// The Rojo project file to read.
+        input: PathBuf,
+
+        /// Write the project to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Split a project into a root file plus one file per top-level instance,
+    /// joined with $path. `migrate` puts them back together.
+    Split {
+        /// The project file to read.
+        input: PathBuf,
+
+        /// The directory to write the files to. The root file keeps the
+        /// input's file name.
+        output: PathBuf,
+    },
+
+    /// Build a single node of a project and write it as a model.
+    Extract {
+        /// The project file to read.
+        input: PathBuf,
+
+        /// Slash-separated path of the node to build, made of the project's
+        /// name and keys, e.g. "Game/ReplicatedStorage/Prefabs/Tree".
+        path: String,
+
+        /// The model file to write.
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Output format, if it can't be inferred from the extension.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+
+    /// Convert a place or model between the XML and binary formats.
+    Convert {
+        /// The file to read. Its format is inferred from the extension.
+        input: PathBuf,
+
+        /// The file to write. Its format is inferred from the extension.
+        output: PathBuf,
+
+        /// Output format, if it can't be inferred from the extension.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+
+    /// Print the instance hierarchy of a project, place or model as a tree.
+    Tree {
+        /// A project (.json) to build, or a place or model file.
+        input: PathBuf,
+
+        /// Only show instances this many levels below the root.
+        #[arg(long)]
+        depth: Option<usize>,
+
+        /// Draw the tree with ASCII characters instead of box-drawing ones.
+        #[arg(long)]
+        ascii: bool,
+    },
+
+    /// Print the instances of a project, place or model that match a path
+    /// glob and conditions, with selected properties.
+    Query {
+        /// A project (.json) to build, or a place or model file.
+        input: PathBuf,
+
+        /// Slash-separated path from the root, where `*` matches part of a
+        /// name and `**` any number of names, e.g. "Game/Workspace/**".
+        #[arg(default_value = "**")]
+        path: String,
+
+        /// Only print instances where PROPERTY=VALUE, e.g. Anchored=false or
+        /// ClassName=Script. Can be given more than once.
+        #[arg(long = "where", value_name = "PROPERTY=VALUE", value_parser = rbxbuild::query::parse_predicate)]
+        predicates: Vec<Predicate>,
+
+        /// Properties to print for each instance, separated by commas.
+        #[arg(long, value_delimiter = ',')]
+        select: Vec<String>,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+    },
+
+    /// Print instance counts, script sizes and file sizes for a project,
+    /// place or model.
+    Stats {
+        /// A project (.json) to build, or a place or model file.
+        input: PathBuf,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+    },
+
+    /// Browse the built tree of a project, place or model in the terminal,
+    /// with each instance's resolved properties and the build's diagnostics.
+    Explore {
+        /// A project (.json) to build, or a place or model file.
+        input: PathBuf,
+    },
+
+    /// List the assets a project, place or model refers to, with the
+    /// properties that use each one.
+    Assets {
+        /// A project (.json) to build, or a place or model file.
+        input: PathBuf,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+    },
+
+    /// Build a place and upload it through Roblox Open Cloud, using the
+    /// project's gameId and placeId.
+    Publish {
+        /// A project (.json) to build, or a place file.
+        input: PathBuf,
+
+        #[command(flatten)]
+        publish: PublishArgs,
+
+        #[command(flatten)]
+        build: BuildArgs,
+    },
+
+    /// Rebuild a project whenever a file in its directory is saved, writing
+    /// the output and running the --then actions after each successful
+    /// build.
+    Watch {
+        /// The project (.json) to build.
+        #[arg(default_value = "default.project.json")]
+        project: PathBuf,
+
+        /// Write the result to this file after each build.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Output format. Inferred from the output file's extension if
+        /// omitted.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+
+        /// What to do after each successful build, in order. Can be given
+        /// more than once.
+        #[arg(long = "then", value_enum, value_name = "ACTION")]
+        then: Vec<AfterBuild>,
+
+        #[command(flatten)]
+        publish: PublishArgs,
+
+        #[command(flatten)]
+        build: BuildArgs,
+    },
+
+    /// Serve a project to the Rojo Studio plugin, rebuilding and syncing the
+    /// changes whenever a file in the project's directory is saved.
+    Serve {
+        /// The project (.json) to build.
+        project: PathBuf,
+
+        /// Port to listen on. The plugin connects to 34872 by default.
+        #[arg(long, default_value_t = 34872)]
+        port: u16,
+
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        #[command(flatten)]
+        build: BuildArgs,
+    },
+
+    /// Serve a project's latest build to the companion Studio plugin, which
+    /// inserts each rebuild into the open place.
+    Companion {
+        /// The project (.json) to build.
+        #[arg(required_unless_present = "write_plugin")]
+        project: Option<PathBuf>,
+
+        /// Port to listen on. The plugin polls 34873 unless it was written
+        /// with another --port.
+        #[arg(long, default_value_t = companion::DEFAULT_PORT)]
+        port: u16,
+
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Write the plugin to this file, e.g. in Studio's Plugins folder,
+        /// instead of serving.
+        #[arg(long, value_name = "FILE")]
+        write_plugin: Option<PathBuf>,
+
+        #[command(flatten)]
+        build: BuildArgs,
+    },
+
+    /// Run a language server for project files over stdin and stdout, for
+    /// diagnostics, hover and completion in editors.
+    Lsp,
+
+    /// Keep the reflection database and build cache warm and build projects
+    /// requested over a local TCP socket, one JSON request per line.
+    Daemon {
+        #[arg(long, default_value_t = daemon::DEFAULT_PORT)]
+        port: u16,
+
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+    },
+
+    /// Build projects posted to `/build` over HTTP and return the place or
+    /// model.
+    ServeHttp {
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
+
+        /// Address to listen on. Use 0.0.0.0 to accept connections from other
+        /// machines.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+    },
+
+    /// List the instances and properties that differ between two places,
+    /// models or projects.
+    Diff {
+        /// A place or model file, or a project (.json) to build.
+        old: PathBuf,
+
+        /// A place or model file, or a project (.json) to build.
+        new: PathBuf,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+    },
+}
+
+/// Where and how `publish` and `watch --then publish` upload places.
+#[derive(Debug, clap::Args)]
+struct PublishArgs {
+    /// Whether the new version goes live or is only saved.
+    #[arg(long, value_enum, default_value = "saved")]
+    mode: VersionType,
+
+    /// Universe to publish to, instead of the project's gameId.
+    #[arg(long)]
+    universe_id: Option<u64>,
 
-use resolution::UnresolvedValue;
+    /// Place to publish to, instead of the project's placeId.
+    #[arg(long)]
+    place_id: Option<u64>,
+}
+
+/// What `watch --then` does after a successful build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum AfterBuild {
+    /// Upload the place through Open Cloud, like `rbxbuild publish`.
+    Publish,
+}
+
+/// How many entries of each kind `--size-report` lists.
+const SIZE_REPORT_LIMIT: usize = 20;
+
+/// How many instance differences `--repro-check` lists.
+const REPRO_CHANGE_LIMIT: usize = 20;
+
+/// What `--emit` writes instead of a place or model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Emit {
+    /// The built tree as JSON, with referents, classes and fully resolved
+    /// properties.
+    DomJson,
+    /// The instance hierarchy as a Graphviz DOT graph.
+    Dot,
+    /// Like `dot`, with a dashed edge for each Ref property, for inspecting
+    /// reference cycles.
+    DotRefs,
+}
 
-// Required by resolution module
-const REF_POINTER_ATTRIBUTE_PREFIX: &str = "RojoId_";
+/// How reports such as timings and diffs are printed.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ReportFormat {
+    Text,
+    Json,
+}
 
 fn main() -> Result<()> {
-    // Get JSON input either from command-line argument or stdin
-    let json_input = if let Some(arg) = std::env::args().nth(1) {
+    let options = Options::parse();
+    let config = Config::load(options.config.as_deref())?;
+
+    if let Some(database) = options
+        .reflection_database
+        .as_ref()
+        .or(config.reflection_database.as_ref())
+    {
+        // rbx_reflection_database checks this variable the first time the
+        // database is accessed.
+        std::env::set_var("RBX_DATABASE", database);
+    }
+
+    if let Some(command) = options.command {
+        return run_command(command, &config);
+    }
+
+    let started = Instant::now();
+    if !options.build.no_hooks {
+        config.hooks.run_pre_build()?;
+    }
+
+    // Get JSON input from a file, a command-line argument or stdin
+    let (json_input, source) = if let Some(path) = &options.file {
+        let input = read_project(path, &options.build, &config)?;
+        (input, path.display().to_string())
+    } else if let Some(arg) = options.input {
         // Use command-line argument if provided
-        arg
+        (arg, "<argument>".to_owned())
     } else if !std::io::stdin().is_terminal() {
         // Read from stdin if it's not a terminal (piped input)
-        let mut input = String::new();
-        std::io::stdin().read_to_string(&mut input)?;
-        input
+        let mut input = Vec::new();
+        std::io::stdin().read_to_end(&mut input)?;
+        let input = decode_project(input, "<stdin>", &options.build, &config)?;
+        (input, "<stdin>".to_owned())
     } else {
         // No input provided
         eprintln!(
             "Error: No input provided. Please provide JSON as an argument or pipe it to stdin."
         );
-        eprintln!("Usage: rojo-build-lite '<json>' or echo '<json>' | rojo-build-lite");
+        eprintln!("Usage: rbxbuild '<json>' or echo '<json>' | rbxbuild");
         std::process::exit(1);
     };
 
@@ -39,567 +825,1271 @@ fn main() -> Result<()> {
         std::process::exit(1);
     }
 
+    let timings = options.timing.map(|_| Timings::new());
+    let printer = DiagnosticPrinter::new(options.build.diagnostic_format, &source, &json_input);
+
     // Parse JSON as a project file
-    let project: Project = serde_json::from_str(&json_input)?;
+    let start = Instant::now();
+    let mut project = Project::from_json(&json_input).inspect_err(|error| printer.error(error))?;
+    if let Some(timings) = &timings {
+        timings.record(Phase::Parse, start.elapsed());
+    }
 
-    // Get the project name for the root instance
-    let root_name = project.name.as_deref().unwrap_or("ROOT");
+    // Only the addressed node is built, so the rest of the project doesn't
+    // slow this down or produce warnings
+    if let Some(path) = &options.subtree {
+        let Some(subtree) = project.subtree(path) else {
+            bail!("There is no node at {path} in {source}");
+        };
+        project = subtree;
+    }
 
-    // Convert tree to WeakDom
-    let dom = instantiate(&project.tree, root_name)?;
+    if let Some(name) = &options.name {
+        project.name = Some(name.clone());
+    }
+    if let Some(class) = &options.root_class {
+        project.tree.class_name = Some(class.as_str().into());
+    }
 
-    // Serialize to XML
-    // If the root is DataModel, output its children as siblings (like Rojo does for place files)
-    // Otherwise, output the root instance itself
-    let mut buffer = Vec::new();
-    let root_ref = dom.root_ref();
-    let root_instance = dom.get_by_ref(root_ref).unwrap();
+    // The second build needs the project as it was before the first
+    let repro = options.repro_check.then(|| project.clone());
 
-    let ids_to_write = if root_instance.class == "DataModel" {
-        // Place files don't contain an entry for the DataModel
-        // Write the children as root-level siblings
-        root_instance.children().to_vec()
-    } else {
-        // For models, write the root instance
-        vec![root_ref]
+    let mut build_options = build_options(&options.build, &config, &project)?;
+    build_options.timings = timings;
+    if options.subtree.is_some() && options.build.root.is_none() {
+        build_options.root = RootHandling::Model;
+    }
+
+    let name = project.name.clone().unwrap_or_else(|| "place".to_owned());
+    let output = options.output.or(config.output);
+    let bundle_path = options.bundle.or(config.bundle);
+    let split_output = options.split_output.or(config.split_output);
+    let format = options
+        .format
+        .or(config.format)
+        .or_else(|| output.as_deref().map(OutputFormat::from_path))
+        .unwrap_or(OutputFormat::Xml);
+
+    // Convert tree to WeakDom, keeping what was reported for the bundle and
+    // report
+    let mut reported = Vec::new();
+    let mut dom = build(project, &build_options, &mut |diagnostic: Diagnostic| {
+        printer.print(&diagnostic);
+        reported.push(diagnostic);
+    })
+    .inspect_err(|error| printer.error(error))?;
+
+    if let Some(project) = repro {
+        check_reproducible(&dom, project, &mut build_options, format)?;
+    }
+
+    if let Some(base) = options.merge_into.or(config.merge_into) {
+        let mut merged = input::read_file(&base)?;
+        let stats = merge(&mut merged, dom);
+        eprintln!(
+            "Merged into {}: {} merged, {} added, {} replaced",
+            base.display(),
+            stats.merged,
+            stats.added,
+            stats.replaced
+        );
+
+        // Like any file that was read, the merged root is a DataModel holding
+        // the top-level instances
+        dom = merged;
+        build_options.root = RootHandling::Place;
+    }
+
+    if let Some(emit) = options.emit {
+        let contents = match emit {
+            Emit::DomJson => serde_json::to_string_pretty(&output::dom_to_json(&dom))? + "\n",
+            Emit::Dot => query::render_dot(&dom, false),
+            Emit::DotRefs => query::render_dot(&dom, true),
+        };
+        match &output {
+            Some(path) => fs::write(path, contents)
+                .with_context(|| format!("Could not write {}", path.display()))?,
+            None => print!("{contents}"),
+        }
+
+        if let (Some(format), Some(timings)) = (options.timing, &build_options.timings) {
+            print_timings(format, timings)?;
+        }
+        return Ok(());
+    }
+
+    let bytes = match &output {
+        Some(path) => {
+            let stats = output::write_to_file(path, &dom, format, &build_options)?;
+            eprintln!("Wrote {} to {}", stats, path.display());
+            Some(stats.bytes)
+        }
+        // The bundle or split files hold the output, so there's no need to
+        // print it too
+        None if bundle_path.is_some() || split_output.is_some() => None,
+        None => {
+            // Stream straight to stdout rather than building the whole
+            // document in memory first
+            let mut stdout =
+                BufWriter::with_capacity(output::OUTPUT_BUFFER_SIZE, std::io::stdout().lock());
+            output::write_dom(&mut stdout, &dom, format, &build_options)?;
+
+            if format == OutputFormat::Xml {
+                writeln!(stdout)?;
+            }
+            stdout.flush()?;
+            None
+        }
     };
 
-    to_writer_default(&mut buffer, &dom, &ids_to_write)?;
+    if let Some(dir) = &split_output {
+        let files = output::write_split(dir, &dom, format, &build_options)?;
+        eprintln!("Wrote {} files to {}", files.len(), dir.display());
+    }
+
+    if flag(options.verify, options.no_verify)
+        .or(config.verify)
+        .unwrap_or_default()
+    {
+        // Check what actually went to disk when there's a file; otherwise
+        // serialize again, since stdout can't be read back
+        let written = match &output {
+            Some(path) => {
+                fs::read(path).with_context(|| format!("Could not read {}", path.display()))?
+            }
+            None => {
+                let mut written = Vec::new();
+                output::write_dom(&mut written, &dom, format, &build_options)?;
+                written
+            }
+        };
+
+        let changes = verify_written(&dom, &written, format, &build_options)?;
+        if !changes.is_empty() {
+            for change in &changes {
+                eprintln!("{change}");
+            }
+            bail!(
+                "The written file doesn't match the built tree ({} differences)",
+                changes.len()
+            );
+        }
+        eprintln!("Verified the written file against the built tree");
+    }
+
+    if let Some(path) = options.manifest.or(config.manifest) {
+        let manifest = manifest(&dom, build_options.root);
+        let json = serde_json::to_string_pretty(&manifest)?;
+        fs::write(&path, json + "\n")
+            .with_context(|| format!("Could not write {}", path.display()))?;
+        eprintln!(
+            "Wrote hashes of {} instances to {}",
+            manifest.instances.len(),
+            path.display()
+        );
+    }
+
+    if let Some(path) = options.html_report.or(config.html_report) {
+        let html = html_report(&name, &dom, build_options.root, &reported);
+        fs::write(&path, html).with_context(|| format!("Could not write {}", path.display()))?;
+        eprintln!("Wrote a build report to {}", path.display());
+    }
+
+    if let Some(path) = bundle_path {
+        let entries = bundle::bundle(&name, &dom, format, &build_options, &reported)?;
+        let file = fs::File::create(&path)
+            .with_context(|| format!("Could not create {}", path.display()))?;
+        bundle::write_archive(
+            BufWriter::with_capacity(output::OUTPUT_BUFFER_SIZE, file),
+            ArchiveFormat::from_path(&path),
+            &entries,
+        )?;
+        eprintln!("Bundled {} files into {}", entries.len(), path.display());
+    }
+
+    if !options.build.no_hooks {
+        config.hooks.run_post_build(&BuildInfo {
+            name: &name,
+            output: output.as_deref(),
+            format,
+            bytes,
+            instances: count_instances(&dom, build_options.root),
+            warnings: reported.len(),
+            profile: build_options.profile.as_deref(),
+            duration: started.elapsed(),
+        })?;
+    }
+
+    if let Some(format) = options.size_report {
+        let report = stats::size_report(&dom, SIZE_REPORT_LIMIT);
+        match format {
+            ReportFormat::Text => eprint!("{report}"),
+            ReportFormat::Json => eprintln!("{}", serde_json::to_string(&report)?),
+        }
+    }
 
-    // Print XML to stdout
-    println!("{}", String::from_utf8(buffer)?);
+    if let (Some(format), Some(timings)) = (options.timing, &build_options.timings) {
+        print_timings(format, timings)?;
+    }
 
     Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct Project {
-    /// The name of the top-level instance described by the project.
-    pub name: Option<String>,
+/// Builds `project` again with the same options and fails if it doesn't
+/// serialize exactly like `first`, naming the first byte and instance that
+/// differ. Sharing the options means anything they collected once, like
+/// provenance, is the same for both builds.
+///
+/// The second build runs on as many threads as the first, but without the
+/// cache, so it can't just reuse what the first built and a cached subtree
+/// that doesn't match a fresh build shows up too. The cache and timings are
+/// put back afterwards.
+fn check_reproducible(
+    first: &rbx_dom_weak::WeakDom,
+    project: Project,
+    options: &mut BuildOptions,
+    format: OutputFormat,
+) -> Result<()> {
+    let cache = options.cache.take();
+    let timings = options.timings.take();
+    let second = build(project, options, &mut |_: Diagnostic| {});
+    options.cache = cache;
+    options.timings = timings;
+    let second = second?;
+
+    let mut first_bytes = Vec::new();
+    output::write_dom(&mut first_bytes, first, format, options)?;
+    let mut second_bytes = Vec::new();
+    output::write_dom(&mut second_bytes, &second, format, options)?;
+
+    let Some(divergence) = first_divergence(&first_bytes, &second_bytes) else {
+        eprintln!(
+            "Built the project twice with identical output ({} bytes)",
+            first_bytes.len()
+        );
+        return Ok(());
+    };
 
-    /// The tree of instances described by this project. Projects always
-    /// describe at least one instance.
-    pub tree: ProjectNode,
+    eprintln!("The second build's output first differs at {divergence}");
+    if format == OutputFormat::Xml {
+        for (label, bytes) in [("first", &first_bytes), ("second", &second_bytes)] {
+            let line = bytes
+                .split(|&byte| byte == b'\n')
+                .nth(divergence.line - 1)
+                .unwrap_or_default();
+            eprintln!("  {label}: {}", String::from_utf8_lossy(line).trim());
+        }
+    }
+    for change in diff(first, &second).iter().take(REPRO_CHANGE_LIMIT) {
+        eprintln!("{change}");
+    }
+    bail!("The project doesn't build reproducibly")
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ProjectNode {
-    #[serde(rename = "$className")]
-    pub class_name: Option<String>,
-
-    #[serde(rename = "$properties", default)]
-    pub properties: HashMap<String, UnresolvedValue>,
+/// Reads a project file as [`decode_project`] does.
+fn read_project(path: &Path, args: &BuildArgs, config: &Config) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+    decode_project(bytes, &path.display().to_string(), args, config)
+}
 
-    #[serde(flatten)]
-    pub children: HashMap<String, ProjectNode>,
+/// Turns a project's bytes into text, without a byte order mark, falling
+/// back to Latin-1 with a warning if that's enabled.
+fn decode_project(
+    bytes: Vec<u8>,
+    source: &str,
+    args: &BuildArgs,
+    config: &Config,
+) -> Result<String> {
+    let latin1_fallback = flag(args.latin1_fallback, args.no_latin1_fallback)
+        .or(config.latin1_fallback)
+        .unwrap_or_default();
+    let decoded = input::decode_text(bytes, latin1_fallback)
+        .with_context(|| format!("Could not read {source}"))?;
+    if let Some(offset) = decoded.latin1_from {
+        eprintln!(
+            "Warning: {source} isn't valid UTF-8 from byte {offset} on, so it was read as Latin-1"
+        );
+    }
+    Ok(decoded.text)
 }
 
-/// Instantiate a ProjectNode tree into a WeakDom (ported from Rojo)
-fn instantiate(node: &ProjectNode, instance_name: &str) -> Result<WeakDom> {
-    let root = instantiate_node(node, instance_name)?;
-    Ok(WeakDom::new(root))
+/// What a `--flag` and `--no-flag` pair was set to, if either was given. Only
+/// the last one given is set, so `Some` means the command line decides.
+fn flag(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
 }
 
-/// Convert a ProjectNode into an InstanceBuilder (ported from Rojo)
-fn instantiate_node(node: &ProjectNode, name: &str) -> Result<InstanceBuilder> {
-    // Determine class name - infer from known service names if not specified
-    let class_name = if let Some(class) = &node.class_name {
-        class.as_str()
-    } else {
-        // Try to infer from known services
-        infer_class_from_name(name).unwrap_or("Folder")
+/// Combines build options from the command line, the config file and the
+/// selected profile, with the command line winning, through
+/// [`Config::build_options`].
+fn build_options(args: &BuildArgs, config: &Config, project: &Project) -> Result<BuildOptions> {
+    // Flags replace the config's values, except the ones that have to go on
+    // top of the profile or the project too
+    let mut config = config.clone();
+    for (on, off, value) in [
+        (args.strict, args.no_strict, &mut config.strict),
+        (
+            args.fill_defaults,
+            args.no_fill_defaults,
+            &mut config.fill_defaults,
+        ),
+        (
+            args.deterministic,
+            args.no_deterministic,
+            &mut config.deterministic,
+        ),
+        (
+            args.sort_children,
+            args.no_sort_children,
+            &mut config.sort_children,
+        ),
+        (
+            args.minify_scripts,
+            args.no_minify_scripts,
+            &mut config.minify_scripts,
+        ),
+        (
+            args.normalize_line_endings,
+            args.no_normalize_line_endings,
+            &mut config.normalize_line_endings,
+        ),
+        (
+            args.check_requires,
+            args.no_check_requires,
+            &mut config.check_requires,
+        ),
+        (
+            args.scan_secrets,
+            args.no_scan_secrets,
+            &mut config.scan_secrets,
+        ),
+        (
+            args.strip_descendants,
+            args.no_strip_descendants,
+            &mut config.strip_descendants,
+        ),
+        (
+            args.xml_declaration,
+            args.no_xml_declaration,
+            &mut config.xml_declaration,
+        ),
+    ] {
+        *value = flag(on, off).or(*value);
+    }
+    config.root = args.root.or(config.root);
+    config.template = args.template.or(config.template);
+    config.profile = args.profile.clone().or(config.profile);
+    config.banner = args.banner.clone().or(config.banner);
+    config.provenance = args.provenance.clone().or(config.provenance);
+    config.provenance_style = args.provenance_style.or(config.provenance_style);
+    config.unique_ids = args.unique_ids.or(config.unique_ids);
+    config.float_precision = args.float_precision.or(config.float_precision);
+    config.non_finite = args.non_finite.or(config.non_finite);
+    config.invalid_characters = args.invalid_characters.or(config.invalid_characters);
+    config.name_collisions = args.name_collisions.or(config.name_collisions);
+    config.cache = args.cache.clone().or(config.cache);
+    config.asset_cache = args.asset_cache.clone().or(config.asset_cache);
+    config.api_key_env = args.api_key_env.clone().or(config.api_key_env);
+    if let Some(creator) = args.upload_assets {
+        config.upload_assets = Some(creator.to_string());
+    }
+    for (given, value) in [
+        (&args.only, &mut config.only),
+        (&args.exclude, &mut config.exclude),
+        (&args.with_tags, &mut config.with_tags),
+        (&args.without_tags, &mut config.without_tags),
+        (&args.strip_classes, &mut config.strip_classes),
+    ] {
+        if !given.is_empty() {
+            value.clone_from(given);
+        }
+    }
+    config.limits = Limits {
+        max_instances: args.max_instances.or(config.limits.max_instances),
+        max_depth: args.max_depth.or(config.limits.max_depth),
+        max_source_bytes: args.max_source_bytes.or(config.limits.max_source_bytes),
     };
 
-    // Check if there's an explicit Name property override
-    let instance_name_override: Option<String> = node
-        .properties
-        .get("Name")
-        .and_then(|name_value| name_value.clone().resolve_unambiguous().ok())
-        .and_then(|variant| {
-            if let rbx_dom_weak::types::Variant::String(s) = variant {
-                Some(s.to_string())
-            } else {
-                None
-            }
-        });
+    let overrides = Overrides {
+        defines: args.defines.iter().cloned().collect(),
+        strip_dev: flag(args.strip_dev, args.no_strip_dev),
+        preset: args.preset,
+    };
+    Ok(config.build_options(project, overrides)?)
+}
 
-    let instance_name = instance_name_override.as_deref().unwrap_or(name);
+/// Prints diagnostics in the format `--diagnostic-format` asks for.
+struct DiagnosticPrinter<'a> {
+    format: DiagnosticFormat,
+    editor: Option<EditorFormat<'a>>,
+}
 
-    let mut builder = InstanceBuilder::new(class_name).with_name(instance_name);
+impl<'a> DiagnosticPrinter<'a> {
+    /// `source` is what to call the project in editor diagnostics, and
+    /// `json` its text.
+    fn new(format: DiagnosticFormat, source: &'a str, json: &'a str) -> Self {
+        let editor = (format == DiagnosticFormat::Editor).then(|| EditorFormat::new(source, json));
+        DiagnosticPrinter { format, editor }
+    }
 
-    // Add properties with proper resolution
-    for (key, unresolved) in &node.properties {
-        // Skip the "Name" property as it's already set via with_name()
-        if key == "Name" {
-            continue;
+    fn print(&self, diagnostic: &Diagnostic) {
+        match (&self.editor, self.format) {
+            (Some(editor), _) => eprintln!("{}", editor.line(diagnostic)),
+            (None, DiagnosticFormat::Json) => {
+                eprintln!("{}", serde_json::to_string(diagnostic).unwrap_or_default())
+            }
+            _ => eprintln!("{diagnostic}"),
         }
+    }
 
-        match unresolved.clone().resolve(class_name, key) {
-            Ok(variant) => {
-                builder = builder.with_property(key, variant);
-            }
-            Err(e) => {
-                eprintln!(
-                    "Warning: Failed to resolve property {}.{}: {}",
-                    class_name, key, e
-                );
+    /// Prints an error that stopped the build in the same format, so tools
+    /// reading the diagnostics see it too. Text errors are left to `main`.
+    fn error(&self, error: &rbxbuild::Error) {
+        match (&self.editor, self.format) {
+            (Some(editor), _) => {
+                let position = match error {
+                    rbxbuild::Error::Parse(error) => Some((error.line(), error.column())),
+                    _ => None,
+                };
+                eprintln!("{}", editor.error(position, &error.to_string()));
             }
+            (None, DiagnosticFormat::Json) => self.print(&Diagnostic::error("", error.to_string())),
+            _ => {}
         }
     }
+}
 
-    // Add children
-    for (child_name, child_node) in &node.children {
-        match instantiate_node(child_node, child_name) {
-            Ok(child_builder) => {
-                builder = builder.with_child(child_builder);
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to instantiate child {}: {}", child_name, e);
+/// Counts instances the way `stats` does, leaving out a place's DataModel.
+fn count_instances(dom: &rbx_dom_weak::WeakDom, root: RootHandling) -> usize {
+    output::top_level_refs(dom, root)
+        .into_iter()
+        .map(|referent| query::descendants(dom, referent).count())
+        .sum()
+}
+
+fn print_timings(format: ReportFormat, timings: &Timings) -> Result<()> {
+    match format {
+        ReportFormat::Text => {
+            eprintln!("Timings (resolve is part of instantiate):");
+            for (phase, duration) in timings.phases() {
+                eprintln!("  {:<12} {:>10.2?}", phase.name(), duration);
             }
         }
+        ReportFormat::Json => {
+            let seconds: serde_json::Map<_, _> = timings
+                .phases()
+                .map(|(phase, duration)| (phase.name().to_owned(), duration.as_secs_f64().into()))
+                .collect();
+            eprintln!("{}", serde_json::to_string(&seconds)?);
+        }
     }
 
-    Ok(builder)
-}
-
-/// Infer a class name from an instance name (common service names)
-fn infer_class_from_name(name: &str) -> Option<&'static str> {
-    match name {
-        "Workspace" => Some("Workspace"),
-        "Players" => Some("Players"),
-        "Lighting" => Some("Lighting"),
-        "ReplicatedFirst" => Some("ReplicatedFirst"),
-        "ReplicatedStorage" => Some("ReplicatedStorage"),
-        "ServerScriptService" => Some("ServerScriptService"),
-        "ServerStorage" => Some("ServerStorage"),
-        "StarterGui" => Some("StarterGui"),
-        "StarterPack" => Some("StarterPack"),
-        "StarterPlayer" => Some("StarterPlayer"),
-        "Teams" => Some("Teams"),
-        "SoundService" => Some("SoundService"),
-        "Chat" => Some("Chat"),
-        "LocalizationService" => Some("LocalizationService"),
-        "TestService" => Some("TestService"),
-        _ => None,
+    Ok(())
+}
+
+fn run_command(command: Command, config: &Config) -> Result<()> {
+    match command {
+        Command::BuildAll {
+            dir,
+            output,
+            format,
+            report,
+            build,
+        } => run_build_all(&dir, output.as_deref(), format, report, &build, config),
+        Command::Check {
+            projects,
+            changed_only,
+            budget,
+            strict,
+            diagnostic_format,
+        } => run_check(projects, changed_only, budget, strict, diagnostic_format),
+        Command::Decompile { input, output } => run_decompile(&input, output.as_deref()),
+        Command::Init {
+            output,
+            template,
+            name,
+            force,
+        } => run_init(&output, template, name, force),
+        Command::Migrate { input, output } => run_migrate(&input, output.as_deref()),
+        Command::Split { input, output } => run_split(&input, &output),
+        Command::Extract {
+            input,
+            path,
+            output,
+            format,
+        } => run_extract(&input, &path, &output, format, config),
+        Command::Convert {
+            input,
+            output,
+            format,
+        } => run_convert(&input, &output, format),
+        Command::Tree {
+            input,
+            depth,
+            ascii,
+        } => {
+            let dom = load_artifact(&input, &BuildArgs::default(), config)?;
+            print!("{}", query::render_tree(&dom, depth, ascii));
+            Ok(())
+        }
+        Command::Query {
+            input,
+            path,
+            predicates,
+            select,
+            format,
+        } => run_query(&input, &path, &predicates, &select, format, config),
+        Command::Stats { input, format } => {
+            let dom = load_artifact(&input, &BuildArgs::default(), config)?;
+            let stats = stats::collect(&dom, RootHandling::Auto);
+            match format {
+                ReportFormat::Text => print!("{stats}"),
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+            }
+            Ok(())
+        }
+        Command::Explore { input } => {
+            let mut diagnostics = Vec::new();
+            let args = BuildArgs::default();
+            let dom =
+                load_artifact_reporting(&input, &args, config, &mut |diagnostic: Diagnostic| {
+                    diagnostics.push(diagnostic)
+                })?;
+            explore::run(&dom, diagnostics)?;
+            Ok(())
+        }
+        Command::Assets { input, format } => {
+            let report = assets::scan(&load_artifact(&input, &BuildArgs::default(), config)?);
+            match format {
+                ReportFormat::Text => print!("{report}"),
+                ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            }
+            Ok(())
+        }
+        Command::Publish {
+            input,
+            publish,
+            build,
+        } => run_publish(&input, &publish, &build, config),
+        Command::Watch {
+            project,
+            output,
+            format,
+            then,
+            publish,
+            build,
+        } => run_watch(&project, output, format, &then, &publish, &build, config),
+        Command::Serve {
+            project,
+            port,
+            host,
+            build,
+        } => run_serve(&project, &host, port, &build, config),
+        Command::Companion {
+            project,
+            port,
+            host,
+            write_plugin,
+            build,
+        } => match (write_plugin, project) {
+            (Some(path), _) => {
+                fs::write(&path, companion::plugin_source(port))
+                    .with_context(|| format!("Could not write {}", path.display()))?;
+                eprintln!("Wrote the companion plugin to {}", path.display());
+                Ok(())
+            }
+            (None, Some(project)) => run_companion(&project, &host, port, &build, config),
+            (None, None) => unreachable!("clap requires a project"),
+        },
+        Command::Lsp => {
+            lsp::run(std::io::stdin().lock(), std::io::stdout().lock())?;
+            Ok(())
+        }
+        Command::Daemon { port, host } => {
+            let listener = std::net::TcpListener::bind((host.as_str(), port))
+                .with_context(|| format!("Could not listen on {host}:{port}"))?;
+            eprintln!("Daemon listening on {}", listener.local_addr()?);
+            daemon::run(listener, Arc::new(Daemon::new()))?;
+            eprintln!("Daemon stopped");
+            Ok(())
+        }
+        Command::ServeHttp { port, host } => {
+            let listener = std::net::TcpListener::bind((host.as_str(), port))
+                .with_context(|| format!("Could not listen on {host}:{port}"))?;
+            eprintln!("Listening on http://{}", listener.local_addr()?);
+            serve::serve(listener)?;
+            Ok(())
+        }
+        Command::Diff { old, new, format } => run_diff(&old, &new, format, config),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    //! # Test Suite for JSON to XML Conversion
-    //!
-    //! This module contains comprehensive tests for the rojo-build-lite tool,
-    //! which converts Rojo-style JSON project files to Roblox XML format.
-    //!
-    //! ## Test Coverage
-    //!
-    //! ### Basic Functionality
-    //! - `test_simple_folder`: Verifies basic folder conversion
-    //! - `test_nested_children`: Tests hierarchical instance structures
-    //! - `test_folder_without_explicit_classname`: Tests default Folder inference
-    //!
-    //! ### Name Property Handling
-    //! - `test_no_duplicate_name_property`: Ensures Name properties aren't duplicated when explicitly provided
-    //! - `test_custom_name_override`: Verifies that explicit Name properties override the JSON key
-    //!
-    //! ### Service Inference
-    //! - `test_service_inference`: Tests automatic class name inference for common Roblox services
-    //!
-    //! ### DataModel vs Model Files
-    //! - `test_datamodel_place_file`: Verifies that DataModel children are written as root-level siblings
-    //! - `test_model_file`: Ensures Model instances are properly included in the output
-    //!
-    //! ### Property Type Conversion
-    //! - `test_boolean_property`: Boolean values
-    //! - `test_number_properties`: Float/Int number properties
-    //! - `test_vector3_property`: Vector3 arrays [x, y, z]
-    //! - `test_color3_property`: Color3 arrays [r, g, b]
-    //! - `test_cframe_property`: CFrame 12-element arrays
-    //! - `test_enum_property`: String-based enum values
-    //! - `test_multiple_properties`: Multiple properties on a single instance
-    //!
-    //! ### Scripts
-    //! - `test_script_with_source`: Script instances with Source property
-    //!
-    //! ## Running Tests
-    //!
-    //! Run all tests with:
-    //! ```bash
-    //! cargo test
-    //! ```
-    //!
-    //! Run a specific test:
-    //! ```bash
-    //! cargo test test_no_duplicate_name_property
-    //! ```
-    //!
-    //! Run with verbose output:
-    //! ```bash
-    //! cargo test -- --nocapture
-    //! ```
-
-    use super::*;
-
-    /// Helper function to convert JSON to XML string
-    fn json_to_xml(json_str: &str) -> Result<String> {
-        let project: Project = serde_json::from_str(json_str)?;
-        let root_name = project.name.as_deref().unwrap_or("ROOT");
-        let dom = instantiate(&project.tree, root_name)?;
-
-        let mut buffer = Vec::new();
-        let root_ref = dom.root_ref();
-        let root_instance = dom.get_by_ref(root_ref).unwrap();
-
-        let ids_to_write = if root_instance.class == "DataModel" {
-            root_instance.children().to_vec()
-        } else {
-            vec![root_ref]
-        };
+fn run_build_all(
+    dir: &Path,
+    out_dir: Option<&Path>,
+    format: OutputFormat,
+    report_format: ReportFormat,
+    args: &BuildArgs,
+    config: &Config,
+) -> Result<()> {
+    let projects = batch::discover(dir)
+        .with_context(|| format!("Could not search {} for projects", dir.display()))?;
+    if projects.is_empty() {
+        bail!("No *.project.json files in {}", dir.display());
+    }
 
-        to_writer_default(&mut buffer, &dom, &ids_to_write)?;
-        Ok(String::from_utf8(buffer)?)
+    if !args.no_hooks {
+        config.hooks.run_pre_build()?;
     }
 
-    #[test]
-    fn test_simple_folder() {
-        let json = r#"{
-            "name": "TestProject",
-            "tree": {
-                "$className": "Folder"
-            }
-        }"#;
-
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
-        assert!(xml.contains(r#"<Item class="Folder""#));
-        assert!(xml.contains(r#"<string name="Name">TestProject</string>"#));
-    }
-
-    #[test]
-    fn test_no_duplicate_name_property() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "DataModel",
-                "ServerScriptService": {
-                    "$className": "ServerScriptService",
-                    "MyScript": {
-                        "$className": "Script",
-                        "$properties": {
-                            "Name": "MyScript",
-                            "Source": "print('hello')"
-                        }
-                    }
-                }
-            }
-        }"#;
+    let report = batch::build_all(dir, &projects, |path| {
+        build_project_file(dir, path, out_dir, format, args, config)
+            .map_err(|error| format!("{error:#}"))
+    });
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+    match report_format {
+        ReportFormat::Text => print!("{report}"),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
 
-        // Ensure no duplicate Name properties
-        let name_count = xml
-            .matches(r#"<string name="Name">MyScript</string>"#)
-            .count();
-        assert_eq!(
-            name_count, 1,
-            "Name property should appear exactly once, not duplicated"
-        );
+    let failed = report.failed();
+    if failed > 0 {
+        bail!("{failed} of {} projects failed to build", projects.len());
+    }
+    Ok(())
+}
 
-        // Verify the Source property is also present
-        assert!(xml.contains(r#"<string name="Source">print('hello')</string>"#));
-    }
-
-    #[test]
-    fn test_custom_name_override() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "DataModel",
-                "Workspace": {
-                    "$className": "Workspace",
-                    "PartKey": {
-                        "$className": "Part",
-                        "$properties": {
-                            "Name": "CustomPartName"
-                        }
-                    }
-                }
+/// Builds one project for `build-all`, printing its diagnostics as they come
+/// and running the post-build hooks.
+fn build_project_file(
+    dir: &Path,
+    path: &Path,
+    out_dir: Option<&Path>,
+    format: OutputFormat,
+    args: &BuildArgs,
+    config: &Config,
+) -> Result<Outcome> {
+    let started = Instant::now();
+    let json = read_project(path, args, config)?;
+    let source = path.display().to_string();
+    let printer = DiagnosticPrinter::new(args.diagnostic_format, &source, &json);
+
+    let project = Project::from_json(&json).inspect_err(|error| printer.error(error))?;
+    let name = project.name.clone().unwrap_or_else(|| "place".to_owned());
+    let options = build_options(args, config, &project)?;
+
+    // Builds run side by side, so text diagnostics say which project they
+    // came from
+    let mut warnings = 0;
+    let dom = build(project, &options, &mut |diagnostic: Diagnostic| {
+        warnings += 1;
+        match args.diagnostic_format {
+            DiagnosticFormat::Text => eprintln!("{source}: {diagnostic}"),
+            _ => printer.print(&diagnostic),
+        }
+    })
+    .inspect_err(|error| printer.error(error))?;
+
+    let written = match out_dir {
+        Some(out_dir) => {
+            let extension = output::file_extension(&dom, options.root, format);
+            let output = batch::output_path(dir, out_dir, path, extension);
+            if let Some(parent) = output.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Could not create {}", parent.display()))?;
             }
-        }"#;
-
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+            let stats = output::write_to_file(&output, &dom, format, &options)?;
+            Some((output, stats.bytes))
+        }
+        None => None,
+    };
 
-        // Should use the custom name, not the key
-        assert!(xml.contains(r#"<string name="Name">CustomPartName</string>"#));
-        assert!(!xml.contains(r#"<string name="Name">PartKey</string>"#));
+    if !args.no_hooks {
+        config.hooks.run_post_build(&BuildInfo {
+            name: &name,
+            output: written.as_ref().map(|(output, _)| output.as_path()),
+            format,
+            bytes: written.as_ref().map(|(_, bytes)| *bytes),
+            instances: count_instances(&dom, options.root),
+            warnings,
+            profile: options.profile.as_deref(),
+            duration: started.elapsed(),
+        })?;
     }
 
-    #[test]
-    fn test_service_inference() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "DataModel",
-                "Workspace": {
-                    "MyPart": {
-                        "$className": "Part"
+    Ok(Outcome {
+        output: written.map(|(output, _)| output),
+        warnings,
+    })
+}
+
+fn run_check(
+    projects: Vec<PathBuf>,
+    changed_only: bool,
+    budget: f64,
+    strict: bool,
+    format: DiagnosticFormat,
+) -> Result<()> {
+    let projects = if changed_only {
+        check::staged_projects()?
+    } else if projects.is_empty() {
+        batch::discover(Path::new("."))?
+    } else {
+        projects
+    };
+
+    let budget = Duration::try_from_secs_f64(budget).context("Invalid --budget")?;
+    let summary = check::check_within(projects, budget, move |path| {
+        let Ok(json) = fs::read_to_string(path) else {
+            eprintln!("{}: could not be read", path.display());
+            return Checked::Failed;
+        };
+        let source = path.display().to_string();
+        let printer = DiagnosticPrinter::new(format, &source, &json);
+
+        match embed::validate_json(&json) {
+            Ok(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    match format {
+                        DiagnosticFormat::Text => eprintln!("{source}: {diagnostic}"),
+                        _ => printer.print(diagnostic),
                     }
-                },
-                "ReplicatedStorage": {
-                    "MyFolder": {
-                        "$className": "Folder"
+                }
+                if strict && !diagnostics.is_empty() {
+                    Checked::Failed
+                } else {
+                    Checked::Passed {
+                        warnings: diagnostics.len(),
                     }
                 }
             }
-        }"#;
-
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
-
-        // Should infer Workspace class
-        assert!(xml.contains(r#"<Item class="Workspace""#));
-        // Should infer ReplicatedStorage class
-        assert!(xml.contains(r#"<Item class="ReplicatedStorage""#));
-    }
-
-    #[test]
-    fn test_nested_children() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "Folder",
-                "Child1": {
-                    "$className": "Folder",
-                    "GrandChild": {
-                        "$className": "Folder"
-                    }
-                },
-                "Child2": {
-                    "$className": "Folder"
+            Err(error) => {
+                match format {
+                    DiagnosticFormat::Text => eprintln!("{source}: {error}"),
+                    _ => printer.error(&error),
                 }
+                Checked::Failed
             }
-        }"#;
-
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+        }
+    });
 
-        assert!(xml.contains(r#"<string name="Name">Test</string>"#));
-        assert!(xml.contains(r#"<string name="Name">Child1</string>"#));
-        assert!(xml.contains(r#"<string name="Name">Child2</string>"#));
-        assert!(xml.contains(r#"<string name="Name">GrandChild</string>"#));
+    println!("{summary}");
+    if !summary.succeeded() {
+        // Exiting also stops a check that's still running
+        std::process::exit(1);
     }
+    Ok(())
+}
 
-    #[test]
-    fn test_script_with_source() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "Script",
-                "$properties": {
-                    "Source": "print('Hello, World!')"
-                }
-            }
-        }"#;
+fn run_decompile(path: &Path, output: Option<&Path>) -> Result<()> {
+    let dom = input::read_file(path)?;
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("ROOT");
+    let root = if input::is_place_path(path) {
+        RootHandling::Place
+    } else {
+        RootHandling::Model
+    };
 
-        assert!(xml.contains(r#"<Item class="Script""#));
-        assert!(xml.contains(r#"<string name="Source">print('Hello, World!')</string>"#));
+    let project = decompile(&dom, name, root, &mut |diagnostic: Diagnostic| {
+        eprintln!("{diagnostic}")
+    });
+
+    write_project(&project, output)
+}
+
+fn run_init(output: &Path, template: Template, name: Option<String>, force: bool) -> Result<()> {
+    if output.exists() && !force {
+        bail!(
+            "{} already exists; pass --force to replace it",
+            output.display()
+        );
     }
 
-    #[test]
-    fn test_vector3_property() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "Part",
-                "$properties": {
-                    "Size": [10, 20, 30]
-                }
-            }
-        }"#;
+    let name = name
+        .or_else(|| {
+            let dir = std::env::current_dir().ok()?;
+            Some(dir.file_name()?.to_str()?.to_owned())
+        })
+        .unwrap_or_else(|| "Game".to_owned());
+
+    let project = template.project(&name)?;
+    let extension = match project.tree.class_name.as_deref() {
+        Some("DataModel") => "rbxl",
+        _ => "rbxm",
+    };
+
+    write_project(&project, Some(output))?;
+    eprintln!(
+        "Wrote {} from the {} template. Build it with rbxbuild --file {} -o {name}.{extension}",
+        output.display(),
+        template.name(),
+        output.display()
+    );
+    Ok(())
+}
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+fn run_migrate(path: &Path, output: Option<&Path>) -> Result<()> {
+    let project = migrate(path, &mut |diagnostic: Diagnostic| {
+        eprintln!("{diagnostic}")
+    })?;
+    write_project(&project, output)
+}
 
-        assert!(xml.contains(r#"<Vector3 name="size">"#));
-        assert!(xml.contains("<X>10</X>"));
-        assert!(xml.contains("<Y>20</Y>"));
-        assert!(xml.contains("<Z>30</Z>"));
+fn run_split(path: &Path, output: &Path) -> Result<()> {
+    let json =
+        fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let project = Project::from_json(&json)?;
+
+    let root_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("default.project.json");
+    let fragments = split(&project, root_name)?;
+
+    fs::create_dir_all(output).with_context(|| format!("Could not create {}", output.display()))?;
+    for fragment in &fragments {
+        let path = output.join(&fragment.file_name);
+        let json = serde_json::to_string_pretty(&fragment.contents)?;
+        fs::write(&path, json + "\n")
+            .with_context(|| format!("Could not write {}", path.display()))?;
     }
 
-    #[test]
-    fn test_boolean_property() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "Part",
-                "$properties": {
-                    "Anchored": true
-                }
-            }
-        }"#;
+    eprintln!(
+        "Split into {} files in {}",
+        fragments.len(),
+        output.display()
+    );
+    Ok(())
+}
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+fn write_project(project: &Project, output: Option<&Path>) -> Result<()> {
+    // Going through a Value sorts every map, so decompiling the same file
+    // twice gives the same output
+    let json = serde_json::to_string_pretty(&serde_json::to_value(project)?)?;
 
-        assert!(xml.contains(r#"<bool name="Anchored">true</bool>"#));
+    match output {
+        Some(output) => fs::write(output, json + "\n")
+            .with_context(|| format!("Could not write {}", output.display()))?,
+        None => println!("{json}"),
     }
 
-    #[test]
-    fn test_number_properties() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "Part",
-                "$properties": {
-                    "Transparency": 0.5
-                }
-            }
-        }"#;
+    Ok(())
+}
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+fn run_extract(
+    input: &Path,
+    path: &str,
+    output: &Path,
+    format: Option<OutputFormat>,
+    config: &Config,
+) -> Result<()> {
+    let args = BuildArgs::default();
+    let json = read_project(input, &args, config)?;
+    let Some(project) = Project::from_json(&json)?.subtree(path) else {
+        bail!("There is no node at {path} in {}", input.display());
+    };
 
-        assert!(xml.contains(r#"<float name="Transparency">0.5</float>"#));
-    }
+    // Only the addressed node is built, so the rest of the project doesn't
+    // slow this down or produce warnings. It's built like the full place
+    // would be, defines and config included
+    let mut options = build_options(&args, config, &project)?;
+    options.root = RootHandling::Model;
+    let dom = build(project, &options, &mut |diagnostic: Diagnostic| {
+        eprintln!("{diagnostic}")
+    })?;
 
-    #[test]
-    fn test_color3_property() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "Part",
-                "$properties": {
-                    "Color": [1, 0.5, 0.25]
-                }
-            }
-        }"#;
+    let format = format.unwrap_or_else(|| OutputFormat::from_path(output));
+    let stats = output::write_to_file(output, &dom, format, &options)?;
+    eprintln!("Wrote {} to {}", stats, output.display());
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+    Ok(())
+}
 
-        // Color3 gets serialized as Color3uint8 with a packed integer value
-        assert!(xml.contains(r#"<Color3uint8 name="Color3uint8">"#));
-    }
+fn run_convert(path: &Path, output: &Path, format: Option<OutputFormat>) -> Result<()> {
+    let dom = input::read_file(path)?;
 
-    #[test]
-    fn test_datamodel_place_file() {
-        let json = r#"{
-            "name": "PlaceFile",
-            "tree": {
-                "$className": "DataModel",
-                "Workspace": {
-                    "$className": "Workspace"
-                },
-                "ServerScriptService": {
-                    "$className": "ServerScriptService"
-                }
-            }
-        }"#;
+    // The root of a file that was read is a DataModel holding its top-level
+    // instances, which is how a place is written whether it holds services
+    // or a model's instances
+    let options = BuildOptions {
+        root: RootHandling::Place,
+        ..Default::default()
+    };
+    let format = format.unwrap_or_else(|| OutputFormat::from_path(output));
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+    let stats = output::write_to_file(output, &dom, format, &options)?;
+    eprintln!("Wrote {} to {}", stats, output.display());
 
-        // DataModel children should be at root level (siblings)
-        assert!(!xml.contains(r#"<Item class="DataModel""#));
-        assert!(xml.contains(r#"<Item class="Workspace""#));
-        assert!(xml.contains(r#"<Item class="ServerScriptService""#));
-    }
+    Ok(())
+}
 
-    #[test]
-    fn test_model_file() {
-        let json = r#"{
-            "name": "MyModel",
-            "tree": {
-                "$className": "Model",
-                "Part1": {
-                    "$className": "Part"
+fn run_query(
+    path: &Path,
+    glob: &str,
+    predicates: &[Predicate],
+    select: &[String],
+    format: ReportFormat,
+    config: &Config,
+) -> Result<()> {
+    let dom = load_artifact(path, &BuildArgs::default(), config)?;
+    let matches = query::select(&dom, glob, predicates);
+
+    match format {
+        ReportFormat::Text => {
+            for (path, instance) in &matches {
+                println!("{path} ({})", instance.class);
+                for property in select {
+                    let value = query::property_value(instance, property)
+                        .map_or_else(|| "(none)".to_owned(), |value| value.to_string());
+                    println!("  {property} = {value}");
                 }
             }
-        }"#;
+        }
+        ReportFormat::Json => {
+            let matches: Vec<_> = matches
+                .iter()
+                .map(|(path, instance)| {
+                    let properties: serde_json::Map<_, _> = select
+                        .iter()
+                        .filter_map(|property| {
+                            Some((property.clone(), query::property_value(instance, property)?))
+                        })
+                        .collect();
+                    serde_json::json!({
+                        "path": path,
+                        "class": instance.class.as_str(),
+                        "properties": properties,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&matches)?);
+        }
+    }
+
+    Ok(())
+}
+
+fn run_diff(old: &Path, new: &Path, format: ReportFormat, config: &Config) -> Result<()> {
+    let args = BuildArgs::default();
+    let changes = diff(
+        &load_artifact(old, &args, config)?,
+        &load_artifact(new, &args, config)?,
+    );
+
+    match format {
+        ReportFormat::Text => {
+            for change in &changes {
+                println!("{change}");
+            }
+            eprintln!("{} differences", changes.len());
+        }
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&changes)?),
+    }
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+    Ok(())
+}
 
-        // Model should be included (not like DataModel)
-        assert!(xml.contains(r#"<Item class="Model""#));
-        assert!(xml.contains(r#"<string name="Name">MyModel</string>"#));
+fn run_publish(
+    input: &Path,
+    args: &PublishArgs,
+    build_args: &BuildArgs,
+    config: &Config,
+) -> Result<()> {
+    // A project is built the same way `rbxbuild` would build it, so the
+    // uploaded place matches what a build writes, metadata included
+    let mut project = None;
+    if input.extension().is_some_and(|ext| ext == "json") {
+        let json = read_project(input, build_args, config)?;
+        project = Some(Project::from_json(&json)?);
     }
+    let (game_id, place_id) = project
+        .as_ref()
+        .map_or((None, None), |project| (project.game_id, project.place_id));
+    let target = publish_target(args, game_id, place_id)?;
+    let api_key = api_key(build_args, config)?;
+
+    let (dom, options) = match project {
+        Some(project) => {
+            let options = build_options(build_args, config, &project)?;
+            let dom = build(project, &options, &mut |diagnostic: Diagnostic| {
+                eprintln!("{diagnostic}")
+            })?;
+            (dom, options)
+        }
+        None => (input::read_file(input)?, BuildOptions::default()),
+    };
+    publish_place(&dom, &options, target, args.mode, &api_key)
+}
 
-    #[test]
-    fn test_cframe_property() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "Part",
-                "$properties": {
-                    "CFrame": [0, 10, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1]
-                }
-            }
-        }"#;
+/// The place to publish to. IDs passed on the command line win over the
+/// project's.
+fn publish_target(
+    args: &PublishArgs,
+    game_id: Option<u64>,
+    place_id: Option<u64>,
+) -> Result<PublishTarget> {
+    match (args.universe_id.or(game_id), args.place_id.or(place_id)) {
+        (Some(universe_id), Some(place_id)) => Ok(PublishTarget {
+            universe_id,
+            place_id,
+        }),
+        _ => bail!("Set gameId and placeId in the project, or pass --universe-id and --place-id"),
+    }
+}
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+/// Reads the Open Cloud API key from the variable `--api-key-env` or the
+/// config names.
+fn api_key(args: &BuildArgs, config: &Config) -> Result<String> {
+    let variable = args
+        .api_key_env
+        .as_deref()
+        .or(config.api_key_env.as_deref())
+        .unwrap_or(DEFAULT_API_KEY_ENV);
+    std::env::var(variable).with_context(|| format!("Set {variable} to an Open Cloud API key"))
+}
 
-        assert!(xml.contains(r#"<CoordinateFrame name="CFrame">"#));
-        assert!(xml.contains("<X>0</X>"));
-        assert!(xml.contains("<Y>10</Y>"));
-        assert!(xml.contains("<Z>0</Z>"));
+fn publish_place(
+    dom: &rbx_dom_weak::WeakDom,
+    options: &BuildOptions,
+    target: PublishTarget,
+    mode: VersionType,
+    api_key: &str,
+) -> Result<()> {
+    if !output::is_place(dom, options.root) {
+        bail!("Only places can be published, but the root isn't a DataModel");
     }
 
-    #[test]
-    fn test_enum_property() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "Part",
-                "$properties": {
-                    "Material": "Grass"
-                }
-            }
-        }"#;
+    // Binary is much smaller to upload, and never touches the disk
+    let mut place = Vec::new();
+    output::write_dom(&mut place, dom, OutputFormat::Binary, options)?;
+
+    let version = rbxbuild::publish::publish(target, &place, OutputFormat::Binary, mode, api_key)?;
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+    let action = match mode {
+        VersionType::Saved => "Saved",
+        VersionType::Published => "Published",
+    };
+    eprintln!("{action} version {version} of place {}", target.place_id);
+    Ok(())
+}
 
-        // Material enum should be serialized as token
-        assert!(xml.contains(r#"<token name="Material">"#));
+fn run_watch(
+    path: &Path,
+    output: Option<PathBuf>,
+    format: Option<OutputFormat>,
+    then: &[AfterBuild],
+    publish: &PublishArgs,
+    args: &BuildArgs,
+    config: &Config,
+) -> Result<()> {
+    let output = output.or_else(|| config.output.clone());
+    let format = format
+        .or(config.format)
+        .or_else(|| output.as_deref().map(OutputFormat::from_path))
+        .unwrap_or(OutputFormat::Xml);
+
+    // Better to find out now than after the first build
+    if then.contains(&AfterBuild::Publish) {
+        api_key(args, config)?;
     }
 
-    #[test]
-    fn test_multiple_properties() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "Part",
-                "$properties": {
-                    "Size": [4, 4, 4],
-                    "Anchored": true,
-                    "Transparency": 0.5,
-                    "Color": [1, 0, 0]
-                }
+    let rebuild = || -> Result<()> {
+        let started = Instant::now();
+        if !args.no_hooks {
+            config.hooks.run_pre_build()?;
+        }
+
+        let json = read_project(path, args, config)?;
+        let source = path.display().to_string();
+        let printer = DiagnosticPrinter::new(args.diagnostic_format, &source, &json);
+        let project = Project::from_json(&json).inspect_err(|error| printer.error(error))?;
+        let (game_id, place_id) = (project.game_id, project.place_id);
+        let name = project.name.clone().unwrap_or_else(|| "place".to_owned());
+
+        let options = build_options(args, config, &project)?;
+        let mut warnings = 0;
+        let dom = build(project, &options, &mut |diagnostic: Diagnostic| {
+            warnings += 1;
+            printer.print(&diagnostic)
+        })
+        .inspect_err(|error| printer.error(error))?;
+
+        let bytes = match &output {
+            Some(output) => {
+                let stats = output::write_to_file(output, &dom, format, &options)?;
+                eprintln!("Wrote {} to {}", stats, output.display());
+                Some(stats.bytes)
             }
-        }"#;
-
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
-
-        assert!(xml.contains(r#"<Vector3 name="size">"#));
-        assert!(xml.contains(r#"<bool name="Anchored">true</bool>"#));
-        assert!(xml.contains(r#"<float name="Transparency">0.5</float>"#));
-        assert!(xml.contains(r#"<Color3uint8 name="Color3uint8">"#));
-    }
-
-    #[test]
-    fn test_folder_without_explicit_classname() {
-        let json = r#"{
-            "name": "Test",
-            "tree": {
-                "$className": "DataModel",
-                "Workspace": {
-                    "$className": "Workspace",
-                    "SomeFolder": {
-                        "InnerFolder": {}
-                    }
+            None => {
+                eprintln!("Built {}", path.display());
+                None
+            }
+        };
+
+        if !args.no_hooks {
+            config.hooks.run_post_build(&BuildInfo {
+                name: &name,
+                output: output.as_deref(),
+                format,
+                bytes,
+                instances: count_instances(&dom, options.root),
+                warnings,
+                profile: options.profile.as_deref(),
+                duration: started.elapsed(),
+            })?;
+        }
+
+        for action in then {
+            match action {
+                AfterBuild::Publish => {
+                    let target = publish_target(publish, game_id, place_id)?;
+                    publish_place(
+                        &dom,
+                        &options,
+                        target,
+                        publish.mode,
+                        &api_key(args, config)?,
+                    )?;
                 }
             }
-        }"#;
+        }
+
+        Ok(())
+    };
+
+    if let Err(error) = rebuild() {
+        eprintln!("Error: {error:#}");
+    }
+    eprintln!("Watching {} for changes", path.display());
+    on_project_change(path, rebuild);
+
+    Ok(())
+}
+
+fn run_serve(
+    project: &Path,
+    host: &str,
+    port: u16,
+    args: &BuildArgs,
+    config: &Config,
+) -> Result<()> {
+    let dom = load_artifact(project, args, config)?;
+    let session = Arc::new(LiveSession::new(dom.root().name.clone(), &dom));
+
+    let listener = std::net::TcpListener::bind((host, port))
+        .with_context(|| format!("Could not listen on {host}:{port}"))?;
+    eprintln!(
+        "Serving {} to the Rojo plugin on {}",
+        project.display(),
+        listener.local_addr()?
+    );
+
+    let server = Arc::clone(&session);
+    thread::spawn(move || {
+        if let Err(error) = serve::serve_with(listener, move |request| server.handle(request)) {
+            eprintln!("Error: {error}");
+            std::process::exit(1);
+        }
+    });
+
+    on_project_change(project, || {
+        if session.update(&load_artifact(project, args, config)?) {
+            eprintln!("Rebuilt {}", project.display());
+        }
+        Ok(())
+    });
+
+    Ok(())
+}
+
+fn run_companion(
+    project: &Path,
+    host: &str,
+    port: u16,
+    args: &BuildArgs,
+    config: &Config,
+) -> Result<()> {
+    let dom = load_artifact(project, args, config)?;
+    let companion = Arc::new(Companion::new(dom.root().name.clone(), &dom));
+
+    let listener = std::net::TcpListener::bind((host, port))
+        .with_context(|| format!("Could not listen on {host}:{port}"))?;
+    eprintln!(
+        "Serving {} to the companion plugin on {}",
+        project.display(),
+        listener.local_addr()?
+    );
+
+    let server = Arc::clone(&companion);
+    thread::spawn(move || {
+        if let Err(error) = serve::serve_with(listener, move |request| server.handle(request)) {
+            eprintln!("Error: {error}");
+            std::process::exit(1);
+        }
+    });
 
-        let xml = json_to_xml(json).expect("Failed to convert JSON to XML");
+    on_project_change(project, || {
+        companion.update(&load_artifact(project, args, config)?);
+        eprintln!(
+            "Rebuilt {} (build {})",
+            project.display(),
+            companion.version()
+        );
+        Ok(())
+    });
 
-        // Should default to Folder when no className is specified
-        assert!(xml.contains(r#"<string name="Name">SomeFolder</string>"#));
-        assert!(xml.contains(r#"<string name="Name">InnerFolder</string>"#));
+    Ok(())
+}
+
+/// Calls `rebuild` whenever a file in the project's directory changes,
+/// forever.
+fn on_project_change(project: &Path, mut rebuild: impl FnMut() -> Result<()>) {
+    /// How often the project's directory is checked for changes.
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    let dir = project
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    serve::watch::watch(dir, POLL_INTERVAL, || {
+        // Keep the last good build until the project is fixed
+        if let Err(error) = rebuild() {
+            eprintln!("Error: {error:#}");
+        }
+    });
+}
+
+/// Reads a place or model file, or builds a project file the way the default
+/// command would with `args` and the config file.
+fn load_artifact(path: &Path, args: &BuildArgs, config: &Config) -> Result<rbx_dom_weak::WeakDom> {
+    load_artifact_reporting(path, args, config, &mut |diagnostic: Diagnostic| {
+        eprintln!("{diagnostic}")
+    })
+}
+
+/// Like [`load_artifact`], but hands building a project's diagnostics to
+/// `diagnostics` instead of printing them.
+fn load_artifact_reporting(
+    path: &Path,
+    args: &BuildArgs,
+    config: &Config,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<rbx_dom_weak::WeakDom> {
+    if path.extension().is_some_and(|ext| ext == "json") {
+        let json = read_project(path, args, config)?;
+        let project = Project::from_json(&json)?;
+        let options = build_options(args, config, &project)?;
+
+        Ok(build(project, &options, diagnostics)?)
+    } else {
+        Ok(input::read_file(path)?)
     }
 }
@@ -0,0 +1,159 @@
+//! Per-instance content hashes, for telling which instances changed between
+//! two builds.
+//!
+//! Paths are the same as in [`diff`](crate::diff): relative to the top level
+//! of the file, with a `(2)`, `(3)`, ... suffix for siblings that share a
+//! name. `Ref` properties are hashed as the path of the instance they point
+//! to, since referents are different on every build.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance, WeakDom,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::diff::keyed;
+use crate::options::RootHandling;
+use crate::output::top_level_refs;
+
+/// Every instance of a build, depth-first, returned by [`manifest`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Manifest {
+    pub instances: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub class: String,
+
+    /// BLAKE3 hash of the instance's properties, as hex. Doesn't cover its
+    /// children, which have entries of their own.
+    pub hash: String,
+}
+
+/// Lists every instance that would be written, with a hash of its
+/// properties. `root` decides what's at the top level, like when writing the
+/// DOM.
+pub fn manifest(dom: &WeakDom, root: RootHandling) -> Manifest {
+    // Paths are all assigned before hashing, so Refs can point anywhere
+    let mut paths = HashMap::new();
+    let mut order = Vec::new();
+    let mut stack: Vec<_> = keyed(dom, &top_level_refs(dom, root))
+        .into_iter()
+        .rev()
+        .collect();
+
+    while let Some((path, referent)) = stack.pop() {
+        let instance = dom.get_by_ref(referent).unwrap();
+
+        // Pushed in reverse so children come out in order
+        for (segment, child) in keyed(dom, instance.children()).into_iter().rev() {
+            stack.push((format!("{path}/{segment}"), child));
+        }
+
+        paths.insert(referent, path);
+        order.push(referent);
+    }
+
+    let instances = order
+        .into_iter()
+        .map(|referent| {
+            let instance = dom.get_by_ref(referent).unwrap();
+            ManifestEntry {
+                path: paths[&referent].clone(),
+                class: instance.class.to_string(),
+                hash: hash_properties(instance, &paths),
+            }
+        })
+        .collect();
+
+    Manifest { instances }
+}
+
+fn hash_properties(instance: &Instance, paths: &HashMap<Ref, String>) -> String {
+    // A BTreeMap so the properties are always hashed in the same order
+    let properties: BTreeMap<&str, Value> = instance
+        .properties
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Variant::Ref(target) => match paths.get(target) {
+                    Some(path) => serde_json::json!({ "Ref": path }),
+                    None => Value::Null,
+                },
+                other => serde_json::to_value(other).unwrap_or_default(),
+            };
+            (name.as_str(), value)
+        })
+        .collect();
+
+    let bytes = serde_json::to_vec(&properties).unwrap_or_default();
+    blake3::hash(&bytes).to_hex().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn build(transparency: f32) -> WeakDom {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Workspace")
+                    .with_name("Workspace")
+                    .with_child(
+                        InstanceBuilder::new("Model").with_name("House").with_child(
+                            InstanceBuilder::new("Part")
+                                .with_name("Wall")
+                                .with_property("Transparency", transparency),
+                        ),
+                    )
+                    .with_child(InstanceBuilder::new("Part").with_name("Wall")),
+            ),
+        );
+
+        // Point House.PrimaryPart at its wall
+        let workspace = dom.root().children()[0];
+        let house = dom.get_by_ref(workspace).unwrap().children()[0];
+        let wall = dom.get_by_ref(house).unwrap().children()[0];
+        dom.get_by_ref_mut(house)
+            .unwrap()
+            .properties
+            .insert("PrimaryPart".into(), Variant::Ref(wall));
+
+        dom
+    }
+
+    #[test]
+    fn hashes_are_stable_across_builds() {
+        let first = manifest(&build(0.0), RootHandling::Auto);
+        let second = manifest(&build(0.0), RootHandling::Auto);
+        assert_eq!(first, second);
+
+        let paths: Vec<_> = first.instances.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "Workspace",
+                "Workspace/House",
+                "Workspace/House/Wall",
+                "Workspace/Wall"
+            ]
+        );
+
+        let changed = manifest(&build(0.5), RootHandling::Auto);
+        let differs: Vec<_> = first
+            .instances
+            .iter()
+            .zip(&changed.instances)
+            .filter(|(a, b)| a.hash != b.hash)
+            .map(|(a, _)| a.path.as_str())
+            .collect();
+        assert_eq!(differs, ["Workspace/House/Wall"]);
+    }
+}
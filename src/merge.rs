@@ -0,0 +1,158 @@
+//! Grafting a built project onto an existing place.
+//!
+//! Useful when part of a place (terrain, lighting, hand-built maps) is made in
+//! Studio and only the rest is generated. Instances are matched by path: an
+//! instance that exists in both keeps everything the project doesn't mention,
+//! has the properties the project sets overwritten, and has its children
+//! merged the same way. Siblings sharing a name are matched in order.
+
+use std::collections::{HashMap, VecDeque};
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+
+use crate::options::RootHandling;
+use crate::output::top_level_refs;
+
+/// What [`merge`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeStats {
+    /// Instances that existed in both and were merged.
+    pub merged: usize,
+    /// Instances (with their descendants) that only existed in the project.
+    pub added: usize,
+    /// Instances that were replaced because the project gave them another
+    /// class.
+    pub replaced: usize,
+}
+
+/// Merges the top-level instances of `built` into the root of `base`.
+pub fn merge(base: &mut WeakDom, mut built: WeakDom) -> MergeStats {
+    let mut stats = MergeStats::default();
+    let mut stack = vec![(base.root_ref(), top_level_refs(&built, RootHandling::Auto))];
+
+    while let Some((base_parent, built_children)) = stack.pop() {
+        let mut candidates = children_by_name(base, base_parent);
+
+        for built_ref in built_children {
+            let built_instance = built.get_by_ref_mut(built_ref).unwrap();
+            let existing = candidates
+                .get_mut(built_instance.name.as_str())
+                .and_then(VecDeque::pop_front);
+
+            match existing {
+                Some(base_ref)
+                    if base.get_by_ref(base_ref).unwrap().class == built_instance.class =>
+                {
+                    let properties = std::mem::take(&mut built_instance.properties);
+                    let children = built_instance.children().to_vec();
+
+                    base.get_by_ref_mut(base_ref)
+                        .unwrap()
+                        .properties
+                        .extend(properties);
+                    stack.push((base_ref, children));
+                    stats.merged += 1;
+                }
+                Some(base_ref) => {
+                    base.destroy(base_ref);
+                    built.transfer(built_ref, base, base_parent);
+                    stats.replaced += 1;
+                }
+                None => {
+                    built.transfer(built_ref, base, base_parent);
+                    stats.added += 1;
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+fn children_by_name(dom: &WeakDom, parent: Ref) -> HashMap<String, VecDeque<Ref>> {
+    let mut children: HashMap<String, VecDeque<Ref>> = HashMap::new();
+
+    for &child in dom.get_by_ref(parent).unwrap().children() {
+        let name = dom.get_by_ref(child).unwrap().name.clone();
+        children.entry(name).or_default().push_back(child);
+    }
+
+    children
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{types::Variant, InstanceBuilder};
+    use ustr::Ustr;
+
+    use crate::query::find_by_path;
+
+    #[test]
+    fn merges_by_path() {
+        let mut base = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_name("Game")
+                .with_child(
+                    InstanceBuilder::new("Lighting")
+                        .with_name("Lighting")
+                        .with_property("Brightness", 2.0f32)
+                        .with_property("ClockTime", 14.0f32),
+                )
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_name("Workspace")
+                        .with_child(InstanceBuilder::new("Terrain").with_name("Terrain"))
+                        .with_child(InstanceBuilder::new("Part").with_name("Spawn")),
+                ),
+        );
+
+        let built = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_name("Project")
+                .with_child(
+                    InstanceBuilder::new("Lighting")
+                        .with_name("Lighting")
+                        .with_property("ClockTime", 6.0f32),
+                )
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_name("Workspace")
+                        .with_child(InstanceBuilder::new("SpawnLocation").with_name("Spawn")),
+                )
+                .with_child(
+                    InstanceBuilder::new("ServerScriptService")
+                        .with_name("ServerScriptService")
+                        .with_child(InstanceBuilder::new("Script").with_name("Main")),
+                ),
+        );
+
+        let stats = merge(&mut base, built);
+        assert_eq!(
+            stats,
+            MergeStats {
+                merged: 2,
+                added: 1,
+                replaced: 1
+            }
+        );
+
+        let lighting = find_by_path(&base, "Game/Lighting").unwrap();
+        assert_eq!(
+            lighting.properties.get(&Ustr::from("Brightness")),
+            Some(&Variant::Float32(2.0))
+        );
+        assert_eq!(
+            lighting.properties.get(&Ustr::from("ClockTime")),
+            Some(&Variant::Float32(6.0))
+        );
+
+        assert!(find_by_path(&base, "Game/Workspace/Terrain").is_some());
+        assert_eq!(
+            find_by_path(&base, "Game/Workspace/Spawn").unwrap().class,
+            "SpawnLocation"
+        );
+        assert!(find_by_path(&base, "Game/ServerScriptService/Main").is_some());
+    }
+}
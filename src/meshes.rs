@@ -0,0 +1,177 @@
+//! Checks for MeshParts built from a project.
+//!
+//! Studio measures a mesh and computes its collision geometry when it's
+//! imported, and stores both in the MeshPart. rbxbuild can't download meshes,
+//! so a project has to give the mesh's size itself, and collision geometry is
+//! left for Studio to compute.
+
+use rbx_dom_weak::{types::Variant, WeakDom};
+use ustr::Ustr;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::query;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+/// `Enum.CollisionFidelity.Box`, which needs no collision geometry.
+const BOX_FIDELITY: u32 = 2;
+
+/// Checks every MeshPart for properties only Studio can fill in.
+///
+/// A MeshPart without a mesh, or with a mesh but no `InitialSize`, gets a
+/// warning; `Size` defaults to `InitialSize` when it's missing, so the mesh
+/// shows at its natural size. A MeshPart that can collide and isn't set to
+/// `Box` collision fidelity gets a warning too, since it has no collision
+/// geometry until the place is saved in Studio.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckMeshParts;
+
+impl Transform for CheckMeshParts {
+    fn name(&self) -> &str {
+        "check-mesh-parts"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let initial_size_key = Ustr::from("InitialSize");
+        let size_key = Ustr::from("Size");
+        let mut resized = Vec::new();
+
+        for part in query::descendants(dom, dom.root_ref()) {
+            if part.class != "MeshPart" {
+                continue;
+            }
+
+            let path = query::path_of(dom, part.referent());
+            let property = |name: &str| part.properties.get(&Ustr::from(name));
+            let mut warn = |property: &str, message: &str| {
+                diagnostics
+                    .report(Diagnostic::warning(path.clone(), message).with_property(property));
+            };
+
+            let has_mesh = ["MeshId", "MeshContent"]
+                .into_iter()
+                .any(|name| property(name).is_some_and(|value| !content_is_empty(value)));
+            if !has_mesh {
+                warn(
+                    "MeshId",
+                    "This MeshPart has no MeshId, so it shows up as a plain block",
+                );
+                continue;
+            }
+
+            match part.properties.get(&initial_size_key) {
+                Some(initial_size) => {
+                    if !part.properties.contains_key(&size_key) {
+                        resized.push((part.referent(), initial_size.clone()));
+                    }
+                }
+                None => warn(
+                    "InitialSize",
+                    "The mesh's size can't be measured without Studio, so Size won't scale \
+                     it correctly. Set InitialSize to the size Studio shows for the mesh, or \
+                     import the MeshPart in Studio and build from a model file",
+                ),
+            }
+
+            let can_collide = !matches!(property("CanCollide"), Some(Variant::Bool(false)));
+            let has_geometry =
+                property("PhysicalConfigData").is_some() || property("PhysicsData").is_some();
+            let fidelity = match property("CollisionFidelity") {
+                Some(Variant::Enum(fidelity)) => fidelity.to_u32(),
+                _ => 0,
+            };
+            if can_collide && !has_geometry && fidelity != BOX_FIDELITY {
+                warn(
+                    "CollisionFidelity",
+                    "Collision geometry can't be computed without Studio, so this MeshPart \
+                     collides as a box until the place is saved in Studio. Set \
+                     CollisionFidelity to Box to make that explicit, or CanCollide to false",
+                );
+            }
+        }
+
+        for (referent, size) in resized {
+            let part = dom.get_by_ref_mut(referent).unwrap();
+            part.properties.insert(size_key, size);
+        }
+
+        Ok(())
+    }
+}
+
+fn content_is_empty(value: &Variant) -> bool {
+    match value {
+        Variant::ContentId(id) => id.as_str().is_empty(),
+        Variant::Content(content) => content.as_uri().unwrap_or_default().is_empty(),
+        Variant::String(uri) => uri.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rbx_dom_weak::{
+        types::{ContentId, Enum, Vector3},
+        InstanceBuilder,
+    };
+
+    #[test]
+    fn checks_mesh_parts() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("Model").with_name("Props"));
+        let initial_size = Vector3::new(2.0, 3.0, 4.0);
+        let rock = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("MeshPart")
+                .with_name("Rock")
+                .with_property("MeshId", ContentId::from("rbxassetid://123"))
+                .with_property("InitialSize", initial_size)
+                .with_property("CollisionFidelity", Enum::from_u32(BOX_FIDELITY)),
+        );
+        dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("MeshPart")
+                .with_name("Tree")
+                .with_property("MeshId", ContentId::from("rbxassetid://456")),
+        );
+        dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("MeshPart").with_name("Empty"),
+        );
+
+        let mut diagnostics = Vec::new();
+        CheckMeshParts
+            .transform_dom(&mut dom, &mut |diagnostic: Diagnostic| {
+                diagnostics.push(diagnostic)
+            })
+            .unwrap();
+
+        let warned: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.path.as_str(),
+                    diagnostic.property.as_deref().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            warned,
+            [
+                ("Props/Tree", "InitialSize"),
+                ("Props/Tree", "CollisionFidelity"),
+                ("Props/Empty", "MeshId"),
+            ]
+        );
+
+        let rock = dom.get_by_ref(rock).unwrap();
+        assert_eq!(
+            rock.properties.get(&Ustr::from("Size")),
+            Some(&Variant::Vector3(initial_size))
+        );
+    }
+}
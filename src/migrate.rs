@@ -0,0 +1,489 @@
+//! Turning a Rojo project that uses `$path` into a self-contained one.
+//!
+//! Files and directories referenced by `$path` are read the way Rojo reads
+//! them and inlined into the tree:
+//!
+//! | File | Becomes |
+//! | --- | --- |
+//! | `*.server.luau`, `*.client.luau`, `*.luau` (or `.lua`) | Script, LocalScript or ModuleScript with its `Source` |
+//! | `init.*` scripts in a directory | The directory itself, as that script |
+//! | `*.model.json` | The JSON model's instances |
+//! | `*.project.json` | The nested project's tree |
+//! | `*.rbxm`, `*.rbxmx` | The model, decompiled into nodes |
+//! | `*.txt` | A StringValue |
+//! | `*.meta.json`, `init.meta.json` | Class name and properties for the matching file or directory |
+//!
+//! Anything else is skipped with a warning.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Map, Value};
+
+use crate::decompile::decompile;
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::error::Result;
+use crate::input;
+use crate::options::RootHandling;
+use crate::project::Project;
+
+const SCRIPT_SUFFIXES: &[(&str, &str)] = &[
+    (".server.luau", "Script"),
+    (".server.lua", "Script"),
+    (".client.luau", "LocalScript"),
+    (".client.lua", "LocalScript"),
+    (".luau", "ModuleScript"),
+    (".lua", "ModuleScript"),
+];
+
+/// Reads the project at `path` and inlines everything its `$path` keys
+/// refer to.
+pub fn migrate(path: &Path, diagnostics: &mut dyn DiagnosticSink) -> Result<Project> {
+    let value = migrate_project(path, diagnostics)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+fn migrate_project(path: &Path, diagnostics: &mut dyn DiagnosticSink) -> Result<Value> {
+    let mut project = read_json(path)?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let name = project
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or("ROOT")
+        .to_owned();
+
+    if let Some(tree) = project.get_mut("tree") {
+        migrate_node(tree, base, &name, diagnostics)?;
+    }
+
+    // Only keep what this tool understands, dropping things like servePort
+    if let Value::Object(map) = &mut project {
+        map.retain(|key, _| {
+            matches!(
+                key.as_str(),
+                "name"
+                    | "tree"
+                    | "defines"
+                    | "gameId"
+                    | "placeId"
+                    | "meta"
+                    | "preset"
+                    | "collisionGroups"
+            )
+        });
+    }
+
+    Ok(project)
+}
+
+fn migrate_node(
+    node: &mut Value,
+    base: &Path,
+    path: &str,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<()> {
+    let Value::Object(map) = node else {
+        return Ok(());
+    };
+
+    map.remove("$ignoreUnknownInstances");
+    move_attributes(map);
+
+    if let Some(Value::String(relative)) = map.remove("$path") {
+        let target = base.join(&relative);
+
+        match snapshot(&target, diagnostics)? {
+            Some(Value::Object(snapshot)) => merge_into(map, snapshot),
+            _ => diagnostics.report(Diagnostic::warning(
+                path,
+                format!("$path {relative} doesn't exist or can't be migrated"),
+            )),
+        }
+    }
+
+    for (key, child) in map.iter_mut() {
+        if !key.starts_with('$') {
+            migrate_node(child, base, &format!("{path}/{key}"), diagnostics)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds what was read from disk to a node, keeping anything the project sets
+/// explicitly. Children in both are merged the same way.
+fn merge_into(node: &mut Map<String, Value>, snapshot: Map<String, Value>) {
+    for (key, value) in snapshot {
+        match (node.get_mut(&key), value) {
+            (Some(Value::Object(existing)), Value::Object(value)) if key == "$properties" => {
+                for (property, value) in value {
+                    existing.entry(property).or_insert(value);
+                }
+            }
+            (Some(Value::Object(existing)), Value::Object(value)) if !key.starts_with('$') => {
+                merge_into(existing, value);
+            }
+            (Some(_), _) => {}
+            (None, value) => {
+                node.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Reads a file or directory into a node, or `None` if it isn't something
+/// that becomes an instance.
+fn snapshot(path: &Path, diagnostics: &mut dyn DiagnosticSink) -> Result<Option<Value>> {
+    if path.is_dir() {
+        snapshot_dir(path, diagnostics).map(Some)
+    } else if path.is_file() {
+        Ok(snapshot_file(path, diagnostics)?.map(|(_, node)| node))
+    } else {
+        Ok(None)
+    }
+}
+
+fn snapshot_dir(path: &Path, diagnostics: &mut dyn DiagnosticSink) -> Result<Value> {
+    let nested = path.join("default.project.json");
+    if nested.is_file() {
+        return tree_of(&nested, diagnostics);
+    }
+
+    let mut node = Map::new();
+    let mut metas = Vec::new();
+
+    for entry in sorted_entries(path)? {
+        let file_name = file_name(&entry);
+
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        if let Some(class) = script_class(&file_name, "init") {
+            node.insert("$className".to_owned(), class.into());
+            node.insert(
+                "$properties".to_owned(),
+                json!({ "Source": read_string(&entry)? }),
+            );
+        } else if file_name == "init.meta.json" {
+            apply_meta(&mut node, read_json(&entry)?);
+        } else if let Some(stem) = file_name.strip_suffix(".meta.json") {
+            metas.push((stem.to_owned(), entry));
+        } else if entry.is_dir() {
+            node.insert(file_name, snapshot_dir(&entry, diagnostics)?);
+        } else if let Some((name, child)) = snapshot_file(&entry, diagnostics)? {
+            node.insert(name, child);
+        }
+    }
+
+    for (name, meta) in metas {
+        match node.get_mut(&name) {
+            Some(Value::Object(child)) => apply_meta(child, read_json(&meta)?),
+            _ => diagnostics.report(Diagnostic::warning(
+                meta.display().to_string(),
+                format!("There is no {name} for this meta file to apply to"),
+            )),
+        }
+    }
+
+    node.entry("$className").or_insert_with(|| "Folder".into());
+    Ok(Value::Object(node))
+}
+
+/// Reads a single file, returning the instance name it gets and its node.
+fn snapshot_file(
+    path: &Path,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Option<(String, Value)>> {
+    let file_name = file_name(path);
+
+    for &(suffix, class) in SCRIPT_SUFFIXES {
+        if let Some(name) = file_name.strip_suffix(suffix) {
+            let node = json!({
+                "$className": class,
+                "$properties": { "Source": read_string(path)? },
+            });
+            return Ok(Some((name.to_owned(), node)));
+        }
+    }
+
+    if let Some(name) = file_name.strip_suffix(".project.json") {
+        let tree = tree_of(path, diagnostics)?;
+        return Ok(Some((name.to_owned(), tree)));
+    }
+
+    if let Some(name) = file_name.strip_suffix(".model.json") {
+        return Ok(Some((name.to_owned(), json_model(read_json(path)?))));
+    }
+
+    if let Some(name) = file_name.strip_suffix(".txt") {
+        let node = json!({
+            "$className": "StringValue",
+            "$properties": { "Value": read_string(path)? },
+        });
+        return Ok(Some((name.to_owned(), node)));
+    }
+
+    if let Some(name) = file_name
+        .strip_suffix(".rbxm")
+        .or_else(|| file_name.strip_suffix(".rbxmx"))
+    {
+        let dom = input::read_file(path)?;
+        let project = decompile(&dom, name, RootHandling::Model, diagnostics);
+        return Ok(Some((name.to_owned(), serde_json::to_value(project.tree)?)));
+    }
+
+    diagnostics.report(Diagnostic::warning(
+        path.display().to_string(),
+        "Skipped a file that can't be migrated",
+    ));
+    Ok(None)
+}
+
+/// Converts a Rojo JSON model (`ClassName`, `Properties`, `Children`) into a
+/// project node.
+fn json_model(model: Value) -> Value {
+    let Value::Object(mut model) = model else {
+        return Value::Object(Map::new());
+    };
+
+    let mut node = Map::new();
+
+    if let Some(class) = model.remove("ClassName") {
+        node.insert("$className".to_owned(), class);
+    }
+
+    let mut properties = match model.remove("Properties") {
+        Some(Value::Object(properties)) => properties,
+        _ => Map::new(),
+    };
+    if let Some(attributes) = model.remove("Attributes") {
+        properties.insert("Attributes".to_owned(), tag_attributes(attributes));
+    }
+    if !properties.is_empty() {
+        node.insert("$properties".to_owned(), Value::Object(properties));
+    }
+
+    if let Some(Value::Array(children)) = model.remove("Children") {
+        for child in children {
+            let name = child
+                .get("Name")
+                .and_then(Value::as_str)
+                .unwrap_or("Instance")
+                .to_owned();
+            let mut child = json_model(child);
+
+            // Siblings can share a name in a model but not in a project
+            let mut key = name.clone();
+            let mut count = 1;
+            while node.contains_key(&key) {
+                count += 1;
+                key = format!("_{name} ({count})");
+            }
+            if key != name {
+                if let Value::Object(child) = &mut child {
+                    let properties = child
+                        .entry("$properties")
+                        .or_insert_with(|| Value::Object(Map::new()));
+                    properties["Name"] = name.into();
+                }
+            }
+
+            node.insert(key, child);
+        }
+    }
+
+    Value::Object(node)
+}
+
+/// Applies a `.meta.json` file's `className`, `properties` and `attributes`
+/// to a node.
+fn apply_meta(node: &mut Map<String, Value>, meta: Value) {
+    let Value::Object(mut meta) = meta else {
+        return;
+    };
+
+    if let Some(class) = meta.remove("className") {
+        node.insert("$className".to_owned(), class);
+    }
+
+    let mut properties = match meta.remove("properties") {
+        Some(Value::Object(properties)) => properties,
+        _ => Map::new(),
+    };
+    if let Some(attributes) = meta.remove("attributes") {
+        properties.insert("Attributes".to_owned(), tag_attributes(attributes));
+    }
+
+    if !properties.is_empty() {
+        let existing = node
+            .entry("$properties")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(existing) = existing {
+            existing.extend(properties);
+        }
+    }
+}
+
+/// Rojo projects can set attributes with `$attributes`; here they're an
+/// `Attributes` property.
+fn move_attributes(node: &mut Map<String, Value>) {
+    if let Some(attributes) = node.remove("$attributes") {
+        let properties = node
+            .entry("$properties")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(properties) = properties {
+            properties
+                .entry("Attributes")
+                .or_insert_with(|| tag_attributes(attributes));
+        }
+    }
+}
+
+/// Rojo lets attributes be plain numbers, strings and booleans, but an
+/// `Attributes` value needs each one tagged with its type.
+fn tag_attributes(attributes: Value) -> Value {
+    let Value::Object(attributes) = attributes else {
+        return attributes;
+    };
+
+    attributes
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::Bool(value) => json!({ "Bool": value }),
+                Value::Number(value) => json!({ "Float64": value }),
+                Value::String(value) => json!({ "String": value }),
+                other => other,
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+fn tree_of(project: &Path, diagnostics: &mut dyn DiagnosticSink) -> Result<Value> {
+    let mut project = migrate_project(project, diagnostics)?;
+    Ok(project
+        .get_mut("tree")
+        .map(Value::take)
+        .unwrap_or_else(|| Value::Object(Map::new())))
+}
+
+fn script_class(file_name: &str, stem: &str) -> Option<&'static str> {
+    SCRIPT_SUFFIXES
+        .iter()
+        .find(|(suffix, _)| file_name.strip_suffix(suffix) == Some(stem))
+        .map(|&(_, class)| class)
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|err| with_path(err, dir))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn read_string(path: &Path) -> Result<String> {
+    Ok(fs::read_to_string(path).map_err(|err| with_path(err, path))?)
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    Ok(serde_json::from_str(&read_string(path)?)?)
+}
+
+fn with_path(err: std::io::Error, path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        err.kind(),
+        format!("Could not read {}: {err}", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inlines_paths() {
+        let dir = std::env::temp_dir().join(format!("rbxbuild-migrate-{}", std::process::id()));
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("shared/Signal")).unwrap();
+
+        fs::write(
+            dir.join("default.project.json"),
+            r#"{
+                "name": "Game",
+                "servePort": 34872,
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": {
+                        "Shared": { "$path": "src/shared", "$attributes": { "Version": 2 } }
+                    },
+                    "ServerScriptService": { "Main": { "$path": "src/main.server.luau" } }
+                }
+            }"#,
+        )
+        .unwrap();
+        fs::write(src.join("main.server.luau"), "print('hi')").unwrap();
+        fs::write(src.join("shared/Util.luau"), "return {}").unwrap();
+        fs::write(
+            src.join("shared/Util.meta.json"),
+            r#"{"properties": {"Disabled": true}}"#,
+        )
+        .unwrap();
+        fs::write(src.join("shared/Signal/init.luau"), "return 1").unwrap();
+        fs::write(src.join("shared/Signal/Connection.luau"), "return 2").unwrap();
+        fs::write(
+            src.join("shared/Config.model.json"),
+            r#"{"ClassName": "Configuration", "Children": [
+                {"Name": "Speed", "ClassName": "NumberValue", "Properties": {"Value": 16}},
+                {"Name": "Speed", "ClassName": "NumberValue"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let mut warnings = Vec::new();
+        let project = migrate(&dir.join("default.project.json"), &mut |d: Diagnostic| {
+            warnings.push(d)
+        });
+        fs::remove_dir_all(&dir).unwrap();
+        let project = project.unwrap();
+
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let json = serde_json::to_value(&project).unwrap();
+        let tree = &json["tree"];
+
+        assert_eq!(
+            tree["ServerScriptService"]["Main"],
+            json!({ "$className": "Script", "$properties": { "Source": "print('hi')" } })
+        );
+
+        let shared = &tree["ReplicatedStorage"]["Shared"];
+        assert_eq!(shared["$className"], "Folder");
+        assert_eq!(
+            shared["$properties"]["Attributes"]["Version"],
+            json!({ "Float64": 2.0 })
+        );
+        assert_eq!(shared["Util"]["$properties"]["Disabled"], true);
+        assert_eq!(shared["Signal"]["$className"], "ModuleScript");
+        assert_eq!(
+            shared["Signal"]["Connection"]["$properties"]["Source"],
+            "return 2"
+        );
+        assert_eq!(shared["Config"]["Speed"]["$properties"]["Value"], 16.0);
+        assert_eq!(
+            shared["Config"]["_Speed (2)"]["$properties"]["Name"],
+            "Speed"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Handling of siblings that end up with the same name.
+//!
+//! Keys in a project are unique among their siblings, but a `Name` property
+//! can override one, and stripping classes moves children next to their
+//! grandparent's. Roblox allows siblings with the same name, yet anything
+//! that finds instances by name, from `FindFirstChild` to `require` paths,
+//! only ever sees one of them.
+
+use std::collections::HashSet;
+
+use rbx_dom_weak::WeakDom;
+use serde::Deserialize;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::query;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum NameCollisionPolicy {
+    /// Fail the build.
+    Error,
+
+    /// Keep both names as they are.
+    #[default]
+    Warn,
+
+    /// Rename the later siblings to `Name_2`, `Name_3` and so on.
+    AutoSuffix,
+}
+
+/// Reports every instance with a sibling of the same name before it, and
+/// handles it according to the policy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NameCollisions(pub NameCollisionPolicy);
+
+impl Transform for NameCollisions {
+    fn name(&self) -> &str {
+        "name-collisions"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let mut collisions = Vec::new();
+
+        for instance in query::descendants(dom, dom.root_ref()) {
+            let names: Vec<&str> = instance
+                .children()
+                .iter()
+                .map(|&child| dom.get_by_ref(child).unwrap().name.as_str())
+                .collect();
+            let mut seen = HashSet::new();
+            let mut used: HashSet<String> = names.iter().map(|name| name.to_string()).collect();
+
+            for (&child, name) in instance.children().iter().zip(&names) {
+                if seen.insert(*name) {
+                    continue;
+                }
+
+                let renamed = (self.0 == NameCollisionPolicy::AutoSuffix).then(|| {
+                    let renamed = (2..)
+                        .map(|n| format!("{name}_{n}"))
+                        .find(|renamed| !used.contains(renamed))
+                        .unwrap();
+                    used.insert(renamed.clone());
+                    renamed
+                });
+                collisions.push((instance.referent(), child, name.to_string(), renamed));
+            }
+        }
+
+        let count = collisions.len();
+        for (parent, child, name, renamed) in collisions {
+            let path = query::path_of(dom, parent);
+            diagnostics.report(match (self.0, &renamed) {
+                (NameCollisionPolicy::Error, _) => {
+                    Diagnostic::error(path, format!("More than one child is named {name}"))
+                }
+                (_, Some(renamed)) => Diagnostic::warning(
+                    path,
+                    format!("More than one child is named {name}, so one was renamed to {renamed}"),
+                ),
+                (_, None) => Diagnostic::warning(
+                    path,
+                    format!(
+                        "More than one child is named {name}, so only one can be found by name"
+                    ),
+                ),
+            });
+
+            if let Some(renamed) = renamed {
+                dom.get_by_ref_mut(child).unwrap().name = renamed;
+            }
+        }
+
+        match (self.0, count) {
+            (NameCollisionPolicy::Error, 1) => Err("found a name collision".into()),
+            (NameCollisionPolicy::Error, 2..) => {
+                Err(format!("found {count} name collisions").into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn handles_name_collisions() {
+        let build = || {
+            let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_name("Game"));
+            for name in ["Door", "Door", "Door_2", "Door", "Window"] {
+                dom.insert(
+                    dom.root_ref(),
+                    InstanceBuilder::new("Model").with_name(name),
+                );
+            }
+            dom
+        };
+        let run = |policy| {
+            let mut dom = build();
+            let mut diagnostics = Vec::new();
+            let result = NameCollisions(policy)
+                .transform_dom(&mut dom, &mut |diagnostic: Diagnostic| {
+                    diagnostics.push(diagnostic)
+                });
+            let names: Vec<_> = dom
+                .root()
+                .children()
+                .iter()
+                .map(|&child| dom.get_by_ref(child).unwrap().name.clone())
+                .collect();
+            (names, diagnostics, result.is_ok())
+        };
+
+        let (names, diagnostics, ok) = run(NameCollisionPolicy::Error);
+        assert!(!ok);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(names, ["Door", "Door", "Door_2", "Door", "Window"]);
+
+        let (names, diagnostics, ok) = run(NameCollisionPolicy::Warn);
+        assert!(ok);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(names, ["Door", "Door", "Door_2", "Door", "Window"]);
+
+        let (names, _, ok) = run(NameCollisionPolicy::AutoSuffix);
+        assert!(ok);
+        assert_eq!(names, ["Door", "Door_3", "Door_2", "Door_4", "Window"]);
+    }
+}
@@ -0,0 +1,107 @@
+//! Node.js bindings through N-API, built into `node/rbxbuild.node` by
+//! `npm run build` in `node/`.
+//!
+//! `build` and `validate` run on libuv's thread pool and return promises, so
+//! a server can keep answering requests while places build. `buildSync` is
+//! there for scripts that don't mind blocking.
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::diagnostics::Diagnostic;
+use crate::embed;
+use crate::output::OutputFormat;
+
+/// A build warning, as handed to JavaScript.
+#[napi(object)]
+pub struct JsDiagnostic {
+    pub severity: String,
+    pub path: String,
+    pub property: Option<String>,
+    pub message: String,
+}
+
+impl From<Diagnostic> for JsDiagnostic {
+    fn from(diagnostic: Diagnostic) -> Self {
+        JsDiagnostic {
+            severity: diagnostic.severity.to_string().to_lowercase(),
+            path: diagnostic.path,
+            property: diagnostic.property,
+            message: diagnostic.message,
+        }
+    }
+}
+
+fn parse_format(format: Option<&str>) -> Result<OutputFormat> {
+    match format {
+        None | Some("binary") => Ok(OutputFormat::Binary),
+        Some("xml") => Ok(OutputFormat::Xml),
+        Some(other) => Err(Error::new(
+            Status::InvalidArg,
+            format!("Unknown output format {other:?}, expected \"binary\" or \"xml\""),
+        )),
+    }
+}
+
+fn build_error(error: crate::Error) -> Error {
+    Error::new(Status::GenericFailure, error.to_string())
+}
+
+pub struct BuildTask {
+    project: String,
+    format: OutputFormat,
+}
+
+impl Task for BuildTask {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        embed::build_json(&self.project, self.format, &mut |_: Diagnostic| {}).map_err(build_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output.into())
+    }
+}
+
+pub struct ValidateTask {
+    project: String,
+}
+
+impl Task for ValidateTask {
+    type Output = Vec<Diagnostic>;
+    type JsValue = Vec<JsDiagnostic>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        embed::validate_json(&self.project).map_err(build_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output.into_iter().map(JsDiagnostic::from).collect())
+    }
+}
+
+/// Builds a project from its JSON, resolving to the place or model file. The
+/// format is `"binary"` unless `"xml"` is given.
+#[napi(ts_return_type = "Promise<Buffer>")]
+pub fn build(project: String, format: Option<String>) -> Result<AsyncTask<BuildTask>> {
+    let format = parse_format(format.as_deref())?;
+    Ok(AsyncTask::new(BuildTask { project, format }))
+}
+
+/// Like `build`, but blocks the event loop until the file is built.
+#[napi]
+pub fn build_sync(project: String, format: Option<String>) -> Result<Buffer> {
+    let format = parse_format(format.as_deref())?;
+    embed::build_json(&project, format, &mut |_: Diagnostic| {})
+        .map(Buffer::from)
+        .map_err(build_error)
+}
+
+/// Builds a project without writing it anywhere, resolving to its warnings.
+/// Rejects if it doesn't build.
+#[napi(ts_return_type = "Promise<Array<JsDiagnostic>>")]
+pub fn validate(project: String) -> AsyncTask<ValidateTask> {
+    AsyncTask::new(ValidateTask { project })
+}
@@ -0,0 +1,141 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+
+use crate::characters::InvalidCharacters;
+use crate::defines::Defines;
+use crate::floats::NonFiniteFloats;
+use crate::limits::Limits;
+use crate::meshes::CheckMeshParts;
+use crate::names::NameCollisions;
+use crate::placement::CheckPlacement;
+use crate::project::Project;
+use crate::resolver::PropertyResolver;
+use crate::scripts::{CheckLinkedSources, InjectConstants};
+use crate::timing::Timings;
+use crate::transform::Pipeline;
+
+/// Everything that changes how a project is built and serialized.
+///
+/// The CLI fills this in from its flags and `rbxbuild.toml` with
+/// `Config::build_options`, which library users with the `config` feature
+/// can call too. Without a config, start from [`BuildOptions::for_project`],
+/// which builds a project the way the CLI does without flags or a config
+/// file. `BuildOptions::default()`
+/// has no defines, metadata or transforms at all, so `=` expressions that use
+/// the project's defines fail and nothing is checked.
+#[derive(Debug, Default)]
+pub struct BuildOptions {
+    /// Turn resolution warnings into errors.
+    pub strict: bool,
+
+    /// Give every instance the reflection database's default value for each
+    /// property it doesn't set, like files saved by Studio.
+    pub fill_defaults: bool,
+
+    /// Whether the build has to be byte-identical from one run to the next.
+    /// On its own this only changes how the cache is keyed;
+    /// `Config::build_options` also sorts children, canonicalizes floats,
+    /// pins the build time and leaves out UniqueIds when it's set.
+    pub deterministic: bool,
+
+    /// Build siblings sorted by name instead of in the order the project
+    /// lists them, for output that diffs the same however the project is
+    /// reordered.
+    pub sort_children: bool,
+
+    /// Which instances end up at the top level of the output file.
+    pub root: RootHandling,
+
+    /// Extra name to class mappings checked before the built-in services.
+    pub services: HashMap<String, String>,
+
+    /// Key suffixes that give a node without `$className` its class, such as
+    /// `server` for `Main.server`. The suffix is left out of the name.
+    pub class_suffixes: HashMap<String, String>,
+
+    /// The profile selected for this build, if any. Its defines are expected
+    /// to already be merged into `defines`.
+    pub profile: Option<String>,
+
+    /// Values available to property expressions.
+    pub defines: Defines,
+
+    /// Build tags to include. Nodes with `$buildTags` are left out unless
+    /// they have one of these.
+    pub with_tags: Vec<String>,
+
+    /// Build tags to leave out, even when a node has another tag that's in
+    /// `with_tags`.
+    pub without_tags: Vec<String>,
+
+    /// Limits on the number of instances, the depth of the tree and the size
+    /// of script sources, which fail the build when it goes over them.
+    pub limits: Limits,
+
+    /// Metadata written into place files, usually the project's
+    /// [`meta`](crate::Project::meta). Models don't have any.
+    pub meta: BTreeMap<String, String>,
+
+    /// Start XML output with an `<?xml version="1.0" encoding="utf-8"?>`
+    /// declaration, for tools that expect one. Studio doesn't write it and
+    /// binary output ignores it.
+    pub xml_declaration: bool,
+
+    /// Hooks run around the built-in property resolution, in order.
+    pub resolvers: Vec<Box<dyn PropertyResolver>>,
+
+    /// Passes run over the project before instantiation and over the DOM
+    /// afterwards.
+    pub transforms: Pipeline,
+
+    /// Collects how long each phase of the build took, if set.
+    pub timings: Option<Timings>,
+
+    /// Reuse top-level subtrees that haven't changed since an earlier build.
+    #[cfg(feature = "cache")]
+    pub cache: Option<crate::cache::BuildCache>,
+}
+
+impl BuildOptions {
+    /// Options that build `project` the way the CLI does when it's given no
+    /// flags and there's no `rbxbuild.toml`: the project's defines and
+    /// metadata, its defines filled into scripts, and the checks the CLI
+    /// always runs. `$generate` scripts and heightmaps, which read files, are
+    /// left out.
+    ///
+    /// `strict` is passed in rather than set afterwards, since the linked
+    /// source check is made with it.
+    pub fn for_project(project: &Project, strict: bool) -> Self {
+        let mut options = BuildOptions {
+            strict,
+            defines: project.defines.clone(),
+            meta: project.meta.clone(),
+            ..Default::default()
+        };
+
+        let transforms = &mut options.transforms;
+        transforms.push(InjectConstants::new(project.defines.clone()));
+        transforms.push(NonFiniteFloats::default());
+        transforms.push(InvalidCharacters::default());
+        transforms.push(NameCollisions::default());
+        transforms.push(CheckLinkedSources::new(strict));
+        transforms.push(CheckMeshParts);
+        transforms.push(CheckPlacement);
+        options
+    }
+}
+
+/// Controls how the root of the tree is written out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum RootHandling {
+    /// Write a DataModel root's children as a place, anything else as a model.
+    #[default]
+    Auto,
+    /// Always write the root's children as top-level siblings.
+    Place,
+    /// Always write the root instance itself.
+    Model,
+}
@@ -0,0 +1,508 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::options::{BuildOptions, RootHandling};
+use crate::query::descendants;
+use crate::report::escape;
+use crate::split::unique_file_name;
+use crate::timing::{self, Phase};
+
+/// Buffer size used when writing output. Much larger than `BufWriter`'s
+/// default, since the serializers issue a lot of small writes.
+pub const OUTPUT_BUFFER_SIZE: usize = 1 << 20;
+
+/// Written at the start of XML output with
+/// [`BuildOptions::xml_declaration`].
+pub const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Roblox XML (.rbxlx / .rbxmx)
+    Xml,
+    /// Roblox binary (.rbxl / .rbxm)
+    Binary,
+}
+
+impl OutputFormat {
+    /// Guesses the format from a file extension, defaulting to XML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("rbxl" | "rbxm") => OutputFormat::Binary,
+            _ => OutputFormat::Xml,
+        }
+    }
+}
+
+/// Returns the instances that should be written at the top level of the file.
+///
+/// By default, if the root is a DataModel its children are written as siblings
+/// (like Rojo does for place files). Otherwise, the root instance itself is
+/// written.
+pub fn top_level_refs(dom: &WeakDom, root: RootHandling) -> Vec<Ref> {
+    if is_place(dom, root) {
+        // Place files don't contain an entry for the DataModel
+        dom.root().children().to_vec()
+    } else {
+        vec![dom.root_ref()]
+    }
+}
+
+/// Whether the DOM is written as a place rather than a model.
+pub fn is_place(dom: &WeakDom, root: RootHandling) -> bool {
+    match root {
+        RootHandling::Auto => dom.root().class == "DataModel",
+        RootHandling::Place => true,
+        RootHandling::Model => false,
+    }
+}
+
+/// The file extension for the DOM written in `format`, e.g. `rbxl` for a
+/// binary place.
+pub fn file_extension(dom: &WeakDom, root: RootHandling, format: OutputFormat) -> &'static str {
+    match (is_place(dom, root), format) {
+        (true, OutputFormat::Xml) => "rbxlx",
+        (true, OutputFormat::Binary) => "rbxl",
+        (false, OutputFormat::Xml) => "rbxmx",
+        (false, OutputFormat::Binary) => "rbxm",
+    }
+}
+
+/// Serializes the DOM in the given format.
+///
+/// Each format needs its cargo feature (`xml` or `binary`) to be enabled;
+/// formats that were compiled out return [`Error::Serialize`].
+pub fn write_dom<W: Write>(
+    mut writer: W,
+    dom: &WeakDom,
+    format: OutputFormat,
+    options: &BuildOptions,
+) -> Result<()> {
+    let ids_to_write = top_level_refs(dom, options.root);
+    let meta = if is_place(dom, options.root) {
+        encode_meta(&options.meta, format)
+    } else {
+        Vec::new()
+    };
+
+    if options.xml_declaration && format == OutputFormat::Xml {
+        writer.write_all(XML_DECLARATION.as_bytes())?;
+    }
+
+    timing::timed(options.timings.as_ref(), Phase::Serialize, || {
+        if meta.is_empty() {
+            write_refs(writer, dom, &ids_to_write, format)
+        } else {
+            let writer = MetaWriter {
+                inner: writer,
+                format,
+                meta: Some(meta),
+                position: 0,
+            };
+            write_refs(writer, dom, &ids_to_write, format)
+        }
+    })
+}
+
+/// Length of the binary format's header, which the `META` chunk follows.
+const BINARY_HEADER_LEN: usize = 32;
+
+/// Encodes metadata the way Studio writes it: `<Meta>` tags for XML, and an
+/// uncompressed `META` chunk for the binary format.
+fn encode_meta(meta: &BTreeMap<String, String>, format: OutputFormat) -> Vec<u8> {
+    if meta.is_empty() {
+        return Vec::new();
+    }
+
+    match format {
+        OutputFormat::Xml => meta
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "\n\t<Meta name=\"{}\">{}</Meta>",
+                    escape(name),
+                    escape(value)
+                )
+            })
+            .collect::<String>()
+            .into_bytes(),
+        OutputFormat::Binary => {
+            let mut data = (meta.len() as u32).to_le_bytes().to_vec();
+            for (name, value) in meta {
+                for string in [name, value] {
+                    data.extend((string.len() as u32).to_le_bytes());
+                    data.extend(string.as_bytes());
+                }
+            }
+
+            // Name, compressed length (0 for uncompressed), length and a
+            // reserved field
+            let mut chunk = b"META".to_vec();
+            chunk.extend(0u32.to_le_bytes());
+            chunk.extend((data.len() as u32).to_le_bytes());
+            chunk.extend(0u32.to_le_bytes());
+            chunk.extend(data);
+            chunk
+        }
+    }
+}
+
+/// Passes a serialized file through, adding the encoded metadata where it
+/// belongs: right after the `<roblox>` tag in XML, and after the header in
+/// the binary format.
+struct MetaWriter<W> {
+    inner: W,
+    format: OutputFormat,
+    meta: Option<Vec<u8>>,
+    position: usize,
+}
+
+impl<W: Write> Write for MetaWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(meta) = &self.meta else {
+            return self.inner.write(buf);
+        };
+
+        let split = match self.format {
+            OutputFormat::Xml => buf.iter().position(|&byte| byte == b'>').map(|end| end + 1),
+            OutputFormat::Binary => (BINARY_HEADER_LEN - self.position <= buf.len())
+                .then(|| BINARY_HEADER_LEN - self.position),
+        };
+
+        match split {
+            Some(split) => {
+                self.inner.write_all(&buf[..split])?;
+                self.inner.write_all(meta)?;
+                self.inner.write_all(&buf[split..])?;
+                self.meta = None;
+                Ok(buf.len())
+            }
+            None => {
+                let written = self.inner.write(buf)?;
+                self.position += written;
+                Ok(written)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// How many bytes the given instances and their descendants take up when
+/// serialized together as one file.
+pub fn serialized_size(dom: &WeakDom, refs: &[Ref], format: OutputFormat) -> Result<u64> {
+    let mut writer = CountingWriter {
+        inner: io::sink(),
+        bytes: 0,
+    };
+    write_refs(&mut writer, dom, refs, format)?;
+    Ok(writer.bytes)
+}
+
+#[cfg_attr(not(all(feature = "xml", feature = "binary")), allow(unused_variables))]
+fn write_refs<W: Write>(
+    writer: W,
+    dom: &WeakDom,
+    refs: &[Ref],
+    format: OutputFormat,
+) -> Result<()> {
+    match format {
+        #[cfg(feature = "xml")]
+        OutputFormat::Xml => rbx_xml::to_writer_default(writer, dom, refs)
+            .map_err(|err| Error::Serialize(Box::new(err))),
+        #[cfg(feature = "binary")]
+        OutputFormat::Binary => {
+            rbx_binary::to_writer(writer, dom, refs).map_err(|err| Error::Serialize(Box::new(err)))
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(Error::Serialize(
+            format!("rbxbuild was built without support for {format:?} output").into(),
+        )),
+    }
+}
+
+/// Describes the DOM as JSON for debugging: every instance with its referent,
+/// class, fully resolved properties and children, starting at the root.
+///
+/// Properties are written as `rbx_dom_weak` holds them, tagged with their
+/// type (`{"Vector3": [4, 1, 2]}`), and `Ref` properties hold the referent of
+/// the instance they point to.
+pub fn dom_to_json(dom: &WeakDom) -> serde_json::Value {
+    instance_to_json(dom, dom.root_ref())
+}
+
+fn instance_to_json(dom: &WeakDom, referent: Ref) -> serde_json::Value {
+    let instance = dom.get_by_ref(referent).unwrap();
+
+    // Going through a Value sorts the properties
+    let properties = serde_json::to_value(&instance.properties).unwrap_or_default();
+    let children: Vec<_> = instance
+        .children()
+        .iter()
+        .map(|&child| instance_to_json(dom, child))
+        .collect();
+
+    serde_json::json!({
+        "referent": referent.to_string(),
+        "name": instance.name,
+        "className": instance.class.as_str(),
+        "properties": properties,
+        "children": children,
+    })
+}
+
+/// How much was written by [`write_to_file`] and how long it took.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WriteStats {
+    pub bytes: u64,
+    pub duration: Duration,
+}
+
+impl WriteStats {
+    /// Bytes written per second.
+    pub fn throughput(&self) -> f64 {
+        self.bytes as f64 / self.duration.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for WriteStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MB: f64 = 1_000_000.0;
+
+        write!(
+            f,
+            "{:.1} MB in {:.2}s ({:.1} MB/s)",
+            self.bytes as f64 / MB,
+            self.duration.as_secs_f64(),
+            self.throughput() / MB
+        )
+    }
+}
+
+/// Serializes the DOM into a file, creating its parent directories if needed.
+pub fn write_to_file(
+    path: &Path,
+    dom: &WeakDom,
+    format: OutputFormat,
+    options: &BuildOptions,
+) -> Result<WriteStats> {
+    let start = Instant::now();
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut writer = create(path)?;
+    write_dom(&mut writer, dom, format, options)?;
+    writer.flush()?;
+
+    Ok(WriteStats {
+        bytes: writer.bytes,
+        duration: start.elapsed(),
+    })
+}
+
+/// The file [`write_split`] lists the files it wrote in.
+pub const SPLIT_INDEX_FILE: &str = "index.json";
+
+/// One of the files written by [`write_split`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitFile {
+    /// Name of the instance the file holds.
+    pub name: String,
+    pub class_name: String,
+    /// The file's name in the output directory.
+    pub file: String,
+    /// How many instances the file holds, counting the top-level one.
+    pub instances: usize,
+    pub bytes: u64,
+}
+
+/// Writes each top-level instance, such as each service of a place, to its
+/// own model file in `dir`, and lists the files in `index.json` there.
+///
+/// Files are named after the instances, with characters that aren't allowed
+/// in file names replaced and a number added when two names collide.
+pub fn write_split(
+    dir: &Path,
+    dom: &WeakDom,
+    format: OutputFormat,
+    options: &BuildOptions,
+) -> Result<Vec<SplitFile>> {
+    fs::create_dir_all(dir)?;
+
+    let extension = match format {
+        OutputFormat::Xml => "rbxmx",
+        OutputFormat::Binary => "rbxm",
+    };
+    let mut used = HashSet::from([SPLIT_INDEX_FILE.to_owned()]);
+    let mut files = Vec::new();
+
+    for referent in top_level_refs(dom, options.root) {
+        let instance = dom.get_by_ref(referent).unwrap();
+        let file = unique_file_name(&instance.name, extension, &mut used);
+
+        let mut writer = create(&dir.join(&file))?;
+        if options.xml_declaration && format == OutputFormat::Xml {
+            writer.write_all(XML_DECLARATION.as_bytes())?;
+        }
+        timing::timed(options.timings.as_ref(), Phase::Serialize, || {
+            write_refs(&mut writer, dom, &[referent], format)
+        })?;
+        writer.flush()?;
+
+        files.push(SplitFile {
+            name: instance.name.clone(),
+            class_name: instance.class.to_string(),
+            file,
+            instances: descendants(dom, referent).count(),
+            bytes: writer.bytes,
+        });
+    }
+
+    let index =
+        serde_json::to_string_pretty(&files).map_err(|err| Error::Serialize(Box::new(err)))?;
+    fs::write(dir.join(SPLIT_INDEX_FILE), index + "\n")?;
+
+    Ok(files)
+}
+
+fn create(path: &Path) -> Result<CountingWriter<BufWriter<File>>> {
+    let file = File::create(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("Could not create {}: {err}", path.display()),
+        )
+    })?;
+
+    Ok(CountingWriter {
+        inner: BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, file),
+        bytes: 0,
+    })
+}
+
+struct CountingWriter<W> {
+    inner: W,
+    bytes: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn writes_place_metadata() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace"));
+
+        let options = BuildOptions {
+            meta: [("ExplicitAutoJoints".to_owned(), "true".to_owned())].into(),
+            ..Default::default()
+        };
+
+        let mut xml = Vec::new();
+        write_dom(&mut xml, &dom, OutputFormat::Xml, &options).unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+        assert!(xml.starts_with("<roblox"));
+        assert!(xml.contains(r#"<Meta name="ExplicitAutoJoints">true</Meta>"#));
+
+        let mut binary = Vec::new();
+        write_dom(&mut binary, &dom, OutputFormat::Binary, &options).unwrap();
+        assert_eq!(&binary[BINARY_HEADER_LEN..BINARY_HEADER_LEN + 4], b"META");
+
+        for (bytes, format) in [
+            (xml.into_bytes(), OutputFormat::Xml),
+            (binary, OutputFormat::Binary),
+        ] {
+            let read = crate::input::read_dom(bytes.as_slice(), format).unwrap();
+            assert_eq!(read.root().children().len(), 1);
+        }
+
+        // Models don't have metadata
+        let model = BuildOptions {
+            root: RootHandling::Model,
+            ..options
+        };
+        let mut xml = Vec::new();
+        write_dom(&mut xml, &dom, OutputFormat::Xml, &model).unwrap();
+        assert!(!String::from_utf8(xml).unwrap().contains("<Meta"));
+    }
+
+    #[test]
+    fn writes_an_xml_declaration() {
+        let dom = WeakDom::new(InstanceBuilder::new("Folder"));
+        let options = BuildOptions {
+            xml_declaration: true,
+            ..Default::default()
+        };
+
+        let mut xml = Vec::new();
+        write_dom(&mut xml, &dom, OutputFormat::Xml, &options).unwrap();
+        assert!(xml.starts_with(XML_DECLARATION.as_bytes()));
+        crate::input::read_dom(xml.as_slice(), OutputFormat::Xml).unwrap();
+    }
+
+    #[test]
+    fn writes_each_service_to_its_own_file() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("Workspace").with_name("Workspace"),
+        );
+        dom.insert(workspace, InstanceBuilder::new("Part").with_name("Floor"));
+        for _ in 0..2 {
+            dom.insert(
+                dom.root_ref(),
+                InstanceBuilder::new("Folder").with_name("Shared"),
+            );
+        }
+
+        let dir = std::env::temp_dir().join(format!("rbxbuild-split-{}", std::process::id()));
+        let files = write_split(&dir, &dom, OutputFormat::Xml, &BuildOptions::default()).unwrap();
+
+        let names: Vec<_> = files.iter().map(|file| file.file.as_str()).collect();
+        assert_eq!(
+            names,
+            ["Workspace.rbxmx", "Shared.rbxmx", "Shared (2).rbxmx"]
+        );
+        assert_eq!(files[0].instances, 2);
+
+        let workspace = crate::input::read_file(&dir.join("Workspace.rbxmx")).unwrap();
+        assert_eq!(workspace.root().children().len(), 1);
+        let index = fs::read_to_string(dir.join(SPLIT_INDEX_FILE)).unwrap();
+        assert!(index.contains(r#""className": "Workspace""#));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+//! Checks for services and DataModels in the wrong place.
+//!
+//! A service only works as a direct child of the DataModel, and a DataModel
+//! can only be the root of a place. Anywhere else they're almost always a
+//! mistake, like a service key nested one level too deep, and Studio either
+//! refuses the file or quietly moves things around when it's opened.
+
+use rbx_dom_weak::{Instance, WeakDom};
+use rbx_reflection::ClassTag;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::instantiate::KNOWN_SERVICES;
+use crate::query;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+/// Warns about every service that isn't directly under a DataModel root,
+/// and every DataModel that isn't the root.
+///
+/// Services directly under a misplaced DataModel only get the DataModel's
+/// warning.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckPlacement;
+
+impl Transform for CheckPlacement {
+    fn name(&self) -> &str {
+        "check-placement"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        for instance in query::descendants(dom, dom.root_ref()).skip(1) {
+            let parent = dom.get_by_ref(instance.parent()).unwrap();
+            let path = || query::path_of(dom, instance.referent());
+
+            if instance.class == "DataModel" {
+                diagnostics.report(Diagnostic::warning(
+                    path(),
+                    "A DataModel can only be the root of a place, so this one won't load",
+                ));
+            } else if is_service(instance) && parent.class != "DataModel" {
+                let message = format!(
+                    "{} is a service, so it has to be directly under the DataModel of a place",
+                    instance.class
+                );
+                diagnostics.report(Diagnostic::warning(path(), message));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_service(instance: &Instance) -> bool {
+    match crate::database::get() {
+        Ok(database) => database
+            .classes
+            .get(instance.class.as_str())
+            .is_some_and(|class| class.tags.contains(&ClassTag::Service)),
+        Err(_) => KNOWN_SERVICES.contains(&instance.class.as_str()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn warns_about_misplaced_services() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_name("Game"));
+        let workspace = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace"));
+        dom.insert(workspace, InstanceBuilder::new("Lighting"));
+        dom.insert(workspace, InstanceBuilder::new("Folder").with_name("Map"));
+        let nested = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("DataModel").with_name("Nested"),
+        );
+        dom.insert(nested, InstanceBuilder::new("ReplicatedStorage"));
+
+        let mut diagnostics = Vec::new();
+        CheckPlacement
+            .transform_dom(&mut dom, &mut |diagnostic: Diagnostic| {
+                diagnostics.push(diagnostic)
+            })
+            .unwrap();
+
+        let paths: Vec<_> = diagnostics.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["Game/Workspace/Lighting", "Game/Nested"]);
+    }
+}
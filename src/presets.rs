@@ -0,0 +1,144 @@
+//! Named sets of Workspace and Lighting settings, so places don't all have to
+//! repeat the same property block. A project picks one with its `preset` key,
+//! and the preset only fills in properties the project doesn't set.
+//!
+//! The presets are kept as project nodes in `src/presets/` and compiled into
+//! the binary.
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::project::{Project, ProjectNode};
+use crate::resolver::HookResult;
+use crate::templates::merge_under;
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// Streaming with the improved model behavior, deferred signals,
+    /// adaptive physics and Future lighting.
+    Modern,
+
+    /// Like `modern`, with a shorter streaming radius and Voxel lighting
+    /// without environment lighting, for low-end devices.
+    Performance,
+
+    /// No streaming, so the whole place is always loaded, with brighter
+    /// Future lighting and softer shadows, for small places meant to be
+    /// looked at.
+    Showcase,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Modern, Preset::Performance, Preset::Showcase];
+
+    /// The name used in projects and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Modern => "modern",
+            Preset::Performance => "performance",
+            Preset::Showcase => "showcase",
+        }
+    }
+
+    /// The preset as a DataModel node with Workspace and Lighting children.
+    pub fn node(self) -> ProjectNode {
+        let source = match self {
+            Preset::Modern => include_str!("presets/modern.json"),
+            Preset::Performance => include_str!("presets/performance.json"),
+            Preset::Showcase => include_str!("presets/showcase.json"),
+        };
+        serde_json::from_str(source).expect("built-in presets are valid project nodes")
+    }
+
+    /// Fills in whatever `tree` doesn't set from the preset. Only places are
+    /// changed; anything else is reported and left alone.
+    pub fn apply(
+        self,
+        tree: &mut ProjectNode,
+        root_name: &str,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) {
+        if tree.class_name.as_deref() != Some("DataModel") {
+            diagnostics.report(Diagnostic::warning(
+                root_name,
+                format!(
+                    "The {} preset was ignored, since presets only apply to places",
+                    self.name()
+                ),
+            ));
+            return;
+        }
+
+        merge_under(tree, self.node());
+    }
+}
+
+/// Selects a preset for the build, replacing the project's own.
+#[derive(Debug, Clone, Copy)]
+pub struct SelectPreset(pub Preset);
+
+impl Transform for SelectPreset {
+    fn name(&self) -> &str {
+        "preset"
+    }
+
+    fn transform_project(
+        &self,
+        project: &mut Project,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        project.preset = Some(self.0);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::BuildOptions;
+
+    #[test]
+    fn presets_fill_in_places() {
+        for preset in Preset::ALL {
+            let project = Project::from_json(&format!(
+                r#"{{
+                    "name": "Game",
+                    "preset": "{}",
+                    "tree": {{
+                        "$className": "DataModel",
+                        "Lighting": {{ "$properties": {{ "ClockTime": 6 }} }}
+                    }}
+                }}"#,
+                preset.name()
+            ))
+            .unwrap();
+
+            let mut diagnostics = Vec::new();
+            let dom = crate::build(
+                project,
+                &BuildOptions::default(),
+                &mut |diagnostic: Diagnostic| diagnostics.push(diagnostic),
+            )
+            .unwrap();
+            assert!(diagnostics.is_empty(), "{}: {diagnostics:?}", preset.name());
+
+            let lighting = crate::query::find_by_path(&dom, "Game/Lighting").unwrap();
+            assert_eq!(
+                crate::query::property_value(lighting, "ClockTime"),
+                Some(serde_json::json!(6.0))
+            );
+            assert!(crate::query::find_by_path(&dom, "Game/Workspace").is_some());
+        }
+
+        let mut diagnostics = Vec::new();
+        let mut model = ProjectNode::new("Model");
+        Preset::Modern.apply(&mut model, "Tree", &mut |diagnostic: Diagnostic| {
+            diagnostics.push(diagnostic)
+        });
+        assert!(model.children.is_empty());
+        assert_eq!(diagnostics.len(), 1);
+    }
+}
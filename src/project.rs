@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use indexmap::IndexMap;
+use rbx_dom_weak::types::Variant;
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use ustr::{Ustr, UstrMap};
+
+use crate::collision::CollisionGroups;
+use crate::defines::Defines;
+use crate::error::Result;
+use crate::humanoid::Outfit;
+use crate::input::BOM;
+use crate::presets::Preset;
+use crate::resolution::{AmbiguousValue, UnresolvedValue};
+use crate::surface::SurfaceMaps;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    /// The name of the top-level instance described by the project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The tree of instances described by this project. Projects always
+    /// describe at least one instance.
+    pub tree: ProjectNode,
+
+    /// Named values that property expressions can refer to.
+    #[serde(default, skip_serializing_if = "Defines::is_empty")]
+    pub defines: Defines,
+
+    /// The universe `publish` uploads to, named as in Rojo projects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_id: Option<u64>,
+
+    /// The place `publish` uploads to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub place_id: Option<u64>,
+
+    /// Entries for a place file's metadata, which Studio writes as `<Meta>`
+    /// tags in `.rbxlx` files and a `META` chunk in `.rbxl` files, e.g.
+    /// `{"ExplicitAutoJoints": "true"}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+
+    /// Workspace and Lighting settings used wherever the tree doesn't set
+    /// its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<Preset>,
+
+    /// Collision groups written into the Workspace, see the `collision`
+    /// module.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collision_groups: Option<CollisionGroups>,
+}
+
+/// A node in a project tree.
+///
+/// Class and property names are interned, so generated projects that repeat
+/// the same few names across thousands of nodes only store each one once.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectNode {
+    #[serde(rename = "$className", skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<Ustr>,
+
+    #[serde(
+        rename = "$properties",
+        default,
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub properties: UstrMap<UnresolvedValue>,
+
+    /// Luau script whose result is merged into this node. Only run when the
+    /// build includes [`GenerateNodes`](crate::generate::GenerateNodes).
+    #[serde(rename = "$generate", skip_serializing_if = "Option::is_none")]
+    pub generate: Option<String>,
+
+    /// Texture maps for a SurfaceAppearance child, see the `surface` module.
+    #[serde(rename = "$surface", skip_serializing_if = "Option::is_none")]
+    pub surface: Option<SurfaceMaps>,
+
+    /// Appearance of a HumanoidDescription, see the `humanoid` module.
+    #[serde(rename = "$outfit", skip_serializing_if = "Option::is_none")]
+    pub outfit: Option<Outfit>,
+
+    /// Tags that leave this node out of builds that don't ask for one of
+    /// them, see [`filter_build_tags`](crate::filter::filter_build_tags).
+    #[serde(rename = "$buildTags", default, skip_serializing_if = "Vec::is_empty")]
+    pub build_tags: Vec<String>,
+
+    /// Where this node goes among its siblings. Siblings are built in
+    /// ascending `$order`, counting nodes without one as 0, and keep the
+    /// order the project lists them in otherwise.
+    #[serde(rename = "$order", skip_serializing_if = "Option::is_none")]
+    pub order: Option<i64>,
+
+    /// Child nodes, in the order the project lists them, which is the order
+    /// they're built in.
+    #[serde(flatten)]
+    pub children: IndexMap<String, ProjectNode>,
+}
+
+impl Project {
+    /// Parses a project from a JSON string. A leading byte order mark, which
+    /// Windows tools like to write, is skipped.
+    ///
+    /// There's no limit on how deeply the tree can be nested: the stack is
+    /// grown as needed instead of giving up after serde_json's default of
+    /// 128 levels.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut deserializer =
+            serde_json::Deserializer::from_str(json.strip_prefix(BOM).unwrap_or(json));
+        deserializer.disable_recursion_limit();
+
+        let project = Project::deserialize(serde_stacker::Deserializer::new(&mut deserializer))?;
+        deserializer.end()?;
+        Ok(project)
+    }
+
+    pub fn new(name: impl Into<String>, tree: ProjectNode) -> Self {
+        Project {
+            name: Some(name.into()),
+            tree,
+            defines: Defines::new(),
+            game_id: None,
+            place_id: None,
+            meta: BTreeMap::new(),
+            preset: None,
+            collision_groups: None,
+        }
+    }
+
+    /// Turns the node at `path` into a project of its own, keeping the
+    /// defines. The path is made of the keys used in the project file,
+    /// starting with the project's name, like a diagnostic's path (e.g.
+    /// `Game/ReplicatedStorage/Prefabs/Tree`). Returns `None` if there's no
+    /// such node.
+    pub fn subtree(self, path: &str) -> Option<Project> {
+        let mut segments = path.split('/');
+        let mut name = self.name.unwrap_or_else(|| "ROOT".to_owned());
+        if segments.next()? != name {
+            return None;
+        }
+
+        let mut tree = self.tree;
+        for segment in segments {
+            tree = tree.children.shift_remove(segment)?;
+            name = segment.to_owned();
+        }
+
+        // The IDs, metadata, preset and collision groups are for the whole
+        // place, not a model taken out of it
+        Some(Project {
+            name: Some(name),
+            tree,
+            defines: self.defines,
+            game_id: None,
+            place_id: None,
+            meta: BTreeMap::new(),
+            preset: None,
+            collision_groups: None,
+        })
+    }
+}
+
+impl ProjectNode {
+    /// Creates a node with the given class name and no properties or children.
+    pub fn new(class_name: impl Into<Ustr>) -> Self {
+        ProjectNode {
+            class_name: Some(class_name.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Sets a property, replacing any previous value with the same name.
+    pub fn property(mut self, name: impl Into<Ustr>, value: impl Into<UnresolvedValue>) -> Self {
+        self.properties.insert(name.into(), value.into());
+        self
+    }
+
+    /// Adds a child, replacing any previous child with the same name.
+    pub fn child(mut self, name: impl Into<String>, node: ProjectNode) -> Self {
+        self.children.insert(name.into(), node);
+        self
+    }
+}
+
+// Written by hand rather than derived because `#[serde(flatten)]` buffers
+// the children and deserializes them again from the buffer, which recurses
+// once per level without ever growing the stack, see `Project::from_json`.
+impl<'de> Deserialize<'de> for ProjectNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_map(ProjectNodeVisitor)
+    }
+}
+
+struct ProjectNodeVisitor;
+
+impl<'de> Visitor<'de> for ProjectNodeVisitor {
+    type Value = ProjectNode;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a project node")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> std::result::Result<ProjectNode, A::Error> {
+        let mut node = ProjectNode::default();
+        let mut seen = Vec::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            if key.starts_with('$') && seen.contains(&key) {
+                return Err(de::Error::custom(format_args!("duplicate field `{key}`")));
+            }
+
+            match key.as_str() {
+                "$className" => node.class_name = map.next_value()?,
+                "$properties" => node.properties = map.next_value()?,
+                "$generate" => node.generate = map.next_value()?,
+                "$surface" => node.surface = map.next_value()?,
+                "$outfit" => node.outfit = map.next_value()?,
+                "$buildTags" => node.build_tags = map.next_value()?,
+                "$order" => node.order = map.next_value()?,
+                _ => {
+                    let child = map.next_value()?;
+                    node.children.insert(key, child);
+                    continue;
+                }
+            }
+            seen.push(key);
+        }
+
+        Ok(node)
+    }
+}
+
+impl From<Variant> for UnresolvedValue {
+    fn from(value: Variant) -> Self {
+        UnresolvedValue::FullyQualified(value)
+    }
+}
+
+impl From<AmbiguousValue> for UnresolvedValue {
+    fn from(value: AmbiguousValue) -> Self {
+        UnresolvedValue::Ambiguous(value)
+    }
+}
+
+macro_rules! ambiguous_from {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for UnresolvedValue {
+                fn from(value: $ty) -> Self {
+                    UnresolvedValue::Ambiguous(AmbiguousValue::$variant(value.into()))
+                }
+            }
+        )*
+    };
+}
+
+ambiguous_from! {
+    bool => Bool,
+    f64 => Number,
+    f32 => Number,
+    i32 => Number,
+    String => String,
+    &str => String,
+    Vec<String> => StringArray,
+    [f64; 2] => Array2,
+    [f64; 3] => Array3,
+    [f64; 4] => Array4,
+    [f64; 12] => Array12,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builder_round_trips_through_json() {
+        let project = Project::new(
+            "Test",
+            ProjectNode::new("DataModel").child(
+                "Workspace",
+                ProjectNode::default().child(
+                    "Baseplate",
+                    ProjectNode::new("Part")
+                        .property("Anchored", true)
+                        .property("Size", [64.0, 1.0, 64.0])
+                        .property("Material", "Grass"),
+                ),
+            ),
+        );
+
+        let json = serde_json::to_string(&project).unwrap();
+        let parsed: Project = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, project);
+    }
+
+    #[test]
+    fn serializes_rojo_project_shape() {
+        let node = ProjectNode::new("Folder").child("Inner", ProjectNode::default());
+        let value = serde_json::to_value(&node).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "$className": "Folder",
+                "Inner": {}
+            })
+        );
+    }
+
+    #[test]
+    fn subtree_by_path() {
+        let project = Project::new(
+            "Game",
+            ProjectNode::new("DataModel").child(
+                "ReplicatedStorage",
+                ProjectNode::default().child("Tree", ProjectNode::new("Model")),
+            ),
+        );
+
+        let tree = project
+            .clone()
+            .subtree("Game/ReplicatedStorage/Tree")
+            .unwrap();
+        assert_eq!(tree.name.as_deref(), Some("Tree"));
+        assert_eq!(tree.tree, ProjectNode::new("Model"));
+
+        assert_eq!(project.clone().subtree("Game"), Some(project.clone()));
+        assert!(project.clone().subtree("Game/Workspace").is_none());
+        assert!(project.subtree("Other/ReplicatedStorage").is_none());
+    }
+}
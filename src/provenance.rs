@@ -0,0 +1,365 @@
+//! Recording which build a place or model came from inside the built tree,
+//! so a running game can report it.
+//!
+//! The git commit, build time, rbxbuild version and profile are written at a
+//! path below the root (e.g. `ReplicatedStorage/BuildInfo`) as one of:
+//!
+//! - attributes `BuildCommit`, `BuildTime`, `BuildVersion` and
+//!   `BuildProfile` on an instance that's already there,
+//! - a `StringValue` holding them as a JSON object,
+//! - a `ModuleScript` returning them as a table, e.g.
+//!   `require(ReplicatedStorage.BuildInfo).commit`.
+
+use std::{
+    process::Command,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rbx_dom_weak::{
+    types::{Attributes, Ref, Variant},
+    InstanceBuilder, WeakDom,
+};
+use serde::Deserialize;
+use ustr::Ustr;
+
+use crate::defines::{DefineValue, Defines};
+use crate::diagnostics::DiagnosticSink;
+use crate::resolver::HookResult;
+use crate::scripts::table_literal;
+use crate::transform::Transform;
+
+/// Where a build came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    /// The checked out commit, with `-dirty` appended if there are
+    /// uncommitted changes. `None` outside a git repository.
+    pub commit: Option<String>,
+
+    /// When the build ran, in RFC 3339 format (UTC).
+    pub time: String,
+
+    /// The rbxbuild version.
+    pub version: String,
+    pub profile: Option<String>,
+}
+
+impl Provenance {
+    /// Collects the provenance of a build running now in the current
+    /// directory. `SOURCE_DATE_EPOCH` takes the place of the current time if
+    /// it's set.
+    pub fn collect(profile: Option<&str>) -> Self {
+        Provenance {
+            commit: git_commit(),
+            time: timestamp(build_time()),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            profile: profile.map(str::to_owned),
+        }
+    }
+
+    /// The fields that are set, by name.
+    pub fn fields(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("commit", self.commit.as_deref()),
+            ("time", Some(self.time.as_str())),
+            ("version", Some(self.version.as_str())),
+            ("profile", self.profile.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect()
+    }
+}
+
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let mut commit = String::from_utf8(output.stdout).ok()?.trim().to_owned();
+
+    let clean = Command::new("git")
+        .args(["diff", "--quiet", "HEAD"])
+        .status()
+        .is_ok_and(|status| status.success());
+    if !clean {
+        commit.push_str("-dirty");
+    }
+
+    Some(commit)
+}
+
+static PINNED_TIME: OnceLock<i64> = OnceLock::new();
+
+/// Makes the build time the time of the checked out commit, or the Unix epoch
+/// outside a git repository, for the rest of the process. Deterministic
+/// builds do this, so the timestamps they write only change with the source.
+/// `SOURCE_DATE_EPOCH` still wins if it's set.
+pub fn pin_build_time() {
+    PINNED_TIME.get_or_init(|| commit_time().unwrap_or(0));
+}
+
+fn commit_time() -> Option<i64> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%ct"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()?.trim().parse().ok()
+}
+
+/// Seconds since the Unix epoch, or `SOURCE_DATE_EPOCH` if it's set, so
+/// reproducible builds don't change with the time they ran at.
+pub(crate) fn build_time() -> i64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<i64>().ok())
+        .or_else(|| PINNED_TIME.get().copied())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_secs() as i64)
+        })
+}
+
+/// Converts seconds since the Unix epoch to a year, month and day in the
+/// proleptic Gregorian calendar.
+pub(crate) fn civil_date(seconds: i64) -> (i64, u32, u32) {
+    // Days since 0000-03-01, so leap days fall at the end of each year
+    let days = seconds.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+
+    // Months counted from March
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+
+    // January and February belong to the next year
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp.
+fn timestamp(seconds: i64) -> String {
+    let (year, month, day) = civil_date(seconds);
+    let time = seconds.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// How provenance is written into the tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum ProvenanceStyle {
+    /// Attributes on the instance at the path, which has to exist.
+    Attributes,
+
+    /// A StringValue at the path, holding a JSON object.
+    StringValue,
+
+    /// A ModuleScript at the path, returning a table.
+    #[default]
+    ModuleScript,
+}
+
+/// Writes a build's [`Provenance`] into the built tree. The path is
+/// slash-separated and starts below the root, e.g.
+/// `ReplicatedStorage/BuildInfo`; an instance already at the path is replaced
+/// unless the style is [`ProvenanceStyle::Attributes`].
+#[derive(Debug, Clone)]
+pub struct InjectProvenance {
+    pub path: String,
+    pub style: ProvenanceStyle,
+    pub provenance: Provenance,
+}
+
+impl InjectProvenance {
+    pub fn new(path: impl Into<String>, style: ProvenanceStyle, provenance: Provenance) -> Self {
+        InjectProvenance {
+            path: path.into(),
+            style,
+            provenance,
+        }
+    }
+}
+
+impl Transform for InjectProvenance {
+    fn name(&self) -> &str {
+        "provenance"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let segments: Vec<&str> = self.path.split('/').filter(|s| !s.is_empty()).collect();
+        let fields = self.provenance.fields();
+
+        if self.style == ProvenanceStyle::Attributes {
+            let referent = find(dom, &segments)
+                .ok_or_else(|| format!("There's no instance at {}", self.path))?;
+            let instance = dom.get_by_ref_mut(referent).unwrap();
+
+            let key = Ustr::from("Attributes");
+            let mut attributes = match instance.properties.remove(&key) {
+                Some(Variant::Attributes(attributes)) => attributes,
+                _ => Attributes::new(),
+            };
+            for (name, value) in fields {
+                let name = format!("Build{}{}", name[..1].to_uppercase(), &name[1..]);
+                attributes.insert(name, Variant::String(value.to_owned()));
+            }
+            instance
+                .properties
+                .insert(key, Variant::Attributes(attributes));
+            return Ok(());
+        }
+
+        let Some((name, parent_path)) = segments.split_last() else {
+            return Err("The provenance path needs a name for the new instance".into());
+        };
+        let parent = find(dom, parent_path)
+            .ok_or_else(|| format!("There's no instance at {}", parent_path.join("/")))?;
+
+        if let Some(existing) = find(dom, &segments) {
+            dom.destroy(existing);
+        }
+
+        let builder = match self.style {
+            ProvenanceStyle::StringValue => {
+                let object: serde_json::Map<_, _> = fields
+                    .into_iter()
+                    .map(|(name, value)| (name.to_owned(), value.into()))
+                    .collect();
+                InstanceBuilder::new("StringValue")
+                    .with_property("Value", serde_json::to_string(&object)?)
+            }
+            _ => {
+                let table: Defines = fields
+                    .into_iter()
+                    .map(|(name, value)| (name.to_owned(), DefineValue::String(value.to_owned())))
+                    .collect();
+                InstanceBuilder::new("ModuleScript")
+                    .with_property("Source", format!("return {}\n", table_literal(&table)))
+            }
+        };
+        dom.insert(parent, builder.with_name(*name));
+
+        Ok(())
+    }
+}
+
+/// Follows `segments` down from the root, taking the first child with each
+/// name.
+fn find(dom: &WeakDom, segments: &[&str]) -> Option<Ref> {
+    let mut current = dom.root_ref();
+    for segment in segments {
+        current = dom
+            .get_by_ref(current)?
+            .children()
+            .iter()
+            .copied()
+            .find(|&child| {
+                dom.get_by_ref(child)
+                    .is_some_and(|child| child.name == *segment)
+            })?;
+    }
+    Some(current)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostics::Diagnostic;
+    use crate::query;
+
+    fn provenance() -> Provenance {
+        Provenance {
+            commit: Some("0123abcd".to_owned()),
+            time: timestamp(1_718_454_896),
+            version: "1.2.3".to_owned(),
+            profile: None,
+        }
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(951_782_400), (2000, 2, 29));
+        assert_eq!(civil_date(1_735_689_599), (2024, 12, 31));
+        assert_eq!(civil_date(1_735_689_600), (2025, 1, 1));
+        assert_eq!(timestamp(1_718_454_896), "2024-06-15T12:34:56Z");
+    }
+
+    #[test]
+    fn injects_provenance() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_name("Game"));
+        let storage = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("ReplicatedStorage").with_name("ReplicatedStorage"),
+        );
+        dom.insert(
+            storage,
+            InstanceBuilder::new("Folder").with_name("BuildInfo"),
+        );
+
+        let transform =
+            |style| InjectProvenance::new("ReplicatedStorage/BuildInfo", style, provenance());
+        let mut sink = |_: Diagnostic| {};
+
+        transform(ProvenanceStyle::ModuleScript)
+            .transform_dom(&mut dom, &mut sink)
+            .unwrap();
+        let script = query::find_by_path(&dom, "Game/ReplicatedStorage/BuildInfo").unwrap();
+        assert_eq!(script.class, "ModuleScript");
+        assert_eq!(
+            script.properties.get(&Ustr::from("Source")),
+            Some(&Variant::String(
+                "return { commit = \"0123abcd\", time = \"2024-06-15T12:34:56Z\", version = \"1.2.3\" }\n"
+                    .to_owned()
+            ))
+        );
+        assert_eq!(dom.get_by_ref(storage).unwrap().children().len(), 1);
+
+        transform(ProvenanceStyle::Attributes)
+            .transform_dom(&mut dom, &mut sink)
+            .unwrap();
+        let script = query::find_by_path(&dom, "Game/ReplicatedStorage/BuildInfo").unwrap();
+        let Some(Variant::Attributes(attributes)) =
+            script.properties.get(&Ustr::from("Attributes"))
+        else {
+            panic!("no attributes");
+        };
+        assert_eq!(
+            attributes.get("BuildCommit"),
+            Some(&Variant::String("0123abcd".to_owned()))
+        );
+        assert_eq!(attributes.get("BuildProfile"), None);
+
+        let missing = InjectProvenance::new(
+            "Nowhere/BuildInfo",
+            ProvenanceStyle::StringValue,
+            provenance(),
+        );
+        assert!(missing.transform_dom(&mut dom, &mut sink).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Uploading a built place through Roblox's Open Cloud place publishing API.
+//!
+//! Needs an Open Cloud API key with the `universe-places:write` scope for the
+//! universe being published to. Uploading the assets a project refers to is in
+//! [`assets`].
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::output::OutputFormat;
+
+pub mod assets;
+
+/// Where Open Cloud's place publishing API lives.
+const API_BASE: &str = "https://apis.roblox.com/universes/v1";
+
+/// The environment variable the Open Cloud API key is read from unless
+/// another one is named.
+pub const DEFAULT_API_KEY_ENV: &str = "ROBLOX_API_KEY";
+
+/// Whether an upload goes live or is only saved as a new version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum VersionType {
+    /// Save a new version without updating the live place.
+    #[default]
+    Saved,
+    /// Save a new version and make it live for players.
+    Published,
+}
+
+impl VersionType {
+    fn as_str(self) -> &'static str {
+        match self {
+            VersionType::Saved => "Saved",
+            VersionType::Published => "Published",
+        }
+    }
+}
+
+/// The place to upload to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublishTarget {
+    pub universe_id: u64,
+    pub place_id: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PublishResponse {
+    version_number: u64,
+}
+
+/// Uploads a serialized place and returns the version number Roblox gave
+/// it.
+pub fn publish(
+    target: PublishTarget,
+    place: &[u8],
+    format: OutputFormat,
+    version_type: VersionType,
+    api_key: &str,
+) -> Result<u64> {
+    let content_type = match format {
+        OutputFormat::Xml => "application/xml",
+        OutputFormat::Binary => "application/octet-stream",
+    };
+
+    let response = ureq::post(&endpoint(target, version_type))
+        .set("x-api-key", api_key)
+        .set("Content-Type", content_type)
+        .send_bytes(place);
+
+    match response {
+        Ok(response) => {
+            let body = response.into_string()?;
+            let parsed: PublishResponse = serde_json::from_str(&body).map_err(|_| {
+                Error::Publish(format!("Unexpected response from Open Cloud: {body}"))
+            })?;
+            Ok(parsed.version_number)
+        }
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            Err(Error::Publish(format!(
+                "Open Cloud returned {status}: {}",
+                error_message(&body)
+            )))
+        }
+        Err(error) => Err(Error::Publish(error.to_string())),
+    }
+}
+
+fn endpoint(target: PublishTarget, version_type: VersionType) -> String {
+    format!(
+        "{API_BASE}/{}/places/{}/versions?versionType={}",
+        target.universe_id,
+        target.place_id,
+        version_type.as_str()
+    )
+}
+
+/// Open Cloud errors are JSON with a `message`, but fall back to the whole
+/// body for anything else, such as a gateway's HTML page.
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| json.get("message")?.as_str().map(str::to_owned))
+        .unwrap_or_else(|| body.trim().to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn endpoints() {
+        let target = PublishTarget {
+            universe_id: 123,
+            place_id: 456,
+        };
+
+        assert_eq!(
+            endpoint(target, VersionType::Published),
+            "https://apis.roblox.com/universes/v1/123/places/456/versions?versionType=Published"
+        );
+        assert_eq!(
+            endpoint(target, VersionType::Saved),
+            "https://apis.roblox.com/universes/v1/123/places/456/versions?versionType=Saved"
+        );
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            error_message(r#"{"code": "PERMISSION_DENIED", "message": "Invalid API key"}"#),
+            "Invalid API key"
+        );
+        assert_eq!(error_message("Bad Gateway\n"), "Bad Gateway");
+    }
+}
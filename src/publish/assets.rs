@@ -0,0 +1,416 @@
+//! Uploading local files referenced as `{"$asset": "path"}` through Open
+//! Cloud's assets API while building.
+//!
+//! [`AssetUploader`] is a [`PropertyResolver`] that swaps each reference for
+//! an `rbxassetid://` URL. Uploaded files are remembered by a hash of their
+//! contents in a JSON cache file, so a file is only uploaded again when it
+//! changes. Commit the cache file to share the IDs with everyone who builds
+//! the project.
+//!
+//! Needs an Open Cloud API key with the `asset:read` and `asset:write`
+//! scopes for the creator the assets are uploaded to.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::error_message;
+use crate::resolution::{AmbiguousValue, UnresolvedValue};
+use crate::resolver::{HookResult, PropertyContext, PropertyResolver};
+
+/// Where Open Cloud's assets API lives.
+const API_BASE: &str = "https://apis.roblox.com/assets/v1";
+
+/// The cache file used when none is given.
+pub const DEFAULT_CACHE_FILE: &str = "rbxbuild-assets.json";
+
+/// How often an upload that's still being processed is checked on.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many times an upload is checked on before giving up.
+const MAX_POLLS: usize = 60;
+
+/// Who uploaded assets belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Creator {
+    User(u64),
+    Group(u64),
+}
+
+impl std::fmt::Display for Creator {
+    /// Writes the creator the way [`parse_creator`] reads it.
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Creator::User(id) => write!(formatter, "user:{id}"),
+            Creator::Group(id) => write!(formatter, "group:{id}"),
+        }
+    }
+}
+
+impl Creator {
+    fn to_json(self) -> serde_json::Value {
+        match self {
+            Creator::User(id) => json!({ "userId": id.to_string() }),
+            Creator::Group(id) => json!({ "groupId": id.to_string() }),
+        }
+    }
+}
+
+/// Parses `user:ID` or `group:ID`.
+pub fn parse_creator(text: &str) -> Result<Creator, String> {
+    let (kind, id) = text
+        .split_once(':')
+        .ok_or_else(|| format!("Expected user:ID or group:ID, got {text:?}"))?;
+    let id = id
+        .parse()
+        .map_err(|_| format!("Invalid creator ID {id:?}"))?;
+
+    match kind {
+        "user" => Ok(Creator::User(id)),
+        "group" => Ok(Creator::Group(id)),
+        _ => Err(format!(
+            "Unknown creator kind {kind:?}, expected user or group"
+        )),
+    }
+}
+
+/// The cache file's contents: uploaded asset IDs keyed by the BLAKE3 hash of
+/// the file that was uploaded.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AssetCache {
+    assets: BTreeMap<String, CachedAsset>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedAsset {
+    asset_id: u64,
+
+    /// The file the asset was first uploaded from, for people reading the
+    /// cache.
+    path: String,
+}
+
+/// Uploads the files that properties refer to with `$asset` and substitutes
+/// their asset IDs.
+pub struct AssetUploader {
+    /// Directory `$asset` paths are relative to.
+    base: PathBuf,
+    cache_path: PathBuf,
+    creator: Creator,
+    api_key: String,
+
+    /// Held for the whole of an upload, so a file used by several properties
+    /// is only uploaded once.
+    cache: Mutex<AssetCache>,
+}
+
+impl AssetUploader {
+    /// Reads the cache file if there is one. `$asset` paths are resolved
+    /// against `base`.
+    pub fn new(
+        base: impl Into<PathBuf>,
+        cache_path: impl Into<PathBuf>,
+        creator: Creator,
+        api_key: impl Into<String>,
+    ) -> crate::Result<Self> {
+        let cache_path = cache_path.into();
+        let cache = match fs::read_to_string(&cache_path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|error| {
+                crate::Error::Publish(format!(
+                    "Invalid asset cache {}: {error}",
+                    cache_path.display()
+                ))
+            })?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => AssetCache::default(),
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(AssetUploader {
+            base: base.into(),
+            cache_path,
+            creator,
+            api_key: api_key.into(),
+            cache: Mutex::new(cache),
+        })
+    }
+
+    /// Returns the asset ID for a file, uploading it unless the cache already
+    /// has one for its contents.
+    fn asset_id(&self, path: &Path) -> Result<u64, String> {
+        let full_path = self.base.join(path);
+        let contents = fs::read(&full_path)
+            .map_err(|error| format!("Could not read asset {}: {error}", full_path.display()))?;
+        let hash = blake3::hash(&contents).to_hex().to_string();
+
+        let mut cache = self.cache.lock().unwrap_or_else(|error| error.into_inner());
+        if let Some(cached) = cache.assets.get(&hash) {
+            return Ok(cached.asset_id);
+        }
+
+        let asset_id = self.upload(path, &hash, &contents)?;
+        log::info!("Uploaded {} as asset {asset_id}", path.display());
+
+        cache.assets.insert(
+            hash,
+            CachedAsset {
+                asset_id,
+                path: path.to_string_lossy().replace('\\', "/"),
+            },
+        );
+
+        // Saved after every upload, so a build that fails later doesn't lose
+        // what was already uploaded
+        let json = serde_json::to_string_pretty(&*cache).map_err(|error| error.to_string())?;
+        fs::write(&self.cache_path, json + "\n").map_err(|error| {
+            format!(
+                "Uploaded {} as asset {asset_id}, but could not write {}: {error}",
+                path.display(),
+                self.cache_path.display()
+            )
+        })?;
+
+        Ok(asset_id)
+    }
+
+    fn upload(&self, path: &Path, hash: &str, contents: &[u8]) -> Result<u64, String> {
+        let (asset_type, content_type) = asset_type(path)?;
+        let display_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let request = json!({
+            "assetType": asset_type,
+            "displayName": display_name,
+            "description": "Uploaded by rbxbuild",
+            "creationContext": { "creator": self.creator.to_json() },
+        });
+        // The file's own hash won't turn up inside it
+        let boundary = format!("rbxbuild-{hash}");
+        let body = multipart_body(&boundary, &request, &display_name, content_type, contents);
+
+        let response = ureq::post(&format!("{API_BASE}/assets"))
+            .set("x-api-key", &self.api_key)
+            .set(
+                "Content-Type",
+                &format!("multipart/form-data; boundary={boundary}"),
+            )
+            .send_bytes(&body);
+        let mut operation = read_operation(response)?;
+
+        for _ in 0..MAX_POLLS {
+            if operation.done {
+                return operation
+                    .response
+                    .and_then(|response| response.asset_id.parse().ok())
+                    .ok_or_else(|| {
+                        format!(
+                            "Open Cloud didn't return an asset ID for {}",
+                            path.display()
+                        )
+                    });
+            }
+
+            thread::sleep(POLL_INTERVAL);
+            let response = ureq::get(&format!("{API_BASE}/{}", operation.path))
+                .set("x-api-key", &self.api_key)
+                .call();
+            operation = read_operation(response)?;
+        }
+
+        Err(format!(
+            "Open Cloud was still processing {} after {} seconds",
+            path.display(),
+            MAX_POLLS as u64 * POLL_INTERVAL.as_secs()
+        ))
+    }
+}
+
+impl PropertyResolver for AssetUploader {
+    fn before_resolve(
+        &self,
+        _ctx: &PropertyContext<'_>,
+        value: &UnresolvedValue,
+    ) -> HookResult<Option<UnresolvedValue>> {
+        let UnresolvedValue::Ambiguous(AmbiguousValue::Asset(reference)) = value else {
+            return Ok(None);
+        };
+
+        // Left as a string so it becomes Content or ContentId depending on
+        // the property
+        let asset_id = self.asset_id(&reference.path)?;
+        Ok(Some(UnresolvedValue::Ambiguous(AmbiguousValue::String(
+            format!("rbxassetid://{asset_id}"),
+        ))))
+    }
+}
+
+/// A long-running operation, which is what Open Cloud returns for uploads.
+#[derive(Deserialize)]
+struct Operation {
+    /// E.g. `operations/{id}`, relative to the API base.
+    path: String,
+
+    #[serde(default)]
+    done: bool,
+
+    response: Option<UploadedAsset>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadedAsset {
+    asset_id: String,
+}
+
+fn read_operation(response: Result<ureq::Response, ureq::Error>) -> Result<Operation, String> {
+    match response {
+        Ok(response) => {
+            let body = response.into_string().map_err(|error| error.to_string())?;
+            serde_json::from_str(&body)
+                .map_err(|_| format!("Unexpected response from Open Cloud: {body}"))
+        }
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            Err(format!(
+                "Open Cloud returned {status}: {}",
+                error_message(&body)
+            ))
+        }
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// The Open Cloud asset type and MIME type for a file, from its extension.
+fn asset_type(path: &Path) -> Result<(&'static str, &'static str), String> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    Ok(match extension.as_str() {
+        "png" => ("Image", "image/png"),
+        "jpg" | "jpeg" => ("Image", "image/jpeg"),
+        "bmp" => ("Image", "image/bmp"),
+        "tga" => ("Image", "image/tga"),
+        "mp3" => ("Audio", "audio/mpeg"),
+        "ogg" => ("Audio", "audio/ogg"),
+        "wav" => ("Audio", "audio/wav"),
+        "flac" => ("Audio", "audio/flac"),
+        "fbx" => ("Model", "model/fbx"),
+        _ => {
+            return Err(format!(
+                "Can't upload {}: only images, audio and FBX models are supported",
+                path.display()
+            ))
+        }
+    })
+}
+
+fn multipart_body(
+    boundary: &str,
+    request: &serde_json::Value,
+    file_name: &str,
+    content_type: &str,
+    contents: &[u8],
+) -> Vec<u8> {
+    let file_name = file_name.replace(['"', '\\', '\r', '\n'], "_");
+
+    let mut body = Vec::with_capacity(contents.len() + 512);
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\n\
+             Content-Disposition: form-data; name=\"request\"\r\n\
+             Content-Type: application/json\r\n\r\n\
+             {request}\r\n\
+             --{boundary}\r\n\
+             Content-Disposition: form-data; name=\"fileContent\"; filename=\"{file_name}\"\r\n\
+             Content-Type: {content_type}\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(contents);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    body
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_creators() {
+        assert_eq!(parse_creator("user:123"), Ok(Creator::User(123)));
+        assert_eq!(parse_creator("group:45"), Ok(Creator::Group(45)));
+        assert!(parse_creator("123").is_err());
+        assert!(parse_creator("team:1").is_err());
+        assert!(parse_creator("user:abc").is_err());
+    }
+
+    #[test]
+    fn builds_multipart_bodies() {
+        let body = multipart_body(
+            "b",
+            &json!({ "assetType": "Image" }),
+            "wall",
+            "image/png",
+            b"PNG",
+        );
+
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--b\r\n\
+             Content-Disposition: form-data; name=\"request\"\r\n\
+             Content-Type: application/json\r\n\r\n\
+             {\"assetType\":\"Image\"}\r\n\
+             --b\r\n\
+             Content-Disposition: form-data; name=\"fileContent\"; filename=\"wall\"\r\n\
+             Content-Type: image/png\r\n\r\n\
+             PNG\r\n--b--\r\n"
+        );
+        assert!(asset_type(Path::new("textures/wall.PNG")).is_ok());
+        assert!(asset_type(Path::new("notes.txt")).is_err());
+    }
+
+    #[test]
+    fn uses_cached_assets() {
+        let dir = std::env::temp_dir().join(format!("rbxbuild-assets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("wall.png"), b"not really a png").unwrap();
+
+        let hash = blake3::hash(b"not really a png").to_hex().to_string();
+        let cache_path = dir.join(DEFAULT_CACHE_FILE);
+        fs::write(
+            &cache_path,
+            json!({ "assets": { hash: { "assetId": 42, "path": "wall.png" } } }).to_string(),
+        )
+        .unwrap();
+
+        // Nothing is uploaded, so no API key is needed
+        let uploader = AssetUploader::new(&dir, &cache_path, Creator::User(1), "").unwrap();
+        let value: UnresolvedValue = serde_json::from_str(r#"{"$asset": "wall.png"}"#).unwrap();
+        let ctx = PropertyContext {
+            class_name: "Decal",
+            property: "Texture",
+            path: "Game/Workspace/Wall/Decal",
+        };
+        let resolved = uploader.before_resolve(&ctx, &value).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            resolved,
+            Some(UnresolvedValue::Ambiguous(AmbiguousValue::String(
+                "rbxassetid://42".to_owned()
+            )))
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Python bindings, built into a `rbxbuild` extension module with maturin
+//! (`maturin build --release`, configured in `pyproject.toml`). The
+//! signatures are described for type checkers in `rbxbuild.pyi`.
+//!
+//! Builds release the GIL, so several threads can build at once.
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+
+use crate::diagnostics::Diagnostic;
+use crate::embed;
+use crate::options::RootHandling;
+use crate::output::OutputFormat;
+
+create_exception!(
+    rbxbuild,
+    BuildError,
+    PyException,
+    "A project couldn't be built or a file couldn't be read."
+);
+
+fn parse_format(format: &str) -> PyResult<OutputFormat> {
+    match format {
+        "binary" => Ok(OutputFormat::Binary),
+        "xml" => Ok(OutputFormat::Xml),
+        other => Err(PyValueError::new_err(format!(
+            "Unknown output format {other:?}, expected \"binary\" or \"xml\""
+        ))),
+    }
+}
+
+fn diagnostic_dict<'py>(py: Python<'py>, diagnostic: &Diagnostic) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("severity", diagnostic.severity.to_string().to_lowercase())?;
+    dict.set_item("path", &diagnostic.path)?;
+    dict.set_item("property", &diagnostic.property)?;
+    dict.set_item("message", &diagnostic.message)?;
+    Ok(dict)
+}
+
+/// Builds a project from its JSON and returns the place or model file.
+#[pyfunction]
+#[pyo3(signature = (project, format = "binary"))]
+fn build_project<'py>(
+    py: Python<'py>,
+    project: &str,
+    format: &str,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let format = parse_format(format)?;
+    let bytes = py
+        .allow_threads(|| embed::build_json(project, format, &mut |_: Diagnostic| {}))
+        .map_err(|error| BuildError::new_err(error.to_string()))?;
+
+    Ok(PyBytes::new_bound(py, &bytes))
+}
+
+/// Builds a project without writing it anywhere and returns its warnings as
+/// dicts with `severity`, `path`, `property` and `message`. Raises
+/// `BuildError` if it doesn't build.
+#[pyfunction]
+fn validate<'py>(py: Python<'py>, project: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let diagnostics = py
+        .allow_threads(|| embed::validate_json(project))
+        .map_err(|error| BuildError::new_err(error.to_string()))?;
+
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic_dict(py, diagnostic))
+        .collect()
+}
+
+/// Turns a model file's contents, or a place's with `place=True`, back into
+/// project JSON.
+#[pyfunction]
+#[pyo3(signature = (data, format = "binary", name = "ROOT", place = false))]
+fn decompile(
+    py: Python<'_>,
+    data: &[u8],
+    format: &str,
+    name: &str,
+    place: bool,
+) -> PyResult<String> {
+    let format = parse_format(format)?;
+    // Files don't say whether they're a place, so that can't be inferred
+    let root = if place {
+        RootHandling::Place
+    } else {
+        RootHandling::Model
+    };
+
+    py.allow_threads(|| embed::decompile_bytes(data, format, name, root, &mut |_: Diagnostic| {}))
+        .map_err(|error| BuildError::new_err(error.to_string()))
+}
+
+#[pymodule]
+#[pyo3(name = "rbxbuild")]
+fn module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add("BuildError", m.py().get_type_bound::<BuildError>())?;
+    m.add_function(wrap_pyfunction!(build_project, m)?)?;
+    m.add_function(wrap_pyfunction!(validate, m)?)?;
+    m.add_function(wrap_pyfunction!(decompile, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,568 @@
+//! Helpers for inspecting a built [`WeakDom`].
+//!
+//! Paths use the same format as [`Diagnostic::path`]: instance names joined
+//! with `/`, starting with the root's name, e.g. `Game/Workspace/Baseplate`.
+//!
+//! ```no_run
+//! # let dom: rbx_dom_weak::WeakDom = unimplemented!();
+//! use rbxbuild::query;
+//!
+//! for part in query::find_by_class(&dom, "Part") {
+//!     println!("{}", query::path_of(&dom, part.referent()));
+//! }
+//! ```
+//!
+//! [`select`] finds instances by a path glob and conditions on their class
+//! and properties, for auditing what a build actually produced.
+//!
+//! [`Diagnostic::path`]: crate::diagnostics::Diagnostic::path
+
+use std::collections::HashMap;
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance, WeakDom,
+};
+use serde_json::Value;
+use ustr::Ustr;
+
+use crate::decompile::shorthand;
+
+/// What [`visit`] should do after visiting an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    /// Continue into the instance's children.
+    Continue,
+    /// Don't visit the instance's children, but continue with its siblings.
+    SkipChildren,
+    /// Stop the traversal.
+    Stop,
+}
+
+/// Depth-first, pre-order iterator over an instance and its descendants.
+pub struct Descendants<'a> {
+    dom: &'a WeakDom,
+    stack: Vec<Ref>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = &'a Instance;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let referent = self.stack.pop()?;
+            if let Some(instance) = self.dom.get_by_ref(referent) {
+                // Pushed in reverse so children come out in order
+                self.stack.extend(instance.children().iter().rev());
+                return Some(instance);
+            }
+        }
+    }
+}
+
+/// Iterates over `root` and all of its descendants, depth-first.
+pub fn descendants(dom: &WeakDom, root: Ref) -> Descendants<'_> {
+    Descendants {
+        dom,
+        stack: vec![root],
+    }
+}
+
+/// Calls `visitor` for the DOM root and each of its descendants depth-first,
+/// along with how deep the instance is below the root.
+pub fn visit<F>(dom: &WeakDom, mut visitor: F)
+where
+    F: FnMut(&Instance, usize) -> Visit,
+{
+    let mut stack = vec![(dom.root_ref(), 0)];
+
+    while let Some((referent, depth)) = stack.pop() {
+        let Some(instance) = dom.get_by_ref(referent) else {
+            continue;
+        };
+
+        match visitor(instance, depth) {
+            Visit::Continue => {
+                stack.extend(instance.children().iter().rev().map(|&c| (c, depth + 1)));
+            }
+            Visit::SkipChildren => {}
+            Visit::Stop => return,
+        }
+    }
+}
+
+/// Finds an instance by its slash-separated path from the DOM root. If
+/// several siblings share a name, the first one is used.
+pub fn find_by_path<'a>(dom: &'a WeakDom, path: &str) -> Option<&'a Instance> {
+    let mut segments = path.split('/');
+
+    let mut current = dom.root();
+    if segments.next()? != current.name {
+        return None;
+    }
+
+    for segment in segments {
+        current = current
+            .children()
+            .iter()
+            .filter_map(|&child| dom.get_by_ref(child))
+            .find(|child| child.name == segment)?;
+    }
+
+    Some(current)
+}
+
+/// Iterates over every instance of exactly the given class, depth-first.
+pub fn find_by_class<'a>(
+    dom: &'a WeakDom,
+    class_name: &'a str,
+) -> impl Iterator<Item = &'a Instance> + 'a {
+    descendants(dom, dom.root_ref()).filter(move |instance| instance.class == class_name)
+}
+
+/// Returns the slash-separated path from the DOM root to an instance, or an
+/// empty string if it isn't in the DOM.
+pub fn path_of(dom: &WeakDom, referent: Ref) -> String {
+    let mut names = Vec::new();
+
+    let mut current = dom.get_by_ref(referent);
+    while let Some(instance) = current {
+        names.push(instance.name.as_str());
+        current = dom.get_by_ref(instance.parent());
+    }
+
+    names.reverse();
+    names.join("/")
+}
+
+/// Draws the DOM as an indented tree, one instance per line with its class
+/// and how many children it has. Instances deeper than `max_depth` below the
+/// root are left out; their parent's child count still shows they're there.
+pub fn render_tree(dom: &WeakDom, max_depth: Option<usize>, ascii: bool) -> String {
+    let (branch, last, pipe) = if ascii {
+        ("|-- ", "`-- ", "|   ")
+    } else {
+        ("├── ", "└── ", "│   ")
+    };
+
+    let mut output = String::new();
+    let mut stack = vec![(dom.root_ref(), String::new(), None, 0)];
+
+    while let Some((referent, prefix, is_last, depth)) = stack.pop() {
+        let Some(instance) = dom.get_by_ref(referent) else {
+            continue;
+        };
+
+        // The root has no connector, and its children's lines start flush
+        let child_prefix = match is_last {
+            None => prefix,
+            Some(is_last) => {
+                output.push_str(&prefix);
+                output.push_str(if is_last { last } else { branch });
+                prefix + if is_last { "    " } else { pipe }
+            }
+        };
+
+        output.push_str(&format!("{} ({}", instance.name, instance.class));
+        match instance.children().len() {
+            0 => {}
+            1 => output.push_str(", 1 child"),
+            n => output.push_str(&format!(", {n} children")),
+        }
+        output.push_str(")\n");
+
+        if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            continue;
+        }
+
+        // Pushed in reverse so children come out in order
+        let children = instance.children();
+        for (index, &child) in children.iter().enumerate().rev() {
+            let is_last = index == children.len() - 1;
+            stack.push((child, child_prefix.clone(), Some(is_last), depth + 1));
+        }
+    }
+
+    output
+}
+
+/// Draws the DOM as a Graphviz DOT graph, with a box per instance and an edge
+/// from each instance to its children. With `refs`, each `Ref` property also
+/// gets a dashed edge to the instance it points to, labelled with the
+/// property's name, which makes reference cycles easy to spot.
+pub fn render_dot(dom: &WeakDom, refs: bool) -> String {
+    let instances: Vec<_> = descendants(dom, dom.root_ref()).collect();
+    let ids: HashMap<Ref, usize> = instances
+        .iter()
+        .enumerate()
+        .map(|(id, instance)| (instance.referent(), id))
+        .collect();
+
+    let mut output = String::from("digraph {\n    node [shape=box];\n");
+
+    for (id, instance) in instances.iter().enumerate() {
+        output.push_str(&format!(
+            "    n{id} [label=\"{}\\n({})\"];\n",
+            escape_dot(&instance.name),
+            escape_dot(&instance.class)
+        ));
+
+        for child in instance.children() {
+            output.push_str(&format!("    n{id} -> n{};\n", ids[child]));
+        }
+
+        if !refs {
+            continue;
+        }
+
+        // Sorted so the same DOM always gives the same graph
+        let mut properties: Vec<_> = instance.properties.iter().collect();
+        properties.sort_by_key(|(name, _)| name.as_str());
+
+        for (name, value) in properties {
+            if let Variant::Ref(target) = value {
+                // Null refs, and refs to instances outside the DOM, have
+                // nothing to point at
+                if let Some(target) = ids.get(target) {
+                    output.push_str(&format!(
+                        "    n{id} -> n{target} [style=dashed, color=blue, label=\"{}\"];\n",
+                        escape_dot(name)
+                    ));
+                }
+            }
+        }
+    }
+
+    output.push_str("}\n");
+    output
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A condition an instance has to meet to be returned by [`select`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    /// The instance is exactly this class.
+    Class(String),
+    /// The property has this value, compared with [`property_value`].
+    Property(String, Value),
+}
+
+impl Predicate {
+    pub fn matches(&self, instance: &Instance) -> bool {
+        match self {
+            Predicate::Class(class) => instance.class == class.as_str(),
+            Predicate::Property(name, expected) => {
+                property_value(instance, name).is_some_and(|actual| values_equal(&actual, expected))
+            }
+        }
+    }
+}
+
+/// Parses a `PROPERTY=VALUE` command line condition. `ClassName=Part`
+/// matches on the class; otherwise values are read as JSON, as they'd be
+/// written in a project (`true`, `[4, 1, 2]`), falling back to a plain
+/// string, so `Material=Grass` works without quotes.
+pub fn parse_predicate(arg: &str) -> Result<Predicate, String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected PROPERTY=VALUE, got {arg:?}"))?;
+
+    if name == "ClassName" {
+        return Ok(Predicate::Class(value.to_owned()));
+    }
+
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_owned()));
+    Ok(Predicate::Property(name.to_owned(), value))
+}
+
+/// Returns a property's value as JSON, in the same shorthand form a
+/// decompiled project would use (`"Material": "Grass"`), or `None` if the
+/// instance doesn't have it. `Name` is the instance's name.
+pub fn property_value(instance: &Instance, property: &str) -> Option<Value> {
+    if property == "Name" {
+        return Some(instance.name.clone().into());
+    }
+
+    let value = instance.properties.get(&Ustr::from(property))?;
+    serde_json::to_value(shorthand(instance.class.as_str(), property, value)).ok()
+}
+
+/// Finds every instance, depth-first, whose path matches `glob` (see
+/// [`glob_matches`]) and that meets all of the predicates, along with its
+/// path.
+pub fn select<'a>(
+    dom: &'a WeakDom,
+    glob: &str,
+    predicates: &[Predicate],
+) -> Vec<(String, &'a Instance)> {
+    let mut matches = Vec::new();
+    let mut names: Vec<&str> = Vec::new();
+
+    let mut stack = vec![(dom.root_ref(), 0)];
+
+    while let Some((referent, depth)) = stack.pop() {
+        let Some(instance) = dom.get_by_ref(referent) else {
+            continue;
+        };
+
+        names.truncate(depth);
+        names.push(&instance.name);
+        let path = names.join("/");
+
+        if glob_matches(glob, &path) && predicates.iter().all(|p| p.matches(instance)) {
+            matches.push((path, instance));
+        }
+
+        stack.extend(instance.children().iter().rev().map(|&c| (c, depth + 1)));
+    }
+
+    matches
+}
+
+/// Whether a slash-separated path matches a glob. Within a name, `*` matches
+/// any run of characters and `?` any single one; a `**` segment matches any
+/// number of names, so `**/Door` finds every Door.
+pub fn glob_matches(glob: &str, path: &str) -> bool {
+    let glob: Vec<&str> = glob.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    segments_match(&glob, &path)
+}
+
+fn segments_match(glob: &[&str], path: &[&str]) -> bool {
+    match glob.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((pattern, rest)) => path.split_first().is_some_and(|(name, path)| {
+            wildcard_matches(pattern, name) && segments_match(rest, path)
+        }),
+    }
+}
+
+/// Whether a name matches a pattern, where `*` matches any run of characters
+/// and `?` any single one.
+pub(crate) fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of the name it has taken so far
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, taken)) => {
+                    backtrack = Some((star, taken + 1));
+                    p = star + 1;
+                    n = taken + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// JSON equality, except that `4` and `4.0` are the same number.
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn sample() -> WeakDom {
+        WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_name("Game")
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_name("Workspace")
+                        .with_child(
+                            InstanceBuilder::new("Part")
+                                .with_name("Baseplate")
+                                .with_property("Anchored", true),
+                        )
+                        .with_child(
+                            InstanceBuilder::new("Model")
+                                .with_name("House")
+                                .with_child(InstanceBuilder::new("Part").with_name("Door")),
+                        ),
+                )
+                .with_child(InstanceBuilder::new("Lighting").with_name("Lighting")),
+        )
+    }
+
+    #[test]
+    fn descendants_are_depth_first() {
+        let dom = sample();
+        let names: Vec<_> = descendants(&dom, dom.root_ref())
+            .map(|instance| instance.name.as_str())
+            .collect();
+
+        assert_eq!(
+            names,
+            [
+                "Game",
+                "Workspace",
+                "Baseplate",
+                "House",
+                "Door",
+                "Lighting"
+            ]
+        );
+    }
+
+    #[test]
+    fn visit_can_skip_and_stop() {
+        let dom = sample();
+
+        let mut seen = Vec::new();
+        visit(&dom, |instance, depth| {
+            seen.push((instance.name.clone(), depth));
+            match instance.name.as_str() {
+                "House" => Visit::SkipChildren,
+                "Lighting" => Visit::Stop,
+                _ => Visit::Continue,
+            }
+        });
+
+        let seen: Vec<_> = seen.iter().map(|(n, d)| (n.as_str(), *d)).collect();
+        assert_eq!(
+            seen,
+            [
+                ("Game", 0),
+                ("Workspace", 1),
+                ("Baseplate", 2),
+                ("House", 2),
+                ("Lighting", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn paths_round_trip() {
+        let dom = sample();
+
+        let door = find_by_path(&dom, "Game/Workspace/House/Door").unwrap();
+        assert_eq!(door.class, "Part");
+        assert_eq!(path_of(&dom, door.referent()), "Game/Workspace/House/Door");
+
+        assert!(find_by_path(&dom, "Game/Workspace/Garage").is_none());
+        assert!(find_by_path(&dom, "Other/Workspace").is_none());
+    }
+
+    #[test]
+    fn renders_trees() {
+        let dom = sample();
+
+        assert_eq!(
+            render_tree(&dom, None, false),
+            "Game (DataModel, 2 children)
+├── Workspace (Workspace, 2 children)
+│   ├── Baseplate (Part)
+│   └── House (Model, 1 child)
+│       └── Door (Part)
+└── Lighting (Lighting)
+"
+        );
+
+        assert_eq!(
+            render_tree(&dom, Some(1), true),
+            "Game (DataModel, 2 children)
+|-- Workspace (Workspace, 2 children)
+`-- Lighting (Lighting)
+"
+        );
+    }
+
+    #[test]
+    fn renders_dot() {
+        let mut dom = sample();
+        let house = find_by_class(&dom, "Model").next().unwrap().referent();
+        let door = dom.get_by_ref(house).unwrap().children()[0];
+        dom.get_by_ref_mut(house)
+            .unwrap()
+            .properties
+            .insert("PrimaryPart".into(), Variant::Ref(door));
+
+        let dot = render_dot(&dom, false);
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.contains("    n3 [label=\"House\\n(Model)\"];\n    n3 -> n4;\n"));
+        assert!(!dot.contains("PrimaryPart"));
+
+        assert!(render_dot(&dom, true)
+            .contains("    n3 -> n4 [style=dashed, color=blue, label=\"PrimaryPart\"];\n"));
+        assert_eq!(escape_dot(r#"Say "hi"\"#), r#"Say \"hi\"\\"#);
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("Game/Workspace/*", "Game/Workspace/House"));
+        assert!(!glob_matches(
+            "Game/Workspace/*",
+            "Game/Workspace/House/Door"
+        ));
+        assert!(glob_matches("**/Door", "Game/Workspace/House/Door"));
+        assert!(glob_matches("Game/**", "Game"));
+        assert!(glob_matches("*/W?rk*", "Game/Workspace"));
+        assert!(!glob_matches("*/W?rk", "Game/Workspace"));
+    }
+
+    #[test]
+    fn select_with_predicates() {
+        let dom = sample();
+
+        let paths = |glob, predicates: &[Predicate]| -> Vec<String> {
+            select(&dom, glob, predicates)
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect()
+        };
+
+        let parts = [parse_predicate("ClassName=Part").unwrap()];
+        assert_eq!(
+            paths("**", &parts),
+            ["Game/Workspace/Baseplate", "Game/Workspace/House/Door"]
+        );
+
+        let anchored = [parse_predicate("Anchored=true").unwrap()];
+        assert_eq!(paths("**", &anchored), ["Game/Workspace/Baseplate"]);
+
+        let named = [parse_predicate("Name=House").unwrap()];
+        assert_eq!(paths("Game/*/*", &named), ["Game/Workspace/House"]);
+
+        assert!(parse_predicate("Anchored").is_err());
+    }
+
+    #[test]
+    fn find_parts() {
+        let dom = sample();
+        let parts: Vec<_> = find_by_class(&dom, "Part")
+            .map(|instance| instance.name.as_str())
+            .collect();
+
+        assert_eq!(parts, ["Baseplate", "Door"]);
+    }
+}
@@ -0,0 +1,260 @@
+//! A self-contained HTML page describing a build, for sharing results with
+//! people who won't open a terminal or Studio.
+//!
+//! The page has a summary, the build's diagnostics, size statistics and a
+//! collapsible instance tree with each instance's properties. Everything,
+//! styles included, is inline, and the tree folds with plain
+//! `<details>` elements, so the file works offline and without scripts.
+
+use std::fmt::Write;
+
+use rbx_dom_weak::{types::Ref, WeakDom};
+use serde_json::Value;
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::options::RootHandling;
+use crate::query::property_value;
+use crate::stats::{self, Bytes};
+
+/// How many of the largest subtrees the report lists.
+const LARGEST_SUBTREES: usize = 10;
+
+/// Property values longer than this are folded away in the tree.
+const FOLD_LENGTH: usize = 120;
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 72em; color: #222; }
+h1 { margin-bottom: 0.2em; }
+.summary { color: #555; }
+table { border-collapse: collapse; margin: 0.5em 0 1em; }
+th, td { text-align: left; padding: 0.2em 0.8em; border-bottom: 1px solid #ddd; vertical-align: top; }
+td.number { text-align: right; }
+.warning { color: #9a6700; }
+.error { color: #cf222e; }
+details { margin-left: 1.2em; }
+summary { cursor: pointer; }
+.class { color: #777; }
+code, pre { font-family: ui-monospace, monospace; font-size: 0.9em; }
+pre { background: #f6f8fa; padding: 0.5em; overflow-x: auto; }
+";
+
+/// Renders the report for a built DOM and what was reported while building
+/// it. `root` decides what counts as the top level, like when writing.
+pub fn html_report(
+    name: &str,
+    dom: &WeakDom,
+    root: RootHandling,
+    diagnostics: &[Diagnostic],
+) -> String {
+    let stats = stats::collect(dom, root);
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+
+    let mut html = String::new();
+    let name = escape(name);
+
+    write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{name} build report</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <h1>{name}</h1>\n<p class=\"summary\">Built with rbxbuild {}: {}, {} ({} of source), \
+         {}, {}</p>\n",
+        env!("CARGO_PKG_VERSION"),
+        plural(stats.instances, "instance"),
+        plural(stats.scripts, "script"),
+        Bytes(stats.source_bytes as u64),
+        plural(count(Severity::Warning), "warning"),
+        plural(count(Severity::Error), "error"),
+    )
+    .unwrap();
+
+    html.push_str("<h2>Diagnostics</h2>\n");
+    if diagnostics.is_empty() {
+        html.push_str("<p>No warnings or errors.</p>\n");
+    } else {
+        html.push_str("<table>\n<tr><th>Severity</th><th>Path</th><th>Message</th></tr>\n");
+        for diagnostic in diagnostics {
+            let class = match diagnostic.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            let mut path = diagnostic.path.clone();
+            if let Some(property) = &diagnostic.property {
+                path = format!("{path}.{property}");
+            }
+
+            writeln!(
+                html,
+                "<tr class=\"{class}\"><td>{}</td><td><code>{}</code></td><td>{}</td></tr>",
+                diagnostic.severity,
+                escape(&path),
+                escape(&diagnostic.message)
+            )
+            .unwrap();
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Size</h2>\n");
+    let sizes: Vec<_> = [("XML", stats.xml_bytes), ("binary", stats.binary_bytes)]
+        .into_iter()
+        .filter_map(|(format, bytes)| Some(format!("{} as {format}", Bytes(bytes?))))
+        .collect();
+    if !sizes.is_empty() {
+        writeln!(html, "<p>{}</p>", sizes.join(", ")).unwrap();
+    }
+
+    html.push_str(
+        "<table>\n<tr><th>Top level</th><th>Class</th><th>Instances</th>\
+         <th>Scripts</th><th>Size</th></tr>\n",
+    );
+    for top_level in &stats.top_level {
+        let size = top_level
+            .binary_bytes
+            .map(|bytes| Bytes(bytes).to_string())
+            .unwrap_or_default();
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td class=\"number\">{}</td>\
+             <td class=\"number\">{}</td><td class=\"number\">{size}</td></tr>",
+            escape(&top_level.name),
+            escape(&top_level.class),
+            top_level.instances,
+            top_level.scripts,
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n");
+
+    let size_report = stats::size_report(dom, LARGEST_SUBTREES);
+    if !size_report.subtrees.is_empty() {
+        html.push_str("<table>\n<tr><th>Largest subtrees</th><th>Estimated size</th></tr>\n");
+        for entry in &size_report.subtrees {
+            writeln!(
+                html,
+                "<tr><td><code>{}</code></td><td class=\"number\">{}</td></tr>",
+                escape(&entry.path),
+                Bytes(entry.bytes)
+            )
+            .unwrap();
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Instances</h2>\n");
+    write_instance(&mut html, dom, dom.root_ref(), true);
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn write_instance(html: &mut String, dom: &WeakDom, referent: Ref, open: bool) {
+    let instance = dom.get_by_ref(referent).unwrap();
+
+    writeln!(
+        html,
+        "<details{}><summary>{} <span class=\"class\">{}</span></summary>",
+        if open { " open" } else { "" },
+        escape(&instance.name),
+        escape(&instance.class)
+    )
+    .unwrap();
+
+    if !instance.properties.is_empty() {
+        // Sorted so the report reads the same every time
+        let mut names: Vec<_> = instance
+            .properties
+            .keys()
+            .map(|name| name.as_str())
+            .collect();
+        names.sort_unstable();
+
+        html.push_str("<table>\n");
+        for name in names {
+            let value = match property_value(instance, name) {
+                Some(Value::String(text)) if text.len() > FOLD_LENGTH => format!(
+                    "<details><summary>{} characters</summary><pre>{}</pre></details>",
+                    text.chars().count(),
+                    escape(&text)
+                ),
+                Some(value) => format!("<code>{}</code>", escape(&value.to_string())),
+                None => String::new(),
+            };
+            writeln!(html, "<tr><th>{}</th><td>{value}</td></tr>", escape(name)).unwrap();
+        }
+        html.push_str("</table>\n");
+    }
+
+    for &child in instance.children() {
+        write_instance(html, dom, child, false);
+    }
+
+    html.push_str("</details>\n");
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {noun}"),
+        _ => format!("{count} {noun}s"),
+    }
+}
+
+/// Escapes text for HTML, or XML.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn report_contents() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_name("Game")
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_name("Workspace")
+                        .with_child(
+                            InstanceBuilder::new("Part")
+                                .with_name("<Baseplate>")
+                                .with_property("Anchored", true),
+                        ),
+                ),
+        );
+        let diagnostics = [Diagnostic::warning(
+            "Game/Workspace",
+            "Unknown class \"Thing\"",
+        )];
+
+        let html = html_report("A & B", &dom, RootHandling::Auto, &diagnostics);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>A &amp; B build report</title>"));
+        assert!(html.contains("1 warning, 0 errors"));
+        assert!(html.contains("<td>Unknown class &quot;Thing&quot;</td>"));
+        assert!(html.contains(
+            "<details><summary>&lt;Baseplate&gt; <span class=\"class\">Part</span></summary>\n\
+             <table>\n<tr><th>Anchored</th><td><code>true</code></td></tr>"
+        ));
+        assert!(html.ends_with("</details>\n</body>\n</html>\n"));
+    }
+}
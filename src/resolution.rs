@@ -1,11 +1,11 @@
 // Copied from upstream https://github.com/rojo-rbx/rojo at commit 441c469
 
-use std::borrow::Borrow;
+use std::{borrow::Borrow, collections::BTreeMap, path::PathBuf};
 
 use anyhow::{bail, format_err};
 use rbx_dom_weak::types::{
-    Attributes, CFrame, Color3, Content, ContentId, Enum, Font, MaterialColors, Matrix3, Tags,
-    Variant, VariantType, Vector2, Vector3,
+    Attributes, CFrame, Color3, Color3uint8, Content, ContentId, Enum, Font, MaterialColors,
+    Matrix3, Tags, TerrainMaterials, Variant, VariantType, Vector2, Vector3,
 };
 use rbx_reflection::{DataType, PropertyDescriptor};
 use serde::{Deserialize, Serialize};
@@ -26,14 +26,14 @@ pub enum UnresolvedValue {
 }
 
 impl UnresolvedValue {
-    pub fn resolve(self, class_name: &str, prop_name: &str) -> anyhow::Result<Variant> {
+    pub(crate) fn resolve(self, class_name: &str, prop_name: &str) -> anyhow::Result<Variant> {
         match self {
             UnresolvedValue::FullyQualified(full) => Ok(full),
             UnresolvedValue::Ambiguous(partial) => partial.resolve(class_name, prop_name),
         }
     }
 
-    pub fn resolve_unambiguous(self) -> anyhow::Result<Variant> {
+    pub(crate) fn resolve_unambiguous(self) -> anyhow::Result<Variant> {
         match self {
             UnresolvedValue::FullyQualified(full) => Ok(full),
             UnresolvedValue::Ambiguous(partial) => partial.resolve_unambiguous(),
@@ -52,19 +52,89 @@ pub enum AmbiguousValue {
     Array3([f64; 3]),
     Array4([f64; 4]),
     Array12([f64; 12]),
+    ExpressionArray(Vec<NumberOrExpression>),
+    Asset(AssetReference),
+    Heightmap(HeightmapReference),
     Attributes(Attributes),
     Font(Font),
     MaterialColors(MaterialColors),
+
+    /// Terrain material colors written more loosely than [`MaterialColors`]
+    /// allows, e.g. `{"Grass": "#6a7f3f", "Rock": [102, 108, 111]}`.
+    MaterialColorMap(BTreeMap<String, MaterialColor>),
+}
+
+/// A terrain material's color, either `[r, g, b]` from 0 to 255 like Studio
+/// shows it, or `#rrggbb`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MaterialColor {
+    Rgb([u8; 3]),
+    Hex(String),
+}
+
+/// An element of an array that mixes plain numbers with `=` expressions, see
+/// the `expression` module.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NumberOrExpression {
+    Number(f64),
+    Expression(String),
+}
+
+/// A local file to upload as an asset, written `{"$asset": "path"}`. Only
+/// builds that upload assets can resolve these, see the `publish::assets`
+/// module.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetReference {
+    #[serde(rename = "$asset")]
+    pub path: PathBuf,
+}
+
+/// Terrain generated from a grayscale heightmap image, written
+/// `{"$heightmap": "path", "size": [x, y, z]}` as `Terrain.SmoothGrid`. Only
+/// builds with a heightmap importer can resolve these, see the `terrain`
+/// module.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HeightmapReference {
+    #[serde(rename = "$heightmap")]
+    pub path: PathBuf,
+
+    /// The terrain's extent in studs. A white pixel is `y` studs high.
+    pub size: [f64; 3],
+
+    /// The center of the terrain's base, the origin by default.
+    #[serde(default)]
+    pub position: [f64; 3],
+
+    /// The material used wherever the material map doesn't say otherwise.
+    #[serde(default = "default_terrain_material")]
+    pub material: String,
+
+    /// An image the same shape as the heightmap, whose colors pick the
+    /// material of each column through `materials`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub material_map: Option<PathBuf>,
+
+    /// Material map colors, as `#rrggbb`, and the materials they stand for.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, String>,
+}
+
+fn default_terrain_material() -> String {
+    "Grass".to_owned()
 }
 
 impl AmbiguousValue {
-    pub fn resolve(self, class_name: &str, prop_name: &str) -> anyhow::Result<Variant> {
-        let property = find_descriptor(class_name, prop_name)
+    pub(crate) fn resolve(self, class_name: &str, prop_name: &str) -> anyhow::Result<Variant> {
+        let property = find_descriptor(class_name, prop_name)?
             .ok_or_else(|| format_err!("Unknown property {}.{}", class_name, prop_name))?;
 
         match &property.data_type {
             DataType::Enum(enum_name) => {
-                let database = rbx_reflection_database::get().unwrap();
+                let database = crate::database::get()?;
 
                 let enum_descriptor = database.enums.get(enum_name).ok_or_else(|| {
                     format_err!("Unknown enum {}. This is a Rojo bug!", enum_name)
@@ -154,6 +224,26 @@ impl AmbiguousValue {
                     Ok(value.into())
                 }
 
+                (VariantType::MaterialColors, AmbiguousValue::MaterialColorMap(colors)) => {
+                    material_colors(colors)
+                }
+
+                (_, AmbiguousValue::Asset(reference)) => Err(format_err!(
+                    "Property {}.{} refers to the local file {}, which has to be \
+                     uploaded by building with --upload-assets",
+                    class_name,
+                    prop_name,
+                    reference.path.display(),
+                )),
+
+                (_, AmbiguousValue::Heightmap(reference)) => Err(format_err!(
+                    "Property {}.{} is generated from the heightmap {}, which needs \
+                     a build with the terrain feature",
+                    class_name,
+                    prop_name,
+                    reference.path.display(),
+                )),
+
                 (VariantType::Ref, AmbiguousValue::String(_)) => Err(format_err!(
                     "Cannot resolve Ref properties as a String.\
                     Use an attribute named `{REF_POINTER_ATTRIBUTE_PREFIX}{prop_name}"
@@ -174,7 +264,7 @@ impl AmbiguousValue {
         }
     }
 
-    pub fn resolve_unambiguous(self) -> anyhow::Result<Variant> {
+    pub(crate) fn resolve_unambiguous(self) -> anyhow::Result<Variant> {
         match self {
             AmbiguousValue::Bool(value) => Ok(value.into()),
             AmbiguousValue::Number(value) => Ok(value.into()),
@@ -194,27 +284,73 @@ impl AmbiguousValue {
             AmbiguousValue::Array3(_) => "an array of three numbers",
             AmbiguousValue::Array4(_) => "an array of four numbers",
             AmbiguousValue::Array12(_) => "an array of twelve numbers",
+            AmbiguousValue::ExpressionArray(_) => "an array of numbers and expressions",
+            AmbiguousValue::Asset(_) => "a local asset",
+            AmbiguousValue::Heightmap(_) => "a heightmap",
             AmbiguousValue::Attributes(_) => "an object containing attributes",
             AmbiguousValue::Font(_) => "an object describing a Font",
-            AmbiguousValue::MaterialColors(_) => "an object describing MaterialColors",
+            AmbiguousValue::MaterialColors(_) | AmbiguousValue::MaterialColorMap(_) => {
+                "an object describing MaterialColors"
+            }
         }
     }
 }
 
-fn find_descriptor(
+fn material_colors(colors: BTreeMap<String, MaterialColor>) -> anyhow::Result<Variant> {
+    let mut material_colors = MaterialColors::new();
+
+    for (name, color) in colors {
+        let material: TerrainMaterials =
+            serde_json::from_value(name.clone().into()).map_err(|_| {
+                format_err!(
+                    "{name} is not a terrain material with a color, such as Grass, Rock or Sand"
+                )
+            })?;
+        let [r, g, b] = match color {
+            MaterialColor::Rgb(rgb) => rgb,
+            MaterialColor::Hex(hex) => parse_hex_color(&hex).ok_or_else(|| {
+                format_err!("Expected a color for {name} like #6a7f3f, got {hex}")
+            })?,
+        };
+        material_colors.set_color(material, Color3uint8::new(r, g, b));
+    }
+
+    Ok(material_colors.into())
+}
+
+/// Parses a `#rrggbb` color. The `#` is optional.
+pub(crate) fn parse_hex_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    let channel = |index: usize| {
+        hex.get(index * 2..index * 2 + 2)
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+    };
+
+    if hex.len() != 6 {
+        return None;
+    }
+    Some([channel(0)?, channel(1)?, channel(2)?])
+}
+
+pub(crate) fn find_descriptor(
     class_name: &str,
     prop_name: &str,
-) -> Option<&'static PropertyDescriptor<'static>> {
-    let database = rbx_reflection_database::get().unwrap();
+) -> anyhow::Result<Option<&'static PropertyDescriptor<'static>>> {
+    let database = crate::database::get()?;
     let mut current_class_name = class_name;
 
     loop {
-        let class = database.classes.get(current_class_name)?;
+        let Some(class) = database.classes.get(current_class_name) else {
+            return Ok(None);
+        };
         if let Some(descriptor) = class.properties.get(prop_name) {
-            return Some(descriptor);
+            return Ok(Some(descriptor));
         }
 
-        current_class_name = class.superclass.as_deref()?;
+        match class.superclass.as_deref() {
+            Some(superclass) => current_class_name = superclass,
+            None => return Ok(None),
+        }
     }
 }
 
@@ -401,4 +537,57 @@ mod test {
             Variant::MaterialColors(material_colors)
         )
     }
+
+    #[test]
+    fn material_color_maps() {
+        let mut material_colors = MaterialColors::new();
+        material_colors.set_color(TerrainMaterials::Grass, Color3uint8::new(106, 127, 63));
+        material_colors.set_color(TerrainMaterials::Rock, Color3uint8::new(102, 108, 111));
+
+        assert_eq!(
+            resolve(
+                "Terrain",
+                "MaterialColors",
+                r##"{ "Grass": "#6a7f3f", "Rock": [102, 108, 111] }"##
+            ),
+            Variant::MaterialColors(material_colors)
+        );
+
+        let unresolved: UnresolvedValue =
+            serde_json::from_str(r#"{ "Lava": [255, 0, 0] }"#).unwrap();
+        let error = unresolved.resolve("Terrain", "MaterialColors").unwrap_err();
+        assert!(error.to_string().contains("Lava is not a terrain material"));
+    }
+
+    #[test]
+    fn local_assets() {
+        let unresolved: UnresolvedValue =
+            serde_json::from_str(r#"{"$asset": "textures/wall.png"}"#).unwrap();
+        assert_eq!(
+            unresolved,
+            UnresolvedValue::Ambiguous(AmbiguousValue::Asset(AssetReference {
+                path: "textures/wall.png".into()
+            }))
+        );
+
+        // Without an uploader there's no ID to put in the property
+        let error = unresolved.resolve("Decal", "Texture").unwrap_err();
+        assert!(error.to_string().contains("--upload-assets"));
+    }
+
+    #[test]
+    fn heightmaps() {
+        let unresolved: UnresolvedValue =
+            serde_json::from_str(r#"{"$heightmap": "terrain/height.png", "size": [512, 64, 512]}"#)
+                .unwrap();
+        let UnresolvedValue::Ambiguous(AmbiguousValue::Heightmap(reference)) = &unresolved else {
+            panic!("not a heightmap: {unresolved:?}");
+        };
+        assert_eq!(reference.material, "Grass");
+        assert_eq!(reference.position, [0.0; 3]);
+
+        // Only the importer can read the image
+        let error = unresolved.resolve("Terrain", "SmoothGrid").unwrap_err();
+        assert!(error.to_string().contains("terrain feature"));
+    }
 }
@@ -0,0 +1,201 @@
+//! Hooks for extending property resolution.
+//!
+//! Resolvers registered in [`BuildOptions::resolvers`] run around the
+//! built-in resolution of every property, which lets library users add their
+//! own shorthands without touching the resolution module:
+//!
+//! ```
+//! use rbxbuild::{resolver::PropertyContext, BuildOptions, UnresolvedValue};
+//!
+//! let mut options = BuildOptions::default();
+//! options.resolvers.push(Box::new(
+//!     |ctx: &PropertyContext<'_>, value: &UnresolvedValue| -> rbxbuild::resolver::HookResult<_> {
+//!         match value {
+//!             UnresolvedValue::Ambiguous(rbxbuild::AmbiguousValue::String(name))
+//!                 if ctx.property == "Color" && name == "BrandRed" =>
+//!             {
+//!                 Ok(Some([0.8, 0.1, 0.1].into()))
+//!             }
+//!             _ => Ok(None),
+//!         }
+//!     },
+//! ));
+//! ```
+//!
+//! [`BuildOptions::resolvers`]: crate::BuildOptions::resolvers
+
+use std::fmt;
+
+use anyhow::format_err;
+use rbx_dom_weak::types::Variant;
+
+use crate::defines::Defines;
+use crate::expression;
+use crate::resolution::UnresolvedValue;
+
+pub type HookResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Describes the property being resolved.
+#[derive(Debug, Clone, Copy)]
+pub struct PropertyContext<'a> {
+    pub class_name: &'a str,
+    pub property: &'a str,
+
+    /// Slash-separated path of the project node that owns the property.
+    pub path: &'a str,
+}
+
+/// A hook into property resolution. Both methods default to doing nothing.
+///
+/// Any closure taking a [`PropertyContext`] and an [`UnresolvedValue`] and
+/// returning `HookResult<Option<UnresolvedValue>>` acts as a `before_resolve`
+/// hook.
+pub trait PropertyResolver: Send + Sync {
+    /// Runs before the built-in resolution. Returning a value replaces the one
+    /// from the project for the rest of resolution; return a fully qualified
+    /// value to skip the built-in rules entirely.
+    fn before_resolve(
+        &self,
+        _ctx: &PropertyContext<'_>,
+        _value: &UnresolvedValue,
+    ) -> HookResult<Option<UnresolvedValue>> {
+        Ok(None)
+    }
+
+    /// Runs after the value was resolved and may replace it.
+    fn after_resolve(&self, _ctx: &PropertyContext<'_>, variant: Variant) -> HookResult<Variant> {
+        Ok(variant)
+    }
+}
+
+impl<F> PropertyResolver for F
+where
+    F: Fn(&PropertyContext<'_>, &UnresolvedValue) -> HookResult<Option<UnresolvedValue>>
+        + Send
+        + Sync,
+{
+    fn before_resolve(
+        &self,
+        ctx: &PropertyContext<'_>,
+        value: &UnresolvedValue,
+    ) -> HookResult<Option<UnresolvedValue>> {
+        self(ctx, value)
+    }
+}
+
+impl fmt::Debug for dyn PropertyResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PropertyResolver")
+    }
+}
+
+/// Resolves a property value, running it through every resolver's hooks in
+/// registration order around the built-in resolution.
+pub(crate) fn resolve_property(
+    mut value: UnresolvedValue,
+    ctx: &PropertyContext<'_>,
+    resolvers: &[Box<dyn PropertyResolver>],
+    defines: &Defines,
+) -> anyhow::Result<Variant> {
+    for resolver in resolvers {
+        if let Some(replacement) = resolver
+            .before_resolve(ctx, &value)
+            .map_err(|err| format_err!("{err}"))?
+        {
+            value = replacement;
+        }
+    }
+
+    let mut variant = expression::resolve(value, ctx.class_name, ctx.property, defines)?;
+
+    for resolver in resolvers {
+        variant = resolver
+            .after_resolve(ctx, variant)
+            .map_err(|err| format_err!("{err}"))?;
+    }
+
+    Ok(variant)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::types::Color3;
+
+    use crate::resolution::AmbiguousValue;
+
+    fn palette(
+        ctx: &PropertyContext<'_>,
+        value: &UnresolvedValue,
+    ) -> HookResult<Option<UnresolvedValue>> {
+        match value {
+            UnresolvedValue::Ambiguous(AmbiguousValue::String(name)) if ctx.property == "Color" => {
+                match name.as_str() {
+                    "BrandRed" => Ok(Some([1.0, 0.0, 0.0].into())),
+                    other => Err(format!("Unknown palette color {other}").into()),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    struct Halve;
+
+    impl PropertyResolver for Halve {
+        fn after_resolve(
+            &self,
+            ctx: &PropertyContext<'_>,
+            variant: Variant,
+        ) -> HookResult<Variant> {
+            match variant {
+                Variant::Float32(value) if ctx.property == "Transparency" => {
+                    Ok(Variant::Float32(value / 2.0))
+                }
+                other => Ok(other),
+            }
+        }
+    }
+
+    fn resolve(
+        prop: &str,
+        json: &str,
+        resolvers: &[Box<dyn PropertyResolver>],
+    ) -> anyhow::Result<Variant> {
+        let value: UnresolvedValue = serde_json::from_str(json).unwrap();
+        let ctx = PropertyContext {
+            class_name: "Part",
+            property: prop,
+            path: "Test",
+        };
+
+        resolve_property(value, &ctx, resolvers, &Defines::new())
+    }
+
+    #[test]
+    fn before_hooks_rewrite_values() {
+        let resolvers: Vec<Box<dyn PropertyResolver>> = vec![Box::new(palette)];
+
+        assert_eq!(
+            resolve("Color", "\"BrandRed\"", &resolvers).unwrap(),
+            Variant::Color3(Color3::new(1.0, 0.0, 0.0))
+        );
+        assert!(resolve("Color", "\"Mauve\"", &resolvers).is_err());
+
+        // Other properties fall through to the built-in rules
+        assert_eq!(
+            resolve("Anchored", "true", &resolvers).unwrap(),
+            Variant::Bool(true)
+        );
+    }
+
+    #[test]
+    fn after_hooks_see_resolved_values() {
+        let resolvers: Vec<Box<dyn PropertyResolver>> = vec![Box::new(Halve)];
+
+        assert_eq!(
+            resolve("Transparency", "1", &resolvers).unwrap(),
+            Variant::Float32(0.5)
+        );
+    }
+}
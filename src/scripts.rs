@@ -0,0 +1,63 @@
+//! Built-in transforms over script sources.
+//!
+//! Each of these is a [`Transform`](crate::transform::Transform) that runs on
+//! the built DOM and rewrites the `Source` property of every script. Add them
+//! to [`BuildOptions::transforms`](crate::BuildOptions::transforms) to use
+//! them from the library.
+
+use rbx_dom_weak::{types::Variant, WeakDom};
+use ustr::Ustr;
+
+use crate::query;
+use crate::resolver::HookResult;
+
+mod banner;
+mod constants;
+mod dev_blocks;
+mod line_endings;
+mod linked_source;
+mod minify;
+mod requires;
+#[cfg(feature = "secrets")]
+mod secrets;
+
+pub use banner::{render_banner, AddBanner};
+pub(crate) use constants::table_literal;
+pub use constants::{inject, InjectConstants};
+pub use dev_blocks::{strip_dev_blocks, StripDevBlocks};
+pub use line_endings::{normalize_line_endings, NormalizeLineEndings};
+pub use linked_source::CheckLinkedSources;
+pub use minify::{minify, MinifyScripts};
+pub use requires::CheckRequires;
+#[cfg(feature = "secrets")]
+pub use secrets::{ScanSecrets, SecretPattern, ALLOW_MARKER, DEFAULT_PATTERNS};
+
+/// Calls `f` with the path and `Source` of every instance that has a string
+/// `Source` property.
+pub fn for_each_source<F>(dom: &mut WeakDom, mut f: F) -> HookResult<()>
+where
+    F: FnMut(&str, &mut String) -> HookResult<()>,
+{
+    let source_key = Ustr::from("Source");
+
+    let scripts: Vec<_> = query::descendants(dom, dom.root_ref())
+        .filter(|instance| {
+            matches!(
+                instance.properties.get(&source_key),
+                Some(Variant::String(_))
+            )
+        })
+        .map(|instance| instance.referent())
+        .collect();
+
+    for referent in scripts {
+        let path = query::path_of(dom, referent);
+        let instance = dom.get_by_ref_mut(referent).unwrap();
+
+        if let Some(Variant::String(source)) = instance.properties.get_mut(&source_key) {
+            f(&path, source)?;
+        }
+    }
+
+    Ok(())
+}
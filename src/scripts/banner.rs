@@ -0,0 +1,139 @@
+use rbx_dom_weak::WeakDom;
+
+use crate::defines::{DefineValue, Defines};
+use crate::diagnostics::DiagnosticSink;
+use crate::provenance::{build_time, civil_date};
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+use super::for_each_source;
+
+/// Prepends a banner comment, such as a license header, to every script.
+///
+/// The template can refer to `{year}` and to any define by name, e.g.
+/// `Copyright {year} Acme, v{version}`. Write `{{` and `}}` for literal
+/// braces. A template that doesn't start with `--` is commented out line by
+/// line.
+///
+/// Run this after [`MinifyScripts`](super::MinifyScripts), which would
+/// otherwise remove the banner again.
+#[derive(Debug, Clone, Default)]
+pub struct AddBanner {
+    pub template: String,
+    pub variables: Defines,
+}
+
+impl AddBanner {
+    pub fn new(template: impl Into<String>, variables: Defines) -> Self {
+        AddBanner {
+            template: template.into(),
+            variables,
+        }
+    }
+}
+
+impl Transform for AddBanner {
+    fn name(&self) -> &str {
+        "add-banner"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let banner = render_banner(&self.template, &self.variables, current_year())?;
+
+        for_each_source(dom, |_path, source| {
+            source.insert_str(0, &banner);
+            Ok(())
+        })
+    }
+}
+
+/// Fills in the variables of a banner template and turns it into a comment,
+/// ending in a newline.
+pub fn render_banner(template: &str, variables: &Defines, year: i64) -> Result<String, String> {
+    let mut text = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let name = name.trim();
+
+                match variables.get(name) {
+                    Some(DefineValue::String(value)) => text.push_str(value),
+                    Some(DefineValue::Number(value)) => text.push_str(&value.to_string()),
+                    Some(DefineValue::Bool(value)) => text.push_str(&value.to_string()),
+                    None if name == "year" => text.push_str(&year.to_string()),
+                    None => return Err(format!("Unknown banner variable {{{name}}}")),
+                }
+            }
+            c => text.push(c),
+        }
+    }
+
+    let text = text.trim_end();
+    let mut banner = String::with_capacity(text.len() + 8);
+
+    if text.starts_with("--") {
+        banner.push_str(text);
+        banner.push('\n');
+    } else {
+        for line in text.lines() {
+            banner.push_str("--");
+            if !line.is_empty() {
+                banner.push(' ');
+                banner.push_str(line);
+            }
+            banner.push('\n');
+        }
+    }
+
+    Ok(banner)
+}
+
+/// The current year, or the year of `SOURCE_DATE_EPOCH` if it's set, so
+/// reproducible builds don't change on New Year's Day.
+fn current_year() -> i64 {
+    civil_date(build_time()).0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_variables() {
+        let mut variables = Defines::new();
+        variables.insert(
+            "version".to_owned(),
+            DefineValue::String("1.4.0".to_owned()),
+        );
+
+        assert_eq!(
+            render_banner(
+                "Copyright {year} Acme\n\nv{ version } {{MIT}}",
+                &variables,
+                2024
+            )
+            .unwrap(),
+            "-- Copyright 2024 Acme\n--\n-- v1.4.0 {MIT}\n"
+        );
+        assert_eq!(
+            render_banner("--[[ v{version} ]]\n", &variables, 2024).unwrap(),
+            "--[[ v1.4.0 ]]\n"
+        );
+        assert!(render_banner("{missing}", &variables, 2024).is_err());
+    }
+}
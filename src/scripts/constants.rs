@@ -0,0 +1,188 @@
+use rbx_dom_weak::WeakDom;
+
+use crate::defines::{DefineValue, Defines};
+use crate::diagnostics::DiagnosticSink;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+use super::for_each_source;
+
+const MARKER_START: &str = "--[[RBXBUILD:CONST";
+const MARKER_END: &str = "]]";
+
+/// Replaces constant markers in scripts with Luau literals.
+///
+/// `--[[RBXBUILD:CONST]]` becomes a table of every constant, and
+/// `--[[RBXBUILD:CONST name]]` becomes the value of a single one:
+///
+/// ```lua
+/// local Build = --[[RBXBUILD:CONST]]
+/// local VERSION = --[[RBXBUILD:CONST version]]
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InjectConstants {
+    pub constants: Defines,
+}
+
+impl InjectConstants {
+    pub fn new(constants: Defines) -> Self {
+        InjectConstants { constants }
+    }
+}
+
+impl Transform for InjectConstants {
+    fn name(&self) -> &str {
+        "inject-constants"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        for_each_source(dom, |path, source| {
+            if source.contains(MARKER_START) {
+                *source =
+                    inject(source, &self.constants).map_err(|err| format!("{path}: {err}"))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Replaces every constant marker in `source`.
+pub fn inject(source: &str, constants: &Defines) -> Result<String, String> {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find(MARKER_START) {
+        output.push_str(&rest[..start]);
+
+        let after = &rest[start + MARKER_START.len()..];
+        let end = after
+            .find(MARKER_END)
+            .ok_or_else(|| "Unterminated constant marker".to_owned())?;
+
+        match after[..end].trim() {
+            "" => output.push_str(&table_literal(constants)),
+            name => {
+                let value = constants
+                    .get(name)
+                    .ok_or_else(|| format!("Unknown constant {name}"))?;
+                output.push_str(&literal(value));
+            }
+        }
+
+        rest = &after[end + MARKER_END.len()..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// A Luau table of every constant, with keys in sorted order.
+pub(crate) fn table_literal(constants: &Defines) -> String {
+    let mut names: Vec<_> = constants.keys().collect();
+    names.sort();
+
+    let fields: Vec<_> = names
+        .into_iter()
+        .map(|name| format!("{} = {}", table_key(name), literal(&constants[name])))
+        .collect();
+
+    format!("{{ {} }}", fields.join(", "))
+}
+
+fn table_key(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_owned()
+    } else {
+        format!("[{}]", string_literal(name))
+    }
+}
+
+fn literal(value: &DefineValue) -> String {
+    match value {
+        DefineValue::Bool(value) => value.to_string(),
+        DefineValue::Number(value) if value.is_nan() => "0 / 0".to_owned(),
+        DefineValue::Number(value) if value.is_infinite() => if *value > 0.0 {
+            "math.huge"
+        } else {
+            "-math.huge"
+        }
+        .to_owned(),
+        DefineValue::Number(value) => value.to_string(),
+        DefineValue::String(value) => string_literal(value),
+    }
+}
+
+fn string_literal(value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + 2);
+    output.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c.is_ascii_control() => output.push_str(&format!("\\{:03}", c as u32)),
+            c => output.push(c),
+        }
+    }
+
+    output.push('"');
+    output
+}
+
+fn is_identifier(name: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in",
+        "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+    ];
+
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn constants() -> Defines {
+        let mut constants = Defines::new();
+        constants.insert(
+            "version".to_owned(),
+            DefineValue::String("1.2.0".to_owned()),
+        );
+        constants.insert("debug".to_owned(), DefineValue::Bool(false));
+        constants.insert("max-players".to_owned(), DefineValue::Number(12.0));
+        constants
+    }
+
+    #[test]
+    fn injects_table_and_single_values() {
+        let source = "local Build = --[[RBXBUILD:CONST]]\nlocal V = --[[RBXBUILD:CONST version]]";
+
+        assert_eq!(
+            inject(source, &constants()).unwrap(),
+            "local Build = { debug = false, [\"max-players\"] = 12, version = \"1.2.0\" }\n\
+             local V = \"1.2.0\""
+        );
+    }
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(string_literal("a \"b\"\n\\"), r#""a \"b\"\n\\""#);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(inject("--[[RBXBUILD:CONST missing]]", &constants()).is_err());
+        assert!(inject("--[[RBXBUILD:CONST", &constants()).is_err());
+        assert_eq!(inject("print(1)", &constants()).unwrap(), "print(1)");
+    }
+}
@@ -0,0 +1,123 @@
+use rbx_dom_weak::WeakDom;
+
+use crate::diagnostics::DiagnosticSink;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+use super::for_each_source;
+
+/// Removes development-only regions from every script. See
+/// [`strip_dev_blocks`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripDevBlocks;
+
+impl Transform for StripDevBlocks {
+    fn name(&self) -> &str {
+        "strip-dev-blocks"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        for_each_source(dom, |path, source| {
+            if source.contains("--!dev") {
+                *source = strip_dev_blocks(source).map_err(|err| format!("{path}: {err}"))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Dev,
+    End,
+}
+
+/// Removes every region between a `--!dev` line and the matching `--!end`
+/// line, markers included:
+///
+/// ```lua
+/// --!dev
+/// print("state:", state)
+/// --!end
+/// ```
+///
+/// Regions can be nested. Removed lines are left empty so line numbers in
+/// errors still match the original file.
+pub fn strip_dev_blocks(source: &str) -> Result<String, String> {
+    let mut output = String::with_capacity(source.len());
+    let mut depth = 0usize;
+    let mut opened_at = 0;
+
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        match marker(line) {
+            Some(Marker::Dev) => {
+                if depth == 0 {
+                    opened_at = index + 1;
+                }
+                depth += 1;
+            }
+            Some(Marker::End) => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| format!("line {}: --!end without --!dev", index + 1))?;
+            }
+            None if depth == 0 => {
+                output.push_str(line);
+                continue;
+            }
+            None => {}
+        }
+
+        if line.ends_with('\n') {
+            output.push('\n');
+        }
+    }
+
+    if depth > 0 {
+        return Err(format!("line {opened_at}: --!dev is never closed"));
+    }
+
+    Ok(output)
+}
+
+fn marker(line: &str) -> Option<Marker> {
+    let directive = line.trim().strip_prefix("--!")?;
+    let name = directive.split_whitespace().next()?;
+
+    match name {
+        "dev" => Some(Marker::Dev),
+        "end" => Some(Marker::End),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strips_regions_and_keeps_lines() {
+        let source =
+            "local x = 1\n--!dev\nprint(x)\n  --!dev\n  debug()\n  --!end\n--!end\nreturn x\n";
+        assert_eq!(
+            strip_dev_blocks(source).unwrap(),
+            "local x = 1\n\n\n\n\n\n\nreturn x\n"
+        );
+    }
+
+    #[test]
+    fn leaves_other_directives() {
+        let source = "--!strict\n--!device\nreturn 1";
+        assert_eq!(strip_dev_blocks(source).unwrap(), source);
+    }
+
+    #[test]
+    fn unbalanced_markers() {
+        assert!(strip_dev_blocks("--!dev\nprint(1)").is_err());
+        assert!(strip_dev_blocks("print(1)\n--!end").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use rbx_dom_weak::WeakDom;
+
+use crate::diagnostics::DiagnosticSink;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+use super::for_each_source;
+
+/// Normalizes line endings and removes byte order marks in every script. See
+/// [`normalize_line_endings`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NormalizeLineEndings;
+
+impl Transform for NormalizeLineEndings {
+    fn name(&self) -> &str {
+        "normalize-line-endings"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        for_each_source(dom, |_path, source| {
+            if source.starts_with('\u{feff}') || source.contains('\r') {
+                *source = normalize_line_endings(source);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Converts `\r\n` and lone `\r` line endings to `\n` and removes a leading
+/// byte order mark, so the same script checked out on Windows and Linux
+/// builds to the same bytes.
+pub fn normalize_line_endings(source: &str) -> String {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    source.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalizes_mixed_endings() {
+        assert_eq!(
+            normalize_line_endings("\u{feff}local a = 1\r\nlocal b = 2\rreturn a\n"),
+            "local a = 1\nlocal b = 2\nreturn a\n"
+        );
+    }
+
+    #[test]
+    fn leaves_unix_sources_alone() {
+        let source = "print(\"\u{feff}\")\n";
+        assert_eq!(normalize_line_endings(source), source);
+    }
+}
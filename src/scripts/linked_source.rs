@@ -0,0 +1,184 @@
+use rbx_dom_weak::{types::Variant, WeakDom};
+use ustr::Ustr;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::query;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+const SCRIPT_CLASSES: &[&str] = &["Script", "LocalScript", "ModuleScript"];
+
+/// Checks scripts whose code lives in an external asset through
+/// `LinkedSource`.
+///
+/// A `LinkedSource` that is set but empty, or isn't an asset id, is an error
+/// in strict mode and a warning otherwise. So is a `ScriptGuid` that isn't
+/// of the form `{xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx}`. A script that has
+/// both a linked and a local `Source` gets a warning, since Roblox ignores
+/// the local one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckLinkedSources {
+    pub strict: bool,
+}
+
+impl CheckLinkedSources {
+    pub fn new(strict: bool) -> Self {
+        CheckLinkedSources { strict }
+    }
+}
+
+impl Transform for CheckLinkedSources {
+    fn name(&self) -> &str {
+        "check-linked-sources"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let linked_key = Ustr::from("LinkedSource");
+        let guid_key = Ustr::from("ScriptGuid");
+        let source_key = Ustr::from("Source");
+        let mut errors = 0;
+
+        for script in query::descendants(dom, dom.root_ref()) {
+            if !SCRIPT_CLASSES.contains(&script.class.as_str()) {
+                continue;
+            }
+
+            let mut report = |property: &str, message: String, is_error: bool| {
+                let path = query::path_of(dom, script.referent());
+                let diagnostic = if is_error && self.strict {
+                    errors += 1;
+                    Diagnostic::error(path, message)
+                } else {
+                    Diagnostic::warning(path, message)
+                };
+                diagnostics.report(diagnostic.with_property(property));
+            };
+
+            if let Some(Variant::String(guid)) = script.properties.get(&guid_key) {
+                if !guid.is_empty() && !is_script_guid(guid) {
+                    report("ScriptGuid", format!("{guid:?} is not a GUID"), true);
+                }
+            }
+
+            let linked = match script.properties.get(&linked_key) {
+                Some(Variant::ContentId(id)) => id.as_str(),
+                Some(Variant::Content(content)) => content.as_uri().unwrap_or_default(),
+                _ => continue,
+            };
+
+            if linked.is_empty() {
+                report(
+                    "LinkedSource",
+                    "LinkedSource is set but empty".to_owned(),
+                    true,
+                );
+            } else if !is_asset_id(linked) {
+                report(
+                    "LinkedSource",
+                    format!("{linked:?} is not an asset id"),
+                    true,
+                );
+            }
+
+            if let Some(Variant::String(source)) = script.properties.get(&source_key) {
+                if !linked.is_empty() && !source.trim().is_empty() {
+                    report(
+                        "Source",
+                        "Source is ignored because LinkedSource is set".to_owned(),
+                        false,
+                    );
+                }
+            }
+        }
+
+        match errors {
+            0 => Ok(()),
+            1 => Err("found an invalid linked source".into()),
+            _ => Err(format!("found {errors} invalid linked sources").into()),
+        }
+    }
+}
+
+/// Whether `uri` refers to an uploaded asset, such as `rbxassetid://123` or
+/// `https://www.roblox.com/asset/?id=123`.
+fn is_asset_id(uri: &str) -> bool {
+    let id = uri.strip_prefix("rbxassetid://").or_else(|| {
+        let rest = uri
+            .strip_prefix("https://")
+            .or_else(|| uri.strip_prefix("http://"))?;
+        let rest = rest.strip_prefix("www.").unwrap_or(rest);
+        rest.strip_prefix("roblox.com/asset/?id=")
+            .or_else(|| rest.strip_prefix("roblox.com/asset?id="))
+    });
+
+    id.is_some_and(|id| !id.is_empty() && id.bytes().all(|c| c.is_ascii_digit()))
+}
+
+fn is_script_guid(guid: &str) -> bool {
+    let Some(inner) = guid.strip_prefix('{').and_then(|g| g.strip_suffix('}')) else {
+        return false;
+    };
+
+    let groups: Vec<_> = inner.split('-').collect();
+    groups.iter().map(|group| group.len()).eq([8, 4, 4, 4, 12])
+        && groups
+            .iter()
+            .all(|group| group.bytes().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{types::ContentId, InstanceBuilder};
+
+    #[test]
+    fn asset_ids() {
+        assert!(is_asset_id("rbxassetid://1234"));
+        assert!(is_asset_id("https://www.roblox.com/asset/?id=1234"));
+        assert!(!is_asset_id("rbxassetid://"));
+        assert!(!is_asset_id("rbxasset://scripts/Foo.lua"));
+        assert!(!is_asset_id("https://example.com/asset/?id=1"));
+    }
+
+    #[test]
+    fn script_guids() {
+        assert!(is_script_guid("{0F9A1B2C-3D4E-5F60-7182-93A4B5C6D7E8}"));
+        assert!(!is_script_guid("0F9A1B2C-3D4E-5F60-7182-93A4B5C6D7E8"));
+        assert!(!is_script_guid("{0F9A1B2C-3D4E-5F60-7182}"));
+    }
+
+    #[test]
+    fn strictness() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_name("Root")
+                .with_child(
+                    InstanceBuilder::new("ModuleScript")
+                        .with_name("Linked")
+                        .with_property("LinkedSource", ContentId::from("rbxassetid://1"))
+                        .with_property("Source", "return 1"),
+                )
+                .with_child(
+                    InstanceBuilder::new("ModuleScript")
+                        .with_name("Empty")
+                        .with_property("LinkedSource", ContentId::from("")),
+                ),
+        );
+
+        let mut diagnostics = Vec::new();
+        CheckLinkedSources::new(false)
+            .transform_dom(&mut dom, &mut |d: Diagnostic| diagnostics.push(d))
+            .unwrap();
+        let paths: Vec<_> = diagnostics.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["Root/Linked", "Root/Empty"]);
+
+        assert!(CheckLinkedSources::new(true)
+            .transform_dom(&mut dom, &mut |_: Diagnostic| {})
+            .is_err());
+    }
+}
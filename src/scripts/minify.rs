@@ -0,0 +1,222 @@
+use rbx_dom_weak::WeakDom;
+
+use crate::diagnostics::DiagnosticSink;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+use super::for_each_source;
+
+/// Strips comments and unneeded whitespace from every script. See [`minify`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MinifyScripts;
+
+impl Transform for MinifyScripts {
+    fn name(&self) -> &str {
+        "minify-scripts"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        for_each_source(dom, |_path, source| {
+            *source = minify(source);
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Gap {
+    None,
+    Space,
+    Newline,
+}
+
+/// Removes comments, indentation, blank lines and spaces between tokens from
+/// Luau source.
+///
+/// Line breaks between statements are kept, so the result never relies on
+/// Luau's handling of ambiguous statement boundaries. String literals and
+/// `--!` directives such as `--!strict` are left untouched.
+pub fn minify(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut output = String::with_capacity(source.len());
+    let mut gap = Gap::None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' | b'\t' | b'\r' => {
+                gap = gap.max(Gap::Space);
+                i += 1;
+            }
+            b'\n' => {
+                gap = Gap::Newline;
+                i += 1;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                let start = i;
+                i += 2;
+
+                if let Some(level) = long_bracket_level(&bytes[i..]) {
+                    i = skip_long_bracket(bytes, i, level);
+                } else {
+                    let end = find_line_end(bytes, i);
+                    if bytes.get(i) == Some(&b'!') {
+                        push_token(&mut output, &mut gap, &source[start..end]);
+                    }
+                    i = end;
+                }
+
+                gap = gap.max(Gap::Space);
+            }
+            b'[' if long_bracket_level(&bytes[i..]).is_some() => {
+                let level = long_bracket_level(&bytes[i..]).unwrap();
+                let end = skip_long_bracket(bytes, i, level);
+                push_token(&mut output, &mut gap, &source[i..end]);
+                i = end;
+            }
+            b'"' | b'\'' | b'`' => {
+                let end = skip_string(bytes, i);
+                push_token(&mut output, &mut gap, &source[i..end]);
+                i = end;
+            }
+            _ => {
+                let len = source[i..].chars().next().map_or(1, char::len_utf8);
+                push_token(&mut output, &mut gap, &source[i..i + len]);
+                i += len;
+            }
+        }
+    }
+
+    output
+}
+
+fn push_token(output: &mut String, gap: &mut Gap, token: &str) {
+    if let (Some(last), Some(next)) = (output.bytes().last(), token.bytes().next()) {
+        match *gap {
+            Gap::Newline => output.push('\n'),
+            Gap::Space if needs_space(last, next) => output.push(' '),
+            _ => {}
+        }
+    }
+
+    *gap = Gap::None;
+    output.push_str(token);
+}
+
+/// Whether removing the whitespace between two characters could change how
+/// the source is tokenized.
+fn needs_space(last: u8, next: u8) -> bool {
+    const DELIMITERS: &[u8] = b"(){},;";
+
+    if DELIMITERS.contains(&last) || DELIMITERS.contains(&next) {
+        return false;
+    }
+
+    match (is_word(last), is_word(next)) {
+        (true, true) => true,
+        // `1 ..x` would become a malformed number
+        (true, false) => next == b'.',
+        (false, true) => last == b'.',
+        // Operators that would merge into a different token, like `- -`
+        // into a comment or `< =` into `<=`
+        (false, false) => {
+            (next == b'=' && b"=<>~+-*/%^.".contains(&last))
+                || matches!(
+                    (last, next),
+                    (b'-', b'-' | b'>') | (b'.', b'.') | (b'[', b'[') | (b'/', b'/') | (b':', b':')
+                )
+        }
+    }
+}
+
+fn is_word(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c >= 0x80
+}
+
+fn find_line_end(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|&c| c == b'\n')
+        .map_or(bytes.len(), |offset| start + offset)
+}
+
+/// If `bytes` starts with a long bracket like `[[` or `[==[`, returns its
+/// level (the number of `=`).
+pub(super) fn long_bracket_level(bytes: &[u8]) -> Option<usize> {
+    if bytes.first() != Some(&b'[') {
+        return None;
+    }
+
+    let level = bytes[1..].iter().take_while(|&&c| c == b'=').count();
+    (bytes.get(level + 1) == Some(&b'[')).then_some(level)
+}
+
+/// Returns the index just past the long bracket starting at `start`.
+pub(super) fn skip_long_bracket(bytes: &[u8], start: usize, level: usize) -> usize {
+    let mut close = Vec::with_capacity(level + 2);
+    close.push(b']');
+    close.extend(std::iter::repeat_n(b'=', level));
+    close.push(b']');
+
+    let body = start + level + 2;
+    bytes[body..]
+        .windows(close.len())
+        .position(|window| window == close.as_slice())
+        .map_or(bytes.len(), |offset| body + offset + close.len())
+}
+
+/// Returns the index just past the quoted string starting at `start`.
+pub(super) fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            c if c == quote => return i + 1,
+            // Unterminated; leave the rest of the line alone
+            b'\n' if quote != b'`' => return i,
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strips_comments_and_whitespace() {
+        let source = "local x = 1 -- the answer\n\n\tif x then\n\t\tprint( x )\n\tend\n";
+        assert_eq!(minify(source), "local x=1\nif x then\nprint(x)\nend");
+    }
+
+    #[test]
+    fn strips_block_comments() {
+        let source = "--[==[\n  header\n]==]\nreturn --[[ inline ]] 5";
+        assert_eq!(minify(source), "return 5");
+    }
+
+    #[test]
+    fn keeps_strings_and_directives() {
+        let source = "--!strict\nprint(\"a  -- b\", 'c  d', `{x}  y`)\nlocal s = [[\n  keep ]]";
+        assert_eq!(
+            minify(source),
+            "--!strict\nprint(\"a  -- b\",'c  d',`{x}  y`)\nlocal s=[[\n  keep ]]"
+        );
+    }
+
+    #[test]
+    fn keeps_spaces_that_matter() {
+        assert_eq!(minify("a - -b"), "a- -b");
+        assert_eq!(minify("x = 1 .. y"), "x=1 .. y");
+        assert_eq!(minify("t[ [[k]] ]"), "t[ [[k]]]");
+        assert_eq!(minify("return not x"), "return not x");
+    }
+}
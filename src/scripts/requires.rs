@@ -0,0 +1,442 @@
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+use ustr::Ustr;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::query;
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+use super::minify::{long_bracket_level, skip_long_bracket, skip_string};
+
+/// Fails the build if a script requires a module that isn't in the built
+/// tree.
+///
+/// Requires are followed when their argument is a plain path from `script`,
+/// `game` or `workspace`, made of `.Name`, `["Name"]`, `.Parent`,
+/// `:FindFirstChild("Name")`, `:WaitForChild("Name")` and
+/// `game:GetService("Name")`, or a relative string path like
+/// `require("./Utils")`. Anything computed at runtime is skipped. Paths from
+/// `game` are only checked when building a place.
+///
+/// Each broken require is reported as an error diagnostic on the script's
+/// `Source`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckRequires;
+
+impl Transform for CheckRequires {
+    fn name(&self) -> &str {
+        "check-requires"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let source_key = Ustr::from("Source");
+        let mut count = 0;
+
+        for script in query::descendants(dom, dom.root_ref()) {
+            let Some(Variant::String(source)) = script.properties.get(&source_key) else {
+                continue;
+            };
+
+            for require in find_requires(source) {
+                if let Err(reason) = check(dom, script.referent(), &require.path) {
+                    let message = format!(
+                        "require({}) on line {}: {reason}",
+                        require.argument, require.line
+                    );
+                    diagnostics.report(
+                        Diagnostic::error(query::path_of(dom, script.referent()), message)
+                            .with_property("Source"),
+                    );
+                    count += 1;
+                }
+            }
+        }
+
+        match count {
+            0 => Ok(()),
+            1 => Err("found a broken require".into()),
+            _ => Err(format!("found {count} broken requires").into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Base {
+    Script,
+    Game,
+    Service(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Parent,
+    Child(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RequirePath {
+    base: Base,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug)]
+struct Require {
+    line: usize,
+    argument: String,
+    path: RequirePath,
+}
+
+/// Finds every `require` call in `source` whose argument is a path that can
+/// be followed at build time.
+fn find_requires(source: &str) -> Vec<Require> {
+    let bytes = source.as_bytes();
+    let mut requires = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i += 2;
+                i = match long_bracket_level(&bytes[i..]) {
+                    Some(level) => skip_long_bracket(bytes, i, level),
+                    None => bytes[i..]
+                        .iter()
+                        .position(|&c| c == b'\n')
+                        .map_or(bytes.len(), |offset| i + offset),
+                };
+            }
+            b'[' if long_bracket_level(&bytes[i..]).is_some() => {
+                let level = long_bracket_level(&bytes[i..]).unwrap();
+                i = skip_long_bracket(bytes, i, level);
+            }
+            b'"' | b'\'' | b'`' => i = skip_string(bytes, i),
+            c if is_word_start(c) => {
+                let start = i;
+                while i < bytes.len() && is_word(bytes[i]) {
+                    i += 1;
+                }
+
+                // Skip method calls and fields like `Foo.require(x)`
+                let is_field = source[..start].trim_end().ends_with(['.', ':']);
+                if &source[start..i] != "require" || is_field {
+                    continue;
+                }
+
+                let open = i + (bytes[i..].len() - source[i..].trim_start().len());
+                if bytes.get(open) != Some(&b'(') {
+                    continue;
+                }
+
+                let Some(close) = find_close_paren(bytes, open) else {
+                    break;
+                };
+
+                let argument = source[open + 1..close].trim();
+                if let Some(path) = parse_path(argument) {
+                    requires.push(Require {
+                        line: source[..start].matches('\n').count() + 1,
+                        argument: argument.to_owned(),
+                        path,
+                    });
+                }
+
+                i = close + 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    requires
+}
+
+fn find_close_paren(bytes: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            b'"' | b'\'' | b'`' => {
+                i = skip_string(bytes, i);
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+fn parse_path(argument: &str) -> Option<RequirePath> {
+    if argument.starts_with(['"', '\'']) {
+        return parse_string_path(&parse_quoted(argument)?.0);
+    }
+
+    let mut rest = argument;
+    let base = match take_word(&mut rest)? {
+        "script" => Base::Script,
+        "workspace" => Base::Service("Workspace".to_owned()),
+        "game" => {
+            if let Some(after) = rest.strip_prefix(":GetService(") {
+                let (name, after) = parse_quoted(after.trim_start())?;
+                rest = after.trim_start().strip_prefix(')')?;
+                Base::Service(name)
+            } else {
+                Base::Game
+            }
+        }
+        _ => return None,
+    };
+
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            rest = after;
+            steps.push(match take_word(&mut rest)? {
+                "Parent" => Step::Parent,
+                name => Step::Child(name.to_owned()),
+            });
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let (name, after) = parse_quoted(after.trim_start())?;
+            rest = after.trim_start().strip_prefix(']')?;
+            steps.push(Step::Child(name));
+        } else if let Some(after) = rest.strip_prefix(':') {
+            rest = after;
+            let method = take_word(&mut rest)?;
+            if !matches!(method, "FindFirstChild" | "WaitForChild") {
+                return None;
+            }
+
+            let (name, after) = parse_quoted(rest.strip_prefix('(')?.trim_start())?;
+            let close = after.find(')')?;
+
+            // WaitForChild can be given a timeout, but FindFirstChild's second
+            // argument makes it search every descendant
+            let extra = after[..close].trim();
+            if !extra.is_empty() && (method != "WaitForChild" || !extra.starts_with(',')) {
+                return None;
+            }
+
+            rest = &after[close + 1..];
+            steps.push(Step::Child(name));
+        } else {
+            return None;
+        }
+    }
+
+    Some(RequirePath { base, steps })
+}
+
+/// Parses a Luau string require such as `./Utils` or `../Shared/Signal`.
+/// Aliases other than `@self` can't be followed.
+fn parse_string_path(path: &str) -> Option<RequirePath> {
+    let mut segments = path.split('/');
+    let mut steps = match segments.next()? {
+        "." => vec![Step::Parent],
+        ".." => vec![Step::Parent, Step::Parent],
+        "@self" => Vec::new(),
+        _ => return None,
+    };
+
+    for segment in segments {
+        match segment {
+            "" | "." => {}
+            ".." => steps.push(Step::Parent),
+            name => steps.push(Step::Child(name.to_owned())),
+        }
+    }
+
+    Some(RequirePath {
+        base: Base::Script,
+        steps,
+    })
+}
+
+/// Parses a quoted string without escapes at the start of `input`, returning
+/// its contents and the rest of the input.
+fn parse_quoted(input: &str) -> Option<(String, &str)> {
+    let quote = input.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let end = input[1..].find(quote)? + 1;
+    let contents = &input[1..end];
+
+    if contents.contains('\\') {
+        return None;
+    }
+
+    Some((contents.to_owned(), &input[end + 1..]))
+}
+
+fn take_word<'a>(rest: &mut &'a str) -> Option<&'a str> {
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+    if end == 0 || !is_word_start(rest.as_bytes()[0]) {
+        return None;
+    }
+
+    let word = &rest[..end];
+    *rest = &rest[end..];
+    Some(word)
+}
+
+fn is_word_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+fn is_word(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+/// Follows `path` from `script`, returning why it doesn't lead to a
+/// ModuleScript. Paths that can't be checked in this DOM are accepted.
+fn check(dom: &WeakDom, script: Ref, path: &RequirePath) -> Result<(), String> {
+    let root = dom.root();
+
+    let mut current = match &path.base {
+        Base::Script => script,
+        // Models aren't parented to a DataModel until they are inserted
+        Base::Game | Base::Service(_) if root.class != "DataModel" => return Ok(()),
+        Base::Game => root.referent(),
+        Base::Service(name) => root
+            .children()
+            .iter()
+            .copied()
+            .find(|&child| {
+                dom.get_by_ref(child)
+                    .is_some_and(|child| child.class == name.as_str())
+            })
+            .ok_or_else(|| format!("there is no {name} service"))?,
+    };
+
+    for step in &path.steps {
+        let instance = dom.get_by_ref(current).unwrap();
+
+        current = match step {
+            Step::Parent => Some(instance.parent()).filter(|parent| parent.is_some()),
+            Step::Child(name) => instance.children().iter().copied().find(|&child| {
+                dom.get_by_ref(child)
+                    .is_some_and(|child| child.name == *name)
+            }),
+        }
+        .ok_or_else(|| match step {
+            Step::Parent => format!("{} has no parent", query::path_of(dom, current)),
+            Step::Child(name) => {
+                format!("{} has no child named {name}", query::path_of(dom, current))
+            }
+        })?;
+    }
+
+    let target = dom.get_by_ref(current).unwrap();
+    if target.class != "ModuleScript" {
+        return Err(format!(
+            "{} is a {}, not a ModuleScript",
+            query::path_of(dom, current),
+            target.class
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn child(name: &str) -> Step {
+        Step::Child(name.to_owned())
+    }
+
+    #[test]
+    fn parses_paths() {
+        assert_eq!(
+            parse_path("script.Parent.Utils"),
+            Some(RequirePath {
+                base: Base::Script,
+                steps: vec![Step::Parent, child("Utils")],
+            })
+        );
+        assert_eq!(
+            parse_path(
+                "game:GetService(\"ReplicatedStorage\"):WaitForChild(\"Shared\", 5)[\"Signal\"]"
+            ),
+            Some(RequirePath {
+                base: Base::Service("ReplicatedStorage".to_owned()),
+                steps: vec![child("Shared"), child("Signal")],
+            })
+        );
+        assert_eq!(
+            parse_path("\"../Shared/Signal\""),
+            Some(RequirePath {
+                base: Base::Script,
+                steps: vec![Step::Parent, Step::Parent, child("Shared"), child("Signal")],
+            })
+        );
+        assert_eq!(parse_path("modules[name]"), None);
+        assert_eq!(parse_path("script.Parent:FindFirstChild(name)"), None);
+        assert_eq!(parse_path("\"@pkg/Promise\""), None);
+    }
+
+    #[test]
+    fn finds_requires_outside_comments_and_strings() {
+        let source = "-- require(script.A)\nlocal s = \"require(script.B)\"\n\
+                      local C = require (script.C)\nlocal D = Loader.require(script.D)\n\
+                      local E = require(getModule())";
+
+        let requires = find_requires(source);
+        assert_eq!(requires.len(), 1);
+        assert_eq!(requires[0].line, 3);
+        assert_eq!(requires[0].argument, "script.C");
+    }
+
+    #[test]
+    fn checks_targets() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_name("Game"));
+        let storage = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("ReplicatedStorage").with_name("ReplicatedStorage"),
+        );
+        let client = dom.insert(
+            storage,
+            InstanceBuilder::new("LocalScript").with_name("Client"),
+        );
+        dom.insert(
+            storage,
+            InstanceBuilder::new("ModuleScript").with_name("Utils"),
+        );
+        dom.insert(storage, InstanceBuilder::new("Folder").with_name("Assets"));
+
+        let check_path = |argument: &str| check(&dom, client, &parse_path(argument).unwrap());
+
+        assert!(check_path("script.Parent.Utils").is_ok());
+        assert!(check_path("\"./Utils\"").is_ok());
+        assert!(check_path("game:GetService(\"ReplicatedStorage\").Utils").is_ok());
+        assert_eq!(
+            check_path("script.Parent.Missing"),
+            Err("Game/ReplicatedStorage has no child named Missing".to_owned())
+        );
+        assert_eq!(
+            check_path("script.Parent.Assets"),
+            Err("Game/ReplicatedStorage/Assets is a Folder, not a ModuleScript".to_owned())
+        );
+        assert_eq!(
+            check_path("game:GetService(\"ServerStorage\").Utils"),
+            Err("there is no ServerStorage service".to_owned())
+        );
+    }
+}
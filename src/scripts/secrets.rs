@@ -0,0 +1,163 @@
+use rbx_dom_weak::WeakDom;
+use regex::Regex;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+use super::for_each_source;
+
+/// Lines containing this are never reported, for values that only look like
+/// secrets.
+pub const ALLOW_MARKER: &str = "rbxbuild:allow-secret";
+
+/// Patterns checked by [`ScanSecrets::new`].
+pub const DEFAULT_PATTERNS: &[(&str, &str)] = &[
+    (
+        "Discord webhook",
+        r"https://(?:[a-z]+\.)?discord(?:app)?\.com/api/webhooks/\d+/[\w-]+",
+    ),
+    (
+        "Slack webhook",
+        r"https://hooks\.slack\.com/services/[A-Za-z0-9/_-]+",
+    ),
+    ("GitHub token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b"),
+    ("AWS access key", r"\bAKIA[0-9A-Z]{16}\b"),
+    ("private key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----"),
+    ("Roblox cookie", r"_\|WARNING:-DO-NOT-SHARE-THIS"),
+    (
+        "API key",
+        r#"(?i)\b(?:api[_-]?key|secret|token|password)\w*\s*=\s*["'][^"'\s]{16,}["']"#,
+    ),
+];
+
+/// A named pattern for [`ScanSecrets`].
+#[derive(Debug, Clone)]
+pub struct SecretPattern {
+    pub name: String,
+    pub regex: Regex,
+}
+
+/// Fails the build if any script looks like it contains a secret, such as an
+/// API key or a webhook URL. Anything in a published place can be read by
+/// whoever gets hold of it.
+///
+/// Each match is reported as an error diagnostic on the script's `Source`.
+/// Add [`ALLOW_MARKER`] in a comment on a line to skip it.
+#[derive(Debug, Clone)]
+pub struct ScanSecrets {
+    pub patterns: Vec<SecretPattern>,
+}
+
+impl ScanSecrets {
+    /// Creates a scanner with the [`DEFAULT_PATTERNS`].
+    pub fn new() -> Self {
+        let patterns = DEFAULT_PATTERNS
+            .iter()
+            .map(|&(name, pattern)| SecretPattern {
+                name: name.to_owned(),
+                regex: Regex::new(pattern).unwrap(),
+            })
+            .collect();
+
+        ScanSecrets { patterns }
+    }
+
+    /// Adds a pattern to check for.
+    pub fn with_pattern(
+        mut self,
+        name: impl Into<String>,
+        pattern: &str,
+    ) -> Result<Self, regex::Error> {
+        self.patterns.push(SecretPattern {
+            name: name.into(),
+            regex: Regex::new(pattern)?,
+        });
+        Ok(self)
+    }
+
+    /// Returns the 1-based line number and pattern name of every likely
+    /// secret in `source`.
+    pub fn scan<'a>(&'a self, source: &str) -> Vec<(usize, &'a str)> {
+        let mut found = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            if line.contains(ALLOW_MARKER) {
+                continue;
+            }
+
+            for pattern in &self.patterns {
+                if pattern.regex.is_match(line) {
+                    found.push((index + 1, pattern.name.as_str()));
+                }
+            }
+        }
+
+        found
+    }
+}
+
+impl Default for ScanSecrets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transform for ScanSecrets {
+    fn name(&self) -> &str {
+        "scan-secrets"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let mut count = 0;
+
+        for_each_source(dom, |path, source| {
+            for (line, name) in self.scan(source) {
+                diagnostics.report(
+                    Diagnostic::error(path, format!("Possible {name} on line {line}"))
+                        .with_property("Source"),
+                );
+                count += 1;
+            }
+            Ok(())
+        })?;
+
+        match count {
+            0 => Ok(()),
+            1 => Err("found a possible secret in a script".into()),
+            _ => Err(format!("found {count} possible secrets in scripts").into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_default_patterns() {
+        let source = "local hook = \"https://discord.com/api/webhooks/123/abc-DEF\"\n\
+                      local x = 1\n\
+                      local API_KEY = \"0123456789abcdef0123\"";
+
+        assert_eq!(
+            ScanSecrets::new().scan(source),
+            [(1, "Discord webhook"), (3, "API key")]
+        );
+    }
+
+    #[test]
+    fn custom_patterns_and_allowed_lines() {
+        let scanner = ScanSecrets::new()
+            .with_pattern("internal token", r"itk_[0-9a-f]{8}")
+            .unwrap();
+        let source = "print(\"itk_deadbeef\")\nprint(\"itk_deadbeef\") -- rbxbuild:allow-secret";
+
+        assert_eq!(scanner.scan(source), [(1, "internal token")]);
+        assert!(ScanSecrets::new().with_pattern("bad", "(").is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Rules that blank or remove properties before a build is written, for
+//! builds handed to people who shouldn't see everything in the project.
+//!
+//! Rules are listed in `rbxbuild.toml`, on their own or in a profile:
+//!
+//! ```toml
+//! # Ship a public demo without its module sources
+//! [[profiles.demo.scrub]]
+//! class = "ModuleScript"
+//! property = "Source"
+//!
+//! # Remove webhook URLs wherever they're stored
+//! [[scrub]]
+//! path = "Game/ServerStorage/**"
+//! property = "Value"
+//! matching = "https://discord.com/api/webhooks/*"
+//! action = "remove"
+//! ```
+
+use rbx_dom_weak::{
+    types::{Content, ContentId, Variant},
+    WeakDom,
+};
+use serde::Deserialize;
+use ustr::Ustr;
+
+use crate::diagnostics::DiagnosticSink;
+use crate::query::{self, Predicate};
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ScrubRule {
+    /// Glob the instance's path has to match, as in `rbxbuild query`.
+    /// Matches every instance by default.
+    #[serde(default = "every_path")]
+    pub path: String,
+
+    /// Class the instance has to be.
+    pub class: Option<String>,
+
+    /// The property to scrub.
+    pub property: String,
+
+    /// Only scrub string values that match this pattern, where `*` matches
+    /// any run of characters and `?` any single one.
+    pub matching: Option<String>,
+
+    #[serde(default)]
+    pub action: ScrubAction,
+}
+
+fn every_path() -> String {
+    "**".to_owned()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScrubAction {
+    /// Empty strings and content, and reset anything else to the class's
+    /// default value, or remove it if there's none.
+    #[default]
+    Blank,
+
+    /// Remove the property, so it's not written at all.
+    Remove,
+}
+
+/// Applies scrub rules to every instance they match. Runs as a transform, so
+/// it sees the tree as it's written.
+#[derive(Debug, Clone, Default)]
+pub struct Scrub(pub Vec<ScrubRule>);
+
+impl Transform for Scrub {
+    fn name(&self) -> &str {
+        "scrub"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let mut scrubbed = Vec::new();
+
+        for rule in &self.0 {
+            let key = Ustr::from(&rule.property);
+            let predicates: Vec<_> = rule.class.iter().cloned().map(Predicate::Class).collect();
+
+            for (_, instance) in query::select(dom, &rule.path, &predicates) {
+                let Some(value) = instance.properties.get(&key) else {
+                    continue;
+                };
+                if let Some(pattern) = &rule.matching {
+                    let matched = string_value(value)
+                        .is_some_and(|value| query::wildcard_matches(pattern, value));
+                    if !matched {
+                        continue;
+                    }
+                }
+
+                let blanked = match rule.action {
+                    ScrubAction::Remove => None,
+                    ScrubAction::Blank => blank(instance.class.as_str(), &rule.property, value)?,
+                };
+                scrubbed.push((instance.referent(), key, blanked));
+            }
+        }
+
+        for (referent, key, value) in scrubbed {
+            let instance = dom.get_by_ref_mut(referent).unwrap();
+            match value {
+                Some(value) => instance.properties.insert(key, value),
+                None => instance.properties.remove(&key),
+            };
+        }
+
+        Ok(())
+    }
+}
+
+fn string_value(value: &Variant) -> Option<&str> {
+    match value {
+        Variant::String(value) => Some(value),
+        Variant::ContentId(id) => Some(id.as_str()),
+        Variant::Content(content) => content.as_uri(),
+        _ => None,
+    }
+}
+
+/// The empty version of a value, or `None` if the property is better left
+/// out than given a value.
+fn blank(class: &str, property: &str, value: &Variant) -> HookResult<Option<Variant>> {
+    Ok(match value {
+        Variant::String(_) => Some(Variant::String(String::new())),
+        Variant::ContentId(_) => Some(Variant::ContentId(ContentId::from(""))),
+        Variant::Content(_) => Some(Variant::Content(Content::none())),
+        Variant::BinaryString(_) => Some(Variant::BinaryString(Vec::new().into())),
+        _ => crate::database::get()?
+            .classes
+            .get(class)
+            .and_then(|class| class.default_properties.get(property))
+            .cloned(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostics::Diagnostic;
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn scrubs_matching_properties() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_name("Game"));
+        let module = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("ModuleScript")
+                .with_name("Shop")
+                .with_property("Source", "return {}"),
+        );
+        let webhook = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("StringValue")
+                .with_name("Webhook")
+                .with_property("Value", "https://discord.com/api/webhooks/1/abc"),
+        );
+        let motd = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("StringValue")
+                .with_name("Motd")
+                .with_property("Value", "Welcome!"),
+        );
+
+        let rules = vec![
+            ScrubRule {
+                path: every_path(),
+                class: Some("ModuleScript".to_owned()),
+                property: "Source".to_owned(),
+                matching: None,
+                action: ScrubAction::Blank,
+            },
+            ScrubRule {
+                path: "Game/*".to_owned(),
+                class: None,
+                property: "Value".to_owned(),
+                matching: Some("https://discord.com/api/webhooks/*".to_owned()),
+                action: ScrubAction::Remove,
+            },
+        ];
+        Scrub(rules)
+            .transform_dom(&mut dom, &mut |_: Diagnostic| {})
+            .unwrap();
+
+        let property = |referent, name: &str| {
+            dom.get_by_ref(referent)
+                .unwrap()
+                .properties
+                .get(&Ustr::from(name))
+                .cloned()
+        };
+        assert_eq!(
+            property(module, "Source"),
+            Some(Variant::String(String::new()))
+        );
+        assert_eq!(property(webhook, "Value"), None);
+        assert_eq!(
+            property(motd, "Value"),
+            Some(Variant::String("Welcome!".to_owned()))
+        );
+    }
+}
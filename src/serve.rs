@@ -0,0 +1,314 @@
+//! Building projects over HTTP, for tools that want places on demand without
+//! running the CLI for every one, and serving builds to Studio through
+//! [`rojo`]'s protocol or to the [`companion`] plugin.
+//!
+//! [`serve`] answers each connection on its own thread:
+//!
+//! - `POST /build` takes a project's JSON as the body and returns the built
+//!   place or model. The format follows the `Accept` header
+//!   (`application/x-roblox-rbxl`, `application/octet-stream` or
+//!   `application/x-roblox-rbxm` for binary; `application/xml` or
+//!   `application/x-roblox-rbxlx` for XML) unless `?format=xml|binary` is
+//!   given, and `?as=auto|place|model` and `?strict=true` work like the CLI
+//!   flags.
+//! - `GET /health` returns `{"status": "ok", "version": ...}`.
+//!
+//! A successful build's warning count is in the `X-Rbxbuild-Warnings` header.
+//! Failures are JSON, `{"error": {"code", "message"}, "diagnostics": [...]}`,
+//! with a 4xx status for problems with the request or project and 500 if the
+//! output couldn't be written.
+
+use std::{
+    io::{BufReader, BufWriter},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+};
+
+use serde_json::json;
+
+use crate::diagnostics::Diagnostic;
+use crate::options::{BuildOptions, RootHandling};
+use crate::output::{self, OutputFormat};
+use crate::project::Project;
+
+pub mod companion;
+pub mod http;
+pub mod rojo;
+pub mod watch;
+
+use http::{ReadError, Request, Response};
+
+/// Largest project body that's accepted.
+pub const MAX_BODY: usize = 64 * 1024 * 1024;
+
+/// Builds projects posted to `/build` until the listener fails.
+pub fn serve(listener: TcpListener) -> std::io::Result<()> {
+    serve_with(listener, handle)
+}
+
+/// Accepts connections until the listener fails, answering each request with
+/// `handler` on its own thread.
+pub fn serve_with<F>(listener: TcpListener, handler: F) -> std::io::Result<()>
+where
+    F: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let handler = Arc::clone(&handler);
+        thread::spawn(move || {
+            if let Err(error) = handle_connection(stream, &*handler) {
+                log::debug!("Connection failed: {error}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    handler: &dyn Fn(&Request) -> Response,
+) -> std::io::Result<()> {
+    let response = match http::read_request(BufReader::new(&stream), MAX_BODY) {
+        Ok(request) => {
+            let response = handler(&request);
+            log::info!("{} {} {}", request.method, request.path, response.status);
+            response
+        }
+        Err(ReadError::Rejected(response)) => response,
+        Err(ReadError::Io(error)) => return Err(error),
+    };
+
+    http::write_response(BufWriter::new(&stream), &response)
+}
+
+/// Answers a single request.
+pub fn handle(request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/build") => build(request),
+        ("GET", "/health") => Response::json(
+            200,
+            &json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+        ),
+        (_, "/build" | "/health") => Response::error(
+            405,
+            "method-not-allowed",
+            format!("{} isn't allowed on {}", request.method, request.path),
+        ),
+        _ => Response::error(404, "not-found", format!("No endpoint at {}", request.path)),
+    }
+}
+
+fn build(request: &Request) -> Response {
+    let format = match request.query("format") {
+        Some("xml") => OutputFormat::Xml,
+        Some("binary") => OutputFormat::Binary,
+        Some(other) => {
+            return Response::error(
+                400,
+                "bad-request",
+                format!("Unknown format {other:?}, expected xml or binary"),
+            )
+        }
+        None => match negotiate(request.header("Accept")) {
+            Some(format) => format,
+            None => {
+                return Response::error(
+                    406,
+                    "not-acceptable",
+                    "Accept a Roblox place or model as XML or binary",
+                )
+            }
+        },
+    };
+
+    let root = match request.query("as") {
+        None | Some("auto") => RootHandling::Auto,
+        Some("place") => RootHandling::Place,
+        Some("model") => RootHandling::Model,
+        Some(other) => {
+            return Response::error(
+                400,
+                "bad-request",
+                format!("Unknown root handling {other:?}, expected auto, place or model"),
+            )
+        }
+    };
+
+    let project = match std::str::from_utf8(&request.body)
+        .map_err(|error| error.to_string())
+        .and_then(|json| Project::from_json(json).map_err(|error| error.to_string()))
+    {
+        Ok(project) => project,
+        Err(message) => return Response::error(400, "invalid-project", message),
+    };
+    let name = project.name.clone().unwrap_or_else(|| "place".to_owned());
+
+    let mut options = BuildOptions::for_project(&project, request.query("strict") == Some("true"));
+    options.root = root;
+
+    let mut diagnostics = Vec::new();
+    let dom = match crate::build(project, &options, &mut |diagnostic: Diagnostic| {
+        diagnostics.push(diagnostic)
+    }) {
+        Ok(dom) => dom,
+        Err(error) => return failure(422, "build-failed", error, &diagnostics),
+    };
+
+    let mut body = Vec::new();
+    if let Err(error) = output::write_dom(&mut body, &dom, format, &options) {
+        return failure(500, "serialize-failed", error, &diagnostics);
+    }
+
+    let (content_type, extension) = match (output::is_place(&dom, root), format) {
+        (true, OutputFormat::Xml) => ("application/x-roblox-rbxlx", "rbxlx"),
+        (true, OutputFormat::Binary) => ("application/x-roblox-rbxl", "rbxl"),
+        (false, OutputFormat::Xml) => ("application/x-roblox-rbxmx", "rbxmx"),
+        (false, OutputFormat::Binary) => ("application/x-roblox-rbxm", "rbxm"),
+    };
+
+    // Warnings don't fail the build, so only their count is sent back
+    Response::new(200, content_type, body)
+        .with_header(
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"{}.{extension}\"",
+                name.replace(['"', '\\', '\r', '\n'], "_")
+            ),
+        )
+        .with_header("X-Rbxbuild-Warnings", diagnostics.len().to_string())
+}
+
+fn failure(status: u16, code: &str, error: crate::Error, diagnostics: &[Diagnostic]) -> Response {
+    Response::json(
+        status,
+        &json!({
+            "error": { "code": code, "message": error.to_string() },
+            "diagnostics": diagnostics,
+        }),
+    )
+}
+
+/// Picks the output format for an `Accept` header, preferring higher quality
+/// values and then earlier entries. A missing header or a wildcard gets XML.
+fn negotiate(accept: Option<&str>) -> Option<OutputFormat> {
+    let Some(accept) = accept else {
+        return Some(OutputFormat::Xml);
+    };
+
+    let mut ranges: Vec<(f32, &str)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let media_type = parts.next()?.trim();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            Some((quality, media_type))
+        })
+        .filter(|&(quality, _)| quality > 0.0)
+        .collect();
+
+    // Stable, so equal qualities keep their order
+    ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    ranges.into_iter().find_map(
+        |(_, media_type)| match media_type.to_ascii_lowercase().as_str() {
+            "application/x-roblox-rbxl"
+            | "application/x-roblox-rbxm"
+            | "application/octet-stream" => Some(OutputFormat::Binary),
+            "application/x-roblox-rbxlx"
+            | "application/x-roblox-rbxmx"
+            | "application/xml"
+            | "text/xml"
+            | "application/*"
+            | "*/*" => Some(OutputFormat::Xml),
+            _ => None,
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(method: &str, target: &str, accept: Option<&str>, body: &str) -> Request {
+        let mut raw = format!("{method} {target} HTTP/1.1\r\n");
+        if let Some(accept) = accept {
+            raw.push_str(&format!("Accept: {accept}\r\n"));
+        }
+        raw.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+        http::read_request(raw.as_bytes(), MAX_BODY).unwrap()
+    }
+
+    #[test]
+    fn negotiates_formats() {
+        assert_eq!(negotiate(None), Some(OutputFormat::Xml));
+        assert_eq!(negotiate(Some("*/*")), Some(OutputFormat::Xml));
+        assert_eq!(
+            negotiate(Some("application/octet-stream")),
+            Some(OutputFormat::Binary)
+        );
+        assert_eq!(
+            negotiate(Some("application/xml;q=0.5, application/x-roblox-rbxl")),
+            Some(OutputFormat::Binary)
+        );
+        assert_eq!(
+            negotiate(Some("text/html, application/xml;q=0.1")),
+            Some(OutputFormat::Xml)
+        );
+        assert_eq!(negotiate(Some("text/html, */*;q=0")), None);
+    }
+
+    #[test]
+    fn routes_and_errors() {
+        assert_eq!(handle(&request("GET", "/health", None, "")).status, 200);
+        assert_eq!(handle(&request("GET", "/build", None, "")).status, 405);
+        assert_eq!(handle(&request("GET", "/nope", None, "")).status, 404);
+
+        let response = handle(&request("POST", "/build", None, "{ not json"));
+        assert_eq!(response.status, 400);
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["error"]["code"], "invalid-project");
+
+        let project = r#"{"name": "Game", "tree": {"$className": "DataModel"}}"#;
+        let response = handle(&request("POST", "/build", Some("text/html"), project));
+        assert_eq!(response.status, 406);
+    }
+
+    #[test]
+    #[cfg(feature = "xml")]
+    fn builds_projects() {
+        let project = r#"{
+            "name": "Game",
+            "defines": { "base": 2 },
+            "tree": {
+                "$className": "DataModel",
+                "Workspace": {
+                    "Baseplate": {
+                        "$className": "Part",
+                        "$properties": { "Transparency": "=base / 4" }
+                    }
+                }
+            }
+        }"#;
+        let response = handle(&request("POST", "/build", None, project));
+
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.header("Content-Type"),
+            Some("application/x-roblox-rbxlx")
+        );
+        assert_eq!(
+            response.header("Content-Disposition"),
+            Some("attachment; filename=\"Game.rbxlx\"")
+        );
+        let body = String::from_utf8_lossy(&response.body);
+        assert!(body.contains("Baseplate"));
+        assert!(body.contains(r#"<float name="Transparency">0.5</float>"#));
+    }
+}
@@ -0,0 +1,238 @@
+--[[
+	rbxbuild companion plugin.
+
+	Polls `rbxbuild companion` and inserts every new build into the open
+	place. Instances inside a service, such as Workspace or ReplicatedStorage,
+	go into that service; anything else goes into Workspace. Whatever the
+	previous build inserted is replaced, and each insert can be undone.
+
+	Save this file into Studio's local Plugins folder, then click
+	"Companion" on the rbxbuild toolbar to start pulling builds.
+]]
+
+local ChangeHistoryService = game:GetService("ChangeHistoryService")
+local CollectionService = game:GetService("CollectionService")
+local HttpService = game:GetService("HttpService")
+
+local URL = "http://localhost:34873"
+local POLL_SECONDS = 1
+
+-- Set on everything the plugin inserts, to the name of the project it came
+-- from, so the next build can replace it
+local MARKER = "RbxbuildCompanion"
+
+local toolbar = plugin:CreateToolbar("rbxbuild")
+local button = toolbar:CreateButton(
+	"Companion",
+	"Pull the latest rbxbuild build into this place",
+	"rbxasset://textures/StudioToolbox/AssetPreview/download.png"
+)
+button.ClickableWhenViewportHidden = true
+
+local enabled = false
+local running = true
+local lastVersion = nil
+
+local function vector3(value)
+	return Vector3.new(value[1], value[2], value[3])
+end
+
+-- Property values arrive tagged with their type, e.g. {"Vector3": [1, 2, 3]}
+local decoders = {
+	Bool = function(value) return value end,
+	String = function(value) return value end,
+	Int32 = function(value) return value end,
+	Int64 = function(value) return value end,
+	Float32 = function(value) return value end,
+	Float64 = function(value) return value end,
+	Enum = function(value) return value end,
+	ContentId = function(value) return value end,
+	Content = function(value)
+		if type(value) == "table" then
+			return value.Uri
+		end
+		return nil
+	end,
+	BrickColor = function(value) return BrickColor.new(value) end,
+	Vector2 = function(value) return Vector2.new(value[1], value[2]) end,
+	Vector3 = vector3,
+	Color3 = function(value) return Color3.new(value[1], value[2], value[3]) end,
+	Color3uint8 = function(value) return Color3.fromRGB(value[1], value[2], value[3]) end,
+	UDim = function(value) return UDim.new(value[1], value[2]) end,
+	UDim2 = function(value)
+		return UDim2.new(value[1][1], value[1][2], value[2][1], value[2][2])
+	end,
+	NumberRange = function(value) return NumberRange.new(value[1], value[2]) end,
+	CFrame = function(value)
+		local rows = value.orientation
+		local x, y, z = rows.x or rows[1], rows.y or rows[2], rows.z or rows[3]
+		local position = value.position
+		return CFrame.new(
+			position[1], position[2], position[3],
+			x[1], x[2], x[3],
+			y[1], y[2], y[3],
+			z[1], z[2], z[3]
+		)
+	end,
+}
+
+local function decode(tagged)
+	local kind, value = next(tagged)
+	local decoder = decoders[kind]
+	if decoder == nil then
+		return nil, kind
+	end
+	return decoder(value), kind
+end
+
+-- Creates a node and its descendants. Properties are set afterwards, once
+-- every instance a Ref could point to exists.
+local function create(node, created, pending)
+	local ok, instance = pcall(Instance.new, node.className)
+	if not ok then
+		warn(("rbxbuild: can't create %s %q"):format(node.className, node.name))
+		return nil
+	end
+
+	instance.Name = node.name
+	created[node.referent] = instance
+	table.insert(pending, { instance = instance, properties = node.properties })
+
+	for _, child in ipairs(node.children) do
+		local childInstance = create(child, created, pending)
+		if childInstance then
+			childInstance.Parent = instance
+		end
+	end
+
+	return instance
+end
+
+local function applyProperties(pending, created)
+	local skipped = 0
+
+	for _, entry in ipairs(pending) do
+		local instance = entry.instance
+		for name, tagged in pairs(entry.properties) do
+			local kind, value = next(tagged)
+			local ok = true
+
+			if kind == "Ref" then
+				ok = pcall(function()
+					instance[name] = created[value]
+				end)
+			elseif kind == "Tags" then
+				for _, tag in ipairs(value) do
+					CollectionService:AddTag(instance, tag)
+				end
+			elseif kind == "Attributes" then
+				for attribute, attributeValue in pairs(value) do
+					local decoded = decode(attributeValue)
+					if decoded ~= nil then
+						instance:SetAttribute(attribute, decoded)
+					end
+				end
+			else
+				local decoded = decode(tagged)
+				ok = decoded ~= nil and pcall(function()
+					instance[name] = decoded
+				end)
+			end
+
+			if not ok then
+				skipped += 1
+			end
+		end
+	end
+
+	return skipped
+end
+
+local function removePrevious(name)
+	for _, container in ipairs(game:GetChildren()) do
+		-- Some services can't be read from plugins
+		pcall(function()
+			for _, child in ipairs(container:GetChildren()) do
+				if child:GetAttribute(MARKER) == name then
+					child:Destroy()
+				end
+			end
+		end)
+	end
+end
+
+local function insert(build)
+	local created, pending, roots = {}, {}, {}
+
+	for _, node in ipairs(build.instances) do
+		local service = game:FindService(node.className)
+		if service then
+			for _, child in ipairs(node.children) do
+				table.insert(roots, { node = child, parent = service })
+			end
+		else
+			table.insert(roots, { node = node, parent = workspace })
+		end
+	end
+
+	ChangeHistoryService:SetWaypoint("Before rbxbuild companion insert")
+	removePrevious(build.name)
+
+	for _, root in ipairs(roots) do
+		local instance = create(root.node, created, pending)
+		if instance then
+			instance:SetAttribute(MARKER, build.name)
+			instance.Parent = root.parent
+		end
+	end
+
+	local skipped = applyProperties(pending, created)
+	ChangeHistoryService:SetWaypoint("Inserted rbxbuild build " .. build.version)
+
+	if skipped > 0 then
+		warn(("rbxbuild: inserted build %d, skipping %d properties"):format(build.version, skipped))
+	else
+		print(("rbxbuild: inserted build %d"):format(build.version))
+	end
+end
+
+local function poll()
+	local ok, response = pcall(HttpService.GetAsync, HttpService, URL .. "/companion/version", true)
+	if not ok then
+		return
+	end
+
+	local version = HttpService:JSONDecode(response).version
+	if version == lastVersion then
+		return
+	end
+
+	local modelOk, model = pcall(HttpService.GetAsync, HttpService, URL .. "/companion/model", true)
+	if modelOk then
+		local build = HttpService:JSONDecode(model)
+		insert(build)
+		lastVersion = build.version
+	end
+end
+
+button.Click:Connect(function()
+	enabled = not enabled
+	button:SetActive(enabled)
+	lastVersion = nil
+end)
+
+plugin.Unloading:Connect(function()
+	running = false
+end)
+
+task.spawn(function()
+	while running do
+		if enabled then
+			local ok, err = pcall(poll)
+			if not ok then
+				warn("rbxbuild: " .. tostring(err))
+			end
+		end
+		task.wait(POLL_SECONDS)
+	end
+end)
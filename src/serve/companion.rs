@@ -0,0 +1,166 @@
+//! Serving the latest build to the bundled companion Studio plugin, which
+//! polls for new builds and inserts them into the open place, so generated
+//! content can be pulled into Studio without saving and opening files.
+//!
+//! - `GET /companion/version` returns `{"version", "name"}`. The version goes
+//!   up with every build, so polling it is cheap.
+//! - `GET /companion/model` also returns `instances`, the build's top-level
+//!   instances (a place's services) in the format `--emit dom-json` writes.
+//!
+//! [`plugin_source`] is the plugin itself, a single script for Studio's
+//! local Plugins folder.
+
+use std::sync::Mutex;
+
+use rbx_dom_weak::WeakDom;
+use serde_json::json;
+
+use super::http::{Request, Response};
+use crate::options::RootHandling;
+use crate::output;
+
+/// The port the plugin polls unless it was written for another one.
+pub const DEFAULT_PORT: u16 = 34873;
+
+const PLUGIN_SOURCE: &str = include_str!("companion.lua");
+
+/// The plugin's source, polling `port` on localhost.
+pub fn plugin_source(port: u16) -> String {
+    PLUGIN_SOURCE.replace(
+        &format!("localhost:{DEFAULT_PORT}"),
+        &format!("localhost:{port}"),
+    )
+}
+
+/// The latest build of a project, shared between the rebuilding thread and
+/// the server.
+pub struct Companion {
+    name: String,
+    build: Mutex<Build>,
+}
+
+struct Build {
+    version: u64,
+
+    /// The `/companion/model` response, serialized once per build rather
+    /// than once per poll.
+    model: Vec<u8>,
+}
+
+impl Companion {
+    pub fn new(name: impl Into<String>, dom: &WeakDom) -> Self {
+        let name = name.into();
+        let model = model_json(&name, 1, dom);
+
+        Companion {
+            name,
+            build: Mutex::new(Build { version: 1, model }),
+        }
+    }
+
+    /// Replaces the served build with a new one.
+    pub fn update(&self, dom: &WeakDom) {
+        let mut build = self.build.lock().unwrap_or_else(|error| error.into_inner());
+        build.version += 1;
+        build.model = model_json(&self.name, build.version, dom);
+    }
+
+    pub fn version(&self) -> u64 {
+        self.build
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .version
+    }
+
+    /// Answers a request from the plugin.
+    pub fn handle(&self, request: &Request) -> Response {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/companion/version") => Response::json(
+                200,
+                &json!({ "version": self.version(), "name": self.name }),
+            ),
+            ("GET", "/companion/model") => {
+                let build = self.build.lock().unwrap_or_else(|error| error.into_inner());
+                Response::new(200, "application/json", build.model.clone())
+            }
+            (_, "/companion/version" | "/companion/model") => Response::error(
+                405,
+                "method-not-allowed",
+                format!("{} isn't allowed on {}", request.method, request.path),
+            ),
+            _ => Response::error(404, "not-found", format!("No endpoint at {}", request.path)),
+        }
+    }
+}
+
+fn model_json(name: &str, version: u64, dom: &WeakDom) -> Vec<u8> {
+    let root = output::dom_to_json(dom);
+
+    // A place's services are inserted into the ones Studio already has, so
+    // only a model's root is sent as an instance of its own
+    let instances = if output::is_place(dom, RootHandling::Auto) {
+        root["children"].clone()
+    } else {
+        json!([root])
+    };
+
+    serde_json::to_vec(&json!({
+        "version": version,
+        "name": name,
+        "instances": instances,
+    }))
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use rbx_dom_weak::InstanceBuilder;
+
+    use super::*;
+
+    fn get(path: &str) -> Request {
+        Request {
+            method: "GET".to_owned(),
+            path: path.to_owned(),
+            query: Vec::new(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn model(companion: &Companion) -> serde_json::Value {
+        serde_json::from_slice(&companion.handle(&get("/companion/model")).body).unwrap()
+    }
+
+    #[test]
+    fn serves_builds() {
+        let place = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Workspace")
+                    .with_child(InstanceBuilder::new("Part").with_name("Rock")),
+            ),
+        );
+        let companion = Companion::new("Game", &place);
+
+        let served = model(&companion);
+        assert_eq!(served["version"], 1);
+        assert_eq!(served["instances"][0]["className"], "Workspace");
+        assert_eq!(served["instances"][0]["children"][0]["name"], "Rock");
+
+        let model_dom = WeakDom::new(InstanceBuilder::new("Model").with_name("Tree"));
+        companion.update(&model_dom);
+
+        let version = companion.handle(&get("/companion/version"));
+        let version: serde_json::Value = serde_json::from_slice(&version.body).unwrap();
+        assert_eq!(version, json!({ "version": 2, "name": "Game" }));
+        assert_eq!(model(&companion)["instances"][0]["name"], "Tree");
+
+        assert_eq!(companion.handle(&get("/nope")).status, 404);
+    }
+
+    #[test]
+    fn writes_the_plugin_for_a_port() {
+        assert!(plugin_source(DEFAULT_PORT).contains("http://localhost:34873"));
+        assert!(plugin_source(4000).contains("http://localhost:4000\""));
+    }
+}
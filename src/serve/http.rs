@@ -0,0 +1,344 @@
+//! Just enough HTTP/1.1 to answer one request per connection.
+//!
+//! Bodies need a `Content-Length`; chunked requests are turned away, which
+//! every client we care about copes with. Responses always close the
+//! connection.
+
+use std::io::{self, BufRead, Read, Write};
+
+use serde_json::Value;
+
+/// Longest request line or header line that's accepted.
+const MAX_LINE: usize = 8 * 1024;
+
+/// Most header lines a request can have.
+const MAX_HEADERS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+
+    /// The path without its query string, e.g. `/build`.
+    pub path: String,
+
+    /// Query parameters, percent-decoded, in order.
+    pub query: Vec<(String, String)>,
+
+    /// Headers in order, with names as they were sent.
+    pub headers: Vec<(String, String)>,
+
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The first header with this name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The first query parameter with this name.
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Response {
+            status,
+            headers: vec![("Content-Type".to_owned(), content_type.to_owned())],
+            body: body.into(),
+        }
+    }
+
+    pub fn json(status: u16, value: &Value) -> Self {
+        let mut body = serde_json::to_vec_pretty(value).unwrap_or_default();
+        body.push(b'\n');
+        Response::new(status, "application/json", body)
+    }
+
+    /// A JSON error body, `{"error": {"code": ..., "message": ...}}`, which
+    /// is what every failure returns.
+    pub fn error(status: u16, code: &str, message: impl Into<String>) -> Self {
+        Response::json(
+            status,
+            &serde_json::json!({
+                "error": { "code": code, "message": message.into() }
+            }),
+        )
+    }
+
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// The first header with this name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Why a request couldn't be read.
+#[derive(Debug)]
+pub enum ReadError {
+    /// The connection failed or closed early; there's nobody to answer.
+    Io(io::Error),
+    /// The request was malformed or unsupported, and this says why.
+    Rejected(Response),
+}
+
+impl From<io::Error> for ReadError {
+    fn from(error: io::Error) -> Self {
+        ReadError::Io(error)
+    }
+}
+
+/// Reads one request. Bodies over `max_body` bytes are rejected before
+/// they're read.
+pub fn read_request<R: BufRead>(mut reader: R, max_body: usize) -> Result<Request, ReadError> {
+    let request_line = read_line(&mut reader)?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(bad_request("Malformed request line"));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(ReadError::Rejected(Response::error(
+            505,
+            "unsupported-version",
+            format!("{version} isn't supported"),
+        )));
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(bad_request("Too many headers"));
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(bad_request(format!("Malformed header {line:?}")));
+        };
+        headers.push((name.trim().to_owned(), value.trim().to_owned()));
+    }
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, parse_query(query)),
+        None => (target, Vec::new()),
+    };
+
+    let mut request = Request {
+        method: method.to_owned(),
+        path: percent_decode(path),
+        query,
+        headers,
+        body: Vec::new(),
+    };
+
+    if request.header("Transfer-Encoding").is_some() {
+        return Err(ReadError::Rejected(Response::error(
+            411,
+            "length-required",
+            "Send the body with a Content-Length instead of chunked",
+        )));
+    }
+
+    let length = match request.header("Content-Length") {
+        Some(length) => length
+            .parse::<usize>()
+            .map_err(|_| bad_request(format!("Invalid Content-Length {length:?}")))?,
+        None => 0,
+    };
+    if length > max_body {
+        return Err(ReadError::Rejected(Response::error(
+            413,
+            "too-large",
+            format!("Bodies can be at most {max_body} bytes"),
+        )));
+    }
+
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+
+    Ok(request)
+}
+
+pub fn write_response<W: Write>(mut writer: W, response: &Response) -> io::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\n",
+        response.status,
+        reason(response.status)
+    )?;
+    for (name, value) in &response.headers {
+        write!(writer, "{name}: {value}\r\n")?;
+    }
+    write!(
+        writer,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    )?;
+    writer.write_all(&response.body)?;
+    writer.flush()
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, ReadError> {
+    let mut line = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE as u64 + 1)
+        .read_until(b'\n', &mut line)?;
+
+    if line.last() != Some(&b'\n') {
+        return Err(if line.len() > MAX_LINE {
+            bad_request("Line too long")
+        } else {
+            ReadError::Io(io::ErrorKind::UnexpectedEof.into())
+        });
+    }
+
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line).map_err(|_| bad_request("Request isn't valid UTF-8"))
+}
+
+fn bad_request(message: impl Into<String>) -> ReadError {
+    ReadError::Rejected(Response::error(400, "bad-request", message))
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&key.replace('+', " ")),
+                percent_decode(&value.replace('+', " ")),
+            )
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        500 => "Internal Server Error",
+        505 => "HTTP Version Not Supported",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_requests() {
+        let raw = "POST /build?format=binary&name=My%20Game+2 HTTP/1.1\r\n\
+                   Host: localhost\r\n\
+                   content-length: 4\r\n\
+                   \r\n\
+                   {}\r\nextra";
+        let request = read_request(raw.as_bytes(), 1024).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/build");
+        assert_eq!(request.query("format"), Some("binary"));
+        assert_eq!(request.query("name"), Some("My Game 2"));
+        assert_eq!(request.header("Content-Length"), Some("4"));
+        assert_eq!(request.body, b"{}\r\n");
+    }
+
+    #[test]
+    fn rejects_bad_requests() {
+        let status = |raw: &str| match read_request(raw.as_bytes(), 8) {
+            Err(ReadError::Rejected(response)) => response.status,
+            other => panic!("expected a rejection, got {other:?}"),
+        };
+
+        assert_eq!(status("GET /\r\n\r\n"), 400);
+        assert_eq!(status("GET / HTTP/2\r\n\r\n"), 505);
+        assert_eq!(status("POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\n"), 413);
+        assert_eq!(
+            status("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            411
+        );
+
+        assert!(matches!(
+            read_request("GET / HTTP/1.1\r\nHost: x".as_bytes(), 8),
+            Err(ReadError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn writes_responses() {
+        let mut written = Vec::new();
+        let response = Response::new(200, "text/plain", "hi").with_header("X-Test", "1");
+        write_response(&mut written, &response).unwrap();
+
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nX-Test: 1\r\n\
+             Content-Length: 2\r\nConnection: close\r\n\r\nhi"
+        );
+    }
+}
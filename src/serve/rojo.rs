@@ -0,0 +1,453 @@
+//! Enough of Rojo's serve protocol for its Studio plugin to connect and keep
+//! a place in sync with a build.
+//!
+//! The plugin asks for server info at `/api/rojo`, reads the whole tree with
+//! `/api/read/{root}`, then long-polls `/api/subscribe/{cursor}` for patches.
+//! [`LiveSession::update`] turns each rebuild into a patch of added, removed
+//! and updated instances. Writing changes back from Studio isn't supported.
+//!
+//! Instance ids are derived from each instance's path and class, so the same
+//! instance has the same id in every build and a rebuild only sends what
+//! actually changed.
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Condvar, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use rbx_dom_weak::{types::Variant, WeakDom};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::http::{Request, Response};
+use crate::diff::keyed;
+
+/// The version of Rojo's protocol that's spoken.
+pub const PROTOCOL_VERSION: u32 = 4;
+
+/// How long a subscribe request waits for a change before returning nothing.
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What Rojo sends for a parent or `Ref` that points at nothing.
+const NULL_ID: &str = "00000000000000000000000000000000";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct WireInstance {
+    id: String,
+    parent: String,
+    name: String,
+    class_name: String,
+    properties: BTreeMap<String, Value>,
+    children: Vec<String>,
+    metadata: Metadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Metadata {
+    /// Always set, so the plugin leaves alone whatever Studio added, such as
+    /// Terrain and the Camera.
+    ignore_unknown_instances: bool,
+}
+
+/// One set of changes, sent to subscribers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Message {
+    removed: Vec<String>,
+    added: BTreeMap<String, WireInstance>,
+    updated: Vec<Update>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Update {
+    id: String,
+    changed_name: Option<String>,
+    changed_class_name: Option<String>,
+    /// Removed properties are null.
+    changed_properties: BTreeMap<String, Value>,
+    changed_metadata: Option<Metadata>,
+}
+
+/// A built tree in the shape the plugin reads it.
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    root: String,
+    instances: HashMap<String, WireInstance>,
+}
+
+struct State {
+    snapshot: Snapshot,
+    messages: Vec<Message>,
+}
+
+/// The tree being served and every patch sent since the session started.
+pub struct LiveSession {
+    session_id: String,
+    project_name: String,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl LiveSession {
+    pub fn new(project_name: impl Into<String>, dom: &WeakDom) -> Self {
+        LiveSession {
+            session_id: session_id(),
+            project_name: project_name.into(),
+            state: Mutex::new(State {
+                snapshot: snapshot(dom),
+                messages: Vec::new(),
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Replaces the served tree with a rebuild and sends subscribers what
+    /// changed. Returns whether anything did.
+    pub fn update(&self, dom: &WeakDom) -> bool {
+        let new = snapshot(dom);
+
+        let mut state = self.state.lock().unwrap();
+        let message = patch(&state.snapshot, &new);
+        state.snapshot = new;
+
+        if message == Message::default() {
+            return false;
+        }
+        state.messages.push(message);
+        self.changed.notify_all();
+        true
+    }
+
+    /// Answers one of the plugin's requests.
+    pub fn handle(&self, request: &Request) -> Response {
+        let path = request.path.trim_end_matches('/');
+
+        if request.method != "GET" {
+            return Response::error(
+                405,
+                "method-not-allowed",
+                "Only reading the tree is supported",
+            );
+        }
+
+        if path == "/api/rojo" {
+            let state = self.state.lock().unwrap();
+            return Response::json(
+                200,
+                &json!({
+                    "sessionId": self.session_id,
+                    "serverVersion": env!("CARGO_PKG_VERSION"),
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "projectName": self.project_name,
+                    "expectedPlaceIds": null,
+                    "unexpectedPlaceIds": null,
+                    "gameId": null,
+                    "placeId": null,
+                    "rootInstanceId": state.snapshot.root,
+                }),
+            );
+        }
+
+        if let Some(ids) = path.strip_prefix("/api/read/") {
+            return self.read(ids.split(',').filter(|id| !id.is_empty()));
+        }
+
+        if let Some(cursor) = path.strip_prefix("/api/subscribe/") {
+            return match cursor.parse() {
+                Ok(cursor) => self.subscribe(cursor, SUBSCRIBE_TIMEOUT),
+                Err(_) => Response::error(400, "bad-request", "Invalid message cursor"),
+            };
+        }
+
+        Response::error(404, "not-found", format!("No endpoint at {path}"))
+    }
+
+    fn read<'a>(&self, ids: impl Iterator<Item = &'a str>) -> Response {
+        let state = self.state.lock().unwrap();
+        let instances = &state.snapshot.instances;
+
+        // Each requested instance comes with all of its descendants
+        let mut found = BTreeMap::new();
+        let mut stack: Vec<&str> = ids.collect();
+        while let Some(id) = stack.pop() {
+            if let Some(instance) = instances.get(id) {
+                stack.extend(instance.children.iter().map(String::as_str));
+                found.insert(id, instance);
+            }
+        }
+
+        Response::json(
+            200,
+            &json!({
+                "sessionId": self.session_id,
+                "messageCursor": state.messages.len(),
+                "instances": found,
+            }),
+        )
+    }
+
+    fn subscribe(&self, cursor: usize, timeout: Duration) -> Response {
+        let state = self.state.lock().unwrap();
+        let (state, _) = self
+            .changed
+            .wait_timeout_while(state, timeout, |state| state.messages.len() <= cursor)
+            .unwrap();
+
+        let messages = state.messages.get(cursor..).unwrap_or_default();
+        Response::json(
+            200,
+            &json!({
+                "sessionId": self.session_id,
+                "messageCursor": state.messages.len(),
+                "messages": messages,
+            }),
+        )
+    }
+}
+
+fn snapshot(dom: &WeakDom) -> Snapshot {
+    // Every id is needed before any properties, since Refs can point anywhere
+    let mut paths = vec![(dom.root_ref(), String::new(), NULL_ID.to_owned())];
+    let mut ids = HashMap::new();
+
+    let mut index = 0;
+    while let Some((referent, path, _)) = paths.get(index) {
+        let instance = dom.get_by_ref(*referent).unwrap();
+        let id = instance_id(path, &instance.class);
+        let path = path.clone();
+        ids.insert(*referent, id.clone());
+
+        for (segment, child) in keyed(dom, instance.children()) {
+            paths.push((child, format!("{path}/{segment}"), id.clone()));
+        }
+        index += 1;
+    }
+
+    let instances = paths
+        .into_iter()
+        .map(|(referent, _, parent)| {
+            let instance = dom.get_by_ref(referent).unwrap();
+            let properties = instance
+                .properties
+                .iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        Variant::Ref(target) => {
+                            json!({ "Ref": ids.get(target).map_or(NULL_ID, String::as_str) })
+                        }
+                        other => serde_json::to_value(other).unwrap_or_default(),
+                    };
+                    (name.to_string(), value)
+                })
+                .collect();
+
+            let id = ids[&referent].clone();
+            let wire = WireInstance {
+                id: id.clone(),
+                parent,
+                name: instance.name.clone(),
+                class_name: instance.class.to_string(),
+                properties,
+                children: instance
+                    .children()
+                    .iter()
+                    .map(|child| ids[child].clone())
+                    .collect(),
+                metadata: Metadata {
+                    ignore_unknown_instances: true,
+                },
+            };
+            (id, wire)
+        })
+        .collect();
+
+    Snapshot {
+        root: ids[&dom.root_ref()].clone(),
+        instances,
+    }
+}
+
+/// What changed between two snapshots. A class change is a different id,
+/// and so a removal and an addition.
+fn patch(old: &Snapshot, new: &Snapshot) -> Message {
+    let mut message = Message::default();
+
+    // Only the topmost removed instance of each subtree needs to be sent
+    let removed: BTreeSet<&str> = old
+        .instances
+        .keys()
+        .map(String::as_str)
+        .filter(|id| !new.instances.contains_key(*id))
+        .collect();
+    message.removed = removed
+        .iter()
+        .filter(|&&id| !removed.contains(old.instances[id].parent.as_str()))
+        .map(|&id| id.to_owned())
+        .collect();
+
+    for (id, instance) in &new.instances {
+        let Some(previous) = old.instances.get(id) else {
+            message.added.insert(id.clone(), instance.clone());
+            continue;
+        };
+
+        let mut changed_properties = BTreeMap::new();
+        for (name, value) in &instance.properties {
+            if previous.properties.get(name) != Some(value) {
+                changed_properties.insert(name.clone(), value.clone());
+            }
+        }
+        for name in previous.properties.keys() {
+            if !instance.properties.contains_key(name) {
+                changed_properties.insert(name.clone(), Value::Null);
+            }
+        }
+
+        if !changed_properties.is_empty() {
+            message.updated.push(Update {
+                id: id.clone(),
+                changed_name: None,
+                changed_class_name: None,
+                changed_properties,
+                changed_metadata: None,
+            });
+        }
+    }
+
+    message.updated.sort_by(|a, b| a.id.cmp(&b.id));
+    message
+}
+
+/// A 128-bit id in the same hex form as Rojo's, from an instance's path and
+/// class.
+fn instance_id(path: &str, class: &str) -> String {
+    let half = |seed: u8| {
+        let mut hasher = DefaultHasher::new();
+        (seed, path, class).hash(&mut hasher);
+        hasher.finish()
+    };
+    format!("{:016x}{:016x}", half(0), half(1))
+}
+
+/// A UUID-shaped id that's different for every run.
+fn session_id() -> String {
+    let mut hasher = DefaultHasher::new();
+    SystemTime::now().hash(&mut hasher);
+    std::process::id().hash(&mut hasher);
+    let high = hasher.finish();
+    high.hash(&mut hasher);
+    let low = hasher.finish();
+
+    let hex = format!("{high:016x}{low:016x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn build(anchored: bool, with_door: bool) -> WeakDom {
+        let mut house = InstanceBuilder::new("Model").with_name("House");
+        if with_door {
+            house = house.with_child(
+                InstanceBuilder::new("Part")
+                    .with_name("Door")
+                    .with_child(InstanceBuilder::new("Decal").with_name("Handle")),
+            );
+        }
+
+        WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_name("Game")
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_name("Workspace")
+                        .with_child(
+                            InstanceBuilder::new("Part")
+                                .with_name("Baseplate")
+                                .with_property("Anchored", anchored),
+                        )
+                        .with_child(house),
+                ),
+        )
+    }
+
+    fn get(session: &LiveSession, path: &str) -> Value {
+        let request = Request {
+            method: "GET".to_owned(),
+            path: path.to_owned(),
+            query: Vec::new(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        };
+        let response = session.handle(&request);
+        assert_eq!(response.status, 200);
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn ids_are_stable() {
+        let first = snapshot(&build(true, true));
+        let second = snapshot(&build(true, true));
+        assert_eq!(first, second);
+        assert_eq!(first.root.len(), 32);
+        assert_eq!(patch(&first, &second), Message::default());
+    }
+
+    #[test]
+    fn patches_changes() {
+        let old = snapshot(&build(true, true));
+        let new = snapshot(&build(false, false));
+        let message = patch(&old, &new);
+
+        let door = instance_id("/Workspace/House/Door", "Part");
+        let baseplate = instance_id("/Workspace/Baseplate", "Part");
+        assert_eq!(message.removed, [door]);
+        assert!(message.added.is_empty());
+        assert_eq!(message.updated.len(), 1);
+        assert_eq!(message.updated[0].id, baseplate);
+        assert_eq!(
+            message.updated[0].changed_properties["Anchored"],
+            json!({ "Bool": false })
+        );
+
+        // Adding it back sends the whole subtree
+        let message = patch(&new, &old);
+        assert_eq!(message.added.len(), 2);
+    }
+
+    #[test]
+    fn serves_the_protocol() {
+        let session = LiveSession::new("Game", &build(true, true));
+
+        let info = get(&session, "/api/rojo");
+        assert_eq!(info["protocolVersion"], PROTOCOL_VERSION);
+        let root = info["rootInstanceId"].as_str().unwrap().to_owned();
+
+        let read = get(&session, &format!("/api/read/{root}"));
+        assert_eq!(read["messageCursor"], 0);
+        assert_eq!(read["instances"].as_object().unwrap().len(), 6);
+        assert_eq!(read["instances"][&root]["ClassName"], "DataModel");
+
+        assert!(session.update(&build(false, true)));
+        assert!(!session.update(&build(false, true)));
+
+        let subscribe = get(&session, "/api/subscribe/0");
+        assert_eq!(subscribe["messageCursor"], 1);
+        assert_eq!(subscribe["messages"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,91 @@
+//! Polling a project's directory for edits, for servers that rebuild as
+//! files change.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+/// A hash of the path, size and modification time of every file under
+/// `dir`, which changes whenever a file is added, removed or saved. Hidden
+/// files and directories, such as `.git`, are skipped.
+pub fn fingerprint(dir: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let mut stack = vec![dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        // Sorted so the hash doesn't depend on the order the OS lists them in
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                stack.push(entry.path());
+            } else {
+                entry.path().hash(&mut hasher);
+                metadata.len().hash(&mut hasher);
+                metadata.modified().ok().hash(&mut hasher);
+            }
+        }
+    }
+
+    hasher.finish()
+}
+
+/// Calls `on_change` every time [`fingerprint`] of `dir` changes, checking
+/// every `interval`. Files written by `on_change` itself, such as a build's
+/// output, don't count as changes. Never returns.
+pub fn watch(dir: &Path, interval: Duration, mut on_change: impl FnMut()) {
+    let mut last = fingerprint(dir);
+
+    loop {
+        thread::sleep(interval);
+
+        if fingerprint(dir) != last {
+            on_change();
+            last = fingerprint(dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fingerprint_follows_edits() {
+        let dir = std::env::temp_dir().join(format!("rbxbuild-watch-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.luau"), "print(1)").unwrap();
+
+        let before = fingerprint(&dir);
+        assert_eq!(fingerprint(&dir), before);
+
+        fs::write(dir.join(".hidden"), "ignored").unwrap();
+        assert_eq!(fingerprint(&dir), before);
+
+        fs::write(dir.join("src/main.luau"), "print(12)").unwrap();
+        let edited = fingerprint(&dir);
+        assert_ne!(edited, before);
+
+        fs::write(dir.join("src/other.luau"), "").unwrap();
+        assert_ne!(fingerprint(&dir), edited);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
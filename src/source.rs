@@ -0,0 +1,424 @@
+//! Finding your way around a project file's text, which is usually invalid
+//! JSON while it's being typed. The scanner never fails: it skips what it
+//! doesn't understand and keeps track of which key each token belongs to.
+//!
+//! The language server uses it to find its way around open
+//! documents, and [editor diagnostics](crate::diagnostics::EditorFormat) to
+//! point at the key each diagnostic is about.
+
+use std::ops::Range;
+
+use crate::diagnostics::Diagnostic;
+
+/// A key in the document and what it was set to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    /// The keys from the document's root down to this one, e.g. `["tree",
+    /// "Workspace", "$properties", "Gravity"]`.
+    pub path: Vec<String>,
+
+    /// Byte range of the key, including its quotes.
+    pub span: Range<usize>,
+
+    /// Byte range of the value's first token, if there is one.
+    pub value: Option<Range<usize>>,
+
+    /// The value, if it's a string.
+    pub string: Option<String>,
+}
+
+/// What's at a position in the document, for completion.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Context {
+    /// An object's key, in the object at `path`. `span` is the string being
+    /// typed, or empty if there isn't one yet.
+    Key {
+        path: Vec<String>,
+        span: Range<usize>,
+    },
+
+    /// The value of the key at `path`.
+    Value {
+        path: Vec<String>,
+        span: Range<usize>,
+    },
+
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    OpenObject,
+    CloseObject,
+    OpenArray,
+    CloseArray,
+    Colon,
+    Comma,
+    String(String),
+    /// Numbers, `true`, `false`, `null`, and anything else that's unquoted.
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Key,
+    Colon,
+    Value,
+    Comma,
+}
+
+#[derive(Debug)]
+enum Frame {
+    Object { key: Option<String>, expect: Expect },
+    Array,
+}
+
+/// Splits the text into tokens with their byte ranges. Unterminated strings
+/// end at the end of their line.
+fn tokenize(text: &str) -> Vec<(Token, Range<usize>)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let token = match bytes[i] {
+            b'{' => Token::OpenObject,
+            b'}' => Token::CloseObject,
+            b'[' => Token::OpenArray,
+            b']' => Token::CloseArray,
+            b':' => Token::Colon,
+            b',' => Token::Comma,
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' && bytes[i] != b'\n' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                let end = i.min(bytes.len());
+                let raw = &text[start + 1..end];
+                // Step over the closing quote, if there is one
+                if i < bytes.len() && bytes[i] == b'"' {
+                    i += 1;
+                }
+                tokens.push((Token::String(unescape(raw)), start..i.min(bytes.len())));
+                continue;
+            }
+            byte if byte.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            _ => {
+                while i < bytes.len() && !b"{}[]:,\" \t\r\n".contains(&bytes[i]) {
+                    i += 1;
+                }
+                tokens.push((Token::Other, start..i));
+                continue;
+            }
+        };
+        i += 1;
+        tokens.push((token, start..i));
+    }
+
+    tokens
+}
+
+fn unescape(raw: &str) -> String {
+    serde_json::from_str(&format!("\"{raw}\"")).unwrap_or_else(|_| raw.to_owned())
+}
+
+struct Scanner {
+    stack: Vec<Frame>,
+    keys: Vec<Key>,
+}
+
+impl Scanner {
+    fn new() -> Self {
+        Scanner {
+            stack: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// The keys leading to the innermost object or array.
+    fn path(&self) -> Vec<String> {
+        let enclosing = self.stack.len().saturating_sub(1);
+        self.stack[..enclosing]
+            .iter()
+            .filter_map(|frame| match frame {
+                Frame::Object { key, .. } => key.clone(),
+                Frame::Array => None,
+            })
+            .collect()
+    }
+
+    fn expect(&self) -> Option<Expect> {
+        match self.stack.last() {
+            Some(Frame::Object { expect, .. }) => Some(*expect),
+            _ => None,
+        }
+    }
+
+    fn set_expect(&mut self, next: Expect) {
+        if let Some(Frame::Object { expect, .. }) = self.stack.last_mut() {
+            *expect = next;
+        }
+    }
+
+    /// Notes that a value starting at `span` was given for the current key.
+    fn value(&mut self, span: Range<usize>, string: Option<String>) {
+        if self.expect() == Some(Expect::Value) {
+            if let Some(key) = self.keys.last_mut() {
+                key.value = Some(span);
+                key.string = string;
+            }
+            self.set_expect(Expect::Comma);
+        }
+    }
+
+    fn step(&mut self, token: &Token, span: Range<usize>) {
+        match token {
+            Token::OpenObject => {
+                self.value(span, None);
+                self.stack.push(Frame::Object {
+                    key: None,
+                    expect: Expect::Key,
+                });
+            }
+            Token::OpenArray => {
+                self.value(span, None);
+                self.stack.push(Frame::Array);
+            }
+            Token::CloseObject | Token::CloseArray => {
+                self.stack.pop();
+            }
+            Token::Colon => {
+                if self.expect() == Some(Expect::Colon) {
+                    self.set_expect(Expect::Value);
+                }
+            }
+            Token::Comma => {
+                if let Some(Frame::Object { key, expect }) = self.stack.last_mut() {
+                    *key = None;
+                    *expect = Expect::Key;
+                }
+            }
+            Token::String(string) if self.expect() == Some(Expect::Key) => {
+                let mut path = self.path();
+                path.push(string.clone());
+                self.keys.push(Key {
+                    path,
+                    span,
+                    value: None,
+                    string: None,
+                });
+                if let Some(Frame::Object { key, expect }) = self.stack.last_mut() {
+                    *key = Some(string.clone());
+                    *expect = Expect::Colon;
+                }
+            }
+            Token::String(string) => self.value(span, Some(string.clone())),
+            Token::Other => self.value(span, None),
+        }
+    }
+
+    /// What a token at the scanner's current position would be.
+    fn context(&self, span: Range<usize>) -> Context {
+        match self.stack.last() {
+            Some(Frame::Object {
+                expect: Expect::Key,
+                ..
+            }) => Context::Key {
+                path: self.path(),
+                span,
+            },
+            Some(Frame::Object {
+                key: Some(key),
+                expect: Expect::Value,
+            }) => {
+                let mut path = self.path();
+                path.push(key.clone());
+                Context::Value { path, span }
+            }
+            _ => Context::None,
+        }
+    }
+}
+
+/// Every key in the document, in order.
+pub fn keys(text: &str) -> Vec<Key> {
+    let mut scanner = Scanner::new();
+    for (token, span) in tokenize(text) {
+        scanner.step(&token, span);
+    }
+    scanner.keys
+}
+
+/// What the byte `offset` is in, or in front of.
+pub fn context_at(text: &str, offset: usize) -> Context {
+    let mut scanner = Scanner::new();
+
+    for (token, span) in tokenize(text) {
+        if span.start >= offset {
+            break;
+        }
+        // Inside a string, or right at the end of an unterminated one
+        let inside = offset < span.end || offset == span.end && !closed(text, &span);
+        if matches!(token, Token::String(_)) && inside {
+            return scanner.context(span);
+        }
+        scanner.step(&token, span);
+    }
+
+    scanner.context(offset..offset)
+}
+
+fn closed(text: &str, span: &Range<usize>) -> bool {
+    span.len() >= 2 && text.as_bytes()[span.end - 1] == b'"'
+}
+
+/// The key whose name or value is at `offset`.
+pub fn key_at(keys: &[Key], offset: usize) -> Option<(&Key, bool)> {
+    keys.iter().find_map(|key| {
+        if key.span.contains(&offset) {
+            Some((key, false))
+        } else if key
+            .value
+            .as_ref()
+            .is_some_and(|value| value.contains(&offset))
+        {
+            Some((key, true))
+        } else {
+            None
+        }
+    })
+}
+
+/// Finds the key a diagnostic is about: its property in `$properties` if it
+/// has one and it's there, or else its node. The diagnostic's path starts
+/// with the root's name, which is `tree` in the document.
+pub fn locate(keys: &[Key], diagnostic: &Diagnostic) -> Option<Range<usize>> {
+    let mut node = vec!["tree".to_owned()];
+    node.extend(diagnostic.path.split('/').skip(1).map(str::to_owned));
+
+    let find = |path: &[String]| keys.iter().find(|key| key.path == path);
+
+    let property_key = diagnostic.property.as_ref().and_then(|property| {
+        let mut path = node.clone();
+        path.push("$properties".to_owned());
+        path.push(property.clone());
+        find(&path)
+    });
+
+    property_key
+        .or_else(|| find(&node))
+        .map(|key| key.span.clone())
+}
+
+/// Converts a byte offset to an LSP position, counting UTF-16 code units.
+pub fn position(text: &str, offset: usize) -> (u32, u32) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let character = before[line_start..].encode_utf16().count();
+    (line as u32, character as u32)
+}
+
+/// Converts an LSP position to a byte offset, clamped to the line's end.
+pub fn offset(text: &str, line: u32, character: u32) -> usize {
+    let mut start = 0;
+    for _ in 0..line {
+        match text[start..].find('\n') {
+            Some(index) => start += index + 1,
+            None => return text.len(),
+        }
+    }
+
+    let mut units = 0;
+    for (index, char) in text[start..].char_indices() {
+        if units >= character as usize || char == '\n' {
+            return start + index;
+        }
+        units += char.len_utf16();
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PROJECT: &str = r#"{
+  "name": "Game",
+  "tree": {
+    "$className": "DataModel",
+    "Workspace": {
+      "$properties": { "Gravity": 100, "Name": "World" },
+      "Rock": { "$className": "Part" }
+    }
+  }
+}"#;
+
+    #[test]
+    fn finds_keys() {
+        let keys = keys(PROJECT);
+        let paths: Vec<String> = keys.iter().map(|key| key.path.join("/")).collect();
+
+        assert_eq!(
+            paths,
+            [
+                "name",
+                "tree",
+                "tree/$className",
+                "tree/Workspace",
+                "tree/Workspace/$properties",
+                "tree/Workspace/$properties/Gravity",
+                "tree/Workspace/$properties/Name",
+                "tree/Workspace/Rock",
+                "tree/Workspace/Rock/$className",
+            ]
+        );
+        assert_eq!(keys[8].string.as_deref(), Some("Part"));
+        assert_eq!(&PROJECT[keys[5].value.clone().unwrap()], "100");
+    }
+
+    #[test]
+    fn finds_the_context_while_typing() {
+        let text = r#"{ "tree": { "$className": "Pa"#;
+        assert_eq!(
+            context_at(text, text.len()),
+            Context::Value {
+                path: vec!["tree".into(), "$className".into()],
+                span: 26..29,
+            }
+        );
+
+        let text = r#"{ "tree": { "$properties": { "#;
+        assert_eq!(
+            context_at(text, text.len()),
+            Context::Key {
+                path: vec!["tree".into(), "$properties".into()],
+                span: text.len()..text.len(),
+            }
+        );
+
+        let text = r#"{ "tree": { "Rock": {} }, "na" }"#;
+        assert_eq!(
+            context_at(text, 28),
+            Context::Key {
+                path: vec![],
+                span: 26..30,
+            }
+        );
+    }
+
+    #[test]
+    fn converts_positions() {
+        let text = "{\n  \"é😀\": 1\n}";
+        let one = text.find('1').unwrap();
+
+        assert_eq!(position(text, one), (1, 9));
+        assert_eq!(offset(text, 1, 9), one);
+        assert_eq!(offset(text, 1, 99), one + 1);
+        assert_eq!(offset(text, 9, 0), text.len());
+    }
+}
@@ -0,0 +1,161 @@
+//! Breaking a large project into one file per top-level instance.
+//!
+//! Each top-level instance with children (the services of a place, usually)
+//! is moved into its own `<Name>.project.json` fragment, and the root file
+//! refers to it with `$path`, the way a Rojo project refers to a nested
+//! project. [`migrate`](crate::migrate::migrate) puts the pieces back
+//! together, so a generated project can be split for review and joined again
+//! before building.
+
+use std::collections::HashSet;
+
+use serde_json::{json, Value};
+
+use crate::error::Result;
+use crate::project::Project;
+
+/// A file written by [`split`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragment {
+    /// The file name, relative to the directory the root file is in.
+    pub file_name: String,
+
+    /// The project JSON to write, with every map sorted.
+    pub contents: Value,
+}
+
+/// Splits a project into a root file called `root_name` followed by a
+/// fragment for each top-level instance that has children.
+pub fn split(project: &Project, root_name: &str) -> Result<Vec<Fragment>> {
+    let mut root = serde_json::to_value(project)?;
+    let mut fragments = Vec::new();
+    let mut used = HashSet::from([root_name.to_lowercase()]);
+
+    if let Some(Value::Object(tree)) = root.get_mut("tree") {
+        for (key, node) in tree.iter_mut() {
+            if key.starts_with('$') || !has_children(node) {
+                continue;
+            }
+
+            let file_name = unique_file_name(key, "project.json", &mut used);
+            let tree = std::mem::replace(node, json!({ "$path": file_name }));
+
+            fragments.push(Fragment {
+                file_name,
+                contents: json!({ "name": key, "tree": tree }),
+            });
+        }
+    }
+
+    fragments.insert(
+        0,
+        Fragment {
+            file_name: root_name.to_owned(),
+            contents: root,
+        },
+    );
+    Ok(fragments)
+}
+
+fn has_children(node: &Value) -> bool {
+    node.as_object()
+        .is_some_and(|node| node.keys().any(|key| !key.starts_with('$')))
+}
+
+/// Instance names can hold characters that aren't allowed in file names, and
+/// two names can map to the same file name once those are replaced.
+pub(crate) fn unique_file_name(name: &str, extension: &str, used: &mut HashSet<String>) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let stem = match stem.trim_matches(['.', ' ']) {
+        "" => "_",
+        trimmed => trimmed,
+    };
+
+    let mut file_name = format!("{stem}.{extension}");
+    let mut count = 1;
+    while !used.insert(file_name.to_lowercase()) {
+        count += 1;
+        file_name = format!("{stem} ({count}).{extension}");
+    }
+    file_name
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::diagnostics::Diagnostic;
+    use crate::migrate::migrate;
+
+    #[test]
+    fn splits_and_joins_again() {
+        let project = Project::from_json(
+            r#"{
+                "name": "Game",
+                "tree": {
+                    "$className": "DataModel",
+                    "Lighting": { "$properties": { "ClockTime": 14 } },
+                    "Workspace": {
+                        "Baseplate": { "$className": "Part", "$properties": { "Anchored": true } }
+                    },
+                    "ReplicatedStorage": {
+                        "Shared": { "$className": "Folder" }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let fragments = split(&project, "default.project.json").unwrap();
+        let names: Vec<_> = fragments.iter().map(|f| f.file_name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "default.project.json",
+                "ReplicatedStorage.project.json",
+                "Workspace.project.json"
+            ]
+        );
+
+        let root = &fragments[0].contents["tree"];
+        assert_eq!(
+            root["Workspace"],
+            json!({ "$path": "Workspace.project.json" })
+        );
+        assert_eq!(root["Lighting"]["$properties"]["ClockTime"], 14.0);
+
+        let dir = std::env::temp_dir().join(format!("rbxbuild-split-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for fragment in &fragments {
+            std::fs::write(dir.join(&fragment.file_name), fragment.contents.to_string()).unwrap();
+        }
+
+        let joined = migrate(&dir.join("default.project.json"), &mut |_: Diagnostic| {});
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(joined.unwrap(), project);
+    }
+
+    #[test]
+    fn file_names_are_safe_and_unique() {
+        let mut used = HashSet::new();
+        assert_eq!(
+            unique_file_name("A/B", "project.json", &mut used),
+            "A_B.project.json"
+        );
+        assert_eq!(
+            unique_file_name("A:B", "project.json", &mut used),
+            "A_B (2).project.json"
+        );
+        assert_eq!(
+            unique_file_name("..", "project.json", &mut used),
+            "_.project.json"
+        );
+    }
+}
@@ -0,0 +1,448 @@
+//! Instance counts and sizes of a built DOM, for tracking how a place grows.
+//!
+//! [`collect`] gives totals; [`size_report`] ranks the properties, scripts and
+//! subtrees that take up the most space, for finding what's bloating a file.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+use serde::Serialize;
+use ustr::Ustr;
+
+use crate::options::RootHandling;
+use crate::output::{serialized_size, top_level_refs, OutputFormat};
+use crate::query::{descendants, path_of};
+
+/// Counts for a DOM as a whole, returned by [`collect`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    /// Every instance that would be written, not counting a DataModel root.
+    pub instances: usize,
+
+    /// Instances with a `Source` property.
+    pub scripts: usize,
+
+    /// Total length of every script's `Source`.
+    pub source_bytes: usize,
+
+    /// Size of the whole file as XML, if the `xml` feature is enabled.
+    pub xml_bytes: Option<u64>,
+
+    /// Size of the whole file in the binary format, if the `binary` feature
+    /// is enabled.
+    pub binary_bytes: Option<u64>,
+
+    /// How many instances there are of each class.
+    pub classes: BTreeMap<String, usize>,
+
+    /// The same counts for each top-level instance (each service of a place)
+    /// and its descendants, in order.
+    pub top_level: Vec<TopLevelStats>,
+}
+
+/// Counts for one top-level instance and its descendants.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopLevelStats {
+    pub name: String,
+    pub class: String,
+    pub instances: usize,
+    pub scripts: usize,
+    pub source_bytes: usize,
+
+    /// Size of this subtree on its own in the binary format, if the `binary`
+    /// feature is enabled.
+    pub binary_bytes: Option<u64>,
+}
+
+/// Counts the instances, classes and scripts of a DOM and measures how large
+/// it is when serialized. `root` decides what's at the top level, like when
+/// writing the DOM.
+pub fn collect(dom: &WeakDom, root: RootHandling) -> Stats {
+    let source = Ustr::from("Source");
+    let refs = top_level_refs(dom, root);
+
+    let mut stats = Stats {
+        xml_bytes: serialized_size(dom, &refs, OutputFormat::Xml).ok(),
+        binary_bytes: serialized_size(dom, &refs, OutputFormat::Binary).ok(),
+        ..Default::default()
+    };
+
+    for &referent in &refs {
+        let instance = dom.get_by_ref(referent).unwrap();
+        let mut top_level = TopLevelStats {
+            name: instance.name.clone(),
+            class: instance.class.to_string(),
+            instances: 0,
+            scripts: 0,
+            source_bytes: 0,
+            binary_bytes: serialized_size(dom, &[referent], OutputFormat::Binary).ok(),
+        };
+
+        for instance in descendants(dom, referent) {
+            top_level.instances += 1;
+            *stats.classes.entry(instance.class.to_string()).or_default() += 1;
+
+            if let Some(Variant::String(text)) = instance.properties.get(&source) {
+                top_level.scripts += 1;
+                top_level.source_bytes += text.len();
+            }
+        }
+
+        stats.instances += top_level.instances;
+        stats.scripts += top_level.scripts;
+        stats.source_bytes += top_level.source_bytes;
+        stats.top_level.push(top_level);
+    }
+
+    stats
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instances: {}", self.instances)?;
+        writeln!(
+            f,
+            "Scripts:   {} ({} of source)",
+            self.scripts,
+            Bytes(self.source_bytes as u64)
+        )?;
+
+        let sizes: Vec<_> = [("XML", self.xml_bytes), ("binary", self.binary_bytes)]
+            .into_iter()
+            .filter_map(|(format, bytes)| Some(format!("{} as {format}", Bytes(bytes?))))
+            .collect();
+        if !sizes.is_empty() {
+            writeln!(f, "Size:      {}", sizes.join(", "))?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Top level:")?;
+        for top_level in &self.top_level {
+            write!(
+                f,
+                "  {:<24} {:>8} instances {:>6} scripts",
+                top_level.name, top_level.instances, top_level.scripts
+            )?;
+            if let Some(bytes) = top_level.binary_bytes {
+                write!(f, " {:>10}", Bytes(bytes).to_string())?;
+            }
+            writeln!(f)?;
+        }
+
+        // Most common classes first
+        let mut classes: Vec<_> = self.classes.iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        writeln!(f)?;
+        writeln!(f, "Classes:")?;
+        for (class, count) in classes {
+            writeln!(f, "  {class:<24} {count:>8}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The largest contributors to a DOM's size, from [`size_report`].
+///
+/// Sizes are estimates of how many bytes each value takes up in the binary
+/// format before compression, so they're good for ranking but won't add up
+/// to the size of the file exactly.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SizeReport {
+    /// Individual property values, largest first.
+    pub properties: Vec<SizeEntry>,
+    /// Scripts by the size of their `Source`, largest first.
+    pub scripts: Vec<SizeEntry>,
+    /// Instances along with their descendants, largest first. The root isn't
+    /// included, since it always holds everything.
+    pub subtrees: Vec<SizeEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SizeEntry {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+    pub bytes: u64,
+}
+
+/// Ranks the `limit` largest properties, scripts and subtrees of a DOM.
+pub fn size_report(dom: &WeakDom, limit: usize) -> SizeReport {
+    /// Rough cost of an instance itself: its referent, class and parent
+    const INSTANCE_OVERHEAD: u64 = 12;
+
+    let root = dom.root_ref();
+    let order: Vec<Ref> = descendants(dom, root).map(|i| i.referent()).collect();
+
+    let mut properties = Largest::new(limit);
+    let mut scripts = Largest::new(limit);
+    let mut subtrees = Largest::new(limit);
+    let mut subtree_sizes: HashMap<Ref, u64> = HashMap::new();
+
+    // Reversed, so every instance comes after its descendants
+    for &referent in order.iter().rev() {
+        let instance = dom.get_by_ref(referent).unwrap();
+        let mut size = INSTANCE_OVERHEAD + instance.name.len() as u64;
+
+        for (name, value) in &instance.properties {
+            let bytes = estimated_size(value);
+            size += bytes;
+
+            properties.push(bytes, (referent, Some(*name)));
+            if let ("Source", Variant::String(_)) = (name.as_str(), value) {
+                scripts.push(bytes, (referent, None));
+            }
+        }
+
+        for child in instance.children() {
+            size += subtree_sizes.remove(child).unwrap_or(0);
+        }
+        subtree_sizes.insert(referent, size);
+
+        if referent != root {
+            subtrees.push(size, (referent, None));
+        }
+    }
+
+    let entries = |largest: Largest<(Ref, Option<Ustr>)>| {
+        largest
+            .into_sorted()
+            .into_iter()
+            .map(|(bytes, (referent, property))| SizeEntry {
+                path: path_of(dom, referent),
+                property: property.map(|property| property.to_string()),
+                bytes,
+            })
+            .collect()
+    };
+
+    SizeReport {
+        properties: entries(properties),
+        scripts: entries(scripts),
+        subtrees: entries(subtrees),
+    }
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sections = [
+            ("Largest properties", &self.properties),
+            ("Largest scripts", &self.scripts),
+            ("Largest subtrees", &self.subtrees),
+        ];
+
+        for (index, (title, entries)) in sections.into_iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{title}:")?;
+
+            for entry in entries {
+                write!(
+                    f,
+                    "  {:>10}  {}",
+                    Bytes(entry.bytes).to_string(),
+                    entry.path
+                )?;
+                if let Some(property) = &entry.property {
+                    write!(f, ".{property}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Roughly how many bytes a value takes up in the binary format.
+fn estimated_size(value: &Variant) -> u64 {
+    // Strings and other variable-length values are stored with their length
+    const LENGTH: u64 = 4;
+
+    match value {
+        Variant::String(text) => LENGTH + text.len() as u64,
+        Variant::BinaryString(bytes) => LENGTH + bytes.as_ref().len() as u64,
+        Variant::SharedString(shared) => LENGTH + shared.data().len() as u64,
+        Variant::ContentId(id) => LENGTH + id.as_str().len() as u64,
+        Variant::Content(content) => LENGTH + content.as_uri().map_or(0, str::len) as u64,
+        Variant::Tags(tags) => LENGTH + tags.iter().map(|tag| tag.len() as u64 + 1).sum::<u64>(),
+        Variant::Bool(_) => 1,
+        Variant::Int32(_) | Variant::Float32(_) | Variant::Enum(_) | Variant::BrickColor(_) => 4,
+        Variant::Int64(_) | Variant::Float64(_) | Variant::Vector2(_) | Variant::UDim(_) => 8,
+        Variant::Vector3(_) | Variant::Color3(_) => 12,
+        Variant::UDim2(_) | Variant::Rect(_) => 16,
+        Variant::Ref(_) => 4,
+        // An id for axis-aligned rotations, or the whole matrix
+        Variant::CFrame(_) => 49,
+        Variant::NumberSequence(sequence) => LENGTH + 12 * sequence.keypoints.len() as u64,
+        Variant::ColorSequence(sequence) => LENGTH + 20 * sequence.keypoints.len() as u64,
+        // Anything else is rare enough that its JSON form is close enough
+        other => serde_json::to_vec(other).map_or(0, |json| json.len() as u64),
+    }
+}
+
+/// Keeps the `limit` largest items pushed into it without holding on to
+/// everything else.
+struct Largest<T> {
+    limit: usize,
+    items: Vec<(u64, T)>,
+}
+
+impl<T> Largest<T> {
+    fn new(limit: usize) -> Self {
+        Largest {
+            limit,
+            items: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, bytes: u64, item: T) {
+        if self.limit == 0 {
+            return;
+        }
+
+        self.items.push((bytes, item));
+        if self.items.len() >= self.limit * 2 {
+            self.shrink();
+        }
+    }
+
+    fn shrink(&mut self) {
+        // Stable, so ties keep the order they were found in
+        self.items.sort_by_key(|&(bytes, _)| Reverse(bytes));
+        self.items.truncate(self.limit);
+    }
+
+    fn into_sorted(mut self) -> Vec<(u64, T)> {
+        self.shrink();
+        self.items
+    }
+}
+
+/// A byte count written with a unit, e.g. `1.5 MB`.
+pub(crate) struct Bytes(pub u64);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+        if self.0 < 1000 {
+            return write!(f, "{} B", self.0);
+        }
+
+        let mut value = self.0 as f64;
+        let mut unit = "B";
+        for next in UNITS {
+            if value < 1000.0 {
+                break;
+            }
+            value /= 1000.0;
+            unit = next;
+        }
+        write!(f, "{value:.1} {unit}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn counts_by_class_and_top_level() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_name("Workspace")
+                        .with_child(InstanceBuilder::new("Part").with_name("A"))
+                        .with_child(InstanceBuilder::new("Part").with_name("B")),
+                )
+                .with_child(
+                    InstanceBuilder::new("ServerScriptService")
+                        .with_name("ServerScriptService")
+                        .with_child(
+                            InstanceBuilder::new("Script")
+                                .with_name("Main")
+                                .with_property("Source", "print('hi')"),
+                        ),
+                ),
+        );
+
+        let stats = collect(&dom, RootHandling::Auto);
+        assert_eq!(stats.instances, 5);
+        assert_eq!(stats.scripts, 1);
+        assert_eq!(stats.source_bytes, 11);
+        assert_eq!(stats.classes["Part"], 2);
+        assert!(!stats.classes.contains_key("DataModel"));
+
+        let top_level: Vec<_> = stats
+            .top_level
+            .iter()
+            .map(|t| (t.name.as_str(), t.instances, t.scripts))
+            .collect();
+        assert_eq!(
+            top_level,
+            [("Workspace", 3, 0), ("ServerScriptService", 2, 1)]
+        );
+    }
+
+    #[test]
+    fn ranks_largest_contributors() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_name("Game")
+                .with_child(
+                    InstanceBuilder::new("ServerScriptService")
+                        .with_name("ServerScriptService")
+                        .with_child(
+                            InstanceBuilder::new("Script")
+                                .with_name("Big")
+                                .with_property("Source", "x".repeat(1000)),
+                        )
+                        .with_child(
+                            InstanceBuilder::new("Script")
+                                .with_name("Small")
+                                .with_property("Source", "print('hi')"),
+                        ),
+                ),
+        );
+
+        let report = size_report(&dom, 2);
+
+        let scripts: Vec<_> = report.scripts.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            scripts,
+            [
+                "Game/ServerScriptService/Big",
+                "Game/ServerScriptService/Small"
+            ]
+        );
+        assert_eq!(report.scripts[0].bytes, 1004);
+        assert_eq!(report.properties[0].property.as_deref(), Some("Source"));
+
+        // The service holds both scripts, so it's the largest subtree
+        assert_eq!(report.subtrees[0].path, "Game/ServerScriptService");
+        assert!(report.subtrees[0].bytes > 1004 + 15);
+        assert_eq!(report.subtrees.len(), 2);
+    }
+
+    #[test]
+    fn byte_units() {
+        assert_eq!(Bytes(999).to_string(), "999 B");
+        assert_eq!(Bytes(1_500).to_string(), "1.5 KB");
+        assert_eq!(Bytes(300_000_000).to_string(), "300.0 MB");
+    }
+}
@@ -0,0 +1,124 @@
+//! The `$surface` shorthand for PBR textures.
+//!
+//! A part with physically based textures needs a SurfaceAppearance child with
+//! up to four texture maps. `$surface` describes it on the part itself:
+//!
+//! ```json
+//! "Crate": {
+//!     "$className": "MeshPart",
+//!     "$surface": {
+//!         "color": "rbxassetid://101",
+//!         "normal": "rbxassetid://102",
+//!         "metalness": { "$asset": "textures/crate_metalness.png" },
+//!         "roughness": "rbxassetid://104",
+//!         "AlphaMode": "Overlay"
+//!     }
+//! }
+//! ```
+//!
+//! The maps become `ColorMap`, `NormalMap`, `MetalnessMap` and `RoughnessMap`,
+//! and any other keys are SurfaceAppearance properties under their own names.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use ustr::{ustr, UstrMap};
+
+use crate::project::ProjectNode;
+use crate::resolution::UnresolvedValue;
+use crate::templates::merge_under;
+
+/// The name of the SurfaceAppearance child `$surface` adds.
+pub const SURFACE_APPEARANCE: &str = "SurfaceAppearance";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SurfaceMaps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<UnresolvedValue>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normal: Option<UnresolvedValue>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metalness: Option<UnresolvedValue>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roughness: Option<UnresolvedValue>,
+
+    /// Other SurfaceAppearance properties, such as `AlphaMode` or `Color`.
+    #[serde(flatten)]
+    pub properties: UstrMap<UnresolvedValue>,
+}
+
+impl SurfaceMaps {
+    /// The SurfaceAppearance these maps describe.
+    pub fn node(self) -> ProjectNode {
+        let mut node = ProjectNode::new(SURFACE_APPEARANCE);
+        node.properties = self.properties;
+
+        let maps = [
+            ("ColorMap", self.color),
+            ("NormalMap", self.normal),
+            ("MetalnessMap", self.metalness),
+            ("RoughnessMap", self.roughness),
+        ];
+        for (property, value) in maps {
+            if let Some(value) = value {
+                node.properties.insert(ustr(property), value);
+            }
+        }
+
+        node
+    }
+
+    /// Adds the SurfaceAppearance to `children`. A SurfaceAppearance child
+    /// that's already there wins over the shorthand.
+    pub fn add_to(self, children: &mut IndexMap<String, ProjectNode>) {
+        let node = self.node();
+        match children.get_mut(SURFACE_APPEARANCE) {
+            Some(existing) => merge_under(existing, node),
+            None => {
+                children.insert(SURFACE_APPEARANCE.to_owned(), node);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::project::Project;
+
+    #[test]
+    fn expands_to_a_surface_appearance() {
+        let project = Project::from_json(
+            r#"{
+                "tree": {
+                    "$className": "MeshPart",
+                    "$surface": {
+                        "color": "rbxassetid://101",
+                        "roughness": { "$asset": "textures/roughness.png" },
+                        "AlphaMode": "Overlay"
+                    },
+                    "SurfaceAppearance": {
+                        "$properties": { "ColorMap": "rbxassetid://999" }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut tree = project.tree;
+        let mut children = std::mem::take(&mut tree.children);
+        tree.surface.take().unwrap().add_to(&mut children);
+
+        let surface = &children[SURFACE_APPEARANCE];
+        assert_eq!(surface.class_name.as_deref(), Some(SURFACE_APPEARANCE));
+        assert_eq!(
+            surface.properties[&ustr("ColorMap")],
+            UnresolvedValue::from("rbxassetid://999")
+        );
+        assert!(surface.properties.contains_key(&ustr("RoughnessMap")));
+        assert!(surface.properties.contains_key(&ustr("AlphaMode")));
+        assert!(!surface.properties.contains_key(&ustr("NormalMap")));
+    }
+}
@@ -0,0 +1,203 @@
+//! Starter projects for `rbxbuild init` and `--template`, kept as project
+//! files in `src/templates/` and compiled into the binary.
+
+use serde::Deserialize;
+
+use crate::diagnostics::DiagnosticSink;
+use crate::error::Result;
+use crate::project::{Project, ProjectNode};
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum Template {
+    /// An empty Model, to build a model file from.
+    EmptyModel,
+
+    /// A place with the standard services, a baseplate, a spawn location and
+    /// example server, client and module scripts.
+    #[default]
+    Baseplate,
+
+    /// A place with a short obstacle course between two spawn locations and
+    /// a script that kills players touching parts tagged `Lava`.
+    Obby,
+
+    /// Only StarterGui, with a menu ScreenGui and the script that closes it.
+    Ui,
+}
+
+impl Template {
+    pub const ALL: [Template; 4] = [
+        Template::EmptyModel,
+        Template::Baseplate,
+        Template::Obby,
+        Template::Ui,
+    ];
+
+    /// The name used on the command line and in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Template::EmptyModel => "empty-model",
+            Template::Baseplate => "baseplate",
+            Template::Obby => "obby",
+            Template::Ui => "ui",
+        }
+    }
+
+    /// The template's project file, as shipped.
+    pub fn source(self) -> &'static str {
+        match self {
+            Template::EmptyModel => include_str!("templates/empty-model.project.json"),
+            Template::Baseplate => include_str!("templates/baseplate.project.json"),
+            Template::Obby => include_str!("templates/obby.project.json"),
+            Template::Ui => include_str!("templates/ui.project.json"),
+        }
+    }
+
+    /// The template as a project called `name`.
+    pub fn project(self, name: &str) -> Result<Project> {
+        let mut project = Project::from_json(self.source())?;
+        project.name = Some(name.to_owned());
+        Ok(project)
+    }
+}
+
+/// Merges a template under the project's tree before it's built. The project
+/// wins wherever both set something: its class, its properties and its
+/// children are kept, and the template only fills in what's missing, all the
+/// way down. Defines the project doesn't have are taken from the template.
+#[derive(Debug, Clone, Copy)]
+pub struct ApplyTemplate(pub Template);
+
+impl Transform for ApplyTemplate {
+    fn name(&self) -> &str {
+        "template"
+    }
+
+    fn transform_project(
+        &self,
+        project: &mut Project,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let template = Project::from_json(self.0.source())?;
+
+        merge_under(&mut project.tree, template.tree);
+        for (name, value) in template.defines {
+            project.defines.entry(name).or_insert(value);
+        }
+
+        Ok(())
+    }
+}
+
+/// Fills in whatever `node` doesn't set from `base`, recursing into the
+/// children they both have.
+pub fn merge_under(node: &mut ProjectNode, base: ProjectNode) {
+    if node.class_name.is_none() {
+        node.class_name = base.class_name;
+    }
+
+    if node.generate.is_none() {
+        node.generate = base.generate;
+    }
+
+    if node.surface.is_none() {
+        node.surface = base.surface;
+    }
+
+    if node.outfit.is_none() {
+        node.outfit = base.outfit;
+    }
+
+    if node.build_tags.is_empty() {
+        node.build_tags = base.build_tags;
+    }
+
+    if node.order.is_none() {
+        node.order = base.order;
+    }
+
+    for (key, value) in base.properties {
+        node.properties.entry(key).or_insert(value);
+    }
+
+    for (name, child) in base.children {
+        match node.children.get_mut(&name) {
+            Some(existing) => merge_under(existing, child),
+            None => {
+                node.children.insert(name, child);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostics::Diagnostic;
+    use crate::options::BuildOptions;
+    use ustr::ustr;
+
+    #[test]
+    fn templates_build_cleanly() {
+        for template in Template::ALL {
+            let project = template.project("Game").unwrap();
+            assert_eq!(project.name.as_deref(), Some("Game"));
+
+            let mut diagnostics = Vec::new();
+            crate::build(
+                project,
+                &BuildOptions::default(),
+                &mut |diagnostic: Diagnostic| diagnostics.push(diagnostic),
+            )
+            .unwrap();
+            assert!(
+                diagnostics.is_empty(),
+                "{}: {diagnostics:?}",
+                template.name()
+            );
+        }
+    }
+
+    #[test]
+    fn merges_the_template_under_the_tree() {
+        let mut project = Project::from_json(
+            r#"{
+                "name": "Game",
+                "tree": {
+                    "StarterGui": {
+                        "MainMenu": {
+                            "$properties": { "Enabled": false },
+                            "Panel": { "$className": "ScrollingFrame" }
+                        },
+                        "Hud": { "$className": "ScreenGui" }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        ApplyTemplate(Template::Ui)
+            .transform_project(&mut project, &mut |_: Diagnostic| {})
+            .unwrap();
+
+        let tree = &project.tree;
+        assert_eq!(tree.class_name.as_deref(), Some("DataModel"));
+
+        let gui = &tree.children["StarterGui"];
+        assert!(gui.children.contains_key("Hud"));
+
+        let menu = &gui.children["MainMenu"];
+        assert_eq!(menu.class_name.as_deref(), Some("ScreenGui"));
+        assert!(menu.properties.contains_key(&ustr("Enabled")));
+        assert!(menu.properties.contains_key(&ustr("ResetOnSpawn")));
+        assert!(menu.children.contains_key("Menu"));
+
+        let panel = &menu.children["Panel"];
+        assert_eq!(panel.class_name.as_deref(), Some("ScrollingFrame"));
+        assert!(panel.children.contains_key("Play"));
+    }
+}
@@ -0,0 +1,438 @@
+//! Terrain generated from heightmap images at build time.
+//!
+//! `Terrain.SmoothGrid` can be given as a heightmap instead of a pre-encoded
+//! blob, so terrain goes through the same pipeline as everything else:
+//!
+//! ```json
+//! "Terrain": {
+//!     "$className": "Terrain",
+//!     "$properties": {
+//!         "SmoothGrid": {
+//!             "$heightmap": "terrain/height.png",
+//!             "size": [2048, 256, 2048],
+//!             "materialMap": "terrain/materials.png",
+//!             "materials": { "#3a7d22": "Grass", "#7f7f7f": "Rock" }
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Heightmaps are grayscale (or the red channel of a color) PNG images, 8 or
+//! 16 bits deep, with black at the base and white `size.y` studs above it.
+//! Material map colors are matched to the closest color in `materials`, so
+//! smoothed edges between two materials still pick one of them.
+//!
+//! Terrain is stored as 4 stud voxels in chunks of 32×32×32. The SmoothGrid
+//! blob starts with a version byte (`01`) and the chunk size as a power of
+//! two (`05`). Each chunk that isn't empty follows as its X, Y and Z
+//! coordinates (little-endian `i32`s) and its voxels, X fastest, then Y, then
+//! Z, run-length encoded: a byte with the material in its low 6 bits, bit 6
+//! set if an occupancy byte follows and bit 7 set if a byte with the run's
+//! length minus one follows. Voxels without an occupancy byte are full, or
+//! empty if they're air.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use rbx_dom_weak::types::{BinaryString, Variant};
+
+use crate::project::ProjectNode;
+use crate::resolution::{parse_hex_color, AmbiguousValue, HeightmapReference, UnresolvedValue};
+use crate::resolver::{HookResult, PropertyContext, PropertyResolver};
+
+/// Terrain materials, indexed by their ID in SmoothGrid.
+pub const MATERIALS: [&str; 23] = [
+    "Air",
+    "Water",
+    "Grass",
+    "Slate",
+    "Concrete",
+    "Brick",
+    "Sand",
+    "WoodPlanks",
+    "Rock",
+    "Glacier",
+    "Snow",
+    "Sandstone",
+    "Mud",
+    "Basalt",
+    "Ground",
+    "CrackedLava",
+    "Asphalt",
+    "Cobblestone",
+    "Ice",
+    "LeafyGrass",
+    "Salt",
+    "Limestone",
+    "Pavement",
+];
+
+/// The size of a voxel in studs.
+const VOXEL_SIZE: f64 = 4.0;
+
+const CHUNK_SIZE_LOG2: u8 = 5;
+const CHUNK_SIZE: i32 = 1 << CHUNK_SIZE_LOG2;
+const CHUNK_VOXELS: usize = 1 << (3 * CHUNK_SIZE_LOG2);
+const VERSION: u8 = 1;
+
+/// Generates `Terrain.SmoothGrid` from `{"$heightmap": ...}` values. Images
+/// are looked up relative to `base`.
+#[derive(Debug, Clone, Default)]
+pub struct HeightmapImporter {
+    pub base: PathBuf,
+}
+
+impl HeightmapImporter {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        HeightmapImporter { base: base.into() }
+    }
+}
+
+impl PropertyResolver for HeightmapImporter {
+    fn before_resolve(
+        &self,
+        ctx: &PropertyContext<'_>,
+        value: &UnresolvedValue,
+    ) -> HookResult<Option<UnresolvedValue>> {
+        let UnresolvedValue::Ambiguous(AmbiguousValue::Heightmap(reference)) = value else {
+            return Ok(None);
+        };
+
+        if ctx.class_name != "Terrain" || ctx.property != "SmoothGrid" {
+            return Err(format!(
+                "Heightmaps can only be used for Terrain.SmoothGrid, not {}.{}",
+                ctx.class_name, ctx.property
+            )
+            .into());
+        }
+
+        let smooth_grid = generate(&self.base, reference)?;
+        Ok(Some(UnresolvedValue::FullyQualified(
+            Variant::BinaryString(BinaryString::from(smooth_grid)),
+        )))
+    }
+}
+
+/// Whether a build of `tree` might need a [`HeightmapImporter`]: some property
+/// is a heightmap, or a `$generate` script could return one. Builds with
+/// resolvers can't be cached, so the importer is best left out otherwise.
+pub fn uses_heightmaps(tree: &ProjectNode) -> bool {
+    let mut stack = vec![tree];
+    while let Some(node) = stack.pop() {
+        let heightmap = node.properties.values().any(|value| {
+            matches!(
+                value,
+                UnresolvedValue::Ambiguous(AmbiguousValue::Heightmap(_))
+            )
+        });
+        if heightmap || node.generate.is_some() {
+            return true;
+        }
+        stack.extend(node.children.values());
+    }
+    false
+}
+
+/// Reads the images a heightmap refers to and encodes the terrain they
+/// describe as SmoothGrid.
+pub fn generate(base: &Path, reference: &HeightmapReference) -> HookResult<Vec<u8>> {
+    let heightmap = Image::read(&base.join(&reference.path))?;
+    let default_material = material_id(&reference.material)?;
+
+    let material_map = match &reference.material_map {
+        Some(path) => {
+            let image = Image::read(&base.join(path))?;
+            let mut colors = Vec::new();
+            for (color, material) in &reference.materials {
+                colors.push((parse_color(color)?, material_id(material)?));
+            }
+            if colors.is_empty() {
+                return Err("A material map needs colors in materials".into());
+            }
+            Some((image, colors))
+        }
+        None => None,
+    };
+
+    let grid = voxelize(reference, |u, v| {
+        let material = match &material_map {
+            Some((image, colors)) => closest(image.color(u, v), colors),
+            None => default_material,
+        };
+        (heightmap.height(u, v), material)
+    });
+    Ok(encode(&grid))
+}
+
+fn material_id(name: &str) -> HookResult<u8> {
+    MATERIALS[1..]
+        .iter()
+        .position(|material| *material == name)
+        .map(|index| index as u8 + 1)
+        .ok_or_else(|| format!("Unknown terrain material {name}").into())
+}
+
+fn parse_color(color: &str) -> HookResult<[u8; 3]> {
+    parse_hex_color(color)
+        .ok_or_else(|| format!("Expected a color like #3a7d22, got {color}").into())
+}
+
+/// The material whose color is closest to `color`.
+fn closest(color: [u8; 3], colors: &[([u8; 3], u8)]) -> u8 {
+    let distance = |other: &[u8; 3]| -> i32 {
+        (0..3)
+            .map(|i| (i32::from(color[i]) - i32::from(other[i])).pow(2))
+            .sum()
+    };
+
+    colors
+        .iter()
+        .min_by_key(|(other, _)| distance(other))
+        .map_or(0, |(_, material)| *material)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Voxel {
+    material: u8,
+    /// How full the voxel is, from 0 to 255.
+    occupancy: u8,
+}
+
+type Chunks = BTreeMap<[i32; 3], Vec<Voxel>>;
+
+/// Fills the voxels below the surface described by `sample`, which takes a
+/// position across the terrain (from 0 to 1 along X and Z) and returns the
+/// height there (from 0 to 1) and the material.
+fn voxelize(reference: &HeightmapReference, sample: impl Fn(f64, f64) -> (f64, u8)) -> Chunks {
+    let [size_x, size_y, size_z] = reference.size;
+    let [x, base, z] = reference.position;
+    let (min_x, min_z) = (x - size_x / 2.0, z - size_z / 2.0);
+
+    let voxels = |min: f64, size: f64| {
+        (min / VOXEL_SIZE).floor() as i32..((min + size) / VOXEL_SIZE).ceil() as i32
+    };
+    let bottom = (base / VOXEL_SIZE).floor() as i32;
+
+    let mut chunks = Chunks::new();
+    for voxel_z in voxels(min_z, size_z) {
+        let v = (f64::from(voxel_z) * VOXEL_SIZE + VOXEL_SIZE / 2.0 - min_z) / size_z;
+        if !(0.0..1.0).contains(&v) {
+            continue;
+        }
+
+        for voxel_x in voxels(min_x, size_x) {
+            let u = (f64::from(voxel_x) * VOXEL_SIZE + VOXEL_SIZE / 2.0 - min_x) / size_x;
+            if !(0.0..1.0).contains(&u) {
+                continue;
+            }
+
+            let (height, material) = sample(u, v);
+            let surface = base + height * size_y;
+
+            let mut voxel_y = bottom;
+            while f64::from(voxel_y) * VOXEL_SIZE < surface {
+                let fill = (surface - f64::from(voxel_y) * VOXEL_SIZE) / VOXEL_SIZE;
+                let occupancy = (fill.min(1.0) * 255.0).round() as u8;
+                if occupancy > 0 {
+                    let position = [voxel_x, voxel_y, voxel_z];
+                    let chunk = position.map(|axis| axis.div_euclid(CHUNK_SIZE));
+                    let [x, y, z] = position.map(|axis| axis.rem_euclid(CHUNK_SIZE) as usize);
+
+                    let voxels = chunks
+                        .entry(chunk)
+                        .or_insert_with(|| vec![Voxel::default(); CHUNK_VOXELS]);
+                    voxels[x + CHUNK_SIZE as usize * (y + CHUNK_SIZE as usize * z)] = Voxel {
+                        material,
+                        occupancy,
+                    };
+                }
+                voxel_y += 1;
+            }
+        }
+    }
+
+    chunks
+}
+
+fn encode(chunks: &Chunks) -> Vec<u8> {
+    let mut bytes = vec![VERSION, CHUNK_SIZE_LOG2];
+
+    for (position, voxels) in chunks {
+        for axis in position {
+            bytes.extend_from_slice(&axis.to_le_bytes());
+        }
+
+        let mut index = 0;
+        while index < voxels.len() {
+            let voxel = voxels[index];
+            let run = voxels[index..]
+                .iter()
+                .take(256)
+                .take_while(|&&other| other == voxel)
+                .count();
+
+            let partial = voxel.material != 0 && voxel.occupancy != u8::MAX;
+            let mut flags = voxel.material;
+            if partial {
+                flags |= 0x40;
+            }
+            if run > 1 {
+                flags |= 0x80;
+            }
+
+            bytes.push(flags);
+            if partial {
+                bytes.push(voxel.occupancy);
+            }
+            if run > 1 {
+                bytes.push((run - 1) as u8);
+            }
+            index += run;
+        }
+    }
+
+    bytes
+}
+
+/// A decoded PNG image, with every sample widened to 16 bits.
+struct Image {
+    width: usize,
+    height: usize,
+    channels: usize,
+    samples: Vec<u16>,
+}
+
+impl Image {
+    fn read(path: &Path) -> HookResult<Self> {
+        let file = File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        decoder.set_transformations(png::Transformations::EXPAND);
+
+        let mut reader = decoder
+            .read_info()
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        let buffer = &buffer[..info.buffer_size()];
+
+        let samples = match info.bit_depth {
+            png::BitDepth::Sixteen => buffer
+                .chunks_exact(2)
+                .map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+                .collect(),
+            _ => buffer
+                .iter()
+                .map(|&sample| u16::from(sample) * 257)
+                .collect(),
+        };
+
+        Ok(Image {
+            width: info.width as usize,
+            height: info.height as usize,
+            channels: info.color_type.samples(),
+            samples,
+        })
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> &[u16] {
+        let start = (y * self.width + x) * self.channels;
+        &self.samples[start..start + self.channels]
+    }
+
+    /// The first channel at `u`, `v` (from 0 to 1), interpolated between the
+    /// pixels around it.
+    fn height(&self, u: f64, v: f64) -> f64 {
+        let x = u * (self.width - 1) as f64;
+        let y = v * (self.height - 1) as f64;
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (x - x0 as f64, y - y0 as f64);
+
+        let at = |x, y| f64::from(self.pixel(x, y)[0]) / f64::from(u16::MAX);
+        let top = at(x0, y0) * (1.0 - tx) + at(x1, y0) * tx;
+        let bottom = at(x0, y1) * (1.0 - tx) + at(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    /// The color of the pixel nearest to `u`, `v` (from 0 to 1).
+    fn color(&self, u: f64, v: f64) -> [u8; 3] {
+        let x = (u * (self.width - 1) as f64).round() as usize;
+        let y = (v * (self.height - 1) as f64).round() as usize;
+        let pixel = self.pixel(x, y);
+
+        // Grayscale images are gray in every channel
+        let channel = |index: usize| (pixel[index.min(pixel.len() - 1)] >> 8) as u8;
+        if self.channels < 3 {
+            [channel(0); 3]
+        } else {
+            [channel(0), channel(1), channel(2)]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn reference(size: [f64; 3]) -> HeightmapReference {
+        serde_json::from_value(serde_json::json!({
+            "$heightmap": "height.png",
+            "size": size,
+            "position": [0, 0, 0],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn encodes_a_flat_slab() {
+        // One voxel column across, 6 studs high: a full voxel and a half one
+        let grid = voxelize(&reference([4.0, 12.0, 4.0]), |_, _| (0.5, 2));
+        assert_eq!(grid.len(), 1);
+
+        let mut chunk = vec![VERSION, CHUNK_SIZE_LOG2];
+        chunk.extend_from_slice(&(-1i32).to_le_bytes());
+        chunk.extend_from_slice(&0i32.to_le_bytes());
+        chunk.extend_from_slice(&(-1i32).to_le_bytes());
+        assert_eq!(&encode(&grid)[..14], chunk.as_slice());
+
+        // The column is at the corner of the chunk nearest the origin
+        let voxels = &grid[&[-1, 0, -1]];
+        let index = 31 + 32 * 32 * 31;
+        assert_eq!(
+            voxels[index],
+            Voxel {
+                material: 2,
+                occupancy: 255
+            }
+        );
+        assert_eq!(
+            voxels[index + 32],
+            Voxel {
+                material: 2,
+                occupancy: 128
+            }
+        );
+        assert_eq!(voxels.iter().filter(|voxel| voxel.material != 0).count(), 2);
+    }
+
+    #[test]
+    fn matches_materials() {
+        assert_eq!(material_id("Grass").unwrap(), 2);
+        assert!(material_id("Air").is_err());
+        assert!(material_id("Lava").is_err());
+
+        let colors = [
+            (parse_color("#3a7d22").unwrap(), 2),
+            (parse_color("7f7f7f").unwrap(), 8),
+        ];
+        assert_eq!(closest([60, 120, 40], &colors), 2);
+        assert_eq!(closest([200, 200, 200], &colors), 8);
+        assert!(parse_color("#3a7d").is_err());
+    }
+}
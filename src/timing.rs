@@ -0,0 +1,109 @@
+//! Optional per-phase timing of builds.
+//!
+//! Set [`BuildOptions::timings`] to collect how long each phase of a build
+//! took. Property resolution happens during instantiation, so its time is
+//! included in [`Phase::Instantiate`] as well; with the `parallel` feature it
+//! is summed across threads and can exceed the wall-clock time.
+//!
+//! [`BuildOptions::timings`]: crate::BuildOptions::timings
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Parse,
+    Transform,
+    Instantiate,
+    Resolve,
+    Serialize,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Parse,
+        Phase::Transform,
+        Phase::Instantiate,
+        Phase::Resolve,
+        Phase::Serialize,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Parse => "parse",
+            Phase::Transform => "transform",
+            Phase::Instantiate => "instantiate",
+            Phase::Resolve => "resolve",
+            Phase::Serialize => "serialize",
+        }
+    }
+}
+
+/// Time spent in each phase. Safe to record into from several threads.
+#[derive(Debug, Default)]
+pub struct Timings {
+    nanos: [AtomicU64; Phase::ALL.len()],
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `duration` to the time spent in `phase`.
+    pub fn record(&self, phase: Phase, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.nanos[phase as usize].fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, phase: Phase) -> Duration {
+        Duration::from_nanos(self.nanos[phase as usize].load(Ordering::Relaxed))
+    }
+
+    /// Every phase with the time spent in it, in the order they run.
+    pub fn phases(&self) -> impl Iterator<Item = (Phase, Duration)> + '_ {
+        Phase::ALL.into_iter().map(|phase| (phase, self.get(phase)))
+    }
+}
+
+/// Runs `f`, recording how long it took if timings are being collected.
+pub(crate) fn timed<T>(timings: Option<&Timings>, phase: Phase, f: impl FnOnce() -> T) -> T {
+    match timings {
+        Some(timings) => {
+            let start = Instant::now();
+            let result = f();
+            timings.record(phase, start.elapsed());
+            result
+        }
+        None => f(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn records_accumulate() {
+        let timings = Timings::new();
+        timings.record(Phase::Resolve, Duration::from_millis(2));
+        timings.record(Phase::Resolve, Duration::from_millis(3));
+
+        assert_eq!(timings.get(Phase::Resolve), Duration::from_millis(5));
+        assert_eq!(timings.get(Phase::Parse), Duration::ZERO);
+        assert_eq!(timings.phases().count(), Phase::ALL.len());
+    }
+
+    #[test]
+    fn timed_skips_when_disabled() {
+        assert_eq!(timed(None, Phase::Parse, || 1 + 1), 2);
+
+        let timings = Timings::new();
+        timed(Some(&timings), Phase::Parse, || {
+            std::thread::sleep(Duration::from_millis(1))
+        });
+        assert!(timings.get(Phase::Parse) >= Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,190 @@
+//! Passes that rewrite a build before it is serialized.
+//!
+//! A [`Transform`] can edit the project tree before it is instantiated, the
+//! built [`WeakDom`] afterwards, or both. Transforms are collected into a
+//! [`Pipeline`] and run in the order they were added.
+//!
+//! ```
+//! use rbxbuild::transform::Pipeline;
+//!
+//! let pipeline = Pipeline::new()
+//!     .project_pass("rename-root", |project, _diagnostics| {
+//!         project.name = Some("Renamed".to_owned());
+//!         Ok(())
+//!     })
+//!     .dom_pass("count", |dom, _diagnostics| {
+//!         println!("built {} top-level instances", dom.root().children().len());
+//!         Ok(())
+//!     });
+//! ```
+
+use std::fmt;
+
+use rbx_dom_weak::WeakDom;
+
+use crate::diagnostics::DiagnosticSink;
+use crate::error::{Error, Result};
+use crate::project::Project;
+use crate::resolver::HookResult;
+
+/// A pass over a build. Both methods default to doing nothing.
+pub trait Transform: Send + Sync {
+    /// Short name used in error messages.
+    fn name(&self) -> &str;
+
+    /// Runs before the project is instantiated.
+    fn transform_project(
+        &self,
+        _project: &mut Project,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        Ok(())
+    }
+
+    /// Runs after the project is instantiated, before it is serialized.
+    fn transform_dom(
+        &self,
+        _dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        Ok(())
+    }
+}
+
+/// An ordered list of transforms.
+#[derive(Default)]
+pub struct Pipeline {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Appends a transform to the pipeline.
+    pub fn with(mut self, transform: impl Transform + 'static) -> Self {
+        self.push(transform);
+        self
+    }
+
+    pub fn push(&mut self, transform: impl Transform + 'static) {
+        self.transforms.push(Box::new(transform));
+    }
+
+    /// Appends a closure that edits the project before instantiation.
+    pub fn project_pass<F>(self, name: impl Into<String>, pass: F) -> Self
+    where
+        F: Fn(&mut Project, &mut dyn DiagnosticSink) -> HookResult<()> + Send + Sync + 'static,
+    {
+        self.with(ProjectPass {
+            name: name.into(),
+            pass,
+        })
+    }
+
+    /// Appends a closure that edits the built DOM before serialization.
+    pub fn dom_pass<F>(self, name: impl Into<String>, pass: F) -> Self
+    where
+        F: Fn(&mut WeakDom, &mut dyn DiagnosticSink) -> HookResult<()> + Send + Sync + 'static,
+    {
+        self.with(DomPass {
+            name: name.into(),
+            pass,
+        })
+    }
+
+    pub(crate) fn run_project(
+        &self,
+        project: &mut Project,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Result<()> {
+        for transform in &self.transforms {
+            transform
+                .transform_project(project, diagnostics)
+                .map_err(|err| transform_error(transform.as_ref(), err))?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn run_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Result<()> {
+        for transform in &self.transforms {
+            transform
+                .transform_dom(dom, diagnostics)
+                .map_err(|err| transform_error(transform.as_ref(), err))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.transforms.iter().map(|transform| transform.name()))
+            .finish()
+    }
+}
+
+fn transform_error(
+    transform: &dyn Transform,
+    err: Box<dyn std::error::Error + Send + Sync>,
+) -> Error {
+    Error::Transform {
+        name: transform.name().to_owned(),
+        reason: err.to_string(),
+    }
+}
+
+struct ProjectPass<F> {
+    name: String,
+    pass: F,
+}
+
+impl<F> Transform for ProjectPass<F>
+where
+    F: Fn(&mut Project, &mut dyn DiagnosticSink) -> HookResult<()> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn transform_project(
+        &self,
+        project: &mut Project,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        (self.pass)(project, diagnostics)
+    }
+}
+
+struct DomPass<F> {
+    name: String,
+    pass: F,
+}
+
+impl<F> Transform for DomPass<F>
+where
+    F: Fn(&mut WeakDom, &mut dyn DiagnosticSink) -> HookResult<()> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        (self.pass)(dom, diagnostics)
+    }
+}
@@ -0,0 +1,161 @@
+//! Control over the `UniqueId` and `HistoryId` properties Studio gives every
+//! instance it saves.
+//!
+//! Builds don't write them by default, since nothing in a project sets them.
+//! Some tools expect every instance to have a `UniqueId` though, and places
+//! that were decompiled or merged in can carry old ones that change whenever
+//! the source place is saved again.
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
+};
+
+use rbx_dom_weak::{
+    types::{UniqueId, Variant},
+    WeakDom,
+};
+use serde::Deserialize;
+use ustr::Ustr;
+
+use crate::diagnostics::DiagnosticSink;
+use crate::provenance::build_time;
+use crate::query::{descendants, path_of};
+use crate::resolver::HookResult;
+use crate::transform::Transform;
+
+/// Where the `time` of a `UniqueId` counts from: 2021-01-01T00:00:00Z.
+const UNIQUE_ID_EPOCH: i64 = 1_609_459_200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum UniqueIdMode {
+    /// Remove `UniqueId` and `HistoryId` from every instance.
+    Omit,
+
+    /// Give every instance a `UniqueId` derived from its path, which stays
+    /// the same from build to build.
+    Derive,
+
+    /// Give every instance a new `UniqueId` the way Studio does, from a
+    /// random number, the build time and a counter.
+    Random,
+}
+
+/// Removes or generates the `UniqueId` of every instance in the built tree.
+/// Generated IDs replace whatever the instance had.
+#[derive(Debug, Clone, Copy)]
+pub struct UniqueIds(pub UniqueIdMode);
+
+impl Transform for UniqueIds {
+    fn name(&self) -> &str {
+        "unique-ids"
+    }
+
+    fn transform_dom(
+        &self,
+        dom: &mut WeakDom,
+        _diagnostics: &mut dyn DiagnosticSink,
+    ) -> HookResult<()> {
+        let unique_id = Ustr::from("UniqueId");
+        let referents: Vec<_> = descendants(dom, dom.root_ref())
+            .map(|instance| instance.referent())
+            .collect();
+
+        let ids: Vec<UniqueId> = match self.0 {
+            UniqueIdMode::Omit => {
+                let history_id = Ustr::from("HistoryId");
+                for referent in referents {
+                    let instance = dom.get_by_ref_mut(referent).unwrap();
+                    instance.properties.remove(&unique_id);
+                    instance.properties.remove(&history_id);
+                }
+                return Ok(());
+            }
+            UniqueIdMode::Derive => {
+                // Siblings with the same name are told apart by their order
+                let mut seen: HashMap<String, u64> = HashMap::new();
+                referents
+                    .iter()
+                    .map(|&referent| {
+                        let path = path_of(dom, referent);
+                        let ordinal = seen.entry(path.clone()).or_default();
+                        *ordinal += 1;
+                        let random = fnv1a(format!("{path}\0{ordinal}").as_bytes());
+                        UniqueId::new(0, 0, random as i64)
+                    })
+                    .collect()
+            }
+            UniqueIdMode::Random => {
+                let random = RandomState::new().build_hasher().finish() as i64;
+                let time = (build_time() - UNIQUE_ID_EPOCH).max(0) as u32;
+                (1..=referents.len() as u32)
+                    .map(|index| UniqueId::new(index, time, random))
+                    .collect()
+            }
+        };
+
+        for (referent, id) in referents.into_iter().zip(ids) {
+            let instance = dom.get_by_ref_mut(referent).unwrap();
+            instance.properties.insert(unique_id, Variant::UniqueId(id));
+        }
+
+        Ok(())
+    }
+}
+
+/// 64-bit FNV-1a, which unlike std's hashers is the same on every platform
+/// and Rust version.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostics::Diagnostic;
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn dom() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_name("Game"));
+        let workspace = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("Workspace").with_name("Workspace"),
+        );
+        for _ in 0..2 {
+            dom.insert(workspace, InstanceBuilder::new("Part").with_name("Wall"));
+        }
+        dom
+    }
+
+    fn ids(dom: &WeakDom) -> Vec<Option<Variant>> {
+        descendants(dom, dom.root_ref())
+            .map(|instance| instance.properties.get(&Ustr::from("UniqueId")).cloned())
+            .collect()
+    }
+
+    #[test]
+    fn derived_ids_are_stable_and_distinct() {
+        let mut sink = |_: Diagnostic| {};
+        let (mut first, mut second) = (dom(), dom());
+        UniqueIds(UniqueIdMode::Derive)
+            .transform_dom(&mut first, &mut sink)
+            .unwrap();
+        UniqueIds(UniqueIdMode::Derive)
+            .transform_dom(&mut second, &mut sink)
+            .unwrap();
+
+        let derived = ids(&first);
+        assert_eq!(derived, ids(&second));
+        assert!(derived.iter().all(Option::is_some));
+        assert_ne!(derived[2], derived[3]);
+
+        UniqueIds(UniqueIdMode::Omit)
+            .transform_dom(&mut first, &mut sink)
+            .unwrap();
+        assert!(ids(&first).iter().all(Option::is_none));
+    }
+}
@@ -0,0 +1,30 @@
+//! JavaScript bindings, for building projects client-side in a browser.
+//!
+//! Compiled with `wasm-pack build --target web --no-default-features
+//! --features wasm`, which leaves out everything that needs a terminal, the
+//! file system or threads. `js/index.js` wraps the generated package.
+//!
+//! Local `$asset` files can't be uploaded from the browser, so projects that
+//! use them fail to build.
+
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::Diagnostic;
+use crate::embed;
+use crate::output::OutputFormat;
+
+/// Builds a project from its JSON and returns the serialized place or model,
+/// in binary unless `format` is `"xml"`. Throws if the project doesn't build.
+#[wasm_bindgen(js_name = buildProject)]
+pub fn build_project(json: &str, format: Option<String>) -> Result<Vec<u8>, JsError> {
+    let format = match format.as_deref() {
+        None | Some("binary") => OutputFormat::Binary,
+        Some("xml") => OutputFormat::Xml,
+        Some(other) => return Err(JsError::new(&format!("Unknown output format {other:?}"))),
+    };
+
+    embed::build_json(json, format, &mut |diagnostic: Diagnostic| {
+        log::warn!("{diagnostic}")
+    })
+    .map_err(|error| JsError::new(&error.to_string()))
+}